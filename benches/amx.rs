@@ -0,0 +1,232 @@
+//! Throughput of the wrapper layer. On AArch64, the benchmarks run on the
+//! hardware; elsewhere, they run on the emulator so that the suite at least
+//! builds and runs.
+use amx::{
+    kernels::backend::{Backend, Portable},
+    Amx, AmxAligned, Index4, MemHint, Normal, XBytes, XRow, YBytes, YRow, ZRow, X8,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+/// The number of instructions issued per iteration by the instruction-rate
+/// benchmarks, so that the loop overhead is amortized.
+const NUM_ISSUES: u64 = 64;
+
+/// The size of the buffer streamed through by the `load_store_hint`
+/// benchmarks.
+const STREAM_LEN: usize = 16 << 20;
+
+fn bench_all(c: &mut Criterion, backend: &str, ctx: &mut impl Amx) {
+    let mut group = c.benchmark_group(format!("{}/load_store", backend));
+    let buf = AmxAligned([1; 128]);
+    let mut out = AmxAligned([0; 128]);
+    group.throughput(Throughput::Bytes(64 * 8));
+    group.bench_function("load512", |b| {
+        b.iter(|| {
+            for i in 0..8 {
+                unsafe { ctx.load512(black_box(buf.0.as_ptr()), XRow(i)) };
+            }
+        })
+    });
+    group.bench_function("store512", |b| {
+        b.iter(|| {
+            for i in 0..8 {
+                unsafe { ctx.store512(black_box(out.0.as_mut_ptr()), XRow(i)) };
+            }
+        })
+    });
+    group.throughput(Throughput::Bytes(128 * 4));
+    group.bench_function("load1024_aligned", |b| {
+        b.iter(|| {
+            for i in 0..4 {
+                unsafe { ctx.load1024_aligned(black_box(buf.0.as_ptr()), YRow(i * 2)) };
+            }
+        })
+    });
+    group.finish();
+
+    // Stream through a buffer larger than the caches to see if the hints make
+    // a difference
+    let mut group = c.benchmark_group(format!("{}/load_store_hint", backend));
+    let mut stream = vec![AmxAligned([1u8; 128]); STREAM_LEN / 128];
+    group.throughput(Throughput::Bytes(STREAM_LEN as u64));
+    for &hint in &[MemHint::Normal, MemHint::NonTemporal] {
+        group.bench_function(format!("load512_x_hint/{:?}", hint), |b| {
+            b.iter(|| {
+                for (i, chunk) in stream.iter().enumerate() {
+                    unsafe {
+                        ctx.load512_x_hint(chunk.0.as_ptr(), XRow(i % 4 * 2), hint);
+                        ctx.load512_x_hint(chunk.0[64..].as_ptr(), XRow(i % 4 * 2 + 1), hint);
+                    }
+                }
+            })
+        });
+        group.bench_function(format!("store512_x_hint/{:?}", hint), |b| {
+            b.iter(|| {
+                for (i, chunk) in stream.iter_mut().enumerate() {
+                    unsafe {
+                        ctx.store512_x_hint(chunk.0.as_mut_ptr(), XRow(i % 4 * 2), hint);
+                        ctx.store512_x_hint(chunk.0[64..].as_mut_ptr(), XRow(i % 4 * 2 + 1), hint);
+                    }
+                }
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group(format!("{}/issue", backend));
+    group.throughput(Throughput::Elements(NUM_ISSUES));
+    group.bench_function("mac16", |b| {
+        b.iter(|| {
+            for i in 0..NUM_ISSUES as usize {
+                ctx.outer_product_i16_xy_to_z(
+                    Some(XBytes(i % 8 * 64)),
+                    Some(YBytes(i % 8 * 64)),
+                    ZRow(i % 2),
+                    true,
+                );
+            }
+        })
+    });
+    group.bench_function("fma32", |b| {
+        b.iter(|| {
+            for i in 0..NUM_ISSUES as usize {
+                ctx.outer_product_f32_xy_to_z(
+                    Some(XBytes(i % 8 * 64)),
+                    Some(YBytes(i % 8 * 64)),
+                    ZRow(i % 4),
+                    true,
+                );
+            }
+        })
+    });
+    group.bench_function("fma64", |b| {
+        b.iter(|| {
+            for i in 0..NUM_ISSUES {
+                // `z` tile `i % 8`, accumulating `x[i % 8]` × `y[i % 8]`
+                ctx.fma64(black_box(
+                    ((i % 8) << 6) | ((i % 8) << 16) | ((i % 8) << 20),
+                ));
+            }
+        })
+    });
+    group.bench_function("genlut", |b| {
+        b.iter(|| {
+            for i in 0..NUM_ISSUES as usize {
+                ctx.lut(
+                    XBytes(i % 8 * 64),
+                    XRow(0),
+                    ZRow(i % 64),
+                    (Normal, Index4, X8),
+                );
+            }
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group(format!("{}/gemm_f32", backend));
+    for &size in &[16, 64, 256] {
+        let a = vec![1.0f32; size * size];
+        let b_mat = vec![1.0f32; size * size];
+        let mut c_mat = vec![0.0f32; size * size];
+        group.throughput(Throughput::Elements((size * size * size) as u64));
+        group.bench_function(format!("{0}x{0}x{0}", size), |b| {
+            b.iter(|| amx::kernels::matmul_f32(&mut *ctx, size, size, size, &a, &b_mat, &mut c_mat))
+        });
+    }
+    group.finish();
+
+    // The twiddle factors are `1 / sqrt(radix)`, which makes each iteration
+    // unitary, so the values stay bounded across iterations
+    let mut group = c.benchmark_group(format!("{}/fft_butterfly_c32", backend));
+    for &len in &[512, 8192] {
+        let mut data = vec![1.0f32; 2 * len];
+        group.throughput(Throughput::Elements(len as u64));
+        let twiddles = vec![0.5f32; 2 * len];
+        group.bench_function(format!("radix4/{}", len), |b| {
+            b.iter(|| amx::kernels::fft_butterfly4_c32(&mut *ctx, &mut data, Some(&twiddles)))
+        });
+        let twiddles = vec![(0.125f32).sqrt(); 2 * len];
+        group.bench_function(format!("radix8/{}", len), |b| {
+            b.iter(|| amx::kernels::fft_butterfly8_c32(&mut *ctx, &mut data, Some(&twiddles)))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group(format!("{}/memcpy", backend));
+    for &len in &[4096, 1 << 20] {
+        let src = vec![AmxAligned([1u8; 128]); len / 128];
+        let mut dst = vec![AmxAligned([0u8; 128]); len / 128];
+        // Safety: `AmxAligned<[u8; 128]>` has no padding
+        let (src, dst) = unsafe {
+            (
+                std::slice::from_raw_parts(src.as_ptr() as *const u8, len),
+                std::slice::from_raw_parts_mut(dst.as_mut_ptr() as *mut u8, len),
+            )
+        };
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_function(format!("amx_memcpy/{}", len), |b| {
+            b.iter(|| amx::kernels::amx_memcpy(&mut *ctx, dst, black_box(src)))
+        });
+        group.bench_function(format!("copy_from_slice/{}", len), |b| {
+            b.iter(|| dst.copy_from_slice(black_box(src)))
+        });
+        group.bench_function(format!("amx_fill64/{}", len), |b| {
+            b.iter(|| amx::kernels::amx_fill64(&mut *ctx, dst, black_box(&[1; 64])))
+        });
+        group.bench_function(format!("fill/{}", len), |b| {
+            b.iter(|| dst.fill(black_box(1)))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group(format!("{}/fingerprint_i16", backend));
+    for &len in &[4096, 1 << 20] {
+        let data: Vec<i16> = (0..len / 2).map(|i| i as i16).collect();
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_function(format!("{}", len), |b| {
+            b.iter(|| amx::kernels::fingerprint_i16(&mut *ctx, black_box(&data)))
+        });
+    }
+    group.finish();
+}
+
+/// Benchmark the kernels generic over [`Backend`], which run the same code
+/// path on every backend.
+fn bench_backend(c: &mut Criterion, backend: &str, ctx: &mut impl Backend<f32>) {
+    let mut group = c.benchmark_group(format!("{}/backend", backend));
+    for &size in &[64, 256] {
+        let a = vec![1.0f32; size * size];
+        let b = vec![1.0f32; size * size];
+        let mut out = vec![0.0f32; size * size];
+        group.throughput(Throughput::Elements((2 * size * size * size) as u64));
+        group.bench_function(format!("matmul_f32/{}", size), |bencher| {
+            bencher.iter(|| {
+                amx::kernels::matmul_f32(&mut *ctx, size, size, size, black_box(&a), &b, &mut out)
+            })
+        });
+    }
+    let a = vec![1.0f32; 1 << 16];
+    group.throughput(Throughput::Elements(a.len() as u64));
+    group.bench_function("dot_f32", |bencher| {
+        bencher.iter(|| amx::kernels::dot_f32(&mut *ctx, black_box(&a), &a))
+    });
+    group.finish();
+}
+
+#[cfg(target_arch = "aarch64")]
+fn benches(c: &mut Criterion) {
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    bench_all(c, "native", &mut *ctx);
+    bench_backend(c, "native", &mut *ctx);
+    bench_backend(c, "portable", &mut Portable::new());
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn benches(c: &mut Criterion) {
+    bench_all(c, "emu", &mut amx::AmxEmuCtx::default());
+    bench_backend(c, "emu", &mut amx::AmxEmuCtx::default());
+    bench_backend(c, "portable", &mut Portable::new());
+}
+
+criterion_group!(amx_benches, benches);
+criterion_main!(amx_benches);
@@ -0,0 +1,42 @@
+//! Compares the throughput of the register-zeroing helpers against the naïve
+//! method of loading a zero buffer from memory.
+use amx::{prelude::*, XRow, ZRow};
+use std::{hint::black_box, time::Instant};
+
+fn bench(name: &str, count: usize, mut f: impl FnMut()) {
+    let start = Instant::now();
+    for _ in 0..count {
+        f();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:>24}: {:8.2} ns/iter",
+        name,
+        elapsed.as_secs_f64() * 1e9 / count as f64
+    );
+}
+
+fn main() {
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    let zeros = [0u8; 4096];
+    let count = 1_000_000;
+
+    bench("load512 × 8 (x)", count, || {
+        for i in 0..8 {
+            unsafe { ctx.load512(black_box(&zeros[i * 64]), XRow(i)) };
+        }
+    });
+    bench("clear_x", count, || ctx.clear_x());
+
+    bench("load512 × 64 (z)", count, || {
+        for i in 0..64 {
+            unsafe { ctx.load512(black_box(&zeros[i * 64]), ZRow(i)) };
+        }
+    });
+    bench("clear_z", count, || ctx.clear_z());
+
+    bench("load512 (z row)", count, || unsafe {
+        ctx.load512(black_box(zeros.as_ptr()), ZRow(5))
+    });
+    bench("clear_z_row", count, || ctx.clear_z_row(ZRow(5)));
+}
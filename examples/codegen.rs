@@ -0,0 +1,97 @@
+//! `Amx` methods that should compile down to a single AMX instruction, each
+//! instantiated in an exported function so that the generated code can be
+//! inspected (e.g., by `cargo rustc --release --example codegen -- --emit
+//! asm`). `tests/codegen.rs` checks that every `amx_codegen_*` function
+//! contains exactly one AMX instruction and no calls.
+#![allow(clippy::missing_safety_doc)]
+use amx::{
+    nativeops::AmxOps, Amx, Index4, IntAccumMode, Normal, XBytes, XRow, YBytes, YRow, ZRow, F32, X8,
+};
+
+#[no_mangle]
+pub unsafe fn amx_codegen_load512_x(ctx: &mut AmxOps<'_>, ptr: *const u8, row: usize) {
+    ctx.load512(ptr, XRow(row));
+}
+
+#[no_mangle]
+pub unsafe fn amx_codegen_load512_z(ctx: &mut AmxOps<'_>, ptr: *const u8, row: usize) {
+    ctx.load512(ptr, ZRow(row));
+}
+
+#[no_mangle]
+pub unsafe fn amx_codegen_store512_y(ctx: &mut AmxOps<'_>, ptr: *mut u8, row: usize) {
+    ctx.store512(ptr, YRow(row));
+}
+
+#[no_mangle]
+pub unsafe fn amx_codegen_load1024_aligned(ctx: &mut AmxOps<'_>, ptr: *const u8) {
+    ctx.load1024_aligned(ptr, XRow(2));
+}
+
+#[no_mangle]
+pub unsafe fn amx_codegen_store1024_aligned(ctx: &mut AmxOps<'_>, ptr: *mut u8) {
+    ctx.store1024_aligned(ptr, ZRow(6));
+}
+
+#[no_mangle]
+pub unsafe fn amx_codegen_load512_interleaved(ctx: &mut AmxOps<'_>, ptr: *const u8) {
+    ctx.load512_interleaved(ptr, ZRow(7));
+}
+
+#[no_mangle]
+pub unsafe fn amx_codegen_store512_interleaved(ctx: &mut AmxOps<'_>, ptr: *mut u8) {
+    ctx.store512_interleaved(ptr, ZRow(7));
+}
+
+#[no_mangle]
+pub fn amx_codegen_clear_z_row(ctx: &mut AmxOps<'_>, row: usize) {
+    ctx.clear_z_row(ZRow(row));
+}
+
+#[no_mangle]
+pub fn amx_codegen_copy_z_row_to_x(ctx: &mut AmxOps<'_>) {
+    ctx.copy_z_row_to_x(ZRow(9), XRow(3));
+}
+
+#[no_mangle]
+pub fn amx_codegen_copy_x_to_y(ctx: &mut AmxOps<'_>) {
+    ctx.copy_x_to_y(XRow(1), YRow(4));
+}
+
+#[no_mangle]
+pub fn amx_codegen_outer_product_i16(ctx: &mut AmxOps<'_>, x: usize, y: usize) {
+    ctx.outer_product_i16_xy_to_z(Some(XBytes(x)), Some(YBytes(y)), ZRow(1), true);
+}
+
+#[no_mangle]
+pub fn amx_codegen_outer_product_f32(ctx: &mut AmxOps<'_>, accumulate: bool) {
+    ctx.outer_product_f32_xy_to_z(Some(XBytes(64)), None, ZRow(3), accumulate);
+}
+
+#[no_mangle]
+pub fn amx_codegen_outer_product_u8(ctx: &mut AmxOps<'_>) {
+    ctx.outer_product_u8_to_i32(
+        Some(XBytes(0)),
+        Some(YBytes(16)),
+        ZRow(0),
+        true,
+        IntAccumMode::default(),
+    );
+}
+
+#[no_mangle]
+pub fn amx_codegen_lut(ctx: &mut AmxOps<'_>, input: usize) {
+    ctx.lut(XBytes(input), XRow(7), YRow(2), (Normal, Index4, X8));
+}
+
+#[no_mangle]
+pub fn amx_codegen_reverse_lut(ctx: &mut AmxOps<'_>) {
+    ctx.reverse_lut(YBytes(0), XRow(0), ZRow(5), F32);
+}
+
+#[no_mangle]
+pub fn amx_codegen_permute_x32(ctx: &mut AmxOps<'_>) {
+    ctx.permute_x32(XRow(1), YBytes(64), XRow(2));
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+//! Utilities shared by the examples
+
+/// A xorshift32 pseudorandom number generator.
+pub struct Xorshift32(pub u32);
+
+impl Xorshift32 {
+    pub fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// Generate a number in `[-1, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next() >> 8) as f32 / (1 << 23) as f32 - 1.0
+    }
+}
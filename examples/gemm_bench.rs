@@ -0,0 +1,273 @@
+//! Compares the throughput of `amx::kernels::matmul_f32` against a NEON
+//! baseline and, on macOS, Accelerate's `cblas_sgemm`.
+//!
+//! ```text
+//! cargo run --release --example gemm_bench -- --sizes 256,512,1024 --accelerate
+//! ```
+//!
+//! Don't enable the `blas-shim` feature when running this, or
+//! `cblas_sgemm` will resolve to the crate's own shim instead of Accelerate.
+mod common;
+
+use amx::{Amx, XBytes, YBytes, ZRow};
+use clap::Parser;
+use common::Xorshift32;
+use std::{arch::aarch64::*, time::Instant};
+
+#[derive(Debug, Parser)]
+#[command(author, version, about, long_about = None)]
+struct Opts {
+    /// Square matrix sizes to benchmark
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "64,128,256,512,1024"
+    )]
+    sizes: Vec<usize>,
+    /// Minimum time spent on each measurement, in seconds
+    #[arg(short, long, default_value_t = 0.5)]
+    duration: f64,
+    /// Also benchmark Accelerate's `cblas_sgemm` (macOS only)
+    #[arg(long)]
+    accelerate: bool,
+    /// Don't ask the OS to run the benchmark on a performance core
+    #[arg(long)]
+    no_pin: bool,
+}
+
+fn main() {
+    let opts = Opts::parse();
+
+    if !opts.no_pin {
+        if let Err(e) = amx::affinity::pin_to_performance_core() {
+            println!("couldn't pin the thread: {:?}", e);
+        }
+    }
+    if opts.accelerate && !cfg!(target_os = "macos") {
+        println!("Accelerate is only available on macOS; skipping it");
+    }
+
+    let mut ctx = amx::AmxCtx::new().unwrap();
+
+    // The efficiency is reported relative to what `fma32` achieves without
+    // any memory traffic, which is as fast as a GEMM can possibly go
+    let peak = measure_fma32_peak(&mut *ctx, opts.duration);
+    println!("fma32 peak: {:.1} GFLOPS", peak / 1e9);
+    println!();
+    println!(
+        "{:>6} {:>12} {:>12} {:>12} {:>12}",
+        "size", "backend", "GFLOPS", "efficiency", "max error"
+    );
+
+    let mut rng = Xorshift32(0x2545f491);
+    for &size in &opts.sizes {
+        let (m, n, k) = (size, size, size);
+        let a: Vec<f32> = (0..m * k).map(|_| rng.next_f32()).collect();
+        let b: Vec<f32> = (0..k * n).map(|_| rng.next_f32()).collect();
+        let expected = reference_matmul(m, n, k, &a, &b);
+        let flops = 2.0 * (m * n * k) as f64;
+
+        let report = |name: &str, run: &mut dyn FnMut(&mut [f32])| {
+            let mut c = vec![0.0; m * n];
+            let secs = time(opts.duration, || run(&mut c));
+            let max_error = max_abs_diff(&c, &expected);
+            let gflops = flops / secs / 1e9;
+            println!(
+                "{:>6} {:>12} {:>12.1} {:>11.1}% {:>12.2e}{}",
+                size,
+                name,
+                gflops,
+                gflops * 1e9 / peak * 100.0,
+                max_error,
+                if max_error > tolerance(k) {
+                    "  MISMATCH"
+                } else {
+                    ""
+                }
+            );
+        };
+
+        report("amx", &mut |c| {
+            amx::kernels::matmul_f32(&mut *ctx, m, n, k, &a, &b, c)
+        });
+        report("neon", &mut |c| neon_matmul(m, n, k, &a, &b, c));
+        #[cfg(target_os = "macos")]
+        if opts.accelerate {
+            report("accelerate", &mut |c| accelerate::sgemm(m, n, k, &a, &b, c));
+        }
+    }
+}
+
+/// Run `f` repeatedly for at least `duration` seconds and return the average
+/// time taken by a call, in seconds.
+fn time(duration: f64, mut f: impl FnMut()) -> f64 {
+    // Warm up the caches and the page tables
+    f();
+
+    let start = Instant::now();
+    let mut count = 0u32;
+    loop {
+        f();
+        count += 1;
+        let elapsed = start.elapsed().as_secs_f64();
+        if elapsed >= duration {
+            return elapsed / count as f64;
+        }
+    }
+}
+
+/// Measure the `fma32` throughput in FLOPS. Each instruction performs a
+/// 16×16 outer product (512 FLOPs); the four `z` tiles are independent, so
+/// the loop is limited by the issue rate rather than the latency.
+#[inline(never)]
+fn measure_fma32_peak(ctx: &mut impl Amx, duration: f64) -> f64 {
+    const ISSUES: usize = 1 << 16;
+    let secs = time(duration, || {
+        for _ in 0..ISSUES / 8 {
+            for i in 0..8 {
+                ctx.outer_product_f32_xy_to_z(
+                    Some(XBytes(i * 64)),
+                    Some(YBytes(i * 64)),
+                    ZRow(i % 4),
+                    true,
+                );
+            }
+        }
+    });
+    (ISSUES * 512) as f64 / secs
+}
+
+/// A straightforward NEON implementation of `c = a * b`, blocking the output
+/// into 4×16 tiles held in 16 vector registers.
+fn neon_matmul(m: usize, n: usize, k: usize, a: &[f32], b: &[f32], c: &mut [f32]) {
+    const MR: usize = 4;
+    const NR: usize = 16;
+    assert_eq!(a.len(), m * k);
+    assert_eq!(b.len(), k * n);
+    assert_eq!(c.len(), m * n);
+
+    let (m_main, n_main) = (m - m % MR, n - n % NR);
+    for i in (0..m_main).step_by(MR) {
+        for j in (0..n_main).step_by(NR) {
+            // Safety: `i + MR <= m` and `j + NR <= n`, so every access is
+            //         in bounds
+            unsafe {
+                let mut acc = [[vdupq_n_f32(0.0); NR / 4]; MR];
+                for p in 0..k {
+                    let b_row = b.as_ptr().add(p * n + j);
+                    let b_vecs = [
+                        vld1q_f32(b_row),
+                        vld1q_f32(b_row.add(4)),
+                        vld1q_f32(b_row.add(8)),
+                        vld1q_f32(b_row.add(12)),
+                    ];
+                    for (r, acc_row) in acc.iter_mut().enumerate() {
+                        let a_elem = vdupq_n_f32(*a.get_unchecked((i + r) * k + p));
+                        for (acc, &b_vec) in acc_row.iter_mut().zip(b_vecs.iter()) {
+                            *acc = vfmaq_f32(*acc, a_elem, b_vec);
+                        }
+                    }
+                }
+                for (r, acc_row) in acc.iter().enumerate() {
+                    let c_row = c.as_mut_ptr().add((i + r) * n + j);
+                    for (v, &acc) in acc_row.iter().enumerate() {
+                        vst1q_f32(c_row.add(v * 4), acc);
+                    }
+                }
+            }
+        }
+    }
+
+    // The leftover rows and columns
+    for i in 0..m {
+        let cols = if i < m_main { n_main..n } else { 0..n };
+        for j in cols {
+            c[i * n + j] = (0..k).map(|p| a[i * k + p] * b[p * n + j]).sum();
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod accelerate {
+    use std::os::raw::c_int;
+
+    const CBLAS_ROW_MAJOR: c_int = 101;
+    const CBLAS_NO_TRANS: c_int = 111;
+
+    #[link(name = "Accelerate", kind = "framework")]
+    extern "C" {
+        fn cblas_sgemm(
+            order: c_int,
+            trans_a: c_int,
+            trans_b: c_int,
+            m: c_int,
+            n: c_int,
+            k: c_int,
+            alpha: f32,
+            a: *const f32,
+            lda: c_int,
+            b: *const f32,
+            ldb: c_int,
+            beta: f32,
+            c: *mut f32,
+            ldc: c_int,
+        );
+    }
+
+    /// Calculate `c = a * b` using Accelerate.
+    pub fn sgemm(m: usize, n: usize, k: usize, a: &[f32], b: &[f32], c: &mut [f32]) {
+        assert_eq!(a.len(), m * k);
+        assert_eq!(b.len(), k * n);
+        assert_eq!(c.len(), m * n);
+        let (m, n, k) = (m as c_int, n as c_int, k as c_int);
+        // Safety: The slice lengths match the matrix sizes
+        unsafe {
+            cblas_sgemm(
+                CBLAS_ROW_MAJOR,
+                CBLAS_NO_TRANS,
+                CBLAS_NO_TRANS,
+                m,
+                n,
+                k,
+                1.0,
+                a.as_ptr(),
+                k,
+                b.as_ptr(),
+                n,
+                0.0,
+                c.as_mut_ptr(),
+                n,
+            )
+        };
+    }
+}
+
+/// Calculate `a * b` in `f64` to validate the results against.
+fn reference_matmul(m: usize, n: usize, k: usize, a: &[f32], b: &[f32]) -> Vec<f32> {
+    let mut c = vec![0.0f64; m * n];
+    for i in 0..m {
+        for p in 0..k {
+            let a_elem = a[i * k + p] as f64;
+            for j in 0..n {
+                c[i * n + j] += a_elem * b[p * n + j] as f64;
+            }
+        }
+    }
+    c.into_iter().map(|x| x as f32).collect()
+}
+
+fn max_abs_diff(got: &[f32], expected: &[f32]) -> f32 {
+    got.iter()
+        .zip(expected)
+        .map(|(x, y)| (x - y).abs())
+        .fold(0.0, f32::max)
+}
+
+/// The maximum acceptable error of a `k`-long dot product of elements in
+/// `[-1, 1)`. The rounding errors of random inputs mostly cancel out, so
+/// this is far below the worst-case bound but still well above what a
+/// correct implementation produces.
+fn tolerance(k: usize) -> f32 {
+    k as f32 * f32::EPSILON * 4.0
+}
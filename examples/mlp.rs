@@ -0,0 +1,281 @@
+//! Runs the forward pass of a small multilayer perceptron in three ways and
+//! compares the results:
+//!
+//!  - `f32`: [`amx::kernels::matmul_f32`] for the layers, SiLU built on the
+//!    table-lookup-based [`amx::kernels::exp_approx_f32`], and
+//!    [`amx::kernels::softmax_f32`] for the output.
+//!  - `int8`: the weights and activations are quantized by
+//!    [`amx::kernels::quantize_f32_to_i8`] and multiplied by
+//!    `outer_product_i8_to_i32`, accumulating in 32-bit lanes of `z`. The
+//!    accumulators are rescaled to `f32` before the activation function.
+//!  - `f16`: the weights and activations are converted to `f16` and
+//!    multiplied by `outer_product_f16_xy_to_z`, accumulating in 16-bit
+//!    lanes of `z`.
+//!
+//! The weights and inputs are random, so the output is meaningless; the
+//! point is how close the reduced-precision variants get to `f32`.
+//!
+//! The emulator is used where AMX is unavailable.
+mod common;
+
+use amx::{Amx, IntAccumMode, XBytes, XRow, YBytes, YRow, ZRow};
+use common::Xorshift32;
+use half::f16;
+
+/// The number of inputs processed at once.
+const BATCH: usize = 32;
+
+/// The number of neurons in each layer, starting from the input layer.
+const LAYER_SIZES: [usize; 4] = [64, 128, 96, 10];
+
+struct Layer {
+    inputs: usize,
+    outputs: usize,
+    /// `inputs × outputs`, row-major
+    weights: Vec<f32>,
+    bias: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Precision {
+    F32,
+    Int8,
+    F16,
+}
+
+fn main() {
+    let mut ctx = amx::AnyAmxCtx::best_available();
+
+    let mut rng = Xorshift32(0x2545f491);
+    let layers: Vec<Layer> = LAYER_SIZES
+        .windows(2)
+        .map(|w| Layer::random(&mut rng, w[0], w[1]))
+        .collect();
+    let input: Vec<f32> = (0..BATCH * LAYER_SIZES[0])
+        .map(|_| rng.next_f32())
+        .collect();
+
+    let expected = forward(&mut ctx, &layers, &input, Precision::F32);
+    let classes = argmax_rows(&expected, LAYER_SIZES[3]);
+    println!("f32 predictions: {:?}", classes);
+
+    for &precision in &[Precision::Int8, Precision::F16] {
+        let got = forward(&mut ctx, &layers, &input, precision);
+        let max_error = got
+            .iter()
+            .zip(&expected)
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f32::max);
+        let agree = argmax_rows(&got, LAYER_SIZES[3])
+            .iter()
+            .zip(&classes)
+            .filter(|(x, y)| x == y)
+            .count();
+        println!(
+            "{:?}: max probability error {:.2e}, {}/{} predictions agree with f32",
+            precision, max_error, agree, BATCH
+        );
+    }
+}
+
+impl Layer {
+    /// Initialize a layer with uniformly distributed weights scaled by the
+    /// fan-in.
+    fn random(rng: &mut Xorshift32, inputs: usize, outputs: usize) -> Self {
+        let bound = (3.0 / inputs as f32).sqrt();
+        Self {
+            inputs,
+            outputs,
+            weights: (0..inputs * outputs)
+                .map(|_| rng.next_f32() * bound)
+                .collect(),
+            bias: (0..outputs).map(|_| rng.next_f32() * 0.1).collect(),
+        }
+    }
+}
+
+/// Calculate the class probabilities of `BATCH` inputs.
+fn forward(ctx: &mut impl Amx, layers: &[Layer], input: &[f32], precision: Precision) -> Vec<f32> {
+    let mut act = input.to_vec();
+    for (i, layer) in layers.iter().enumerate() {
+        let mut out = vec![0.0; BATCH * layer.outputs];
+        match precision {
+            Precision::F32 => amx::kernels::matmul_f32(
+                ctx,
+                BATCH,
+                layer.outputs,
+                layer.inputs,
+                &act,
+                &layer.weights,
+                &mut out,
+            ),
+            Precision::Int8 => matmul_int8(ctx, layer, &act, &mut out),
+            Precision::F16 => matmul_f16(ctx, layer, &act, &mut out),
+        }
+        for row in out.chunks_mut(layer.outputs) {
+            for (x, &b) in row.iter_mut().zip(&layer.bias) {
+                *x += b;
+            }
+        }
+
+        if i + 1 < layers.len() {
+            silu(ctx, &mut out);
+        } else {
+            let mut probs = vec![0.0; out.len()];
+            for (logits, probs) in out
+                .chunks(layer.outputs)
+                .zip(probs.chunks_mut(layer.outputs))
+            {
+                amx::kernels::softmax_f32(ctx, logits, probs);
+            }
+            out = probs;
+        }
+        act = out;
+    }
+    act
+}
+
+/// Apply SiLU, i.e., `x * sigmoid(x)`, in place. The exponential is only
+/// ever taken of non-positive numbers, which is what
+/// [`amx::kernels::exp_approx_f32`] is accurate for.
+fn silu(ctx: &mut impl Amx, x: &mut [f32]) {
+    let neg_abs: Vec<f32> = x.iter().map(|x| -x.abs()).collect();
+    let mut exp = vec![0.0; x.len()];
+    amx::kernels::exp_approx_f32(ctx, &neg_abs, &mut exp);
+    for (x, &e) in x.iter_mut().zip(&exp) {
+        // `sigmoid(x) = 1 / (1 + exp(-x)) = exp(x) / (1 + exp(x))`
+        let numerator = if *x >= 0.0 { 1.0 } else { e };
+        *x *= numerator / (1.0 + e);
+    }
+}
+
+/// Get the symmetric quantization scale that maps the largest magnitude in
+/// `x` to `127`.
+fn i8_scale(x: &[f32]) -> f32 {
+    x.iter().fold(f32::MIN_POSITIVE, |max, x| max.max(x.abs())) / 127.0
+}
+
+/// Calculate `out = act * layer.weights` with both operands quantized to
+/// `i8`.
+///
+/// Each `outer_product_i8_to_i32` multiplies 16 rows of `act` by 16 columns
+/// of `weights` for one `k`, so `act` is packed in column-major order and
+/// `weights` in row-major order, 16 elements per `k`. A 64-byte load brings
+/// in four `k`s at once.
+fn matmul_int8(ctx: &mut impl Amx, layer: &Layer, act: &[f32], out: &mut [f32]) {
+    const TILE: usize = 16;
+    let (n, k) = (layer.outputs, layer.inputs);
+    assert_eq!(k % 4, 0);
+
+    // The zero points are zero, so the products of the quantized numbers
+    // only need to be rescaled by the product of the scales
+    let (act_scale, weight_scale) = (i8_scale(act), i8_scale(&layer.weights));
+    let mut act_q = vec![0i8; act.len()];
+    let mut weights_q = vec![0i8; layer.weights.len()];
+    amx::kernels::quantize_f32_to_i8(ctx, act_scale, 0, act, &mut act_q);
+    amx::kernels::quantize_f32_to_i8(ctx, weight_scale, 0, &layer.weights, &mut weights_q);
+
+    let mut act_panel = vec![0i8; k * TILE];
+    let mut weight_panel = vec![0i8; k * TILE];
+    let mut z_row = [0i32; TILE];
+    for i0 in (0..BATCH).step_by(TILE) {
+        for (p, panel) in act_panel.chunks_mut(TILE).enumerate() {
+            for (r, x) in panel.iter_mut().enumerate() {
+                *x = act_q[(i0 + r) * k + p];
+            }
+        }
+
+        for j0 in (0..n).step_by(TILE) {
+            let cols = TILE.min(n - j0);
+            for (p, panel) in weight_panel.chunks_mut(TILE).enumerate() {
+                panel[..cols].copy_from_slice(&weights_q[p * n + j0..][..cols]);
+                panel[cols..].fill(0);
+            }
+
+            for p in (0..k).step_by(4) {
+                // Safety: Reading 64-byte regions within the panels
+                unsafe {
+                    ctx.load512(weight_panel[p * TILE..].as_ptr(), XRow(0));
+                    ctx.load512(act_panel[p * TILE..].as_ptr(), YRow(0));
+                }
+                for s in 0..4 {
+                    ctx.outer_product_i8_to_i32(
+                        Some(XBytes(s * TILE)),
+                        Some(YBytes(s * TILE)),
+                        ZRow(0),
+                        p + s > 0,
+                        IntAccumMode::default(),
+                    );
+                }
+            }
+
+            for r in 0..TILE {
+                // Safety: Writing in a memory region within `z_row`
+                unsafe { ctx.store512(z_row.as_mut_ptr(), ZRow(r * 4)) };
+                for (out, &acc) in out[(i0 + r) * n + j0..][..cols].iter_mut().zip(&z_row) {
+                    *out = acc as f32 * (act_scale * weight_scale);
+                }
+            }
+        }
+    }
+}
+
+/// Calculate `out = act * layer.weights` in `f16`.
+///
+/// Each `outer_product_f16_xy_to_z` multiplies 32 rows of `act` by 32
+/// columns of `weights` for one `k` and accumulates the products in every
+/// second row of `z`. The operands are packed in the same way as
+/// [`matmul_int8`] does.
+fn matmul_f16(ctx: &mut impl Amx, layer: &Layer, act: &[f32], out: &mut [f32]) {
+    const TILE: usize = 32;
+    let (n, k) = (layer.outputs, layer.inputs);
+
+    let zero = f16::ZERO.to_bits();
+    let mut act_panel = vec![zero; k * TILE];
+    let mut weight_panel = vec![zero; k * TILE];
+    let mut z_row = [zero; TILE];
+    for i0 in (0..BATCH).step_by(TILE) {
+        for (p, panel) in act_panel.chunks_mut(TILE).enumerate() {
+            for (r, x) in panel.iter_mut().enumerate() {
+                *x = f16::from_f32(act[(i0 + r) * k + p]).to_bits();
+            }
+        }
+
+        for j0 in (0..n).step_by(TILE) {
+            let cols = TILE.min(n - j0);
+            for (p, panel) in weight_panel.chunks_mut(TILE).enumerate() {
+                for (x, &w) in panel.iter_mut().zip(&layer.weights[p * n + j0..][..cols]) {
+                    *x = f16::from_f32(w).to_bits();
+                }
+                panel[cols..].fill(zero);
+            }
+
+            for p in 0..k {
+                // Safety: Reading 64-byte regions within the panels
+                unsafe {
+                    ctx.load512(weight_panel[p * TILE..].as_ptr(), XRow(0));
+                    ctx.load512(act_panel[p * TILE..].as_ptr(), YRow(0));
+                }
+                ctx.outer_product_f16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), p > 0);
+            }
+
+            for r in 0..TILE {
+                // Safety: Writing in a memory region within `z_row`
+                unsafe { ctx.store512(z_row.as_mut_ptr(), ZRow(r * 2)) };
+                for (out, &acc) in out[(i0 + r) * n + j0..][..cols].iter_mut().zip(&z_row) {
+                    *out = f16::from_bits(acc).to_f32();
+                }
+            }
+        }
+    }
+}
+
+fn argmax_rows(x: &[f32], cols: usize) -> Vec<usize> {
+    x.chunks(cols)
+        .map(|row| {
+            (0..cols)
+                .max_by(|&i, &j| row[i].total_cmp(&row[j]))
+                .unwrap()
+        })
+        .collect()
+}
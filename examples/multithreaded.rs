@@ -1,6 +1,15 @@
-use amx::{prelude::*, XBytes, YBytes, ZRow};
-use clap::Parser;
-use std::time::Instant;
+//! Stress-tests the AMX unit from multiple threads, reporting the throughput
+//! of each thread at regular intervals.
+//!
+//! ```text
+//! cargo run --release --example multithreaded -- -n 4 --ops fma32,ld --duration 10 --csv
+//! ```
+use amx::{Amx, AmxAligned, XBytes, XRow, YBytes, ZRow};
+use clap::{Parser, ValueEnum};
+use std::{
+    process::ExitCode,
+    time::{Duration, Instant},
+};
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -8,45 +17,204 @@ struct Opts {
     /// Number of threads to launch
     #[arg(short, long)]
     num_threads: usize,
+    /// Don't ask the OS to run the threads on performance cores
+    #[arg(long)]
+    no_pin: bool,
+    /// Instructions to issue, cycled through in the specified order
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "mac16")]
+    ops: Vec<Op>,
+    /// Number of seconds each thread runs for (by default, the threads run
+    /// indefinitely)
+    #[arg(long)]
+    duration: Option<f64>,
+    /// Number of seconds between throughput reports
+    #[arg(long, default_value_t = 1.0)]
+    interval: f64,
+    /// Report the throughput in CSV
+    #[arg(long)]
+    csv: bool,
+    /// Verify the register and memory state after every batch of
+    /// instructions against the emulator
+    #[arg(long)]
+    check: bool,
 }
 
-fn main() {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Op {
+    /// Outer product of 16-bit integers
+    Mac16,
+    /// Outer product of `f32`s
+    Fma32,
+    /// Outer product of `f64`s
+    Fma64,
+    /// Load to `x`
+    Ld,
+    /// Store from `z`
+    St,
+}
+
+/// The number of instructions issued between two checks of the clock.
+const BATCH: usize = 1024;
+
+/// The register and memory state after a batch.
+#[derive(PartialEq)]
+struct State {
+    x: [u8; 512],
+    y: [u8; 512],
+    z: [u8; 4096],
+    mem: [u8; 512],
+}
+
+impl State {
+    /// The state at the beginning of a batch in the self-check mode. The
+    /// values are small enough for the accumulation to be exact.
+    fn initial() -> Self {
+        let f32_bytes = |i: usize| (((i % 7) as f32 - 3.5) * 0.5).to_le_bytes();
+        let x = std::array::from_fn(|i| f32_bytes(i / 4)[i % 4]);
+        let y = std::array::from_fn(|i| f32_bytes(i / 4 + 1)[i % 4]);
+        Self {
+            x,
+            y,
+            z: [0; 4096],
+            mem: std::array::from_fn(|i| i as u8),
+        }
+    }
+
+    fn read(ctx: &mut impl Amx, mem: &[u8; 512]) -> Self {
+        Self {
+            x: ctx.read_x(),
+            y: ctx.read_y(),
+            z: ctx.read_z(),
+            mem: *mem,
+        }
+    }
+
+    fn write(&self, ctx: &mut impl Amx, mem: &mut [u8; 512]) {
+        ctx.load_all_x(&self.x);
+        ctx.load_all_y(&self.y);
+        ctx.load_all_z(&self.z);
+        *mem = self.mem;
+    }
+}
+
+fn main() -> ExitCode {
     let opts = Opts::parse();
-    println!("Launching {} threads with AMX enabled", opts.num_threads);
+    if !opts.csv {
+        println!(
+            "Launching {} threads with AMX enabled, issuing {:?}",
+            opts.num_threads, opts.ops
+        );
+    } else {
+        println!("thread,elapsed_s,instructions_per_s,errors");
+    }
+
+    // Compute the expected outcome of a batch
+    let expected = opts.check.then(|| {
+        let mut ctx = amx::AmxEmuCtx::default();
+        let mut mem = AmxAligned([0u8; 512]);
+        State::initial().write(&mut ctx, &mut mem);
+        run_batch(&mut ctx, &opts.ops, &mut mem);
+        State::read(&mut ctx, &mem)
+    });
 
-    for i in 1..opts.num_threads {
-        std::thread::spawn(move || stress_loop(i));
+    let errors: usize = std::thread::scope(|s| {
+        let threads: Vec<_> = (0..opts.num_threads)
+            .map(|i| {
+                let (opts, expected) = (&opts, expected.as_ref());
+                s.spawn(move || stress_loop(i, opts, expected))
+            })
+            .collect();
+        threads.into_iter().map(|t| t.join().unwrap()).sum()
+    });
+
+    if errors > 0 {
+        eprintln!("{} batches failed the self-check", errors);
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Issue `BATCH` instructions, cycling through `ops`.
+#[inline(always)]
+fn run_batch(ctx: &mut impl Amx, ops: &[Op], mem: &mut AmxAligned<[u8; 512]>) {
+    for i in 0..BATCH {
+        // Spread the instructions over registers so that consecutive ones
+        // don't depend on each other
+        match ops[i % ops.len()] {
+            Op::Mac16 => {
+                ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(i % 2), true);
+            }
+            Op::Fma32 => {
+                ctx.outer_product_f32_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(i % 4), true);
+            }
+            Op::Fma64 => {
+                ctx.outer_product_f64_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(i % 8), true);
+            }
+            // Safety: `mem` is large enough for 8 rows
+            Op::Ld => unsafe { ctx.load512(mem[i % 8 * 64..].as_ptr(), XRow(i % 8)) },
+            Op::St => unsafe { ctx.store512(mem[i % 8 * 64..].as_mut_ptr(), ZRow(i % 64)) },
+        }
     }
-    stress_loop(0);
 }
 
+/// Run batches until `opts.duration` elapses, returning the number of
+/// batches that failed the self-check.
 #[inline(never)]
-fn stress_loop(tid: usize) {
+fn stress_loop(tid: usize, opts: &Opts, expected: Option<&State>) -> usize {
+    // The throughput varies greatly between the performance and efficiency
+    // clusters
+    if !opts.no_pin {
+        if let Err(e) = amx::affinity::pin_to_performance_core() {
+            eprintln!("[{:3}] couldn't pin the thread: {:?}", tid, e);
+        }
+    }
+
     let mut ctx = amx::AmxCtx::new().unwrap();
+    let mut mem = AmxAligned([0u8; 512]);
+    let initial = State::initial();
+    initial.write(&mut *ctx, &mut mem);
 
+    let start = Instant::now();
+    let duration = opts.duration.map(Duration::from_secs_f64);
+    let interval = Duration::from_secs_f64(opts.interval);
+    let (mut total_errors, mut errors, mut count) = (0, 0, 0);
+    let mut interval_start = start;
     loop {
-        let start = Instant::now();
-        let count = 10_000_000;
-        for _ in 0..count / 16 {
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), true);
-
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), true);
-            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), true);
-        }
-        let rate = count as f64 / start.elapsed().as_secs_f64();
-        println!("[{:3}] {:2} amxmac16s per second", tid, rate);
+        if let Some(expected) = expected {
+            initial.write(&mut *ctx, &mut mem);
+            run_batch(&mut *ctx, &opts.ops, &mut mem);
+            if State::read(&mut *ctx, &mem) != *expected {
+                errors += 1;
+            }
+        } else {
+            run_batch(&mut *ctx, &opts.ops, &mut mem);
+        }
+        count += BATCH;
+
+        let now = Instant::now();
+        let done = duration.is_some_and(|d| now - start >= d);
+        if now - interval_start < interval && !done {
+            continue;
+        }
+
+        let rate = count as f64 / (now - interval_start).as_secs_f64();
+        if opts.csv {
+            let elapsed = (now - start).as_secs_f64();
+            println!("{},{:.3},{:.0},{}", tid, elapsed, rate, errors);
+        } else if expected.is_some() {
+            println!(
+                "[{:3}] {:.0} instructions per second, {} failed checks",
+                tid, rate, errors
+            );
+        } else {
+            println!("[{:3}] {:.0} instructions per second", tid, rate);
+        }
+        total_errors += errors;
+        (errors, count, interval_start) = (0, 0, now);
+
+        if done {
+            return total_errors;
+        }
     }
 }
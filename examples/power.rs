@@ -0,0 +1,91 @@
+use amx::{prelude::*, XBytes, YBytes, ZRow};
+use clap::Parser;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+#[derive(Debug, Parser)]
+#[command(author, version, about, long_about = None)]
+struct Opts {
+    /// Number of threads to launch
+    #[arg(short, long, default_value_t = 1)]
+    num_threads: usize,
+    /// Duration of the measurement in seconds
+    #[arg(short, long, default_value_t = 5.0)]
+    seconds: f64,
+    /// Don't ask the OS to run the threads on performance cores
+    #[arg(long)]
+    no_pin: bool,
+}
+
+/// The number of `fma32` instructions issued by an iteration of
+/// `stress_loop`
+const BATCH: u64 = 16;
+
+/// The number of floating-point operations performed by an `fma32`
+/// instruction (16 × 16 multiply-adds)
+const FLOP_PER_FMA32: u64 = 2 * 16 * 16;
+
+fn main() {
+    let opts = Opts::parse();
+    let meter = amx::power::EnergyMeter::new().expect("couldn't open the energy counters");
+
+    println!(
+        "Running {} threads with AMX enabled for {} seconds",
+        opts.num_threads, opts.seconds
+    );
+
+    let (num_threads, pin) = (opts.num_threads, !opts.no_pin);
+    let stop = AtomicBool::new(false);
+    let (num_fma32s, report) = meter.measure(|| {
+        std::thread::scope(|s| {
+            let threads: Vec<_> = (0..num_threads)
+                .map(|i| {
+                    let stop = &stop;
+                    s.spawn(move || stress_loop(i, pin, stop))
+                })
+                .collect();
+            std::thread::sleep(Duration::from_secs_f64(opts.seconds));
+            stop.store(true, Ordering::Relaxed);
+            threads.into_iter().map(|t| t.join().unwrap()).sum::<u64>()
+        })
+    });
+
+    let secs = report.elapsed.as_secs_f64();
+    for (name, joules) in &report.channels {
+        println!("{:>20}: {:10.3} J {:8.3} W", name, joules, joules / secs);
+    }
+
+    let gflop = (num_fma32s * FLOP_PER_FMA32) as f64 * 1e-9;
+    println!("{:.1} GFLOP/s", gflop / secs);
+    match report.cpu_joules() {
+        Some(joules) => println!("{:.4} J/GFLOP (CPU)", joules / gflop),
+        None => println!("The CPU energy isn't reported by this system"),
+    }
+}
+
+/// Issue `fma32` instructions until `stop` is set, returning the number of
+/// instructions issued.
+#[inline(never)]
+fn stress_loop(tid: usize, pin: bool, stop: &AtomicBool) -> u64 {
+    if pin {
+        if let Err(e) = amx::affinity::pin_to_performance_core() {
+            println!("[{:3}] couldn't pin the thread: {:?}", tid, e);
+        }
+    }
+
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    let mut count = 0;
+    while !stop.load(Ordering::Relaxed) {
+        for _ in 0..1000 {
+            // Cycle through the four tiles so that consecutive instructions
+            // don't depend on each other
+            for i in 0..BATCH as usize {
+                ctx.outer_product_f32_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(i % 4), true);
+            }
+        }
+        count += 1000 * BATCH;
+    }
+    count
+}
@@ -0,0 +1,109 @@
+//! Placing threads on the CPU cores suitable for AMX
+//!
+//! Apple M1 and later have an AMX unit per CPU cluster, not per core, and the
+//! one in the efficiency (E) cluster has a much lower throughput than the ones
+//! in the performance (P) clusters. A thread's AMX throughput thus depends on
+//! which cluster the OS happens to run it on, which shows up as a confusing
+//! variance in benchmark results. Furthermore, the threads running on the
+//! same cluster share its AMX unit, so running more AMX-heavy threads than
+//! there are P clusters doesn't increase the total throughput much.
+//!
+//! macOS doesn't let applications pin a thread to a specific core. Instead,
+//! [`pin_to_performance_core`] raises the current thread's quality of service
+//! (QoS) class, which makes the scheduler strongly prefer P cores for it.
+//! Linux does, so [`pin_to_core`] binds the current thread to a specific core
+//! there, e.g., to place threads on known clusters.
+
+/// The error type for [`pin_to_performance_core`] and [`pin_to_core`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PinError {
+    /// The target system doesn't provide a way to do this.
+    Unsupported,
+    /// The OS reported the specified error number.
+    Os(i32),
+}
+
+/// Ask the OS to run the current thread on a performance core.
+///
+/// On macOS, this sets the current thread's QoS class to
+/// `QOS_CLASS_USER_INTERACTIVE`. This is a strong hint rather than a hard
+/// guarantee; the thread may still be moved to an efficiency core, e.g., when
+/// the system is under thermal pressure. Returns [`PinError::Unsupported`] on
+/// other systems.
+///
+/// ```rust
+/// if let Err(e) = amx::affinity::pin_to_performance_core() {
+///     eprintln!("couldn't pin the thread: {:?}", e);
+/// }
+/// ```
+pub fn pin_to_performance_core() -> Result<(), PinError> {
+    #[cfg(target_os = "macos")]
+    {
+        extern "C" {
+            fn pthread_set_qos_class_self_np(
+                qos_class: u32,
+                relative_priority: std::os::raw::c_int,
+            ) -> std::os::raw::c_int;
+        }
+
+        /// `QOS_CLASS_USER_INTERACTIVE` from `<sys/qos.h>`
+        const QOS_CLASS_USER_INTERACTIVE: u32 = 0x21;
+
+        // Safety: The function has no preconditions
+        match unsafe { pthread_set_qos_class_self_np(QOS_CLASS_USER_INTERACTIVE, 0) } {
+            0 => Ok(()),
+            e => Err(PinError::Os(e)),
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        Err(PinError::Unsupported)
+    }
+}
+
+/// Bind the current thread to the CPU core `core`.
+///
+/// On Linux, this sets the current thread's CPU affinity mask to the single
+/// core by `sched_setaffinity`. Returns [`PinError::Unsupported`] on other
+/// systems, including macOS, which doesn't provide a way to do this.
+///
+/// ```rust
+/// if let Err(e) = amx::affinity::pin_to_core(0) {
+///     eprintln!("couldn't pin the thread: {:?}", e);
+/// }
+/// ```
+pub fn pin_to_core(core: usize) -> Result<(), PinError> {
+    #[cfg(target_os = "linux")]
+    {
+        extern "C" {
+            fn sched_setaffinity(
+                pid: std::os::raw::c_int,
+                cpusetsize: usize,
+                mask: *const u64,
+            ) -> std::os::raw::c_int;
+        }
+
+        /// `EINVAL` from `<errno.h>`
+        const EINVAL: i32 = 22;
+
+        // `cpu_set_t` from `<sched.h>`, which holds 1024 bits
+        let mut mask = [0u64; 16];
+        *mask.get_mut(core / 64).ok_or(PinError::Os(EINVAL))? = 1 << (core % 64);
+
+        // Safety: `mask` is a valid `cpu_set_t`, and PID 0 refers to the
+        //         current thread
+        match unsafe { sched_setaffinity(0, std::mem::size_of_val(&mask), mask.as_ptr()) } {
+            0 => Ok(()),
+            _ => Err(PinError::Os(
+                std::io::Error::last_os_error().raw_os_error().unwrap_or(0),
+            )),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = core;
+        Err(PinError::Unsupported)
+    }
+}
@@ -0,0 +1,113 @@
+//! Memory buffers satisfying the alignment requirements of AMX
+use crate::{Amx, Lane, LoadStore};
+use std::{
+    mem::size_of,
+    ops::{Deref, DerefMut},
+};
+
+/// Wraps a value of type `T`, aligning it to 128-byte boundaries as required
+/// by [`Amx::load1024_aligned`] and [`Amx::store1024_aligned`].
+///
+/// For arrays of [`Lane`] types, this type provides load and store methods,
+/// which are safe because the buffer's alignment is known to be sufficient.
+/// They panic if the array is too small.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{Amx, AmxAligned, XRow};
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let src = AmxAligned([42u8; 128]);
+/// src.load1024(&mut ctx, XRow(2));
+///
+/// let mut dst = AmxAligned([0u8; 128]);
+/// dst.store1024(&mut ctx, XRow(2));
+/// assert_eq!(dst, src);
+/// assert_eq!(ctx.read_x()[128..256], [42; 128]);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C, align(128))]
+pub struct AmxAligned<T>(pub T);
+
+impl<T> AmxAligned<T> {
+    /// Unwrap the inner value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<E: Lane, const N: usize> AmxAligned<[E; N]> {
+    /// Load the first 64 bytes of `self` to the specified register row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` is smaller than 64 bytes.
+    #[inline]
+    #[track_caller]
+    pub fn load512(&self, ctx: &mut (impl Amx + ?Sized), row: impl LoadStore) {
+        assert!(size_of::<[E; N]>() >= 64, "buffer too small");
+        // Safety: `self` is a memory region of at least 64 bytes
+        unsafe { ctx.load512(self.0.as_ptr(), row) };
+    }
+
+    /// Load the first 128 bytes of `self` to the specified register row and
+    /// the next one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` is smaller than 128 bytes.
+    #[inline]
+    #[track_caller]
+    pub fn load1024(&self, ctx: &mut (impl Amx + ?Sized), row: impl LoadStore) {
+        assert!(size_of::<[E; N]>() >= 128, "buffer too small");
+        // Safety: `self` is a memory region of at least 128 bytes aligned to
+        //         128-byte boundaries
+        unsafe { ctx.load1024_aligned(self.0.as_ptr(), row) };
+    }
+
+    /// Store the specified register row to the first 64 bytes of `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` is smaller than 64 bytes.
+    #[inline]
+    #[track_caller]
+    pub fn store512(&mut self, ctx: &mut (impl Amx + ?Sized), row: impl LoadStore) {
+        assert!(size_of::<[E; N]>() >= 64, "buffer too small");
+        // Safety: `self` is a memory region of at least 64 bytes, and any bit
+        //         pattern is a valid `E`
+        unsafe { ctx.store512(self.0.as_mut_ptr(), row) };
+    }
+
+    /// Store the specified register row and the next one to the first 128
+    /// bytes of `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` is smaller than 128 bytes.
+    #[inline]
+    #[track_caller]
+    pub fn store1024(&mut self, ctx: &mut (impl Amx + ?Sized), row: impl LoadStore) {
+        assert!(size_of::<[E; N]>() >= 128, "buffer too small");
+        // Safety: `self` is a memory region of at least 128 bytes aligned to
+        //         128-byte boundaries, and any bit pattern is a valid `E`
+        unsafe { ctx.store1024_aligned(self.0.as_mut_ptr(), row) };
+    }
+}
+
+impl<T> Deref for AmxAligned<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for AmxAligned<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
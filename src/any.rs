@@ -0,0 +1,275 @@
+//! Runtime selection of the backend
+use crate::{
+    ops::AmxOps,
+    trace::{log_op, TracedOp, TracingOps},
+    AmxEmuCtx, ZRow,
+};
+#[cfg(any(doc, target_arch = "aarch64"))]
+use crate::{AmxCtx, NewAmxCtxError};
+
+/// Either the hardware AMX context or the emulator, chosen at runtime.
+///
+/// This lets code written against [`Amx`](crate::Amx) run natively where AMX
+/// is available and fall back to emulation elsewhere, without being
+/// monomorphized for each backend.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{prelude::*, AnyAmxCtx, XRow};
+/// let mut ctx = AnyAmxCtx::best_available();
+/// let mut x = [0u8; 64];
+/// unsafe {
+///     ctx.load512([42u8; 64].as_ptr(), XRow(1));
+///     ctx.store512(x.as_mut_ptr(), XRow(1));
+/// }
+/// assert_eq!(x, [42u8; 64]);
+/// ```
+pub enum AnyAmxCtx {
+    /// The hardware AMX context.
+    #[cfg(any(doc, target_arch = "aarch64"))]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(target_arch = "aarch64")))]
+    Native(AmxCtx),
+    /// The emulator. Boxed because its register state is much larger than
+    /// `AmxCtx`.
+    Emu(Box<AmxEmuCtx>),
+    /// Another `AnyAmxCtx` whose instructions are traced, constructed by
+    /// [`AmxCtxBuilder::trace`] or [`AmxCtxBuilder::trace_log`].
+    Traced(Box<TracingOps<AnyAmxCtx>>),
+}
+
+impl AnyAmxCtx {
+    /// Construct an [`AmxCtx`] if possible, falling back to [`AmxEmuCtx`]
+    /// otherwise.
+    ///
+    /// The emulator is used if AMX isn't supported (e.g., on Linux) and if
+    /// the current thread already has an `AmxCtx` (see [`AmxCtx::new`]), so
+    /// the returned context always has exclusive access to its register
+    /// contents.
+    pub fn best_available() -> Self {
+        #[cfg(target_arch = "aarch64")]
+        if let Ok(ctx) = AmxCtx::new() {
+            return Self::Native(ctx);
+        }
+        Self::Emu(Box::default())
+    }
+
+    /// Check if `self` uses the hardware.
+    #[inline]
+    pub fn is_native(&self) -> bool {
+        match self {
+            Self::Emu(_) => false,
+            Self::Traced(ctx) => ctx.inner().is_native(),
+            #[cfg(any(doc, target_arch = "aarch64"))]
+            Self::Native(_) => true,
+        }
+    }
+
+    /// Get the hardware context, looking through tracing, e.g., to call
+    /// [`AmxCtx::check_state_canary`]. Returns `None` if `self` uses the
+    /// emulator.
+    #[cfg(any(doc, target_arch = "aarch64"))]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(target_arch = "aarch64")))]
+    pub fn native_mut(&mut self) -> Option<&mut AmxCtx> {
+        match self {
+            Self::Native(ctx) => Some(ctx),
+            Self::Emu(_) => None,
+            Self::Traced(ctx) => ctx.inner_mut().native_mut(),
+        }
+    }
+}
+
+/// Composes the options of a context at construction.
+///
+/// The options apply to the hardware context ([`AmxCtx`]). Those that don't
+/// make sense for the emulator ([`state_canary`](Self::state_canary) and
+/// [`zero_on_drop`](Self::zero_on_drop)) are ignored when
+/// [`build_or_emulate`](Self::build_or_emulate) falls back to it.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{prelude::*, AmxCtxBuilder, XRow, ZRow};
+/// let mut ctx = AmxCtxBuilder::new()
+///     .state_canary(ZRow(63))
+///     .zero_on_drop(true)
+///     .trace_log()
+///     .build_or_emulate();
+/// let mut x = [0u8; 64];
+/// unsafe {
+///     ctx.load512([42u8; 64].as_ptr(), XRow(1));
+///     ctx.store512(x.as_mut_ptr(), XRow(1));
+/// }
+/// assert_eq!(x, [42u8; 64]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AmxCtxBuilder {
+    canary: Option<ZRow>,
+    zero_on_drop: bool,
+    trace: Option<fn(&TracedOp)>,
+}
+
+impl AmxCtxBuilder {
+    /// Construct an `AmxCtxBuilder` with all options disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable the canary in the `z` row `row`. See
+    /// [`AmxCtx::enable_state_canary`].
+    pub fn state_canary(mut self, row: ZRow) -> Self {
+        self.canary = Some(row);
+        self
+    }
+
+    /// Set whether the registers are zeroed when the context is dropped. See
+    /// [`AmxCtx::new_zeroing`].
+    pub fn zero_on_drop(mut self, enable: bool) -> Self {
+        self.zero_on_drop = enable;
+        self
+    }
+
+    /// Call `callback` for every instruction issued through the context. See
+    /// [`TracingOps`].
+    pub fn trace(mut self, callback: fn(&TracedOp)) -> Self {
+        self.trace = Some(callback);
+        self
+    }
+
+    /// Log every instruction issued through the context through the [`log`]
+    /// crate. See [`TracingOps::new`].
+    pub fn trace_log(self) -> Self {
+        self.trace(log_op)
+    }
+
+    /// Construct a hardware context ([`AmxCtx`]) with the options.
+    ///
+    /// Returns [`NewAmxCtxError::AlreadyActive`] if the current thread already
+    /// has an `AmxCtx` (see [`AmxCtx::new`]).
+    #[cfg(any(doc, target_arch = "aarch64"))]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(target_arch = "aarch64")))]
+    pub fn build(self) -> Result<AnyAmxCtx, NewAmxCtxError> {
+        let mut ctx = if self.zero_on_drop {
+            AmxCtx::new_zeroing()?
+        } else {
+            AmxCtx::new()?
+        };
+        if let Some(row) = self.canary {
+            ctx.enable_state_canary(row);
+        }
+        Ok(self.wrap(ctx.into()))
+    }
+
+    /// Construct a hardware context with the options if possible, falling back to
+    /// [`AmxEmuCtx`] otherwise, like [`AnyAmxCtx::best_available`].
+    pub fn build_or_emulate(self) -> AnyAmxCtx {
+        #[cfg(target_arch = "aarch64")]
+        if let Ok(ctx) = self.clone().build() {
+            return ctx;
+        }
+        self.wrap(AnyAmxCtx::Emu(Box::default()))
+    }
+
+    fn wrap(&self, ctx: AnyAmxCtx) -> AnyAmxCtx {
+        match self.trace {
+            Some(callback) => AnyAmxCtx::Traced(Box::new(TracingOps::with_callback(ctx, callback))),
+            None => ctx,
+        }
+    }
+}
+
+impl From<AmxEmuCtx> for AnyAmxCtx {
+    #[inline]
+    fn from(x: AmxEmuCtx) -> Self {
+        Self::Emu(Box::new(x))
+    }
+}
+
+#[cfg(any(doc, target_arch = "aarch64"))]
+impl From<AmxCtx> for AnyAmxCtx {
+    #[inline]
+    fn from(x: AmxCtx) -> Self {
+        Self::Native(x)
+    }
+}
+
+macro_rules! dispatch {
+    ($self:ident.$method:ident($($arg:ident),*)) => {
+        match $self {
+            #[cfg(any(doc, target_arch = "aarch64"))]
+            Self::Native(ctx) => ctx.$method($($arg),*),
+            Self::Emu(ctx) => ctx.$method($($arg),*),
+            Self::Traced(ctx) => ctx.$method($($arg),*),
+        }
+    };
+}
+
+// Safety: Just forwarding the calls
+unsafe impl AmxOps for AnyAmxCtx {
+    unsafe fn ldx(&mut self, x: u64, ptr: *mut ()) {
+        dispatch!(self.ldx(x, ptr))
+    }
+    unsafe fn ldy(&mut self, x: u64, ptr: *mut ()) {
+        dispatch!(self.ldy(x, ptr))
+    }
+    unsafe fn stx(&mut self, x: u64, ptr: *mut ()) {
+        dispatch!(self.stx(x, ptr))
+    }
+    unsafe fn sty(&mut self, x: u64, ptr: *mut ()) {
+        dispatch!(self.sty(x, ptr))
+    }
+    unsafe fn ldz(&mut self, x: u64, ptr: *mut ()) {
+        dispatch!(self.ldz(x, ptr))
+    }
+    unsafe fn stz(&mut self, x: u64, ptr: *mut ()) {
+        dispatch!(self.stz(x, ptr))
+    }
+    unsafe fn ldzi(&mut self, x: u64, ptr: *mut ()) {
+        dispatch!(self.ldzi(x, ptr))
+    }
+    unsafe fn stzi(&mut self, x: u64, ptr: *mut ()) {
+        dispatch!(self.stzi(x, ptr))
+    }
+    fn extrx(&mut self, x: u64) {
+        dispatch!(self.extrx(x))
+    }
+    fn extry(&mut self, x: u64) {
+        dispatch!(self.extry(x))
+    }
+    fn fma64(&mut self, x: u64) {
+        dispatch!(self.fma64(x))
+    }
+    fn fms64(&mut self, x: u64) {
+        dispatch!(self.fms64(x))
+    }
+    fn fma32(&mut self, x: u64) {
+        dispatch!(self.fma32(x))
+    }
+    fn fms32(&mut self, x: u64) {
+        dispatch!(self.fms32(x))
+    }
+    fn mac16(&mut self, x: u64) {
+        dispatch!(self.mac16(x))
+    }
+    fn fma16(&mut self, x: u64) {
+        dispatch!(self.fma16(x))
+    }
+    fn fms16(&mut self, x: u64) {
+        dispatch!(self.fms16(x))
+    }
+    fn vecint(&mut self, x: u64) {
+        dispatch!(self.vecint(x))
+    }
+    fn vecfp(&mut self, x: u64) {
+        dispatch!(self.vecfp(x))
+    }
+    fn matint(&mut self, x: u64) {
+        dispatch!(self.matint(x))
+    }
+    fn matfp(&mut self, x: u64) {
+        dispatch!(self.matfp(x))
+    }
+    fn genlut(&mut self, x: u64) {
+        dispatch!(self.genlut(x))
+    }
+}
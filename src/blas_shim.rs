@@ -0,0 +1,266 @@
+//! CBLAS-compatible GEMM routines
+//!
+//! This module exports `cblas_sgemm` and `cblas_dgemm` with the C ABI and
+//! unmangled names, so a program linked against CBLAS can be linked against
+//! this crate instead (e.g., as a `staticlib`) to compare the performance of
+//! [`kernels::matmul_f32`](crate::kernels::matmul_f32) and
+//! [`kernels::matmul_f64`](crate::kernels::matmul_f64) with other BLAS
+//! implementations.
+//!
+//! Each call constructs its own context by [`AnyAmxCtx::best_available`], so
+//! the emulator is used where AMX isn't available. The operands are packed
+//! into temporary row-major buffers regardless of their layout.
+use crate::{kernels::backend::BackendLane, AnyAmxCtx};
+use std::{
+    ops::{Add, Mul},
+    os::raw::c_int,
+};
+
+/// `CblasRowMajor`
+pub const CBLAS_ROW_MAJOR: c_int = 101;
+/// `CblasColMajor`
+pub const CBLAS_COL_MAJOR: c_int = 102;
+/// `CblasNoTrans`
+pub const CBLAS_NO_TRANS: c_int = 111;
+/// `CblasTrans`
+pub const CBLAS_TRANS: c_int = 112;
+/// `CblasConjTrans`, which is the same as `CblasTrans` for real matrices
+pub const CBLAS_CONJ_TRANS: c_int = 113;
+
+/// Calculate `C = alpha * op(A) * op(B) + beta * C`, where `op(A)`, `op(B)`,
+/// and `C` are `f32` matrices of size `M × K`, `K × N`, and `M × N`,
+/// respectively.
+///
+/// As in the reference implementation, invalid parameters are reported to the
+/// standard error, and the call returns without doing anything.
+///
+/// # Safety
+///
+/// `A`, `B`, and `C` must point to matrices of the sizes and leading
+/// dimensions specified by the other parameters.
+#[no_mangle]
+#[allow(non_snake_case, clippy::too_many_arguments)]
+pub unsafe extern "C" fn cblas_sgemm(
+    Order: c_int,
+    TransA: c_int,
+    TransB: c_int,
+    M: c_int,
+    N: c_int,
+    K: c_int,
+    alpha: f32,
+    A: *const f32,
+    lda: c_int,
+    B: *const f32,
+    ldb: c_int,
+    beta: f32,
+    C: *mut f32,
+    ldc: c_int,
+) {
+    gemm(
+        "cblas_sgemm",
+        Order,
+        TransA,
+        TransB,
+        M,
+        N,
+        K,
+        alpha,
+        A,
+        lda,
+        B,
+        ldb,
+        beta,
+        C,
+        ldc,
+    );
+}
+
+/// The `f64` version of [`cblas_sgemm`].
+///
+/// # Safety
+///
+/// See [`cblas_sgemm`].
+#[no_mangle]
+#[allow(non_snake_case, clippy::too_many_arguments)]
+pub unsafe extern "C" fn cblas_dgemm(
+    Order: c_int,
+    TransA: c_int,
+    TransB: c_int,
+    M: c_int,
+    N: c_int,
+    K: c_int,
+    alpha: f64,
+    A: *const f64,
+    lda: c_int,
+    B: *const f64,
+    ldb: c_int,
+    beta: f64,
+    C: *mut f64,
+    ldc: c_int,
+) {
+    gemm(
+        "cblas_dgemm",
+        Order,
+        TransA,
+        TransB,
+        M,
+        N,
+        K,
+        alpha,
+        A,
+        lda,
+        B,
+        ldb,
+        beta,
+        C,
+        ldc,
+    );
+}
+
+/// A dense matrix in memory described by CBLAS parameters.
+#[derive(Clone, Copy)]
+struct MatrixRef<T> {
+    ptr: *const T,
+    ld: usize,
+    /// The element `(i, j)` is at `i * ld + j` if `true`, `i + j * ld`
+    /// otherwise.
+    row_major: bool,
+}
+
+impl<T: Copy> MatrixRef<T> {
+    /// Get the pointer to the element `(i, j)`.
+    #[inline]
+    fn at(self, i: usize, j: usize) -> *const T {
+        let offset = if self.row_major {
+            i * self.ld + j
+        } else {
+            i + j * self.ld
+        };
+        self.ptr.wrapping_add(offset)
+    }
+
+    /// Copy the `rows × cols` matrix to a row-major `Vec`.
+    ///
+    /// # Safety
+    ///
+    /// The elements must be readable.
+    unsafe fn to_row_major(self, rows: usize, cols: usize) -> Vec<T> {
+        (0..rows)
+            .flat_map(|i| (0..cols).map(move |j| *self.at(i, j)))
+            .collect()
+    }
+}
+
+/// Check the parameters of `cblas_?gemm`. Returns the 1-based position of the
+/// first invalid parameter, following the reference implementation.
+fn check_gemm_params(
+    order: c_int,
+    trans_a: c_int,
+    trans_b: c_int,
+    [m, n, k]: [c_int; 3],
+    [lda, ldb, ldc]: [c_int; 3],
+) -> Result<(), usize> {
+    let is_trans = |trans| match trans {
+        CBLAS_NO_TRANS => Some(false),
+        CBLAS_TRANS | CBLAS_CONJ_TRANS => Some(true),
+        _ => None,
+    };
+    let row_major = match order {
+        CBLAS_ROW_MAJOR => true,
+        CBLAS_COL_MAJOR => false,
+        _ => return Err(1),
+    };
+    let trans_a = is_trans(trans_a).ok_or(2usize)?;
+    let trans_b = is_trans(trans_b).ok_or(3usize)?;
+    for (i, &dim) in [m, n, k].iter().enumerate() {
+        if dim < 0 {
+            return Err(4 + i);
+        }
+    }
+
+    // The minimum leading dimension of a matrix stored as `rows × cols`
+    // in the memory order
+    let min_ld = |rows: c_int, cols: c_int| if row_major { cols } else { rows }.max(1);
+    let (a_rows, a_cols) = if trans_a { (k, m) } else { (m, k) };
+    let (b_rows, b_cols) = if trans_b { (n, k) } else { (k, n) };
+    if lda < min_ld(a_rows, a_cols) {
+        Err(9)
+    } else if ldb < min_ld(b_rows, b_cols) {
+        Err(11)
+    } else if ldc < min_ld(m, n) {
+        Err(14)
+    } else {
+        Ok(())
+    }
+}
+
+/// The implementation of `cblas_?gemm`.
+#[allow(clippy::too_many_arguments)]
+unsafe fn gemm<T>(
+    name: &str,
+    order: c_int,
+    trans_a: c_int,
+    trans_b: c_int,
+    m: c_int,
+    n: c_int,
+    k: c_int,
+    alpha: T,
+    a: *const T,
+    lda: c_int,
+    b: *const T,
+    ldb: c_int,
+    beta: T,
+    c: *mut T,
+    ldc: c_int,
+) where
+    T: BackendLane + Add<Output = T> + Mul<Output = T> + PartialEq,
+{
+    if let Err(pos) = check_gemm_params(order, trans_a, trans_b, [m, n, k], [lda, ldb, ldc]) {
+        eprintln!("Parameter {} to routine {} was incorrect", pos, name);
+        return;
+    }
+
+    let [m, n, k, lda, ldb, ldc] = [m, n, k, lda, ldb, ldc].map(|x| x as usize);
+    let row_major = order == CBLAS_ROW_MAJOR;
+    let zero = T::default();
+
+    // A transposed row-major matrix is a column-major matrix and vice versa
+    let a = MatrixRef {
+        ptr: a,
+        ld: lda,
+        row_major: row_major == (trans_a == CBLAS_NO_TRANS),
+    };
+    let b = MatrixRef {
+        ptr: b,
+        ld: ldb,
+        row_major: row_major == (trans_b == CBLAS_NO_TRANS),
+    };
+    let c = MatrixRef {
+        ptr: c as *const T,
+        ld: ldc,
+        row_major,
+    };
+
+    let (a, b) = (a.to_row_major(m, k), b.to_row_major(k, n));
+    let mut product = vec![zero; m * n];
+    crate::kernels::matmul(
+        &mut AnyAmxCtx::best_available(),
+        m,
+        n,
+        k,
+        &a,
+        &b,
+        &mut product,
+    );
+
+    for (i, product_row) in product.chunks_exact(n.max(1)).enumerate() {
+        for (j, &p) in product_row.iter().enumerate() {
+            let c = c.at(i, j) as *mut T;
+            *c = if beta == zero {
+                alpha * p
+            } else {
+                alpha * p + beta * *c
+            };
+        }
+    }
+}
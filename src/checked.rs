@@ -0,0 +1,113 @@
+//! Operand validation, enabled by the `checked` feature or
+//! [`EmuStrictness::Strict`](crate::EmuStrictness::Strict)
+use crate::ops::opcode::*;
+
+/// Get a mask of `len` bits starting from bit `lo`.
+const fn bits(lo: u32, len: u32) -> u64 {
+    ((1u64 << len) - 1) << lo
+}
+
+/// The fields of a load/store operand, excluding the pointer.
+const MEM_FIELDS: u64 = bits(56, 6) | bits(62, 1);
+
+/// The fields of an `fma*` or `mac16` operand.
+const FMA_FIELDS: u64 = bits(0, 9) // y offset
+    | bits(10, 9) // x offset
+    | bits(20, 6) // z row
+    | bits(27, 3) // skip z, x, y
+    | bits(32, 7) // y lane mask
+    | bits(41, 7) // x lane mask
+    | bits(63, 1); // vector mode
+
+/// Get the operand bits of the AMX instruction `op` that belong to a known
+/// field. Returns `None` if the operand layout of `op` isn't modeled by this
+/// crate (`vecint`, `vecfp`, and `matfp`) or `op` isn't a known instruction.
+///
+/// For load and store instructions, the lower 56 bits (the pointer) are
+/// included.
+pub(crate) fn known_fields(op: u8) -> Option<u64> {
+    Some(match op {
+        // Bit 61 is a hint (`MemHint`)
+        LDX | LDY | STX | STY | LDZ | STZ => MEM_FIELDS | bits(0, 56),
+        // Whether bit 62 selects a 128-byte variant like for `ldz` hasn't been
+        // verified on the hardware (see `characterize_interleaved_1024`), so
+        // it's allowed
+        LDZI | STZI => MEM_FIELDS | bits(0, 56),
+        // Bit 26 selects the column variant, whose lane width is in bits 28–29
+        EXTRX => bits(16, 3) | bits(20, 7) | bits(28, 2),
+        EXTRY => bits(6, 3) | bits(16, 3) | bits(20, 7) | bits(27, 1) | bits(28, 2),
+        FMA64 | FMS64 | FMA32 | FMS32 => FMA_FIELDS,
+        // Bit 62 selects the widening variants
+        MAC16 | FMA16 | FMS16 => FMA_FIELDS | bits(62, 1),
+        MATINT => {
+            bits(0, 9) // y offset
+                | bits(10, 9) // x offset
+                | bits(20, 6) // z row
+                | bits(27, 3) // skip z, x, y
+                | bits(32, 7) // y lane mask
+                | bits(42, 4) // lane width mode
+                | bits(47, 6) // ALU mode
+                | bits(56, 2) // round, saturate
+                | bits(58, 5) // shift amount
+        }
+        GENLUT => {
+            bits(0, 9) // input offset
+                | bits(10, 1) // input in y
+                | bits(20, 7) // output row, output in y/z
+                | bits(53, 4) // mode
+                | bits(60, 3) // table row
+        }
+        _ => return None,
+    })
+}
+
+/// Validate the operand `x` of the AMX instruction `op`, panicking if it has
+/// any reserved bits set or any of its fields is out of range.
+///
+/// For load and store instructions, the lower 56 bits (the pointer) are
+/// ignored. The operands of `vecint`, `vecfp`, and `matfp` aren't validated
+/// because their layouts aren't modeled by this crate yet.
+#[track_caller]
+pub(crate) fn validate(op: u8, x: u64) {
+    match known_fields(op) {
+        Some(fields) => check_reserved(op, x, fields),
+        None if matches!(op, VECINT | VECFP | MATFP) => {}
+        None => unreachable!(),
+    }
+    match op {
+        LDX | LDY | STX | STY => {
+            check_range(op, "register row", (x >> 56) & 0x1f, 8);
+        }
+        GENLUT if x & (1 << 26) == 0 => {
+            // The output is in `x` or `y`. Bit 25 selects `y`.
+            check_range(op, "output row", (x >> 20) & 0x1f, 8);
+        }
+        _ => {}
+    }
+}
+
+#[track_caller]
+fn check_reserved(op: u8, x: u64, fields: u64) {
+    let reserved = x & !fields;
+    if reserved != 0 {
+        panic!(
+            "`{}` operand {:#018x} has reserved bits set: {:#018x}",
+            mnemonic(op),
+            x,
+            reserved
+        );
+    }
+}
+
+#[track_caller]
+fn check_range(op: u8, field: &str, value: u64, end: u64) {
+    if value >= end {
+        panic!(
+            "`{}` operand's {} ({}) is out of range 0..{}",
+            mnemonic(op),
+            field,
+            value,
+            end
+        );
+    }
+}
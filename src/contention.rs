@@ -0,0 +1,250 @@
+//! Measuring how threads contend for the shared AMX units
+//!
+//! The cores of a CPU cluster share a single AMX unit (see [`affinity`]), so
+//! the total throughput of AMX-heavy threads stops growing once the units
+//! they're placed on are saturated.
+//! [`ContentionBench`] runs a set of threads issuing independent outer
+//! products, each placed as specified, and reports the throughput of every
+//! thread. Comparing the reports for increasing thread counts (see
+//! [`ContentionBench::sweep`]) shows how many threads per cluster actually
+//! pay off on the current machine.
+//!
+//! # Example
+//!
+//! ```rust
+//! use amx::contention::{ContentionBench, ContentionOp, Placement};
+//! use std::time::Duration;
+//! let bench = ContentionBench::new(ContentionOp::Fma32).duration(Duration::from_millis(100));
+//! for report in bench.sweep(4, Placement::Performance) {
+//!     println!(
+//!         "{} threads: {:.1} GFLOP/s",
+//!         report.threads.len(),
+//!         report.gops()
+//!     );
+//! }
+//! ```
+use crate::{
+    affinity::{self, PinError},
+    Amx, AmxCtx, XBytes, YBytes, ZRow,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Barrier,
+    },
+    time::{Duration, Instant},
+};
+
+/// The instruction issued by the threads of [`ContentionBench`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ContentionOp {
+    /// `mac16`, the outer product of 16-bit integers
+    Mac16,
+    /// `fma32`, the outer product of `f32`s
+    Fma32,
+    /// `fma64`, the outer product of `f64`s
+    Fma64,
+}
+
+impl ContentionOp {
+    /// Get the number of arithmetic operations performed by an instruction,
+    /// counting a multiply-add as two.
+    pub const fn ops_per_instruction(self) -> u64 {
+        match self {
+            Self::Mac16 => 2 * 32 * 32,
+            Self::Fma32 => 2 * 16 * 16,
+            Self::Fma64 => 2 * 8 * 8,
+        }
+    }
+}
+
+/// Specifies where a thread of [`ContentionBench`] runs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Placement {
+    /// Let the OS decide.
+    Any,
+    /// Ask the OS to run the thread on a performance core. See
+    /// [`affinity::pin_to_performance_core`].
+    Performance,
+    /// Bind the thread to the specified CPU core. See
+    /// [`affinity::pin_to_core`].
+    Core(usize),
+}
+
+/// Measures the AMX throughput of concurrently running threads.
+///
+/// The threads start at the same time, issue the instruction
+/// [`ContentionOp`] on their own `z` tiles for [`duration`](Self::duration),
+/// and stop at the same time.
+#[derive(Debug, Clone)]
+pub struct ContentionBench {
+    op: ContentionOp,
+    duration: Duration,
+    threads: Vec<Placement>,
+}
+
+/// The result of [`ContentionBench::run`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentionReport {
+    /// The instruction issued by the threads.
+    pub op: ContentionOp,
+    /// The wall-clock time taken by the measurement.
+    pub elapsed: Duration,
+    /// The result of each thread, in the order they were added.
+    pub threads: Vec<ThreadReport>,
+}
+
+/// The result of a thread in [`ContentionReport`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreadReport {
+    /// The requested placement of the thread.
+    pub placement: Placement,
+    /// The result of applying `placement`. The thread runs regardless of
+    /// whether it succeeded.
+    pub pin_result: Result<(), PinError>,
+    /// The number of instructions issued by the thread.
+    pub instructions: u64,
+    /// The time the thread spent issuing instructions.
+    pub elapsed: Duration,
+}
+
+/// The number of instructions issued between two checks of the stop flag.
+const BATCH: usize = 256;
+
+impl ContentionBench {
+    /// Construct a `ContentionBench` issuing `op` for a second, with no
+    /// threads.
+    pub fn new(op: ContentionOp) -> Self {
+        Self {
+            op,
+            duration: Duration::from_secs(1),
+            threads: Vec::new(),
+        }
+    }
+
+    /// Set how long the threads run for.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Add a thread placed as specified by `placement`.
+    pub fn thread(mut self, placement: Placement) -> Self {
+        self.threads.push(placement);
+        self
+    }
+
+    /// Add `count` threads placed as specified by `placement`.
+    pub fn threads(mut self, count: usize, placement: Placement) -> Self {
+        self.threads.extend(std::iter::repeat_n(placement, count));
+        self
+    }
+
+    /// Run the threads and measure their throughput.
+    ///
+    /// The calling thread only waits for the others, so it doesn't need to
+    /// (and shouldn't) have an [`AmxCtx`].
+    pub fn run(&self) -> ContentionReport {
+        let barrier = Barrier::new(self.threads.len() + 1);
+        let stop = AtomicBool::new(false);
+        let (op, barrier, stop) = (self.op, &barrier, &stop);
+
+        let (elapsed, threads) = std::thread::scope(|s| {
+            let handles: Vec<_> = self
+                .threads
+                .iter()
+                .map(|&placement| {
+                    s.spawn(move || {
+                        let pin_result = match placement {
+                            Placement::Any => Ok(()),
+                            Placement::Performance => affinity::pin_to_performance_core(),
+                            Placement::Core(core) => affinity::pin_to_core(core),
+                        };
+                        let mut ctx = AmxCtx::new().expect("couldn't enable AMX");
+                        barrier.wait();
+                        let (instructions, elapsed) = stress_loop(&mut ctx, op, stop);
+                        ThreadReport {
+                            placement,
+                            pin_result,
+                            instructions,
+                            elapsed,
+                        }
+                    })
+                })
+                .collect();
+
+            barrier.wait();
+            let start = Instant::now();
+            std::thread::sleep(self.duration);
+            stop.store(true, Ordering::Relaxed);
+            let threads: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+            (start.elapsed(), threads)
+        });
+
+        ContentionReport {
+            op,
+            elapsed,
+            threads,
+        }
+    }
+
+    /// Run `self` with `1..=max_threads` additional threads placed as
+    /// specified by `placement`, returning a report for each thread count.
+    pub fn sweep(&self, max_threads: usize, placement: Placement) -> Vec<ContentionReport> {
+        (1..=max_threads)
+            .map(|count| self.clone().threads(count, placement).run())
+            .collect()
+    }
+}
+
+impl ContentionReport {
+    /// Get the total number of instructions issued per second by all
+    /// threads.
+    pub fn instructions_per_second(&self) -> f64 {
+        self.threads
+            .iter()
+            .map(ThreadReport::instructions_per_second)
+            .sum()
+    }
+
+    /// Get the total throughput of all threads, measured in billions of
+    /// arithmetic operations per second.
+    pub fn gops(&self) -> f64 {
+        self.instructions_per_second() * self.op.ops_per_instruction() as f64 * 1e-9
+    }
+}
+
+impl ThreadReport {
+    /// Get the number of instructions issued per second by the thread.
+    pub fn instructions_per_second(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Issue `op` until `stop` is set, returning the number of instructions
+/// issued and the time taken.
+#[inline(never)]
+fn stress_loop(ctx: &mut AmxCtx, op: ContentionOp, stop: &AtomicBool) -> (u64, Duration) {
+    let start = Instant::now();
+    let mut count = 0;
+    while !stop.load(Ordering::Relaxed) {
+        // Cycle through the tiles so that consecutive instructions don't
+        // depend on each other
+        for i in 0..BATCH {
+            let (x, y) = (Some(XBytes(0)), Some(YBytes(0)));
+            match op {
+                ContentionOp::Mac16 => {
+                    ctx.outer_product_i16_xy_to_z(x, y, ZRow(i % 2), true);
+                }
+                ContentionOp::Fma32 => {
+                    ctx.outer_product_f32_xy_to_z(x, y, ZRow(i % 4), true);
+                }
+                ContentionOp::Fma64 => {
+                    ctx.outer_product_f64_xy_to_z(x, y, ZRow(i % 8), true);
+                }
+            }
+        }
+        count += BATCH as u64;
+    }
+    (count, start.elapsed())
+}
@@ -0,0 +1,71 @@
+//! Disassembling AMX instruction words
+use crate::ops::opcode::{mnemonic, GENLUT, SET_CLR};
+use std::fmt;
+
+/// The bits shared by all AMX instruction words.
+const INST_BASE: u32 = 0x0020_1000;
+
+/// A decoded AMX instruction word. See [`disasm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AmxInst {
+    /// The operation number, e.g., `0` for `ldx`.
+    pub opcode: u8,
+    /// The 5-bit operand field. For `set` and `clr` (operation number `17`),
+    /// this is an immediate (`0` and `1`, respectively). Otherwise, this is the
+    /// number of the general-purpose register holding the operand.
+    pub operand: u8,
+}
+
+impl AmxInst {
+    /// Get the mnemonic of the instruction, e.g., `"ldx"`.
+    pub fn mnemonic(&self) -> &'static str {
+        match (self.opcode, self.operand) {
+            (SET_CLR, 0) => "set",
+            (SET_CLR, _) => "clr",
+            (opcode, _) => mnemonic(opcode),
+        }
+    }
+
+    /// Encode the instruction back into an instruction word.
+    pub fn encode(&self) -> u32 {
+        INST_BASE | ((self.opcode as u32) << 5) | self.operand as u32
+    }
+}
+
+impl fmt::Display for AmxInst {
+    /// Format the instruction in the syntax of Apple's compiler intrinsics,
+    /// e.g., `ldx x3` or `set`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.mnemonic())?;
+        if self.opcode != SET_CLR {
+            write!(f, " x{}", self.operand)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode an AMX instruction word. Returns `None` if `word` isn't one.
+///
+/// # Example
+///
+/// ```rust
+/// let inst = amx::disasm(0x00201185).unwrap();
+/// assert_eq!(inst, amx::AmxInst { opcode: 12, operand: 5 });
+/// assert_eq!(inst.to_string(), "fma32 x5");
+/// assert_eq!(amx::disasm(0x00201220).unwrap().to_string(), "set");
+/// assert_eq!(amx::disasm(0xd503201f), None); // `nop`
+/// ```
+pub fn disasm(word: u32) -> Option<AmxInst> {
+    if word & !0x3ff != INST_BASE {
+        return None;
+    }
+    let inst = AmxInst {
+        opcode: ((word >> 5) & 0x1f) as u8,
+        operand: (word & 0x1f) as u8,
+    };
+    match inst.opcode {
+        SET_CLR if inst.operand > 1 => None,
+        opcode if opcode > GENLUT => None,
+        _ => Some(inst),
+    }
+}
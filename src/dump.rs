@@ -0,0 +1,66 @@
+//! Pretty-printing the register state
+use std::{convert::TryInto, fmt::Write};
+
+/// Specifies how [`Amx::dump`](crate::Amx::dump) formats register rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DumpFormat {
+    /// 64 bytes in hexadecimal.
+    Hex,
+    /// 32 lanes of `i16`.
+    I16,
+    /// 16 lanes of `f32`.
+    F32,
+    /// 8 lanes of `f64`.
+    F64,
+}
+
+impl DumpFormat {
+    /// Format the lanes of a 64-byte register row.
+    fn lanes(self, row: &[u8]) -> Vec<String> {
+        match self {
+            Self::Hex => row.iter().map(|b| format!("{:02x}", b)).collect(),
+            Self::I16 => row
+                .chunks_exact(2)
+                .map(|c| i16::from_le_bytes(c.try_into().unwrap()).to_string())
+                .collect(),
+            Self::F32 => row
+                .chunks_exact(4)
+                .map(|c| format!("{:?}", f32::from_le_bytes(c.try_into().unwrap())))
+                .collect(),
+            Self::F64 => row
+                .chunks_exact(8)
+                .map(|c| format!("{:?}", f64::from_le_bytes(c.try_into().unwrap())))
+                .collect(),
+        }
+    }
+}
+
+/// Format the register sets `x`, `y`, and `z` as a grid with one register row
+/// per line.
+pub(crate) fn dump(x: &[u8; 512], y: &[u8; 512], z: &[u8; 4096], format: DumpFormat) -> String {
+    let rows: Vec<(String, Vec<String>)> = [("x", &x[..]), ("y", &y[..]), ("z", &z[..])]
+        .iter()
+        .flat_map(|&(name, regs)| {
+            regs.chunks_exact(64)
+                .enumerate()
+                .map(move |(i, row)| (format!("{}{}", name, i), format.lanes(row)))
+        })
+        .collect();
+
+    // Align the columns
+    let width = rows
+        .iter()
+        .flat_map(|(_, lanes)| lanes.iter().map(|lane| lane.len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for (label, lanes) in &rows {
+        write!(out, "{:<3} |", label).unwrap();
+        for lane in lanes {
+            write!(out, " {:>1$}", lane, width).unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,130 @@
+//! Element types of outer products
+use crate::{Amx, Lane, LaneMask, XBytes, YBytes, ZRow, ZTile16, ZTile32, ZTile64, ZWriteMode};
+
+/// The trait for element types supported by [`Amx::outer_product`]. This
+/// trait is sealed.
+///
+/// | Type                         | Instruction     | Result      |
+/// | ---------------------------- | --------------- | ----------- |
+/// | `i16`, `u16`                 | `mac16`         | [`ZTile16`] |
+/// | `half::f16` (`half` feature) | `fma16`/`fms16` | [`ZTile16`] |
+/// | `f32`                        | `fma32`/`fms32` | [`ZTile32`] |
+/// | `f64`                        | `fma64`/`fms64` | [`ZTile64`] |
+///
+/// `mac16` can't subtract, so `i16` and `u16` don't support
+/// [`ZWriteMode::Subtract`]. `mac16` computes wrapping products, whose bit patterns don't depend on the
+/// signedness, so `u16` is handled in the same way as `i16`. 8-bit integers
+/// are accumulated into 32-bit integers in a different way; see
+/// [`Amx::outer_product_i8_to_i32`] and [`Amx::outer_product_u8_to_i32`].
+pub trait AmxElement: Lane {
+    /// The tile of `z` holding the result.
+    type Tile;
+    /// How the result is laid out in `z`.
+    const Z_LAYOUT: ZLayout;
+
+    /// The implementation of [`Amx::outer_product_masked`].
+    #[doc(hidden)]
+    fn outer_product_masked(
+        ctx: &mut (impl Amx + ?Sized),
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        mode: ZWriteMode,
+        x_mask: LaneMask,
+        y_mask: LaneMask,
+    ) -> Self::Tile;
+}
+
+/// Describes how the result matrix of an outer product is laid out in `z`.
+///
+/// The element `(i, j)` (the `i`-th row and the `j`-th column) of the result
+/// of an outer product issued with the `z` row index `z_index` is stored in
+/// the `z` row `z_index % row_stride + i * row_stride + j % interleave`, at
+/// the byte offset `j / interleave * element_bytes`. [`locate`](Self::locate)
+/// calculates this.
+///
+/// ```rust
+/// use amx::{AmxElement, ZRow};
+/// let layout = f32::Z_LAYOUT;
+/// assert_eq!((layout.rows(), layout.columns()), (16, 16));
+/// assert_eq!(layout.locate(ZRow(1), 2, 3), (ZRow(9), 12));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ZLayout {
+    /// The distance between the first `z` rows holding two consecutive
+    /// result rows.
+    pub row_stride: usize,
+    /// The number of consecutive `z` rows a result row is distributed over,
+    /// column by column.
+    pub interleave: usize,
+    /// The size of a result element, measured in bytes.
+    pub element_bytes: usize,
+}
+
+impl ZLayout {
+    /// Get the number of rows in the result matrix.
+    #[inline]
+    pub const fn rows(self) -> usize {
+        64 / self.row_stride
+    }
+
+    /// Get the number of columns in the result matrix.
+    #[inline]
+    pub const fn columns(self) -> usize {
+        64 / self.element_bytes * self.interleave
+    }
+
+    /// Get the `z` row and the byte offset in it holding the element
+    /// `(row, column)` of the result of an outer product issued with the `z`
+    /// row index `z_index`.
+    ///
+    /// `row` and `column` must be in range `0..self.rows()` and
+    /// `0..self.columns()`, respectively.
+    #[inline]
+    pub fn locate(self, ZRow(z_index): ZRow, row: usize, column: usize) -> (ZRow, usize) {
+        debug_assert!(row < self.rows() && column < self.columns());
+        (
+            ZRow(z_index % self.row_stride + row * self.row_stride + column % self.interleave),
+            column / self.interleave * self.element_bytes,
+        )
+    }
+}
+
+macro_rules! impl_element {
+    ($($ty:ty => ($tile:ty, $method:ident)),*$(,)*) => {$(
+        impl AmxElement for $ty {
+            type Tile = $tile;
+            const Z_LAYOUT: ZLayout = ZLayout {
+                row_stride: 64 / <$tile>::LEN,
+                interleave: 1,
+                element_bytes: std::mem::size_of::<$ty>(),
+            };
+
+            #[inline(always)]
+            #[track_caller]
+            fn outer_product_masked(
+                ctx: &mut (impl Amx + ?Sized),
+                x_offset_bytes: Option<XBytes>,
+                y_offset_bytes: Option<YBytes>,
+                z_index: ZRow,
+                mode: ZWriteMode,
+                x_mask: LaneMask,
+                y_mask: LaneMask,
+            ) -> Self::Tile {
+                ctx.$method(x_offset_bytes, y_offset_bytes, z_index, mode, x_mask, y_mask)
+            }
+        }
+    )*};
+}
+
+impl_element! {
+    i16 => (ZTile16, outer_product_i16_xy_to_z_masked),
+    u16 => (ZTile16, outer_product_i16_xy_to_z_masked),
+    f32 => (ZTile32, outer_product_f32_xy_to_z_masked),
+    f64 => (ZTile64, outer_product_f64_xy_to_z_masked),
+}
+
+#[cfg(feature = "half")]
+impl_element! {
+    half::f16 => (ZTile16, outer_product_f16_xy_to_z_masked),
+}
@@ -1,10 +1,15 @@
 //! AMX emulation
-use crate::ops::AmxOps;
+use crate::{
+    lane_mask::{LaneMask, X_MASK_POS, Y_MASK_POS},
+    ops::{opcode::*, AmxOps},
+};
+use std::convert::TryInto;
 
 /// An emulated AMX context.
 #[derive(Default, Debug, Copy, Clone)]
 pub struct AmxEmuCtx {
     st: AmxSt,
+    strictness: EmuStrictness,
 }
 
 impl AmxEmuCtx {
@@ -12,6 +17,78 @@ impl AmxEmuCtx {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Return the registers to the all-zero state. The
+    /// [strictness](Self::strictness) is left unchanged.
+    pub fn reset(&mut self) {
+        self.st = AmxSt::default();
+    }
+
+    /// Construct a brand new `AmxEmuCtx` with the specified handling of
+    /// unknown operand bits.
+    pub fn with_strictness(strictness: EmuStrictness) -> Self {
+        Self {
+            strictness,
+            ..Self::default()
+        }
+    }
+
+    /// Get how operands with unknown bits set are handled.
+    pub fn strictness(&self) -> EmuStrictness {
+        self.strictness
+    }
+
+    /// Change how operands with unknown bits set are handled. The register
+    /// state is left intact.
+    pub fn set_strictness(&mut self, strictness: EmuStrictness) {
+        self.strictness = strictness;
+    }
+
+    /// Validate the operand of an instruction if the context is strict.
+    #[inline]
+    #[track_caller]
+    fn check(&self, op: u8, x: u64) {
+        if self.strictness == EmuStrictness::Strict {
+            crate::checked::validate(op, x);
+        }
+    }
+}
+
+/// Specifies how [`AmxEmuCtx`] handles an operand with reserved or unknown
+/// bits set, or with a field out of range.
+///
+/// The default is [`Strict`](Self::Strict) if the `checked` feature is
+/// enabled and [`Permissive`](Self::Permissive) otherwise.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{prelude::*, AmxEmuCtx, EmuStrictness};
+/// let mut ctx = AmxEmuCtx::with_strictness(EmuStrictness::Permissive);
+/// // Bit 9 lies between the `y` and `x` offsets
+/// ctx.fma32(1 << 9);
+///
+/// ctx.set_strictness(EmuStrictness::Strict);
+/// let result = std::panic::catch_unwind(move || ctx.fma32(1 << 9));
+/// assert!(result.is_err());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EmuStrictness {
+    /// Ignore the unknown bits, as the hardware is observed to do for most of
+    /// them.
+    Permissive,
+    /// Panic, which helps catch bugs in the code generating the operands.
+    Strict,
+}
+
+impl Default for EmuStrictness {
+    fn default() -> Self {
+        if cfg!(feature = "checked") {
+            Self::Strict
+        } else {
+            Self::Permissive
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -39,92 +116,673 @@ impl Default for AmxSt {
     }
 }
 
+/// The size of a register row, measured in bytes.
+const ROW_SIZE: usize = 64;
+
+impl AmxSt {
+    /// Get a mutable reference to the `x` or `y` register set.
+    #[inline]
+    fn xy_mut(&mut self, is_y: bool) -> &mut [u8; 512] {
+        if is_y {
+            &mut self.y
+        } else {
+            &mut self.x
+        }
+    }
+
+    /// Read `N` bytes from the `x` or `y` register set starting from the byte
+    /// offset `offset`, wrapping around at the end of the register set.
+    fn read_xy_bytes<const N: usize>(&self, is_y: bool, offset: usize) -> [u8; N] {
+        let regs = if is_y { &self.y } else { &self.x };
+        let mut out = [0u8; N];
+        for (i, out) in out.iter_mut().enumerate() {
+            *out = regs[(offset + i) % regs.len()];
+        }
+        out
+    }
+
+    /// Load `num_rows` rows from memory to the register set `regs`, starting
+    /// from the row `row` and wrapping around at the end of the register set.
+    unsafe fn load_rows(regs: &mut [u8], row: usize, num_rows: usize, ptr: *const u8) {
+        let regs_num_rows = regs.len() / ROW_SIZE;
+        for i in 0..num_rows {
+            let row = (row + i) % regs_num_rows;
+            std::ptr::copy_nonoverlapping(
+                ptr.add(i * ROW_SIZE),
+                regs[row * ROW_SIZE..][..ROW_SIZE].as_mut_ptr(),
+                ROW_SIZE,
+            );
+        }
+    }
+
+    /// Store `num_rows` rows of the register set `regs` to memory, starting
+    /// from the row `row` and wrapping around at the end of the register set.
+    unsafe fn store_rows(regs: &[u8], row: usize, num_rows: usize, ptr: *mut u8) {
+        let regs_num_rows = regs.len() / ROW_SIZE;
+        for i in 0..num_rows {
+            let row = (row + i) % regs_num_rows;
+            std::ptr::copy_nonoverlapping(
+                regs[row * ROW_SIZE..][..ROW_SIZE].as_ptr(),
+                ptr.add(i * ROW_SIZE),
+                ROW_SIZE,
+            );
+        }
+    }
+}
+
+/// Element types supported by [`AmxSt::fma`].
+trait FmaElem: Copy {
+    const SIZE: usize;
+    const ONE: Self;
+    const ZERO: Self;
+
+    fn read(bytes: &[u8]) -> Self;
+    fn write(self, bytes: &mut [u8]);
+
+    /// Calculate `z + x * y` or `z - x * y`.
+    fn fma(z: Self, x: Self, y: Self, sub: bool) -> Self;
+}
+
+impl FmaElem for f64 {
+    const SIZE: usize = 8;
+    const ONE: Self = 1.0;
+    const ZERO: Self = 0.0;
+
+    fn read(bytes: &[u8]) -> Self {
+        Self::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    fn write(self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.to_le_bytes());
+    }
+
+    fn fma(z: Self, x: Self, y: Self, sub: bool) -> Self {
+        if sub { -x } else { x }.mul_add(y, z)
+    }
+}
+
+impl FmaElem for f32 {
+    const SIZE: usize = 4;
+    const ONE: Self = 1.0;
+    const ZERO: Self = 0.0;
+
+    fn read(bytes: &[u8]) -> Self {
+        Self::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    fn write(self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.to_le_bytes());
+    }
+
+    fn fma(z: Self, x: Self, y: Self, sub: bool) -> Self {
+        if sub { -x } else { x }.mul_add(y, z)
+    }
+}
+
+impl FmaElem for i16 {
+    const SIZE: usize = 2;
+    const ONE: Self = 1;
+    const ZERO: Self = 0;
+
+    fn read(bytes: &[u8]) -> Self {
+        Self::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    fn write(self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.to_le_bytes());
+    }
+
+    fn fma(z: Self, x: Self, y: Self, sub: bool) -> Self {
+        if sub {
+            z.wrapping_sub(x.wrapping_mul(y))
+        } else {
+            z.wrapping_add(x.wrapping_mul(y))
+        }
+    }
+}
+
+/// An IEEE 754 binary16 number, represented by its bit pattern.
+#[derive(Debug, Copy, Clone)]
+struct F16(u16);
+
+impl F16 {
+    /// Convert `self` to `f64`. This is exact.
+    fn to_f64(self) -> f64 {
+        let sign = if self.0 & 0x8000 != 0 { -1.0 } else { 1.0 };
+        let exp = ((self.0 >> 10) & 0x1f) as i32;
+        let mantissa = (self.0 & 0x3ff) as f64;
+        sign * match exp {
+            0 => mantissa * 2f64.powi(-24),
+            0x1f if mantissa == 0.0 => f64::INFINITY,
+            0x1f => f64::NAN,
+            _ => (mantissa + 1024.0) * 2f64.powi(exp - 25),
+        }
+    }
+
+    /// Convert `x` to the nearest `F16` (ties to even). NaNs are converted to
+    /// the default NaN.
+    fn from_f64(x: f64) -> Self {
+        let sign = if x.is_sign_negative() { 0x8000 } else { 0 };
+        let a = x.abs();
+        if a.is_nan() {
+            return Self(0x7e00);
+        } else if a >= 65520.0 {
+            // Rounds to infinity
+            return Self(sign | 0x7c00);
+        } else if a <= 2f64.powi(-25) {
+            // Rounds to zero
+            return Self(sign);
+        }
+
+        // Round to a multiple of the unit in the last place of the result
+        let exp = ((a.to_bits() >> 52) as i32 - 1023).max(-14);
+        let ulp = 2f64.powi(exp - 10);
+        let a = (a / ulp).round_ties_even() * ulp;
+
+        // `a` is now exactly representable (possibly carried over to the next
+        // binade)
+        let bits = if a < 2f64.powi(-14) {
+            (a / 2f64.powi(-24)) as u16
+        } else {
+            let a_bits = a.to_bits();
+            let exp = (a_bits >> 52) as i32 - 1023;
+            (((exp + 15) as u16) << 10) | ((a_bits >> 42) & 0x3ff) as u16
+        };
+        Self(sign | bits)
+    }
+}
+
+impl FmaElem for F16 {
+    const SIZE: usize = 2;
+    const ONE: Self = Self(0x3c00);
+    const ZERO: Self = Self(0);
+
+    fn read(bytes: &[u8]) -> Self {
+        Self(u16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn write(self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.0.to_le_bytes());
+    }
+
+    fn fma(z: Self, x: Self, y: Self, sub: bool) -> Self {
+        // The product is exact in `f64`. The sum may not be, but then the
+        // smaller term is too small to affect the rounding to `F16`.
+        let prod = x.to_f64() * y.to_f64();
+        Self::from_f64(z.to_f64() + if sub { -prod } else { prod })
+    }
+}
+
+impl AmxSt {
+    /// Execute a multiply-accumulate instruction (`fma64`, `fms64`, `fma32`,
+    /// `fms32`, `mac16`, `fma16`, or `fms16` without widening).
+    ///
+    /// In matrix mode, the outer product of `x` and `y` is accumulated to every
+    /// `T::SIZE`-th row of `z`. In vector mode (bit 63), the element-wise
+    /// product is accumulated to a single row of `z`. A skipped `x` or `y`
+    /// (bit 28 or 29) is removed from the product, and if both are skipped, the
+    /// product term is removed altogether. A skipped `z` (bit 27) is treated as
+    /// zero. In matrix mode, the elements outside the lanes selected by the
+    /// [`LaneMask`]s (bits 32 and 41) are left unchanged.
+    fn fma<T: FmaElem>(&mut self, x: u64, sub: bool) {
+        let y_offset = (x & 0x1ff) as usize;
+        let x_offset = ((x >> 10) & 0x1ff) as usize;
+        let z_row = ((x >> 20) & 0x3f) as usize;
+        let skip_z = x & (1 << 27) != 0;
+        let skip_x = x & (1 << 28) != 0;
+        let skip_y = x & (1 << 29) != 0;
+        let vector = x & (1 << 63) != 0;
+        let x_mask = LaneMask::from_operand_bits(x >> X_MASK_POS);
+        let y_mask = LaneMask::from_operand_bits(x >> Y_MASK_POS);
+
+        let in_x: [u8; ROW_SIZE] = self.read_xy_bytes(false, x_offset);
+        let in_y: [u8; ROW_SIZE] = self.read_xy_bytes(true, y_offset);
+        let lanes = ROW_SIZE / T::SIZE;
+        let lane = |bytes: &[u8; ROW_SIZE], skip: bool, i: usize| {
+            if skip {
+                T::ONE
+            } else {
+                T::read(&bytes[i * T::SIZE..][..T::SIZE])
+            }
+        };
+
+        let mut update = |row: usize, i: usize, x: T, y: T| {
+            let z = &mut self.z[row * ROW_SIZE + i * T::SIZE..][..T::SIZE];
+            let z_in = if skip_z { T::ZERO } else { T::read(z) };
+            let z_out = if skip_x && skip_y {
+                z_in
+            } else {
+                T::fma(z_in, x, y, sub)
+            };
+            z_out.write(z);
+        };
+
+        if vector {
+            if (x_mask, y_mask) != (LaneMask::All, LaneMask::All) {
+                todo!("lane masks in vector mode")
+            }
+            for i in 0..lanes {
+                update(z_row, i, lane(&in_x, skip_x, i), lane(&in_y, skip_y, i));
+            }
+        } else {
+            for j in (0..lanes).filter(|&j| y_mask.is_enabled(j, lanes)) {
+                let y = lane(&in_y, skip_y, j);
+                let row = j * T::SIZE + z_row % T::SIZE;
+                for i in (0..lanes).filter(|&i| x_mask.is_enabled(i, lanes)) {
+                    update(row, i, lane(&in_x, skip_x, i), y);
+                }
+            }
+        }
+    }
+}
+
+impl AmxSt {
+    /// Get the `z` row or column read by `extrx` or `extry`.
+    ///
+    /// If bit 26 is clear, this is the `z` row `(x >> 20) & 63`. Otherwise,
+    /// this is a column of the tile of `width`-byte elements, where `width`
+    /// is `8 >> ((x >> 28) & 3)`. The tile occupies every `width`-th row, and
+    /// bits 20–25 hold `column * width + (first row of the tile)`, so the
+    /// `i`-th element is `z[base + i * width][column * width..][..width]`.
+    fn extr_source(&self, x: u64) -> [u8; ROW_SIZE] {
+        let field = ((x >> 20) & 0x3f) as usize;
+        if x & (1 << 26) == 0 {
+            return self.z[field * ROW_SIZE..][..ROW_SIZE].try_into().unwrap();
+        }
+        let width = 8 >> ((x >> 28) & 3);
+        let (column, base) = (field / width, field % width);
+        let mut row = [0u8; ROW_SIZE];
+        for (i, out) in row.chunks_exact_mut(width).enumerate() {
+            out.copy_from_slice(&self.z[(base + i * width) * ROW_SIZE + column * width..][..width]);
+        }
+        row
+    }
+}
+
+/// The `matint` lane width mode for `i8` inputs and `i32` outputs.
+pub(crate) const MATINT_LANES_I8_I32: u64 = 10;
+/// The `matint` lane width mode for `u8` inputs and `i32` outputs.
+pub(crate) const MATINT_LANES_U8_I32: u64 = 11;
+/// The position of the five-bit right shift amount in `matint`'s operand.
+pub(crate) const MATINT_SHIFT_POS: u32 = 58;
+/// The `matint` operand bit to saturate the results.
+pub(crate) const MATINT_SATURATE: u64 = 1 << 57;
+/// The `matint` operand bit to round the shifted products to nearest.
+pub(crate) const MATINT_ROUND_NEAREST: u64 = 1 << 56;
+
+impl AmxSt {
+    /// Execute `matint` with 8-bit inputs and 32-bit outputs.
+    ///
+    /// The outer product of the first 16 bytes of `x` and `y` is accumulated
+    /// to every fourth row of `z`, i.e., `z[j * 4 + (z_row & 3)][i] +=
+    /// x[i] * y[j]`. The skip bits and the `y` lane mask are interpreted in
+    /// the same way as [`AmxSt::fma`]; there's no `x` lane mask. Each product is shifted right before accumulation, and
+    /// the accumulation optionally saturates, as described in
+    /// [`IntAccumMode`](crate::IntAccumMode).
+    fn matint_8bit(&mut self, x: u64, signed: bool) {
+        let y_offset = (x & 0x1ff) as usize;
+        let x_offset = ((x >> 10) & 0x1ff) as usize;
+        let z_row = ((x >> 20) & 0x3f) as usize;
+        let skip_z = x & (1 << 27) != 0;
+        let skip_x = x & (1 << 28) != 0;
+        let skip_y = x & (1 << 29) != 0;
+        let shift = ((x >> MATINT_SHIFT_POS) & 0x1f) as u32;
+        let saturate = x & MATINT_SATURATE != 0;
+        let round_nearest = x & MATINT_ROUND_NEAREST != 0;
+        let y_mask = LaneMask::from_operand_bits(x >> Y_MASK_POS);
+
+        let in_x: [u8; 16] = self.read_xy_bytes(false, x_offset);
+        let in_y: [u8; 16] = self.read_xy_bytes(true, y_offset);
+        let lane = |bytes: &[u8; 16], skip: bool, i: usize| match (skip, signed) {
+            (true, _) => 1,
+            (false, true) => bytes[i] as i8 as i64,
+            (false, false) => bytes[i] as i64,
+        };
+
+        for j in (0..16).filter(|&j| y_mask.is_enabled(j, 16)) {
+            let y = lane(&in_y, skip_y, j);
+            let row = j * 4 + z_row % 4;
+            for i in 0..16 {
+                let z = &mut self.z[row * ROW_SIZE + i * 4..][..4];
+                let z_in = if skip_z {
+                    0
+                } else {
+                    i32::from_le_bytes(z.try_into().unwrap()) as i64
+                };
+                let z_out = if skip_x && skip_y {
+                    z_in
+                } else {
+                    let mut prod = lane(&in_x, skip_x, i) * y;
+                    if round_nearest && shift > 0 {
+                        prod += 1 << (shift - 1);
+                    }
+                    z_in + (prod >> shift)
+                };
+                let z_out = if saturate {
+                    z_out.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+                } else {
+                    z_out as i32
+                };
+                z.copy_from_slice(&z_out.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Decode the operand of a load/store instruction, returning the register row
+/// and the number of rows to transfer.
+#[inline]
+fn mem_operand(x: u64) -> (usize, usize) {
+    let row = ((x >> 56) & 0x3f) as usize;
+    let num_rows = if x & (1 << 62) != 0 { 2 } else { 1 };
+    (row, num_rows)
+}
+
 unsafe impl AmxOps for AmxEmuCtx {
     unsafe fn ldx(&mut self, x: u64, ptr: *mut ()) {
-        todo!()
+        self.check(LDX, x);
+        let (row, num_rows) = mem_operand(x);
+        AmxSt::load_rows(&mut self.st.x, row % 8, num_rows, ptr as *const u8);
     }
 
     unsafe fn ldy(&mut self, x: u64, ptr: *mut ()) {
-        todo!()
+        self.check(LDY, x);
+        let (row, num_rows) = mem_operand(x);
+        AmxSt::load_rows(&mut self.st.y, row % 8, num_rows, ptr as *const u8);
     }
 
     unsafe fn stx(&mut self, x: u64, ptr: *mut ()) {
-        todo!()
+        self.check(STX, x);
+        let (row, num_rows) = mem_operand(x);
+        AmxSt::store_rows(&self.st.x, row % 8, num_rows, ptr as *mut u8);
     }
 
     unsafe fn sty(&mut self, x: u64, ptr: *mut ()) {
-        todo!()
+        self.check(STY, x);
+        let (row, num_rows) = mem_operand(x);
+        AmxSt::store_rows(&self.st.y, row % 8, num_rows, ptr as *mut u8);
     }
 
     unsafe fn ldz(&mut self, x: u64, ptr: *mut ()) {
-        todo!()
+        self.check(LDZ, x);
+        let (row, num_rows) = mem_operand(x);
+        AmxSt::load_rows(&mut self.st.z, row, num_rows, ptr as *const u8);
     }
 
     unsafe fn stz(&mut self, x: u64, ptr: *mut ()) {
-        todo!()
+        self.check(STZ, x);
+        let (row, num_rows) = mem_operand(x);
+        AmxSt::store_rows(&self.st.z, row, num_rows, ptr as *mut u8);
     }
 
     unsafe fn ldzi(&mut self, x: u64, ptr: *mut ()) {
-        todo!()
+        self.check(LDZI, x);
+        // The even-numbered 32-bit words go to `z[row & !1]` and the
+        // odd-numbered ones go to `z[row | 1]`. `row & 1` selects which half of
+        // the rows is written.
+        let (row, _) = mem_operand(x);
+        let mut buf = [0u8; ROW_SIZE];
+        std::ptr::copy_nonoverlapping(ptr as *const u8, buf.as_mut_ptr(), ROW_SIZE);
+        let half = (row & 1) * (ROW_SIZE / 2);
+        for (i, word) in buf.chunks_exact(4).enumerate() {
+            let out_row = (row & !1) | (i & 1);
+            let out_pos = out_row * ROW_SIZE + half + i / 2 * 4;
+            self.st.z[out_pos..][..4].copy_from_slice(word);
+        }
     }
 
     unsafe fn stzi(&mut self, x: u64, ptr: *mut ()) {
-        todo!()
+        self.check(STZI, x);
+        // The inverse of `ldzi`
+        let (row, _) = mem_operand(x);
+        let mut buf = [0u8; ROW_SIZE];
+        let half = (row & 1) * (ROW_SIZE / 2);
+        for (i, word) in buf.chunks_exact_mut(4).enumerate() {
+            let in_row = (row & !1) | (i & 1);
+            let in_pos = in_row * ROW_SIZE + half + i / 2 * 4;
+            word.copy_from_slice(&self.st.z[in_pos..][..4]);
+        }
+        std::ptr::copy_nonoverlapping(buf.as_ptr(), ptr as *mut u8, ROW_SIZE);
     }
 
     fn extrx(&mut self, x: u64) {
-        todo!()
+        self.check(EXTRX, x);
+        // `x[(x >> 16) & 7] = z[(x >> 20) & 63]`, or a column of `z` if bit 26
+        // is set
+        let x_row = ((x >> 16) & 7) as usize;
+        let row = self.st.extr_source(x);
+        self.st.x[x_row * ROW_SIZE..][..ROW_SIZE].copy_from_slice(&row);
     }
 
     fn extry(&mut self, x: u64) {
-        todo!()
+        self.check(EXTRY, x);
+        // `y[(x >> 6) & 7] = z[(x >> 20) & 63]`, or `x[(x >> 16) & 7]` instead
+        // of the `z` row if bit 27 is set, or a column of `z` if bit 26 is set
+        let y_row = ((x >> 6) & 7) as usize;
+        let row = if x & (1 << 27) != 0 {
+            let x_row = ((x >> 16) & 7) as usize;
+            self.st.x[x_row * ROW_SIZE..][..ROW_SIZE]
+                .try_into()
+                .unwrap()
+        } else {
+            self.st.extr_source(x)
+        };
+        self.st.y[y_row * ROW_SIZE..][..ROW_SIZE].copy_from_slice(&row);
     }
 
     fn fma64(&mut self, x: u64) {
-        todo!()
+        self.check(FMA64, x);
+        self.st.fma::<f64>(x, false);
     }
 
     fn fms64(&mut self, x: u64) {
-        todo!()
+        self.check(FMS64, x);
+        self.st.fma::<f64>(x, true);
     }
 
     fn fma32(&mut self, x: u64) {
-        todo!()
+        self.check(FMA32, x);
+        self.st.fma::<f32>(x, false);
     }
 
     fn fms32(&mut self, x: u64) {
-        todo!()
+        self.check(FMS32, x);
+        self.st.fma::<f32>(x, true);
     }
 
     fn mac16(&mut self, x: u64) {
-        todo!()
+        self.check(MAC16, x);
+        if x & (1 << 62) != 0 {
+            todo!("widening `mac16`")
+        }
+        self.st.fma::<i16>(x, false);
     }
 
     fn fma16(&mut self, x: u64) {
-        todo!()
+        self.check(FMA16, x);
+        if x & (1 << 62) != 0 {
+            todo!("widening `fma16`")
+        }
+        self.st.fma::<F16>(x, false);
     }
 
     fn fms16(&mut self, x: u64) {
-        todo!()
+        self.check(FMS16, x);
+        if x & (1 << 62) != 0 {
+            todo!("widening `fms16`")
+        }
+        self.st.fma::<F16>(x, true);
     }
 
     fn vecint(&mut self, x: u64) {
+        self.check(VECINT, x);
         todo!()
     }
 
     fn vecfp(&mut self, x: u64) {
+        self.check(VECFP, x);
         todo!()
     }
 
     fn matint(&mut self, x: u64) {
-        todo!()
+        self.check(MATINT, x);
+        let alu_mode = (x >> 47) & 0x3f;
+        if alu_mode != 0 {
+            todo!("`matint` ALU mode {}", alu_mode)
+        }
+        match (x >> 42) & 0xf {
+            MATINT_LANES_I8_I32 => self.st.matint_8bit(x, true),
+            MATINT_LANES_U8_I32 => self.st.matint_8bit(x, false),
+            lanes => todo!("`matint` lane width mode {}", lanes),
+        }
     }
 
     fn matfp(&mut self, x: u64) {
+        self.check(MATFP, x);
         todo!()
     }
 
     fn genlut(&mut self, x: u64) {
-        todo!()
+        self.check(GENLUT, x);
+        let input: [u8; ROW_SIZE] = self
+            .st
+            .read_xy_bytes(x & (1 << 10) != 0, (x & 0x1ff) as usize);
+        let table_row = ((x >> 60) & 7) as usize;
+        let table: [u8; ROW_SIZE] = self.st.read_xy_bytes(false, table_row * ROW_SIZE);
+
+        let output = match (x >> 53) & 0xf {
+            0 => genlut_reverse(&input, &table, 4, LutElem::F32),
+            1 => genlut_reverse(&input, &table, 5, LutElem::F16),
+            2 => genlut_reverse(&input, &table, 4, LutElem::F64),
+            3 => genlut_reverse(&input, &table, 4, LutElem::I32),
+            4 => genlut_reverse(&input, &table, 5, LutElem::I16),
+            5 => genlut_reverse(&input, &table, 4, LutElem::U32),
+            6 => genlut_reverse(&input, &table, 5, LutElem::U16),
+            7 => genlut_normal(&input, &table, 2, 4),
+            8 => genlut_normal(&input, &table, 2, 2),
+            9 => genlut_normal(&input, &table, 2, 1),
+            // Only the lower three bits of each index are used because a table
+            // row only has room for eight 64-bit values
+            10 => genlut_normal(&input, &table, 4, 8),
+            11 => genlut_normal(&input, &table, 4, 4),
+            12 => genlut_normal(&input, &table, 4, 2),
+            13 => genlut_normal(&input, &table, 4, 1),
+            14 => genlut_normal(&input, &table, 5, 2),
+            15 => genlut_normal(&input, &table, 5, 1),
+            _ => unreachable!(),
+        };
+
+        let out_row = ((x >> 20) & 0x3f) as usize;
+        let out = if x & (1 << 26) != 0 {
+            &mut self.st.z[out_row * ROW_SIZE..][..ROW_SIZE]
+        } else {
+            &mut self.st.xy_mut(x & (1 << 25) != 0)[out_row % 8 * ROW_SIZE..][..ROW_SIZE]
+        };
+        out.copy_from_slice(&output);
+    }
+}
+
+/// Element types supported by the reverse `genlut` modes.
+#[derive(Debug, Copy, Clone)]
+enum LutElem {
+    F16,
+    F32,
+    F64,
+    I16,
+    I32,
+    U16,
+    U32,
+}
+
+impl LutElem {
+    fn size(self) -> usize {
+        match self {
+            Self::F16 | Self::I16 | Self::U16 => 2,
+            Self::F32 | Self::I32 | Self::U32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// Decode an element. All supported types are exactly representable by
+    /// `f64`, which makes it a convenient common type for comparison.
+    fn decode(self, bytes: &[u8]) -> f64 {
+        let mut buf = [0u8; 8];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        let bits = u64::from_le_bytes(buf);
+        match self {
+            Self::F16 => F16(bits as u16).to_f64(),
+            Self::F32 => f32::from_bits(bits as u32) as f64,
+            Self::F64 => f64::from_bits(bits),
+            Self::I16 => bits as u16 as i16 as f64,
+            Self::I32 => bits as u32 as i32 as f64,
+            Self::U16 => bits as u16 as f64,
+            Self::U32 => bits as u32 as f64,
+        }
+    }
+}
+
+/// Read the `index`-th `bits`-bit field from a tightly-packed little-endian
+/// bit stream.
+fn read_bit_field(bytes: &[u8], index: usize, bits: usize) -> usize {
+    (0..bits)
+        .map(|i| {
+            let pos = index * bits + i;
+            (((bytes[pos / 8] >> (pos % 8)) & 1) as usize) << i
+        })
+        .sum()
+}
+
+/// Write the `index`-th `bits`-bit field of a tightly-packed little-endian
+/// bit stream.
+fn write_bit_field(bytes: &mut [u8], index: usize, bits: usize, value: usize) {
+    for i in 0..bits {
+        let pos = index * bits + i;
+        bytes[pos / 8] &= !(1 << (pos % 8));
+        bytes[pos / 8] |= (((value >> i) & 1) as u8) << (pos % 8);
+    }
+}
+
+/// Look up `value_size`-byte values from `table` using `index_bits`-bit
+/// indices read from `input`.
+fn genlut_normal(
+    input: &[u8; ROW_SIZE],
+    table: &[u8; ROW_SIZE],
+    index_bits: usize,
+    value_size: usize,
+) -> [u8; ROW_SIZE] {
+    let num_entries = ROW_SIZE / value_size;
+    let mut output = [0u8; ROW_SIZE];
+    for (i, out) in output.chunks_exact_mut(value_size).enumerate() {
+        let index = read_bit_field(input, i, index_bits) % num_entries;
+        out.copy_from_slice(&table[index * value_size..][..value_size]);
+    }
+    output
+}
+
+/// For each element in `input`, find the index of the first element in `table`
+/// greater than it and subtract one. The results are written as
+/// `index_bits`-bit indices.
+///
+/// When the table is sorted in ascending order, this yields the index of the
+/// last element less than or equal to each input element, or all ones if there
+/// is no such element.
+fn genlut_reverse(
+    input: &[u8; ROW_SIZE],
+    table: &[u8; ROW_SIZE],
+    index_bits: usize,
+    elem: LutElem,
+) -> [u8; ROW_SIZE] {
+    let size = elem.size();
+    let table: Vec<f64> = table.chunks_exact(size).map(|x| elem.decode(x)).collect();
+    let mut output = [0u8; ROW_SIZE];
+    for (i, value) in input.chunks_exact(size).enumerate() {
+        let value = elem.decode(value);
+        let index = table
+            .iter()
+            .position(|&entry| entry > value)
+            .unwrap_or(table.len());
+        let index = index.wrapping_sub(1) & ((1 << index_bits) - 1);
+        write_bit_field(&mut output, i, index_bits, index);
     }
+    output
 }
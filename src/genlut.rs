@@ -1,6 +1,6 @@
 //! Wrapper for the `genlut` instruction
 use crate::{
-    regs::{XBytes, XRow, YBytes, YRow, ZRow},
+    regs::{XBytes, XRow, XRowN, YBytes, YRow, YRowN, ZRow, ZRowN},
     AmxOps,
 };
 
@@ -13,6 +13,37 @@ use crate::{
 ///  - `index` is the data type for indices.
 ///  - `value` is the data type for looked-up values.
 ///
+/// The following combinations are supported:
+///
+/// |        Type tag         | Mode | Table entries | Input           | Output           |
+/// | ----------------------- | ---- | ------------- | --------------- | ---------------- |
+/// | `(Normal, Index2, X32)` |    7 | 4 × 32-bit    | 16 × 2-bit      | 16 × 32-bit      |
+/// | `(Normal, Index2, X16)` |    8 | 4 × 16-bit    | 32 × 2-bit      | 32 × 16-bit      |
+/// | `(Normal, Index2, X8)`  |    9 | 4 × 8-bit     | 64 × 2-bit      | 64 × 8-bit       |
+/// | `(Normal, Index4, X64)` |   10 | 8 × 64-bit    | 8 × 4-bit[^1]   | 8 × 64-bit       |
+/// | `(Normal, Index4, X32)` |   11 | 16 × 32-bit   | 16 × 4-bit      | 16 × 32-bit      |
+/// | `(Normal, Index4, X16)` |   12 | 16 × 16-bit   | 32 × 4-bit      | 32 × 16-bit      |
+/// | `(Normal, Index4, X8)`  |   13 | 16 × 8-bit    | 64 × 4-bit      | 64 × 8-bit       |
+/// | `(Normal, Index5, X16)` |   14 | 32 × 16-bit   | 32 × 5-bit      | 32 × 16-bit      |
+/// | `(Normal, Index5, X8)`  |   15 | 32 × 8-bit    | 64 × 5-bit      | 64 × 8-bit       |
+/// | `(Reverse, Index4, F32)`|    0 | 16 × `f32`    | 16 × `f32`      | 16 × 4-bit       |
+/// | `(Reverse, Index5, F16)`|    1 | 32 × `f16`    | 32 × `f16`      | 32 × 5-bit       |
+/// | `(Reverse, Index4, F64)`|    2 | 8 × `f64`     | 8 × `f64`       | 8 × 4-bit        |
+/// | `(Reverse, Index4, I32)`|    3 | 16 × `i32`    | 16 × `i32`      | 16 × 4-bit       |
+/// | `(Reverse, Index5, I16)`|    4 | 32 × `i16`    | 32 × `i16`      | 32 × 5-bit       |
+/// | `(Reverse, Index4, U32)`|    5 | 16 × `u32`    | 16 × `u32`      | 16 × 4-bit       |
+/// | `(Reverse, Index5, U16)`|    6 | 32 × `u16`    | 32 × `u16`      | 32 × 5-bit       |
+///
+/// Indices are tightly packed, starting from the least significant bit of the
+/// first byte. The normal modes replace each index with the table entry at
+/// that index. The reverse modes replace each value `v` with `i - 1`, where
+/// `i` is the minimum index of a table entry greater than `v` (or the number
+/// of table entries if there's no such entry). When the table is sorted in
+/// ascending order, this is the index of the last entry less than or equal to
+/// `v`, or all ones if there's no such entry. The output of the reverse modes
+/// occupies only the first bytes of the output row, and the rest is zeroed.
+///
+/// [^1]: Only the lower three bits of each index are used.
 pub trait LutTy {
     /// The raw LUT mode number for `genlut` instruction.
     fn genlut_mode(&self) -> u64;
@@ -115,24 +146,23 @@ impl<Left: LutTy, Right: LutTy> LutTy for either::Either<Left, Right> {
 }
 
 /// The trait representing `genlut` instruction's input, which can be either
-/// [`XBytes`] or [`YBytes`].
+/// [`XBytes`](type@XBytes) or [`YBytes`](type@YBytes).
 pub trait LutIn {
+    /// Get the operand bits of `genlut` specifying this input.
     fn as_genlut_input_param(&self) -> u64;
 }
 
 impl LutIn for XBytes {
     #[inline(always)]
     fn as_genlut_input_param(&self) -> u64 {
-        debug_assert!(self.0 < 512);
-        self.0 as u64
+        self.operand_bits()
     }
 }
 
 impl LutIn for YBytes {
     #[inline(always)]
     fn as_genlut_input_param(&self) -> u64 {
-        debug_assert!(self.0 < 512);
-        self.0 as u64 | (1u64 << 10) // "input is in Y"
+        self.operand_bits() | (1u64 << 10) // "input is in Y"
     }
 }
 
@@ -150,6 +180,7 @@ impl<Left: LutIn, Right: LutIn> LutIn for either::Either<Left, Right> {
 /// The trait representing `genlut` instruction's output, which can be either
 /// [`XRow`], [`YRow`], or [`ZRow`].
 pub trait LutOut {
+    /// Get the operand bits of `genlut` specifying this output.
     fn as_genlut_output_param(&self) -> u64;
 }
 
@@ -165,11 +196,12 @@ impl LutOut for YRow {
     #[inline(always)]
     fn as_genlut_output_param(&self) -> u64 {
         debug_assert!(self.0 < 8);
-        ((self.0 as u64) << 20) | (1u64 << 25) // "input is in Y"
+        ((self.0 as u64) << 20) | (1u64 << 25) // "output is in Y"
     }
 }
 
 impl LutOut for ZRow {
+    /// Unlike `XRow` and `YRow`, all six bits of the row index are encoded.
     #[inline(always)]
     fn as_genlut_output_param(&self) -> u64 {
         debug_assert!(self.0 < 64);
@@ -177,6 +209,19 @@ impl LutOut for ZRow {
     }
 }
 
+macro_rules! impl_lut_out_for_const_row {
+    ($($ty:ident),*) => {$(
+        impl<const N: usize> LutOut for $ty<N> {
+            #[inline(always)]
+            fn as_genlut_output_param(&self) -> u64 {
+                Self::ROW.as_genlut_output_param()
+            }
+        }
+    )*};
+}
+
+impl_lut_out_for_const_row!(XRowN, YRowN, ZRowN);
+
 #[cfg(feature = "either")]
 impl<Left: LutOut, Right: LutOut> LutOut for either::Either<Left, Right> {
     #[inline]
@@ -188,18 +233,96 @@ impl<Left: LutOut, Right: LutOut> LutOut for either::Either<Left, Right> {
     }
 }
 
+/// The trait for element type tags supported by the reverse modes of
+/// `genlut`, i.e., [`F16`], [`F32`], [`F64`], [`I16`], [`I32`], [`U16`], and
+/// [`U32`].
+pub trait ReverseLutElem {
+    /// The index type of the output ([`Index4`] or [`Index5`]).
+    type Index;
+    /// The number of bits in each output index.
+    const INDEX_BITS: u32;
+
+    /// The raw LUT mode number for `genlut` instruction.
+    fn reverse_genlut_mode(&self) -> u64;
+}
+
+macro_rules! define_reverse_lut_elem {
+    ($(
+        $ty:ident => ($index:ident, $bits:expr)
+    ),*$(,)*) => {$(
+        impl ReverseLutElem for $ty {
+            type Index = $index;
+            const INDEX_BITS: u32 = $bits;
+
+            #[inline(always)]
+            fn reverse_genlut_mode(&self) -> u64 {
+                (Reverse, $index, $ty).genlut_mode()
+            }
+        }
+    )*};
+}
+
+define_reverse_lut_elem! {
+    F16 => (Index5, 5),
+    F32 => (Index4, 4),
+    F64 => (Index4, 4),
+    I16 => (Index5, 5),
+    I32 => (Index4, 4),
+    U16 => (Index5, 5),
+    U32 => (Index4, 4),
+}
+
+/// Pack `indices` into a tightly-packed bit stream of `bits`-bit fields,
+/// suitable as the input of a normal-mode `genlut` instruction (e.g., the
+/// `pattern` parameter of [`Amx::shuffle_bytes`]).
+///
+/// Only the lower `bits` bits of each index are used. `bits` must be in range
+/// `1..=8`, and `indices.len() * bits` must not exceed 512.
+///
+/// [`Amx::shuffle_bytes`]: crate::Amx::shuffle_bytes
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(amx::pack_lut_indices(&[1, 2, 3, 0xff], 4)[..3], [0x21, 0xf3, 0]);
+/// ```
+#[track_caller]
+pub fn pack_lut_indices(indices: &[u8], bits: u32) -> [u8; 64] {
+    assert!((1..=8).contains(&bits));
+    assert!(indices.len() * bits as usize <= 512);
+    let mut out = [0u8; 64];
+    for (i, &index) in indices.iter().enumerate() {
+        for bit in 0..bits as usize {
+            let pos = i * bits as usize + bit;
+            out[pos / 8] |= ((index >> bit) & 1) << (pos % 8);
+        }
+    }
+    out
+}
+
 #[inline(always)]
 pub(crate) fn lut(
     ops: &mut (impl AmxOps + ?Sized),
     input: impl LutIn,
-    XRow(table_row): XRow,
+    table: XRow,
     output: impl LutOut,
     mode: impl LutTy,
+) {
+    lut_raw(ops, input, table, output, mode.genlut_mode());
+}
+
+#[inline(always)]
+pub(crate) fn lut_raw(
+    ops: &mut (impl AmxOps + ?Sized),
+    input: impl LutIn,
+    XRow(table_row): XRow,
+    output: impl LutOut,
+    mode: u64,
 ) {
     ops.genlut(
         input.as_genlut_input_param()
             | output.as_genlut_output_param()
-            | (mode.genlut_mode() << 53)
+            | (mode << 53)
             | ((table_row as u64) << 60),
     );
 }
@@ -0,0 +1,47 @@
+//! Accumulation modes of integer instructions
+use crate::emu::{MATINT_ROUND_NEAREST, MATINT_SATURATE, MATINT_SHIFT_POS};
+
+/// Specifies how the products are accumulated by an integer instruction.
+///
+/// Each product `x * y` is shifted right by `shift` bits (rounded as specified
+/// by `round`) and then added to `z`. The addition saturates at the bounds of
+/// the output type if `saturate` is `true` and wraps around otherwise.
+///
+/// The default value, which has `shift = 0` and `saturate = false`, specifies
+/// the ordinary wrapping multiply-accumulate.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct IntAccumMode {
+    /// The number of bits to shift products right by. Must be in range
+    /// `0..32`.
+    pub shift: u8,
+    /// Saturate the results instead of wrapping around.
+    pub saturate: bool,
+    /// The rounding mode of the right shift.
+    pub round: RoundMode,
+}
+
+/// Specifies the rounding mode of a right shift.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RoundMode {
+    /// Round toward negative infinity (i.e., an arithmetic right shift).
+    #[default]
+    Floor,
+    /// Round to the nearest integer, with ties rounded toward positive
+    /// infinity.
+    Nearest,
+}
+
+impl IntAccumMode {
+    /// Get the operand bits of `matint` representing this mode.
+    #[inline(always)]
+    #[track_caller]
+    pub(crate) fn matint_bits(self) -> u64 {
+        assert!(self.shift < 32, "shift amount out of range");
+        ((self.shift as u64) << MATINT_SHIFT_POS)
+            | if self.saturate { MATINT_SATURATE } else { 0 }
+            | match self.round {
+                RoundMode::Floor => 0,
+                RoundMode::Nearest => MATINT_ROUND_NEAREST,
+            }
+    }
+}
@@ -0,0 +1,2532 @@
+//! Higher-level routines built on AMX instructions
+use crate::{
+    outer_product_operand, pack_lut_indices, Amx, Index4, Lane, Normal, Reduce, XBytes, XRow,
+    YBytes, YRow, ZRow, ZTile64, ZWriteMode, F32, FMA_VECTOR, X32,
+};
+use backend::{Backend, BackendLane};
+use std::{convert::TryInto, sync::OnceLock};
+
+pub mod auto;
+pub mod backend;
+pub mod fallback;
+pub mod image;
+
+/// The number of `f32` elements in a register row.
+const F32_LANES: usize = 16;
+
+/// The number of output columns processed at once by [`matmul_strip`]. Each
+/// column block is accumulated in `size_of::<T>()` interleaved tiles of `z`.
+const MATMUL_BLOCK_COLS: usize = 64;
+
+/// Calculate `c = a * b`, where `a`, `b`, and `c` are row-major `f32`
+/// matrices of size `m × k`, `k × n`, and `m × n`, respectively.
+///
+/// `ctx` can be any [`Backend`], including an AMX context and
+/// [`backend::Portable`].
+///
+/// # Panics
+///
+/// Panics if the slice lengths don't match the specified matrix sizes.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let a = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+/// let b = [1.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+/// let mut c = [0.0; 4];
+/// amx::kernels::matmul_f32(&mut ctx, 2, 2, 3, &a, &b, &mut c);
+/// assert_eq!(c, [4.0, 5.0, 10.0, 11.0]);
+/// ```
+pub fn matmul_f32(
+    ctx: &mut (impl Backend<f32> + ?Sized),
+    m: usize,
+    n: usize,
+    k: usize,
+    a: &[f32],
+    b: &[f32],
+    c: &mut [f32],
+) {
+    matmul(ctx, m, n, k, a, b, c);
+}
+
+/// The `f64` version of [`matmul_f32`].
+pub fn matmul_f64(
+    ctx: &mut (impl Backend<f64> + ?Sized),
+    m: usize,
+    n: usize,
+    k: usize,
+    a: &[f64],
+    b: &[f64],
+    c: &mut [f64],
+) {
+    matmul(ctx, m, n, k, a, b, c);
+}
+
+/// The implementation of [`matmul_f32`] and [`matmul_f64`].
+pub(crate) fn matmul<T: BackendLane>(
+    ctx: &mut (impl Backend<T> + ?Sized),
+    m: usize,
+    n: usize,
+    k: usize,
+    a: &[T],
+    b: &[T],
+    c: &mut [T],
+) {
+    check_matmul_dims(m, n, k, a, b, c);
+    if n == 0 {
+        return;
+    } else if k == 0 {
+        c.fill(T::default());
+        return;
+    }
+
+    let mut a_panel = Vec::new();
+    for (a_strip, c_strip) in a.chunks(T::LANES * k).zip(c.chunks_mut(T::LANES * n)) {
+        matmul_strip(ctx, n, k, a_strip, b, c_strip, &mut a_panel);
+    }
+}
+
+/// The multi-threaded version of [`matmul_f32`].
+///
+/// The rows of the output matrix are partitioned into strips, which are
+/// distributed across the worker threads of the current [`rayon`] thread pool.
+/// Each worker thread creates its own context by [`AmxCtx::run`] for the
+/// duration of a strip.
+///
+/// Rayon may run strips on the calling thread if it's a worker thread of the
+/// pool. Unlike [`AmxCtx::scope`], `AmxCtx::run` doesn't share an existing
+/// context, so this panics rather than clobbering the registers of an
+/// [`AmxCtx`] that such a thread holds.
+///
+/// [`AmxCtx`]: crate::AmxCtx
+/// [`AmxCtx::run`]: crate::AmxCtx::run
+/// [`AmxCtx::scope`]: crate::AmxCtx::scope
+///
+/// # Panics
+///
+/// Panics if the slice lengths don't match the specified matrix sizes or
+/// if AMX can't be enabled, including when a thread running a strip already
+/// has an `AmxCtx`.
+#[cfg(all(feature = "parallel", any(doc, target_arch = "aarch64")))]
+#[cfg_attr(
+    feature = "doc_cfg",
+    doc(cfg(all(feature = "parallel", target_arch = "aarch64")))
+)]
+pub fn matmul_f32_par(m: usize, n: usize, k: usize, a: &[f32], b: &[f32], c: &mut [f32]) {
+    use rayon::prelude::*;
+
+    check_matmul_dims(m, n, k, a, b, c);
+    if n == 0 {
+        return;
+    } else if k == 0 {
+        c.fill(0.0);
+        return;
+    }
+
+    a.par_chunks(F32_LANES * k)
+        .zip(c.par_chunks_mut(F32_LANES * n))
+        .for_each_init(Vec::new, |a_panel, (a_strip, c_strip)| {
+            crate::AmxCtx::run(|ctx| matmul_strip(ctx, n, k, a_strip, b, c_strip, a_panel))
+                .unwrap();
+        });
+}
+
+fn check_matmul_dims<T>(m: usize, n: usize, k: usize, a: &[T], b: &[T], c: &[T]) {
+    assert_eq!(a.len(), m * k, "`a` must have `m * k` elements");
+    assert_eq!(b.len(), k * n, "`b` must have `k * n` elements");
+    assert_eq!(c.len(), m * n, "`c` must have `m * n` elements");
+}
+
+/// Calculate up to `T::LANES` rows of the output matrix. `a_panel` is a
+/// scratch buffer, which is passed from the caller so that it can be reused.
+fn matmul_strip<T: BackendLane>(
+    ctx: &mut (impl Backend<T> + ?Sized),
+    n: usize,
+    k: usize,
+    a_strip: &[T],
+    b: &[T],
+    c_strip: &mut [T],
+    a_panel: &mut Vec<T>,
+) {
+    let lanes = T::LANES;
+
+    // Transpose the strip of `a` so that each column can be loaded to `y` as
+    // a whole
+    a_panel.clear();
+    a_panel.resize(k * lanes, T::default());
+    for (r, a_row) in a_strip.chunks_exact(k).enumerate() {
+        for (col, &value) in a_panel.chunks_exact_mut(lanes).zip(a_row) {
+            col[r] = value;
+        }
+    }
+
+    let mut b_staging = [T::default(); MATMUL_BLOCK_COLS];
+    let mut c_staging = [T::default(); MATMUL_BLOCK_COLS];
+    for j0 in (0..n).step_by(MATMUL_BLOCK_COLS) {
+        let cols = (n - j0).min(MATMUL_BLOCK_COLS);
+        let num_tiles = cols.div_ceil(lanes);
+
+        for (p, a_col) in a_panel.chunks_exact(lanes).enumerate() {
+            let b_row = &b[p * n + j0..][..cols];
+            let b_row = if cols == MATMUL_BLOCK_COLS {
+                b_row
+            } else {
+                b_staging[..cols].copy_from_slice(b_row);
+                &b_staging[..]
+            };
+
+            ctx.load_y(0, a_col);
+            for t in 0..num_tiles {
+                ctx.load_x(t, &b_row[t * lanes..]);
+            }
+            for t in 0..num_tiles {
+                ctx.outer_product(t, 0, t, p != 0);
+            }
+        }
+
+        // `z[r * T::TILES + t]` holds `c[r][j0 + t * lanes..][..lanes]`
+        for (r, c_row) in c_strip.chunks_exact_mut(n).enumerate() {
+            for t in 0..num_tiles {
+                let out = &mut c_row[j0 + t * lanes..j0 + cols.min((t + 1) * lanes)];
+                if out.len() == lanes {
+                    ctx.store_z(r * T::TILES + t, out);
+                } else {
+                    ctx.store_z(r * T::TILES + t, &mut c_staging);
+                    out.copy_from_slice(&c_staging[..out.len()]);
+                }
+            }
+        }
+    }
+}
+
+/// The number of complex output columns processed at once by
+/// [`matmul_c32_strip`]. Each column block takes two tiles of `z`, one for the
+/// real parts and one for the imaginary parts.
+const MATMUL_C32_BLOCK_COLS: usize = 2 * F32_LANES;
+
+/// Calculate `c = a * b`, where `a`, `b`, and `c` are row-major complex
+/// matrices of size `m × k`, `k × n`, and `m × n`, respectively. Each element
+/// is stored as an interleaved pair of `f32`s (the real part followed by the
+/// imaginary part), so the slices have twice as many elements as the
+/// matrices.
+///
+/// The real and imaginary parts are accumulated in separate tiles of `z`,
+/// the real part subtracting `a.im * b.im` by `fms32`.
+///
+/// # Panics
+///
+/// Panics if the slice lengths don't match the specified matrix sizes.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// // (1 + 2i) * (3 - i) = 5 + 5i
+/// let (a, b) = ([1.0, 2.0], [3.0, -1.0]);
+/// let mut c = [0.0; 2];
+/// amx::kernels::matmul_c32(&mut ctx, 1, 1, 1, &a, &b, &mut c);
+/// assert_eq!(c, [5.0, 5.0]);
+/// ```
+pub fn matmul_c32(
+    ctx: &mut (impl Amx + ?Sized),
+    m: usize,
+    n: usize,
+    k: usize,
+    a: &[f32],
+    b: &[f32],
+    c: &mut [f32],
+) {
+    assert_eq!(a.len(), 2 * m * k, "`a` must have `2 * m * k` elements");
+    assert_eq!(b.len(), 2 * k * n, "`b` must have `2 * k * n` elements");
+    assert_eq!(c.len(), 2 * m * n, "`c` must have `2 * m * n` elements");
+    if n == 0 {
+        return;
+    } else if k == 0 {
+        c.fill(0.0);
+        return;
+    }
+
+    let mut a_panel = Vec::new();
+    for (a_strip, c_strip) in a
+        .chunks(2 * F32_LANES * k)
+        .zip(c.chunks_mut(2 * F32_LANES * n))
+    {
+        matmul_c32_strip(ctx, n, k, a_strip, b, c_strip, &mut a_panel);
+    }
+}
+
+/// Calculate up to 16 rows of the output matrix of [`matmul_c32`].
+fn matmul_c32_strip(
+    ctx: &mut (impl Amx + ?Sized),
+    n: usize,
+    k: usize,
+    a_strip: &[f32],
+    b: &[f32],
+    c_strip: &mut [f32],
+    a_panel: &mut Vec<f32>,
+) {
+    let lanes = F32_LANES;
+
+    // Transpose and deinterleave the strip of `a` so that the real and
+    // imaginary parts of each column can be loaded to `y` as a whole
+    a_panel.clear();
+    a_panel.resize(k * 2 * lanes, 0.0);
+    for (r, a_row) in a_strip.chunks_exact(2 * k).enumerate() {
+        for (col, value) in a_panel
+            .chunks_exact_mut(2 * lanes)
+            .zip(a_row.chunks_exact(2))
+        {
+            col[r] = value[0];
+            col[lanes + r] = value[1];
+        }
+    }
+
+    let mut b_staging = [0.0f32; 2 * MATMUL_C32_BLOCK_COLS];
+    let mut c_staging = [[0.0f32; F32_LANES]; 2];
+    for j0 in (0..n).step_by(MATMUL_C32_BLOCK_COLS) {
+        let cols = (n - j0).min(MATMUL_C32_BLOCK_COLS);
+        let num_blocks = cols.div_ceil(lanes);
+
+        for (p, a_col) in a_panel.chunks_exact(2 * lanes).enumerate() {
+            // `b_staging[t * 32..][..16]` holds the real parts and
+            // `b_staging[t * 32 + 16..][..16]` the imaginary parts of the
+            // `t`-th block
+            b_staging.fill(0.0);
+            let b_row = &b[2 * (p * n + j0)..][..2 * cols];
+            for (j, value) in b_row.chunks_exact(2).enumerate() {
+                let (t, col) = (j / lanes, j % lanes);
+                b_staging[t * 2 * lanes + col] = value[0];
+                b_staging[t * 2 * lanes + lanes + col] = value[1];
+            }
+
+            // Safety: Reading memory regions within `a_col` and `b_staging`
+            unsafe {
+                ctx.load512(a_col.as_ptr(), YRow(0));
+                ctx.load512(a_col[lanes..].as_ptr(), YRow(1));
+                for t in 0..num_blocks {
+                    ctx.load512(b_staging[t * 2 * lanes..].as_ptr(), XRow(t * 2));
+                    ctx.load512(b_staging[t * 2 * lanes + lanes..].as_ptr(), XRow(t * 2 + 1));
+                }
+            }
+
+            let (a_re, a_im) = (Some(YBytes(0)), Some(YBytes(64)));
+            for t in 0..num_blocks {
+                let (b_re, b_im) = (Some(XRow(t * 2).offset()), Some(XRow(t * 2 + 1).offset()));
+                let (z_re, z_im) = (ZRow(t * 2), ZRow(t * 2 + 1));
+                // re = a.re * b.re - a.im * b.im
+                ctx.outer_product_f32_xy_to_z(b_re, a_re, z_re, p != 0);
+                ctx.outer_product_f32_xy_to_z(b_im, a_im, z_re, ZWriteMode::Subtract);
+                // im = a.re * b.im + a.im * b.re
+                ctx.outer_product_f32_xy_to_z(b_im, a_re, z_im, p != 0);
+                ctx.outer_product_f32_xy_to_z(b_re, a_im, z_im, true);
+            }
+        }
+
+        // `z[r * 4 + t * 2]` and `z[r * 4 + t * 2 + 1]` hold the real and
+        // imaginary parts of `c[r][j0 + t * 16..][..16]`
+        for (r, c_row) in c_strip.chunks_exact_mut(2 * n).enumerate() {
+            for t in 0..num_blocks {
+                // Safety: Writing in memory regions within `c_staging`
+                unsafe {
+                    ctx.store512(c_staging[0].as_mut_ptr(), ZRow(r * 4 + t * 2));
+                    ctx.store512(c_staging[1].as_mut_ptr(), ZRow(r * 4 + t * 2 + 1));
+                }
+                let out = &mut c_row[2 * (j0 + t * lanes)..2 * (j0 + cols.min((t + 1) * lanes))];
+                for (i, value) in out.chunks_exact_mut(2).enumerate() {
+                    value[0] = c_staging[0][i];
+                    value[1] = c_staging[1][i];
+                }
+            }
+        }
+    }
+}
+
+/// Apply radix-4 FFT butterflies to the columns of a `4 × n` row-major
+/// complex matrix `data`, whose elements are stored as interleaved pairs of
+/// `f32`s like [`matmul_c32`].
+///
+/// Each column `x` is replaced by the forward DFT of `t ∘ x`, where `t` is
+/// the corresponding column of `twiddles` (a matrix of the same size) or all
+/// ones if `twiddles` is `None`. That is, `data[j][b]` becomes
+/// `Σ_p exp(-2πi·jp/4) · twiddles[p][b] · data[p][b]`. This is one stage of
+/// a decimation-in-time FFT of length `4 · n`, computed as a product of the
+/// DFT matrix and `data` by [`matmul_c32`]. The twiddle factors are applied
+/// by the CPU to a copy of `data` beforehand.
+///
+/// # Panics
+///
+/// Panics if `data.len()` isn't a multiple of `2 * 4` or if `twiddles` has a
+/// different length from `data`.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// // One column: [1, 1, 1, 1] -> [4, 0, 0, 0]
+/// let mut data = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+/// amx::kernels::fft_butterfly4_c32(&mut ctx, &mut data, None);
+/// assert_eq!(data, [4.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+/// ```
+pub fn fft_butterfly4_c32(
+    ctx: &mut (impl Amx + ?Sized),
+    data: &mut [f32],
+    twiddles: Option<&[f32]>,
+) {
+    fft_butterfly_c32(ctx, 4, data, twiddles);
+}
+
+/// The radix-8 version of [`fft_butterfly4_c32`].
+pub fn fft_butterfly8_c32(
+    ctx: &mut (impl Amx + ?Sized),
+    data: &mut [f32],
+    twiddles: Option<&[f32]>,
+) {
+    fft_butterfly_c32(ctx, 8, data, twiddles);
+}
+
+/// The implementation of [`fft_butterfly4_c32`] and [`fft_butterfly8_c32`].
+fn fft_butterfly_c32(
+    ctx: &mut (impl Amx + ?Sized),
+    radix: usize,
+    data: &mut [f32],
+    twiddles: Option<&[f32]>,
+) {
+    assert_eq!(
+        data.len() % (2 * radix),
+        0,
+        "`data` must have `2 * radix * n` elements"
+    );
+    let n = data.len() / (2 * radix);
+
+    let mut input = data.to_vec();
+    if let Some(twiddles) = twiddles {
+        assert_eq!(
+            twiddles.len(),
+            data.len(),
+            "`twiddles` must have as many elements as `data`"
+        );
+        for (x, t) in input.chunks_exact_mut(2).zip(twiddles.chunks_exact(2)) {
+            let (re, im) = (x[0] * t[0] - x[1] * t[1], x[0] * t[1] + x[1] * t[0]);
+            x[0] = re;
+            x[1] = im;
+        }
+    }
+
+    matmul_c32(ctx, radix, n, radix, dft_matrix_c32(radix), &input, data);
+}
+
+/// Get the `radix × radix` DFT matrix, built on first use.
+fn dft_matrix_c32(radix: usize) -> &'static [f32] {
+    static DFT4: OnceLock<Box<[f32]>> = OnceLock::new();
+    static DFT8: OnceLock<Box<[f32]>> = OnceLock::new();
+    let matrix = match radix {
+        4 => &DFT4,
+        8 => &DFT8,
+        _ => unreachable!(),
+    };
+    matrix.get_or_init(|| {
+        (0..radix * radix)
+            .flat_map(|i| unit_root_c32(i / radix * (i % radix), radix))
+            .collect()
+    })
+}
+
+/// Get `exp(-2πi·k/radix)` as `[re, im]`. `radix` must divide 8, which makes
+/// the values exact for the multiples of π/2.
+fn unit_root_c32(k: usize, radix: usize) -> [f32; 2] {
+    use std::f32::consts::FRAC_1_SQRT_2 as H;
+    const ROOTS: [[f32; 2]; 8] = [
+        [1.0, 0.0],
+        [H, -H],
+        [0.0, -1.0],
+        [-H, -H],
+        [-1.0, 0.0],
+        [-H, H],
+        [0.0, 1.0],
+        [H, H],
+    ];
+    debug_assert_eq!(8 % radix, 0);
+    ROOTS[k % radix * (8 / radix)]
+}
+
+/// The dimensions of a 2D convolution performed by [`conv2d_f32`] or
+/// [`conv2d_i16`].
+///
+/// The input is a `in_channels × in_height × in_width` array, and the kernel
+/// weights are a `out_channels × in_channels × kernel_height × kernel_width`
+/// array. The output is a `out_channels × out_height × out_width` array, where
+/// `out_height = in_height - kernel_height + 1` and `out_width = in_width -
+/// kernel_width + 1` (i.e., no padding and a stride of one). All arrays are
+/// row-major.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conv2dShape {
+    pub in_channels: usize,
+    pub out_channels: usize,
+    pub in_height: usize,
+    pub in_width: usize,
+    pub kernel_height: usize,
+    pub kernel_width: usize,
+}
+
+impl Conv2dShape {
+    /// Get the height of the output.
+    #[inline]
+    pub fn out_height(&self) -> usize {
+        self.in_height - self.kernel_height + 1
+    }
+
+    /// Get the width of the output.
+    #[inline]
+    pub fn out_width(&self) -> usize {
+        self.in_width - self.kernel_width + 1
+    }
+
+    /// Get the number of products summed for each output element.
+    #[inline]
+    fn kernel_len(&self) -> usize {
+        self.in_channels * self.kernel_height * self.kernel_width
+    }
+
+    fn check<T>(&self, input: &[T], weights: &[T], output: &[T]) {
+        assert!(
+            (1..=self.in_height).contains(&self.kernel_height)
+                && (1..=self.in_width).contains(&self.kernel_width),
+            "the kernel must be non-empty and fit in the input"
+        );
+        assert_eq!(
+            input.len(),
+            self.in_channels * self.in_height * self.in_width,
+            "`input` must have `in_channels * in_height * in_width` elements"
+        );
+        assert_eq!(
+            weights.len(),
+            self.out_channels * self.kernel_len(),
+            "`weights` must have `out_channels * in_channels * kernel_height * \
+             kernel_width` elements"
+        );
+        assert_eq!(
+            output.len(),
+            self.out_channels * self.out_height() * self.out_width(),
+            "`output` must have `out_channels * out_height * out_width` elements"
+        );
+    }
+}
+
+/// Calculate the 2D convolution (strictly speaking, cross-correlation) of
+/// `input` and `weights`, whose dimensions are described by `shape`.
+///
+/// Patches of the input are loaded directly from `input` without being
+/// expanded into a matrix first (im2col), so no scratch memory proportional
+/// to the input size is needed.
+///
+/// # Panics
+///
+/// Panics if the kernel is empty or larger than the input, or if the slice
+/// lengths don't match `shape`.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::kernels::Conv2dShape;
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let shape = Conv2dShape {
+///     in_channels: 1,
+///     out_channels: 1,
+///     in_height: 2,
+///     in_width: 3,
+///     kernel_height: 2,
+///     kernel_width: 2,
+/// };
+/// let input = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+/// let weights = [1.0, 0.0, 0.0, -1.0];
+/// let mut output = [0.0; 2];
+/// amx::kernels::conv2d_f32(&mut ctx, shape, &input, &weights, &mut output);
+/// assert_eq!(output, [-4.0, -4.0]);
+/// ```
+pub fn conv2d_f32(
+    ctx: &mut (impl Amx + ?Sized),
+    shape: Conv2dShape,
+    input: &[f32],
+    weights: &[f32],
+    output: &mut [f32],
+) {
+    conv2d(ctx, shape, input, weights, output);
+}
+
+/// The `i16` version of [`conv2d_f32`]. The products are accumulated with
+/// wrapping arithmetic.
+///
+/// # Panics
+///
+/// Panics if the kernel is empty or larger than the input, or if the slice
+/// lengths don't match `shape`.
+pub fn conv2d_i16(
+    ctx: &mut (impl Amx + ?Sized),
+    shape: Conv2dShape,
+    input: &[i16],
+    weights: &[i16],
+    output: &mut [i16],
+) {
+    conv2d(ctx, shape, input, weights, output);
+}
+
+/// An element type supported by [`matmul_strip`] and [`conv2d`].
+pub(crate) trait OuterProductLane: Lane + Default {
+    /// Issue an accumulating (if `accumulate` is `true`) outer product of the
+    /// element type.
+    fn outer_product(
+        ctx: &mut (impl Amx + ?Sized),
+        x: XBytes,
+        y: YBytes,
+        z: ZRow,
+        accumulate: bool,
+    );
+}
+
+impl OuterProductLane for f32 {
+    #[inline]
+    fn outer_product(
+        ctx: &mut (impl Amx + ?Sized),
+        x: XBytes,
+        y: YBytes,
+        z: ZRow,
+        accumulate: bool,
+    ) {
+        ctx.outer_product_f32_xy_to_z(Some(x), Some(y), z, accumulate);
+    }
+}
+
+impl OuterProductLane for f64 {
+    #[inline]
+    fn outer_product(
+        ctx: &mut (impl Amx + ?Sized),
+        x: XBytes,
+        y: YBytes,
+        z: ZRow,
+        accumulate: bool,
+    ) {
+        ctx.outer_product_f64_xy_to_z(Some(x), Some(y), z, accumulate);
+    }
+}
+
+impl OuterProductLane for i16 {
+    #[inline]
+    fn outer_product(
+        ctx: &mut (impl Amx + ?Sized),
+        x: XBytes,
+        y: YBytes,
+        z: ZRow,
+        accumulate: bool,
+    ) {
+        ctx.outer_product_i16_xy_to_z(Some(x), Some(y), z, accumulate);
+    }
+}
+
+/// The number of output columns processed at once. `T::LANES` output
+/// channels × this many columns fill the whole `z`.
+const CONV_BLOCK_COLS: usize = 64;
+
+/// The implementation of [`conv2d_f32`] and [`conv2d_i16`].
+///
+/// The convolution is treated as a matrix multiplication whose rows are output
+/// channels and whose columns are output pixels. Each outer product takes a
+/// weight for `T::LANES` output channels from `y` and a contiguous run of an
+/// input row (which corresponds to a contiguous run of an output row) from
+/// `x`.
+fn conv2d<T: OuterProductLane>(
+    ctx: &mut (impl Amx + ?Sized),
+    shape: Conv2dShape,
+    input: &[T],
+    weights: &[T],
+    output: &mut [T],
+) {
+    shape.check(input, weights, output);
+    let Conv2dShape {
+        in_height: h,
+        in_width: w,
+        kernel_height: kh,
+        kernel_width: kw,
+        ..
+    } = shape;
+    let (oh, ow, k) = (shape.out_height(), shape.out_width(), shape.kernel_len());
+    if k == 0 {
+        output.fill(T::default());
+        return;
+    }
+
+    // `z[r * num_tiles_max + t]` accumulates the output channel `r` of the
+    // `t`-th group of `T::LANES` columns
+    let lanes = T::LANES;
+    let num_tiles_max = CONV_BLOCK_COLS / lanes;
+
+    let mut w_panel = vec![T::default(); k * lanes];
+    let mut x_staging = [T::default(); CONV_BLOCK_COLS];
+    let mut z_staging = [T::default(); CONV_BLOCK_COLS];
+    for (co0, w_strip) in (0..).step_by(lanes).zip(weights.chunks(k * lanes)) {
+        // Transpose the weights of up to `T::LANES` output channels so that
+        // each kernel element can be loaded to `y` as a whole
+        for (r, w_row) in w_strip.chunks_exact(k).enumerate() {
+            for (p, &value) in w_row.iter().enumerate() {
+                w_panel[p * lanes + r] = value;
+            }
+        }
+        let num_rows = w_strip.len() / k;
+
+        for oy in 0..oh {
+            for ox0 in (0..ow).step_by(CONV_BLOCK_COLS) {
+                let cols = (ow - ox0).min(CONV_BLOCK_COLS);
+                let num_tiles = cols.div_ceil(lanes);
+
+                let mut p = 0;
+                for ci in 0..shape.in_channels {
+                    for dy in 0..kh {
+                        for dx in 0..kw {
+                            let src = &input[(ci * h + oy + dy) * w + ox0 + dx..][..cols];
+                            let src = if cols == CONV_BLOCK_COLS {
+                                src
+                            } else {
+                                x_staging[..cols].copy_from_slice(src);
+                                &x_staging[..]
+                            };
+
+                            // Safety: Reading memory regions within
+                            //         `w_panel` and `src`
+                            unsafe {
+                                ctx.load512(w_panel[p * lanes..].as_ptr(), YRow(0));
+                                for t in 0..num_tiles {
+                                    ctx.load512(src[t * lanes..].as_ptr(), XRow(t));
+                                }
+                            }
+
+                            for t in 0..num_tiles {
+                                T::outer_product(ctx, XRow(t).offset(), YBytes(0), ZRow(t), p != 0);
+                            }
+                            p += 1;
+                        }
+                    }
+                }
+
+                for r in 0..num_rows {
+                    let out_row = &mut output[((co0 + r) * oh + oy) * ow + ox0..][..cols];
+                    for (t, out) in out_row.chunks_mut(lanes).enumerate() {
+                        let z_row = ZRow(r * num_tiles_max + t);
+                        // Safety: Writing in a memory region within `out` or
+                        //         `z_staging`
+                        unsafe {
+                            if out.len() == lanes {
+                                ctx.store512(out.as_mut_ptr(), z_row);
+                            } else {
+                                ctx.store512(z_staging.as_mut_ptr(), z_row);
+                                out.copy_from_slice(&z_staging[..out.len()]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Construct the operand of `fma*` or `mac16` in vector mode, which
+/// accumulates (if `accumulate` is `true`) the element-wise product of the
+/// rows at `x` and `y` to `z`. If `y` is `None`, the row at `x` is accumulated
+/// as it is.
+#[inline]
+fn vector_fma_operand(x: XBytes, y: Option<YBytes>, z: ZRow, accumulate: bool) -> u64 {
+    outer_product_operand(Some(x), y, z, accumulate) | FMA_VECTOR
+}
+
+/// The number of elements processed at once by the reduction kernels, which
+/// fill all rows of `x` (and `y`).
+const fn reduce_chunk_len<T>() -> usize {
+    512 / std::mem::size_of::<T>()
+}
+
+/// Calculate the dot product of `a` and `b`.
+///
+/// The products are accumulated in 128 independent lanes, which are summed
+/// up at the end, so the result may differ from a sequential sum by rounding
+/// errors. Like [`matmul_f32`], this runs on any [`Backend`].
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let dot = amx::kernels::dot_f32(&mut ctx, &[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]);
+/// assert_eq!(dot, 32.0);
+/// ```
+pub fn dot_f32(ctx: &mut (impl Backend<f32> + ?Sized), a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "`a` and `b` must have the same length");
+    reduce_f32(ctx, a, Some(b))
+}
+
+/// Calculate the sum of the elements of `a`.
+///
+/// Like [`dot_f32`], the result may differ from a sequential sum by rounding
+/// errors.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let a: Vec<f32> = (1..=200).map(|i| i as f32).collect();
+/// assert_eq!(amx::kernels::sum_f32(&mut ctx, &a), 20100.0);
+/// ```
+pub fn sum_f32(ctx: &mut (impl Backend<f32> + ?Sized), a: &[f32]) -> f32 {
+    reduce_f32(ctx, a, None)
+}
+
+/// Accumulate the element-wise product of `a` and `b` (or `a` itself if `b`
+/// is `None`) to `z[0..8]` and sum up the result.
+fn reduce_f32(ctx: &mut (impl Backend<f32> + ?Sized), a: &[f32], b: Option<&[f32]>) -> f32 {
+    const CHUNK_LEN: usize = reduce_chunk_len::<f32>();
+    if a.is_empty() {
+        return 0.0;
+    }
+
+    let mut a_staging = [0.0f32; CHUNK_LEN];
+    let mut b_staging = [0.0f32; CHUNK_LEN];
+    for (i, a_chunk) in a.chunks(CHUNK_LEN).enumerate() {
+        let b_chunk = b.map(|b| &b[i * CHUNK_LEN..][..a_chunk.len()]);
+        let (a_chunk, b_chunk) = if a_chunk.len() == CHUNK_LEN {
+            (a_chunk, b_chunk)
+        } else {
+            // Pad the last chunk with zeros
+            a_staging[..a_chunk.len()].copy_from_slice(a_chunk);
+            if let Some(b_chunk) = b_chunk {
+                b_staging[..b_chunk.len()].copy_from_slice(b_chunk);
+            }
+            (&a_staging[..], b_chunk.map(|_| &b_staging[..]))
+        };
+
+        for r in 0..CHUNK_LEN / F32_LANES {
+            ctx.load_x(r, &a_chunk[r * F32_LANES..]);
+            if let Some(b_chunk) = b_chunk {
+                ctx.load_y(r, &b_chunk[r * F32_LANES..]);
+            }
+            ctx.vector_fma(r, b_chunk.map(|_| r), r, i != 0);
+        }
+    }
+
+    // Horizontal reduction
+    let mut z = [0.0f32; CHUNK_LEN];
+    for (r, z_row) in z.chunks_exact_mut(F32_LANES).enumerate() {
+        ctx.store_z(r, z_row);
+    }
+    z.iter().sum()
+}
+
+/// Calculate the dot product of `a` and `b`.
+///
+/// The elements are converted to `f64` before being multiplied, whose
+/// products and partial sums are exact, so the result is exact as well.
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let a = [i16::MIN; 1000];
+/// assert_eq!(amx::kernels::dot_i16(&mut ctx, &a, &a), 1000 << 30);
+/// ```
+pub fn dot_i16(ctx: &mut (impl Amx + ?Sized), a: &[i16], b: &[i16]) -> i64 {
+    /// The number of `f64` lanes in a register row
+    const F64_LANES: usize = 8;
+    const CHUNK_LEN: usize = reduce_chunk_len::<f64>();
+    /// The number of chunks accumulated in `z` before being flushed to
+    /// `sum`. Each lane of `z` grows by at most `2^30` per chunk, so this
+    /// keeps it well within the range where `f64` represents integers
+    /// exactly (`2^53`).
+    const FLUSH_INTERVAL: usize = 1 << 20;
+    assert_eq!(a.len(), b.len(), "`a` and `b` must have the same length");
+
+    let mut sum = 0i64;
+    let mut a_f64 = [0.0f64; CHUNK_LEN];
+    let mut b_f64 = [0.0f64; CHUNK_LEN];
+    let mut z = [0.0f64; CHUNK_LEN];
+    for (a_group, b_group) in a
+        .chunks(CHUNK_LEN * FLUSH_INTERVAL)
+        .zip(b.chunks(CHUNK_LEN * FLUSH_INTERVAL))
+    {
+        for (i, (a_chunk, b_chunk)) in a_group
+            .chunks(CHUNK_LEN)
+            .zip(b_group.chunks(CHUNK_LEN))
+            .enumerate()
+        {
+            a_f64.fill(0.0);
+            b_f64.fill(0.0);
+            for ((a_out, b_out), (&x, &y)) in a_f64
+                .iter_mut()
+                .zip(&mut b_f64)
+                .zip(a_chunk.iter().zip(b_chunk))
+            {
+                *a_out = x as f64;
+                *b_out = y as f64;
+            }
+
+            for r in 0..CHUNK_LEN / F64_LANES {
+                // Safety: Reading memory regions within `a_f64` and `b_f64`
+                unsafe {
+                    ctx.load512(a_f64[r * F64_LANES..].as_ptr(), XRow(r));
+                    ctx.load512(b_f64[r * F64_LANES..].as_ptr(), YRow(r));
+                }
+                let operand =
+                    vector_fma_operand(XRow(r).offset(), Some(YRow(r).offset()), ZRow(r), i != 0);
+                ctx.fma64(operand);
+            }
+        }
+
+        // Horizontal reduction
+        for (r, z_row) in z.chunks_exact_mut(F64_LANES).enumerate() {
+            // Safety: Writing in a memory region within `z_row`
+            unsafe { ctx.store512(z_row.as_mut_ptr(), ZRow(r)) };
+        }
+        sum += z.iter().map(|&x| x as i64).sum::<i64>();
+    }
+    sum
+}
+
+/// The number of `i16` elements in a register row.
+const I16_LANES: usize = 32;
+
+/// The per-lane multipliers of [`fingerprint_i16`].
+const FINGERPRINT_MULTIPLIERS: [i16; I16_LANES] = {
+    // Odd numbers generated by a linear congruential generator
+    let mut out = [0; I16_LANES];
+    let mut state = 0x9e37_79b9u32;
+    let mut i = 0;
+    while i < I16_LANES {
+        state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+        out[i] = ((state >> 16) as u16 | 1) as i16;
+        i += 1;
+    }
+    out
+};
+
+/// The number of independent streams accumulated in `z` by
+/// [`fingerprint_i16`].
+const FINGERPRINT_STREAMS: usize = 8;
+
+/// Calculate a 64-bit fingerprint of `data` by rolling multiply-accumulate
+/// checksums.
+///
+/// `data` is split into blocks of 32 elements, the last one padded with
+/// zeros, and the `i`-th block is fed to the stream `i % 8`. Each stream
+/// keeps 32 lanes of 16-bit state `s`, which start from zero and are updated
+/// by `s = s * m + block` for each block, using wrapping arithmetic and fixed
+/// odd per-lane multipliers `m`. This is computed by vector-mode `mac16`.
+/// Finally, the 256 lanes of the streams (stream-major) are folded into
+/// `data.len()` by the FNV-1a-like mixing step `h = (h ^ lane) * P`, where `P`
+/// is the 64-bit FNV prime.
+///
+/// The result only depends on `data`, so it can be compared across backends
+/// and runs. It's not a cryptographic hash, and it's far from collision-free
+/// against adversarial inputs.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let data: Vec<i16> = (0..1000).collect();
+/// let mut swapped = data.clone();
+/// swapped.swap(0, 256);
+/// assert_ne!(
+///     amx::kernels::fingerprint_i16(&mut ctx, &data),
+///     amx::kernels::fingerprint_i16(&mut ctx, &swapped),
+/// );
+/// ```
+pub fn fingerprint_i16(ctx: &mut (impl Amx + ?Sized), data: &[i16]) -> u64 {
+    const FNV_PRIME: u64 = 0x100_0000_01b3;
+    // Safety: Reading a memory region within `FINGERPRINT_MULTIPLIERS`
+    unsafe { ctx.load512(FINGERPRINT_MULTIPLIERS.as_ptr(), YRow(0)) };
+    for r in 0..FINGERPRINT_STREAMS {
+        ctx.clear_z_row(ZRow(r));
+    }
+
+    let mut staging = [0i16; I16_LANES];
+    for (i, block) in data.chunks(I16_LANES).enumerate() {
+        let block = if block.len() == I16_LANES {
+            block
+        } else {
+            // Pad the last block with zeros
+            staging[..block.len()].copy_from_slice(block);
+            &staging[..]
+        };
+
+        // `z[r] = x[r] * m + block`, where `x[r]` is the old `z[r]`
+        let stream = i % FINGERPRINT_STREAMS;
+        ctx.copy_z_row_to_x(ZRow(stream), XRow(stream));
+        // Safety: Reading a memory region within `block`
+        unsafe { ctx.load512(block.as_ptr(), ZRow(stream)) };
+        ctx.mac16(vector_fma_operand(
+            XRow(stream).offset(),
+            Some(YBytes(0)),
+            ZRow(stream),
+            true,
+        ));
+    }
+
+    let mut state = [0i16; I16_LANES];
+    let mut hash = data.len() as u64;
+    for r in 0..FINGERPRINT_STREAMS {
+        // Safety: Writing in a memory region within `state`
+        unsafe { ctx.store512(state.as_mut_ptr(), ZRow(r)) };
+        for &lane in &state {
+            hash = (hash ^ lane as u16 as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+    hash
+}
+
+/// Find the index of the largest element of `x`.
+///
+/// NaNs are ignored. If there are multiple largest elements, the index of
+/// the first one is returned. If every element is NaN, `0` is returned.
+///
+/// The running maximum is kept in a `genlut` table, and the elements are
+/// compared against it by reverse table lookups ([`Amx::reverse_lut`]), 128
+/// at a time. Only the elements that the lookups find to be no less than the
+/// running maximum are examined by the CPU, which updates the maximum and its
+/// index.
+///
+/// # Panics
+///
+/// Panics if `x` is empty.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let x = [1.0, 5.0, f32::NAN, -3.0, 5.0];
+/// assert_eq!(amx::kernels::argmax_f32(&mut ctx, &x), 1);
+/// assert_eq!(amx::kernels::argmin_f32(&mut ctx, &x), 3);
+/// ```
+pub fn argmax_f32(ctx: &mut (impl Amx + ?Sized), x: &[f32]) -> usize {
+    arg_reduce_f32(ctx, x, Reduce::Max)
+}
+
+/// Find the index of the smallest element of `x`. See [`argmax_f32`] for
+/// details.
+///
+/// # Panics
+///
+/// Panics if `x` is empty.
+pub fn argmin_f32(ctx: &mut (impl Amx + ?Sized), x: &[f32]) -> usize {
+    arg_reduce_f32(ctx, x, Reduce::Min)
+}
+
+/// The implementation of [`argmax_f32`] and [`argmin_f32`]. `op` must be
+/// `Reduce::Max` or `Reduce::Min`.
+fn arg_reduce_f32(ctx: &mut (impl Amx + ?Sized), x: &[f32], op: Reduce) -> usize {
+    const ROWS: usize = 8;
+    const CHUNK_LEN: usize = ROWS * F32_LANES;
+    /// The lowest bit of each 4-bit index
+    const INDEX_LSBS: u64 = 0x1111_1111_1111_1111;
+    assert!(!x.is_empty(), "`x` must not be empty");
+    let is_max = op == Reduce::Max;
+
+    // Start with the first non-NaN element
+    let Some(first) = x.iter().position(|x| !x.is_nan()) else {
+        return 0;
+    };
+    let mut best = first;
+
+    let mut staging = [f32::NAN; CHUNK_LEN];
+    let mut indices = [[0u8; 64]; ROWS];
+    let mut table_value = None;
+    for (i, chunk) in x[first + 1..].chunks(CHUNK_LEN).enumerate() {
+        let start = first + 1 + i * CHUNK_LEN;
+        let chunk = if chunk.len() == CHUNK_LEN {
+            chunk
+        } else {
+            // Pad the last chunk with NaNs, which are ignored
+            staging[..chunk.len()].copy_from_slice(chunk);
+            &staging[..]
+        };
+
+        // The reverse lookup yields `0` for the elements less than the running
+        // extremum and `1` for the others. NaNs yield all ones, as do the
+        // elements greater than or equal to the last entry (`+∞`).
+        if table_value != Some(x[best]) {
+            let mut table = [f32::INFINITY; F32_LANES];
+            table[0] = f32::NEG_INFINITY;
+            table[1] = x[best];
+            // Safety: Reading a memory region within `table`
+            unsafe { ctx.load512(table.as_ptr(), XRow(0)) };
+            table_value = Some(x[best]);
+        }
+        for r in 0..ROWS {
+            // Safety: Reading a memory region within `chunk`
+            unsafe { ctx.load512(chunk[r * F32_LANES..].as_ptr(), YRow(r)) };
+            ctx.reverse_lut(YRow(r).offset(), XRow(0), ZRow(r), F32);
+        }
+
+        for (r, row_indices) in indices.iter_mut().enumerate() {
+            // Safety: Writing a memory region within `row_indices`
+            unsafe { ctx.store512(row_indices.as_mut_ptr(), ZRow(r)) };
+            let row_indices = u64::from_le_bytes(row_indices[..8].try_into().unwrap());
+            let nonzero =
+                (row_indices | (row_indices >> 1) | (row_indices >> 2) | (row_indices >> 3))
+                    & INDEX_LSBS;
+
+            // Examine the elements that may replace the running extremum
+            let mut candidates = if is_max {
+                nonzero
+            } else {
+                !nonzero & INDEX_LSBS
+            };
+            while candidates != 0 {
+                let lane = candidates.trailing_zeros() as usize / 4;
+                candidates &= candidates - 1;
+                let value = chunk[r * F32_LANES + lane];
+                if op.apply(value, x[best]) != x[best] {
+                    best = start + r * F32_LANES + lane;
+                }
+            }
+        }
+    }
+
+    best
+}
+
+/// Calculate `y = alpha * x + y`.
+///
+/// Like [`matmul_f32`], this and [`scale_f32`], [`add_f32`], and [`mul_f32`]
+/// run on any [`Backend`].
+///
+/// # Panics
+///
+/// Panics if `x` and `y` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let mut y = [1.0, 2.0, 3.0];
+/// amx::kernels::axpy_f32(&mut ctx, 2.0, &[1.0, 1.0, -1.0], &mut y);
+/// assert_eq!(y, [3.0, 4.0, 1.0]);
+/// ```
+pub fn axpy_f32(ctx: &mut (impl Backend<f32> + ?Sized), alpha: f32, x: &[f32], y: &mut [f32]) {
+    assert_eq!(x.len(), y.len(), "`x` and `y` must have the same length");
+    elementwise_f32(
+        ctx,
+        y,
+        VecSrc::Slice(x),
+        Some(VecSrc::Splat(alpha)),
+        Some(VecSrc::Out),
+    );
+}
+
+/// Calculate `x = alpha * x`.
+pub fn scale_f32(ctx: &mut (impl Backend<f32> + ?Sized), alpha: f32, x: &mut [f32]) {
+    elementwise_f32(ctx, x, VecSrc::Out, Some(VecSrc::Splat(alpha)), None);
+}
+
+/// Calculate `out[i] = a[i] + b[i]`.
+///
+/// # Panics
+///
+/// Panics if `a`, `b`, and `out` have different lengths.
+pub fn add_f32(ctx: &mut (impl Backend<f32> + ?Sized), a: &[f32], b: &[f32], out: &mut [f32]) {
+    check_elementwise_dims(a, b, out);
+    elementwise_f32(ctx, out, VecSrc::Slice(a), None, Some(VecSrc::Slice(b)));
+}
+
+/// Calculate `out[i] = a[i] * b[i]`.
+///
+/// # Panics
+///
+/// Panics if `a`, `b`, and `out` have different lengths.
+pub fn mul_f32(ctx: &mut (impl Backend<f32> + ?Sized), a: &[f32], b: &[f32], out: &mut [f32]) {
+    check_elementwise_dims(a, b, out);
+    elementwise_f32(ctx, out, VecSrc::Slice(a), Some(VecSrc::Slice(b)), None);
+}
+
+fn check_elementwise_dims(a: &[f32], b: &[f32], out: &[f32]) {
+    assert!(
+        a.len() == out.len() && b.len() == out.len(),
+        "`a`, `b`, and `out` must have the same length"
+    );
+}
+
+/// An operand of [`elementwise_f32`].
+#[derive(Clone, Copy)]
+enum VecSrc<'a> {
+    /// The output slice (before being overwritten)
+    Out,
+    Slice(&'a [f32]),
+    /// A scalar repeated for every element. Only valid as the multiplier.
+    Splat(f32),
+}
+
+/// Calculate `out[i] = a[i] * b[i] + c[i]` by vector-mode `fma32`, 64 bytes at
+/// a time. The multiplier `b` defaults to one, and the addend `c` defaults to
+/// zero. Every `VecSrc::Slice` must be as long as `out`.
+fn elementwise_f32(
+    ctx: &mut (impl Backend<f32> + ?Sized),
+    out: &mut [f32],
+    a: VecSrc<'_>,
+    b: Option<VecSrc<'_>>,
+    c: Option<VecSrc<'_>>,
+) {
+    if let Some(VecSrc::Splat(value)) = b {
+        ctx.load_y(0, &[value; F32_LANES]);
+    }
+
+    let mut staging = [[0.0f32; F32_LANES]; 3];
+    let [a_staging, b_staging, c_staging] = &mut staging;
+    for i0 in (0..out.len()).step_by(F32_LANES) {
+        ctx.load_x(0, elementwise_chunk(a, out, i0, a_staging));
+        if let Some(b @ (VecSrc::Out | VecSrc::Slice(_))) = b {
+            ctx.load_y(0, elementwise_chunk(b, out, i0, b_staging));
+        }
+        if let Some(c) = c {
+            ctx.load_z(0, elementwise_chunk(c, out, i0, c_staging));
+        }
+        ctx.vector_fma(0, b.map(|_| 0), 0, c.is_some());
+
+        let out = &mut out[i0..];
+        if out.len() >= F32_LANES {
+            ctx.store_z(0, out);
+        } else {
+            ctx.store_z(0, a_staging);
+            let len = out.len();
+            out.copy_from_slice(&a_staging[..len]);
+        }
+    }
+}
+
+/// Get `F32_LANES` elements of `src` starting at `i0`, copying them to
+/// `staging` if fewer than that remain.
+fn elementwise_chunk<'a>(
+    src: VecSrc<'a>,
+    out: &'a [f32],
+    i0: usize,
+    staging: &'a mut [f32; F32_LANES],
+) -> &'a [f32] {
+    let src = match src {
+        VecSrc::Out => out,
+        VecSrc::Slice(s) => s,
+        VecSrc::Splat(_) => unreachable!(),
+    };
+    let src = &src[i0..];
+    if src.len() >= F32_LANES {
+        src
+    } else {
+        staging[..src.len()].copy_from_slice(src);
+        staging
+    }
+}
+
+/// Get the length of the buffer [`batch_interleave_f32`] produces for `count`
+/// matrices of `elems` elements each.
+#[inline]
+pub fn batch_interleaved_len(elems: usize, count: usize) -> usize {
+    count.div_ceil(F32_LANES) * F32_LANES * elems
+}
+
+/// Convert `src`, an array of matrices of `elems` elements each, into the
+/// interleaved layout used by [`batched_matmul_f32`].
+///
+/// The matrices are divided into groups of 16, and each group is stored as
+/// `elems` runs of 16 elements, the `i`-th of which consists of the `i`-th
+/// element of every matrix in the group. The last group is padded with zeros.
+///
+/// # Panics
+///
+/// Panics if `elems` is zero, `src.len()` is not a multiple of `elems`, or `dst.len()` is not
+/// [`batch_interleaved_len`]`(elems, src.len() / elems)`.
+pub fn batch_interleave_f32(elems: usize, src: &[f32], dst: &mut [f32]) {
+    let count = check_batch_dims(elems, src.len(), dst.len());
+    dst.fill(0.0);
+    for b in 0..count {
+        let (group, lane) = (b / F32_LANES, b % F32_LANES);
+        for (e, &value) in src[b * elems..][..elems].iter().enumerate() {
+            dst[(group * elems + e) * F32_LANES + lane] = value;
+        }
+    }
+}
+
+/// The inverse of [`batch_interleave_f32`]. The padding in `src` is ignored.
+///
+/// # Panics
+///
+/// Panics if `elems` is zero, `dst.len()` is not a multiple of `elems`, or `src.len()` is not
+/// [`batch_interleaved_len`]`(elems, dst.len() / elems)`.
+pub fn batch_deinterleave_f32(elems: usize, src: &[f32], dst: &mut [f32]) {
+    let count = check_batch_dims(elems, dst.len(), src.len());
+    for b in 0..count {
+        let (group, lane) = (b / F32_LANES, b % F32_LANES);
+        for (e, out) in dst[b * elems..][..elems].iter_mut().enumerate() {
+            *out = src[(group * elems + e) * F32_LANES + lane];
+        }
+    }
+}
+
+/// Validate the buffer lengths for [`batch_interleave_f32`] and return the
+/// number of matrices.
+fn check_batch_dims(elems: usize, len: usize, interleaved_len: usize) -> usize {
+    assert!(
+        elems != 0 && len % elems == 0,
+        "`elems` must be non-zero and divide the matrix array's length"
+    );
+    let count = len / elems;
+    assert_eq!(
+        interleaved_len,
+        batch_interleaved_len(elems, count),
+        "the interleaved buffer's length must be `batch_interleaved_len(elems, count)`"
+    );
+    count
+}
+
+/// Calculate `c[i] = a[i] * b[i]` for each `i`, where `a[i]`, `b[i]`, and
+/// `c[i]` are row-major `f32` matrices of size `m × k`, `k × n`, and `m × n`,
+/// respectively.
+///
+/// `a`, `b`, and `c` must be in the interleaved layout produced by
+/// [`batch_interleave_f32`]. Each lane of a register row corresponds to one
+/// matrix of a group, so sixteen matrices are processed at once by
+/// vector-mode `fma32`s, with every lane of `x`, `y`, and `z` doing useful
+/// work regardless of how small the matrices are. `m` and `n` are limited to
+/// `8` because a column of `a[i]` and a row of `b[i]` must fit in `x` and `y`.
+///
+/// # Panics
+///
+/// Panics if `m` or `n` is not in range `1..=8` or the slice lengths don't
+/// correspond to the same number of groups.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::kernels::{batch_deinterleave_f32, batch_interleave_f32, batch_interleaved_len};
+/// let mut ctx = amx::AmxEmuCtx::default();
+///
+/// // Two 2x2 matrix products
+/// let a = [1.0, 2.0, 3.0, 4.0, /**/ 1.0, 0.0, 0.0, 1.0];
+/// let b = [1.0, 1.0, 0.0, 1.0, /**/ 5.0, 6.0, 7.0, 8.0];
+/// let len = batch_interleaved_len(4, 2);
+/// let (mut a_il, mut b_il, mut c_il) = (vec![0.0; len], vec![0.0; len], vec![0.0; len]);
+/// batch_interleave_f32(4, &a, &mut a_il);
+/// batch_interleave_f32(4, &b, &mut b_il);
+/// amx::kernels::batched_matmul_f32(&mut ctx, 2, 2, 2, &a_il, &b_il, &mut c_il);
+///
+/// let mut c = [0.0; 8];
+/// batch_deinterleave_f32(4, &c_il, &mut c);
+/// assert_eq!(c, [1.0, 3.0, 3.0, 7.0, /**/ 5.0, 6.0, 7.0, 8.0]);
+/// ```
+pub fn batched_matmul_f32(
+    ctx: &mut (impl Amx + ?Sized),
+    m: usize,
+    n: usize,
+    k: usize,
+    a: &[f32],
+    b: &[f32],
+    c: &mut [f32],
+) {
+    assert!(
+        (1..=8).contains(&m) && (1..=8).contains(&n),
+        "`m` and `n` must be in range `1..=8`"
+    );
+    let (a_len, b_len, c_len) = (m * k * F32_LANES, k * n * F32_LANES, m * n * F32_LANES);
+    let num_groups = c.len() / c_len;
+    assert!(
+        c.len() == num_groups * c_len
+            && a.len() == num_groups * a_len
+            && b.len() == num_groups * b_len,
+        "`a`, `b`, and `c` must consist of the same number of interleaved groups"
+    );
+    if k == 0 {
+        c.fill(0.0);
+        return;
+    }
+
+    for ((a_group, b_group), c_group) in a
+        .chunks_exact(a_len)
+        .zip(b.chunks_exact(b_len))
+        .zip(c.chunks_exact_mut(c_len))
+    {
+        for p in 0..k {
+            // Safety: Reading memory regions within `a_group` and `b_group`
+            unsafe {
+                for i in 0..m {
+                    ctx.load512(a_group[(i * k + p) * F32_LANES..].as_ptr(), XRow(i));
+                }
+                for j in 0..n {
+                    ctx.load512(b_group[(p * n + j) * F32_LANES..].as_ptr(), YRow(j));
+                }
+            }
+            for i in 0..m {
+                for j in 0..n {
+                    ctx.fma32(vector_fma_operand(
+                        XRow(i).offset(),
+                        Some(YRow(j).offset()),
+                        ZRow(i * n + j),
+                        p != 0,
+                    ));
+                }
+            }
+        }
+
+        // `z[i * n + j]` holds the elements `(i, j)` of the group
+        for (e, c_elem) in c_group.chunks_exact_mut(F32_LANES).enumerate() {
+            // Safety: Writing in a memory region within `c_elem`
+            unsafe { ctx.store512(c_elem.as_mut_ptr(), ZRow(e)) };
+        }
+    }
+}
+
+/// The error type for [`potrf_f64_tile`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct NotPositiveDefinite {
+    /// The column where a non-positive (or NaN) pivot was encountered.
+    pub column: usize,
+}
+
+/// An 8×8 `f64` matrix in row-major order.
+type F64Tile = [[f64; 8]; 8];
+
+/// Read the 8×8 `f64` matrix held by `tile`, whose `i`-th row is in
+/// `tile.row(i)`.
+fn read_f64_tile(ctx: &mut (impl Amx + ?Sized), tile: ZTile64) -> F64Tile {
+    let mut out = [[0.0; 8]; 8];
+    for (i, row) in out.iter_mut().enumerate() {
+        // Safety: Writing in a memory region within `row`
+        unsafe { ctx.store512(row.as_mut_ptr(), tile.row(i)) };
+    }
+    out
+}
+
+fn write_f64_tile(ctx: &mut (impl Amx + ?Sized), tile: ZTile64, m: &F64Tile) {
+    for (i, row) in m.iter().enumerate() {
+        // Safety: Reading a memory region within `row`
+        unsafe { ctx.load512(row.as_ptr(), tile.row(i)) };
+    }
+}
+
+/// Subtract the outer product of `col` and `row` from the matrix held by
+/// `tile`, i.e., `m[i][j] -= col[i] * row[j]`. Clobbers `x[0]` and `y[0]`.
+fn rank1_update_f64_tile(
+    ctx: &mut (impl Amx + ?Sized),
+    tile: ZTile64,
+    col: &[f64; 8],
+    row: &[f64; 8],
+) {
+    // Safety: Reading memory regions within `row` and `col`
+    unsafe {
+        ctx.load512(row.as_ptr(), XRow(0));
+        ctx.load512(col.as_ptr(), YRow(0));
+    }
+    ctx.outer_product_f64_xy_to_z(
+        Some(XBytes(0)),
+        Some(YBytes(0)),
+        tile.z_index(),
+        ZWriteMode::Subtract,
+    );
+}
+
+/// Calculate the Cholesky factorization `a = l * lᵀ` of the symmetric
+/// positive-definite 8×8 `f64` matrix `a` held by `tile`, replacing it with
+/// the lower triangular matrix `l`.
+///
+/// The `i`-th row of the matrix is in [`tile.row(i)`](ZTile64::row), which is
+/// where the `i`-th row of an `f64` outer product accumulates to, so a
+/// blocked factorization can update a diagonal block and factorize it without
+/// moving it out of `z`. Only the lower triangle of `a` is read,
+/// and the upper triangle of the result is zero. `x[0]` and `y[0]` are
+/// clobbered.
+///
+/// Returns [`NotPositiveDefinite`] if `a` is not positive-definite, in which
+/// case the contents of `tile` are unspecified.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{kernels, Amx, ZTile64};
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let tile = ZTile64 { base: 0 };
+///
+/// // `a = 4 * I`
+/// let mut a = [[0.0f64; 8]; 8];
+/// (0..8).for_each(|i| a[i][i] = 4.0);
+/// for (i, row) in a.iter().enumerate() {
+///     unsafe { ctx.load512(row.as_ptr(), tile.row(i)) };
+/// }
+///
+/// kernels::potrf_f64_tile(&mut ctx, tile).unwrap();
+/// let mut row = [0.0f64; 8];
+/// unsafe { ctx.store512(row.as_mut_ptr(), tile.row(3)) };
+/// assert_eq!(row, [0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0]);
+/// ```
+pub fn potrf_f64_tile(
+    ctx: &mut (impl Amx + ?Sized),
+    tile: ZTile64,
+) -> Result<(), NotPositiveDefinite> {
+    let mut l = [[0.0; 8]; 8];
+    for j in 0..8 {
+        // The trailing submatrix `a[j..][j..]` has been updated with the
+        // columns `0..j` of `l`. Its first column is the column `j` of `l`
+        // up to scaling.
+        let mut a_col = [0.0f64; 8];
+        a_col[j..].copy_from_slice(&read_f64_tile_col(ctx, tile, j)[j..]);
+        let pivot = a_col[j];
+        if pivot.is_nan() || pivot <= 0.0 {
+            return Err(NotPositiveDefinite { column: j });
+        }
+        let d = pivot.sqrt();
+
+        let mut l_col = [0.0f64; 8];
+        l_col[j] = d;
+        for i in j + 1..8 {
+            l_col[i] = a_col[i] / d;
+        }
+        for (l_row, &value) in l.iter_mut().zip(&l_col) {
+            l_row[j] = value;
+        }
+
+        // `a[j + 1..][j + 1..] -= l_col * l_colᵀ` (and the rows and columns
+        // `..=j`, which are overwritten later)
+        l_col[j] = 0.0;
+        rank1_update_f64_tile(ctx, tile, &l_col, &l_col);
+    }
+
+    write_f64_tile(ctx, tile, &l);
+    Ok(())
+}
+
+/// Read the column `j` of the lower triangle of the symmetric matrix held by
+/// `tile`, which is the row `j` of the upper triangle. Only the elements
+/// `j..` are meaningful.
+fn read_f64_tile_col(ctx: &mut (impl Amx + ?Sized), tile: ZTile64, j: usize) -> [f64; 8] {
+    let mut col = [0.0f64; 8];
+    for (i, out) in col.iter_mut().enumerate().skip(j) {
+        let mut row = [0.0f64; 8];
+        // Safety: Writing in a memory region within `row`
+        unsafe { ctx.store512(row.as_mut_ptr(), tile.row(i)) };
+        *out = row[j];
+    }
+    col
+}
+
+/// Solve `x * lᵀ = b` for `x`, where `l` is the lower triangular 8×8 `f64`
+/// matrix held by `l_tile` (e.g., produced by [`potrf_f64_tile`]) and `b` is
+/// the 8×8 `f64` matrix held by `b_tile`, which is replaced with `x`.
+///
+/// This is the panel update of a blocked Cholesky factorization, which turns
+/// a block below the diagonal into the corresponding block of the factor.
+/// Only the lower triangle of `l` is read. `x[0]` and `y[0]` are clobbered.
+///
+/// # Panics
+///
+/// Panics if `l_tile` and `b_tile` are the same tile.
+pub fn trsm_f64_tile(ctx: &mut (impl Amx + ?Sized), l_tile: ZTile64, b_tile: ZTile64) {
+    assert_ne!(l_tile, b_tile, "`l_tile` and `b_tile` must be different");
+    let l = read_f64_tile(ctx, l_tile);
+
+    let mut x = [[0.0; 8]; 8];
+    for j in 0..8 {
+        // The column `j` of `b` has been updated with the columns `0..j` of
+        // `x`
+        let b = read_f64_tile(ctx, b_tile);
+        let mut x_col = [0.0f64; 8];
+        for (i, (x_row, b_row)) in x.iter_mut().zip(&b).enumerate() {
+            x_row[j] = b_row[j] / l[j][j];
+            x_col[i] = x_row[j];
+        }
+
+        // `b[..][j + 1..] -= x_col * l[j + 1..][j]ᵀ`
+        let mut l_col = [0.0f64; 8];
+        for k in j + 1..8 {
+            l_col[k] = l[k][j];
+        }
+        rank1_update_f64_tile(ctx, b_tile, &x_col, &l_col);
+    }
+
+    write_f64_tile(ctx, b_tile, &x);
+}
+
+/// The lower bound of the domain of [`exp_approx_f32`] and `exp_approx_f16`.
+/// Smaller inputs are clamped to this value.
+const EXP_MIN: f32 = -16.0;
+
+/// Build the look-up tables for [`exp_approx_offset`], which are loaded to
+/// `x[0..6]`, converting each entry by `convert`.
+///
+/// `EXP_MIN..0` is split into `T::LANES` segments. Row `0` is the sorted list
+/// of the start points of the segments, and row `1` is the negated midpoints
+/// of the segments. Row `2 + i` is the coefficient of `u^i` of the cubic
+/// polynomial approximating `exp(x)` in each segment, where
+/// `u = x - midpoint`.
+fn build_exp_tables<T: Lane + Default>(convert: impl Fn(f64) -> T) -> Box<[T]> {
+    let width = -EXP_MIN as f64 / T::LANES as f64;
+
+    // Interpolate `exp(u)` at the Chebyshev nodes in `-width / 2..=width / 2`
+    // by the Newton form...
+    let nodes: [f64; 4] = std::array::from_fn(|i| {
+        0.5 * width * ((2 * i + 1) as f64 * std::f64::consts::PI / 8.0).cos()
+    });
+    let mut dd = nodes.map(f64::exp);
+    for level in 1..4 {
+        for i in (level..4).rev() {
+            dd[i] = (dd[i] - dd[i - 1]) / (nodes[i] - nodes[i - level]);
+        }
+    }
+    // ...and expand it into the monomial form
+    let mut poly = [dd[3], 0.0, 0.0, 0.0];
+    for k in (0..3).rev() {
+        // `poly = poly * (u - nodes[k]) + dd[k]`
+        for j in (0..4).rev() {
+            let shifted = if j > 0 { poly[j - 1] } else { 0.0 };
+            poly[j] = shifted - nodes[k] * poly[j];
+        }
+        poly[0] += dd[k];
+    }
+
+    // The segment `0` covers `EXP_MIN..EXP_MIN + width`, where `exp(x)` is
+    // negligible, as well as anything below, and the others cover the
+    // intervals up to `0`. Note that the last segment covers positive numbers
+    // too.
+    let mut tables = vec![T::default(); 6 * T::LANES].into_boxed_slice();
+    tables[0] = convert(f64::NEG_INFINITY);
+    for seg in 1..T::LANES {
+        let start = EXP_MIN as f64 + seg as f64 * width;
+        let mid = start + 0.5 * width;
+        let coefs = poly.iter().map(|&c| c * mid.exp());
+        for (row, value) in [start, -mid].iter().copied().chain(coefs).enumerate() {
+            tables[row * T::LANES + seg] = convert(value);
+        }
+    }
+    tables
+}
+
+/// An element type supported by [`exp_approx_offset`].
+trait ExpLane: Lane + Default + 'static {
+    /// Get the look-up tables built by [`build_exp_tables`].
+    fn exp_tables() -> &'static [Self];
+
+    /// Calculate `self + offset`, clamped to `EXP_MIN..=0`.
+    fn exp_input(self, offset: f32) -> Self;
+
+    /// Write the segment index of each element of `input`, looked up in
+    /// `table`, to `y[0]`.
+    fn find_segments(ctx: &mut (impl Amx + ?Sized), input: XRow, table: XRow);
+
+    /// Write the entries of `table` at the segment indices in `y[0]` to
+    /// `output`.
+    fn lookup_segments(ctx: &mut (impl Amx + ?Sized), table: XRow, output: ZRow);
+
+    /// Issue the `fma*` instruction for `Self`.
+    fn fma(ctx: &mut (impl Amx + ?Sized), operand: u64);
+}
+
+impl ExpLane for f32 {
+    fn exp_tables() -> &'static [Self] {
+        static TABLES: OnceLock<Box<[f32]>> = OnceLock::new();
+        TABLES.get_or_init(|| build_exp_tables(|x| x as f32))
+    }
+
+    #[inline]
+    fn exp_input(self, offset: f32) -> Self {
+        (self + offset).clamp(EXP_MIN, 0.0)
+    }
+
+    #[inline]
+    fn find_segments(ctx: &mut (impl Amx + ?Sized), input: XRow, table: XRow) {
+        ctx.reverse_lut(input.offset(), table, YRow(0), F32);
+    }
+
+    #[inline]
+    fn lookup_segments(ctx: &mut (impl Amx + ?Sized), table: XRow, output: ZRow) {
+        ctx.lut_to_z(YBytes(0), table, output, (Normal, Index4, X32));
+    }
+
+    #[inline]
+    fn fma(ctx: &mut (impl Amx + ?Sized), operand: u64) {
+        ctx.fma32(operand);
+    }
+}
+
+#[cfg(feature = "half")]
+impl ExpLane for half::f16 {
+    fn exp_tables() -> &'static [Self] {
+        static TABLES: OnceLock<Box<[half::f16]>> = OnceLock::new();
+        TABLES.get_or_init(|| build_exp_tables(half::f16::from_f64))
+    }
+
+    #[inline]
+    fn exp_input(self, offset: f32) -> Self {
+        half::f16::from_f32((self.to_f32() + offset).clamp(EXP_MIN, 0.0))
+    }
+
+    #[inline]
+    fn find_segments(ctx: &mut (impl Amx + ?Sized), input: XRow, table: XRow) {
+        ctx.reverse_lut(input.offset(), table, YRow(0), crate::F16);
+    }
+
+    #[inline]
+    fn lookup_segments(ctx: &mut (impl Amx + ?Sized), table: XRow, output: ZRow) {
+        ctx.lut_to_z(
+            YBytes(0),
+            table,
+            output,
+            (Normal, crate::Index5, crate::X16),
+        );
+    }
+
+    #[inline]
+    fn fma(ctx: &mut (impl Amx + ?Sized), operand: u64) {
+        ctx.fma16(operand);
+    }
+}
+
+/// Calculate an approximation of `exp(x)` for each element of `x`.
+///
+/// The domain is split into 16 segments, and each segment is approximated by
+/// a cubic polynomial. The segment of each element is found by a reverse
+/// table lookup (`genlut`), the coefficients are then fetched by normal table
+/// lookups, and the polynomial is evaluated by `fma32`s, 16 elements at a
+/// time.
+///
+/// The inputs are clamped to `-16.0..=0.0`, so this is meant for the
+/// exponentials of non-positive numbers, such as those in [`softmax_f32`]. In
+/// this range, the relative error is below `1e-3` (comparable to the
+/// precision of `f16`) except where the result is smaller than `1e-6`. The
+/// result for a NaN input is unspecified.
+///
+/// # Panics
+///
+/// Panics if `x` and `out` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let mut out = [0.0; 3];
+/// amx::kernels::exp_approx_f32(&mut ctx, &[0.0, -1.0, -100.0], &mut out);
+/// assert!((out[0] - 1.0).abs() < 1e-3);
+/// assert!((out[1] - (-1.0f32).exp()).abs() < 1e-3);
+/// assert!(out[2].abs() < 1e-6);
+/// ```
+pub fn exp_approx_f32(ctx: &mut (impl Amx + ?Sized), x: &[f32], out: &mut [f32]) {
+    assert_eq!(
+        x.len(),
+        out.len(),
+        "`x` and `out` must have the same length"
+    );
+    exp_approx_offset(ctx, x, 0.0, out);
+}
+
+/// The `f16` version of [`exp_approx_f32`].
+///
+/// The domain is split into 32 segments, which are found by the 16-bit
+/// reverse table lookup, and the polynomials are evaluated by `fma16`s, 32
+/// elements at a time. The relative error is below `2e-3` except where the
+/// result is smaller than `1e-4`, below which `f16` numbers are subnormal.
+///
+/// # Panics
+///
+/// Panics if `x` and `out` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// use half::f16;
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let mut out = [f16::ZERO; 2];
+/// amx::kernels::exp_approx_f16(&mut ctx, &[f16::ZERO, f16::NEG_ONE], &mut out);
+/// assert!((out[0].to_f32() - 1.0).abs() < 2e-3);
+/// assert!((out[1].to_f32() - (-1.0f32).exp()).abs() < 1e-3);
+/// ```
+#[cfg(feature = "half")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "half")))]
+pub fn exp_approx_f16(ctx: &mut (impl Amx + ?Sized), x: &[half::f16], out: &mut [half::f16]) {
+    assert_eq!(
+        x.len(),
+        out.len(),
+        "`x` and `out` must have the same length"
+    );
+    exp_approx_offset(ctx, x, 0.0, out);
+}
+
+/// Calculate `exp(x[i] + offset)` for each `i` by the method of
+/// [`exp_approx_f32`].
+fn exp_approx_offset<T: ExpLane>(
+    ctx: &mut (impl Amx + ?Sized),
+    x: &[T],
+    offset: f32,
+    out: &mut [T],
+) {
+    for (i, table) in T::exp_tables().chunks_exact(T::LANES).enumerate() {
+        // Safety: Reading a memory region within `table`
+        unsafe { ctx.load512(table.as_ptr(), XRow(i)) };
+    }
+    let (segment_starts, neg_midpoints, coefs) = (XRow(0), XRow(1), [2, 3, 4, 5].map(XRow));
+    let (input, temp) = (XRow(6), XRow(7));
+
+    let mut staging = vec![T::default(); T::LANES];
+    for (x_chunk, out_chunk) in x.chunks(T::LANES).zip(out.chunks_mut(T::LANES)) {
+        for (out, &value) in staging.iter_mut().zip(x_chunk) {
+            *out = value.exp_input(offset);
+        }
+        // Safety: Reading a memory region within `staging`
+        unsafe { ctx.load512(staging.as_ptr(), input) };
+
+        // `y[0]` = the segment indices
+        T::find_segments(ctx, input, segment_starts);
+        // `y[1]` = `u` = `x - midpoint`
+        T::lookup_segments(ctx, neg_midpoints, ZRow(0));
+        T::fma(ctx, vector_fma_operand(input.offset(), None, ZRow(0), true));
+        ctx.copy_z_row_to_y(ZRow(0), YRow(1));
+
+        // Horner's method. `z[k + 1]` = `(...(c3 * u + c2) * u + ...) +
+        // c[3 - k]`
+        T::lookup_segments(ctx, coefs[3], ZRow(1));
+        for k in 1..4 {
+            T::lookup_segments(ctx, coefs[3 - k], ZRow(k + 1));
+            ctx.copy_z_row_to_x(ZRow(k), temp);
+            T::fma(
+                ctx,
+                vector_fma_operand(temp.offset(), Some(YBytes(64)), ZRow(k + 1), true),
+            );
+        }
+
+        // Safety: Writing in a memory region within `staging`
+        unsafe { ctx.store512(staging.as_mut_ptr(), ZRow(4)) };
+        out_chunk.copy_from_slice(&staging[..out_chunk.len()]);
+    }
+}
+
+/// Calculate the softmax of `x`, i.e., `out[i] = exp(x[i]) / Σ exp(x[j])`.
+///
+/// The maximum element is subtracted from every element before the
+/// exponentials are taken by [`exp_approx_f32`], so the result has a
+/// comparable relative error. The normalization is done by [`sum_f32`] and
+/// [`scale_f32`].
+///
+/// If every element is `-inf` (e.g., a fully masked row of attention
+/// scores), the result is the uniform distribution, which is the limit of
+/// the softmax of equal finite elements. The result is unspecified if `x`
+/// contains a NaN or `inf`.
+///
+/// # Panics
+///
+/// Panics if `x` and `out` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let mut out = [0.0; 3];
+/// amx::kernels::softmax_f32(&mut ctx, &[1000.0, 1000.0, -1000.0], &mut out);
+/// assert!((out[0] - 0.5).abs() < 1e-3);
+/// assert!((out[1] - 0.5).abs() < 1e-3);
+/// assert!(out[2] < 1e-6);
+///
+/// amx::kernels::softmax_f32(&mut ctx, &[f32::NEG_INFINITY; 3], &mut out);
+/// assert_eq!(out, [1.0 / 3.0; 3]);
+/// ```
+pub fn softmax_f32(ctx: &mut (impl Amx + ?Sized), x: &[f32], out: &mut [f32]) {
+    assert_eq!(
+        x.len(),
+        out.len(),
+        "`x` and `out` must have the same length"
+    );
+    if x.is_empty() {
+        return;
+    }
+    let max = x.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    if max == f32::NEG_INFINITY {
+        // `x - max` would be NaN
+        out.fill(1.0 / x.len() as f32);
+        return;
+    }
+    exp_approx_offset(ctx, x, -max, out);
+    let sum = sum_f32(ctx, out);
+    scale_f32(ctx, 1.0 / sum, out);
+}
+
+/// The `f16` version of [`softmax_f32`].
+///
+/// The exponentials are taken by [`exp_approx_f16`]. Their sum can exceed
+/// the range of `f16`, so they're summed up and normalized in `f32` without
+/// AMX.
+///
+/// # Panics
+///
+/// Panics if `x` and `out` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// use half::f16;
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let mut out = [f16::ZERO; 2];
+/// amx::kernels::softmax_f16(&mut ctx, &[f16::from_f32(5.0); 2], &mut out);
+/// assert_eq!(out, [f16::from_f32(0.5); 2]);
+/// ```
+#[cfg(feature = "half")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "half")))]
+pub fn softmax_f16(ctx: &mut (impl Amx + ?Sized), x: &[half::f16], out: &mut [half::f16]) {
+    use half::f16;
+    assert_eq!(
+        x.len(),
+        out.len(),
+        "`x` and `out` must have the same length"
+    );
+    if x.is_empty() {
+        return;
+    }
+    let max = x
+        .iter()
+        .map(|x| x.to_f32())
+        .fold(f32::NEG_INFINITY, f32::max);
+    if max == f32::NEG_INFINITY {
+        out.fill(f16::from_f32(1.0 / x.len() as f32));
+        return;
+    }
+    exp_approx_offset(ctx, x, -max, out);
+    let sum: f32 = out.iter().map(|x| x.to_f32()).sum();
+    for x in out.iter_mut() {
+        *x = f16::from_f32(x.to_f32() / sum);
+    }
+}
+
+/// Evaluate the polynomial `coeffs[0] * x^(n - 1) + ... + coeffs[n - 2] * x +
+/// coeffs[n - 1]` (where `n = coeffs.len()`) at each element of `xs` and
+/// store the results in `out`. An empty `coeffs` represents the zero
+/// polynomial.
+///
+/// The polynomial is evaluated by Horner's method, 128 elements at a time.
+/// Each step multiplies the partial result by `x` and adds a broadcast
+/// coefficient by a vector-mode `fma32`, which doesn't round the product, so
+/// the result is identical to that of `acc = acc.mul_add(x, c)` on each
+/// element.
+///
+/// # Panics
+///
+/// Panics if `xs` and `out` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let mut out = [0.0; 3];
+/// // `x^2 - 2x + 3`
+/// amx::kernels::polyval_f32(&mut ctx, &[1.0, -2.0, 3.0], &[0.0, 1.0, 4.0], &mut out);
+/// assert_eq!(out, [3.0, 2.0, 11.0]);
+/// ```
+pub fn polyval_f32(ctx: &mut (impl Amx + ?Sized), coeffs: &[f32], xs: &[f32], out: &mut [f32]) {
+    const CHUNK_LEN: usize = reduce_chunk_len::<f32>();
+    assert_eq!(
+        xs.len(),
+        out.len(),
+        "`xs` and `out` must have the same length"
+    );
+    let splats: Vec<[f32; F32_LANES]> = coeffs.iter().map(|&c| [c; F32_LANES]).collect();
+    let (leading, rest) = match splats.split_first() {
+        Some(x) => x,
+        None => {
+            out.fill(0.0);
+            return;
+        }
+    };
+
+    let mut x_staging = [0.0f32; CHUNK_LEN];
+    let mut z = [0.0f32; CHUNK_LEN];
+    for (x_chunk, out_chunk) in xs.chunks(CHUNK_LEN).zip(out.chunks_mut(CHUNK_LEN)) {
+        let num_rows = x_chunk.len().div_ceil(F32_LANES);
+        let x_chunk = if x_chunk.len() == CHUNK_LEN {
+            x_chunk
+        } else {
+            // Pad the last chunk with zeros
+            x_staging[..x_chunk.len()].copy_from_slice(x_chunk);
+            &x_staging[..]
+        };
+
+        // `y[r]` = `x`, `z[r]` = the partial result
+        for r in 0..num_rows {
+            // Safety: Reading memory regions within `x_chunk` and `leading`
+            unsafe {
+                ctx.load512(x_chunk[r * F32_LANES..].as_ptr(), YRow(r));
+                ctx.load512(leading.as_ptr(), ZRow(r));
+            }
+        }
+
+        // Horner's method. `z[r]` = `z[r] * x + c`
+        for splat in rest {
+            for r in 0..num_rows {
+                ctx.copy_z_row_to_x(ZRow(r), XRow(r));
+                // Safety: Reading a memory region within `splat`
+                unsafe { ctx.load512(splat.as_ptr(), ZRow(r)) };
+                ctx.fma32(vector_fma_operand(
+                    XRow(r).offset(),
+                    Some(YRow(r).offset()),
+                    ZRow(r),
+                    true,
+                ));
+            }
+        }
+
+        for r in 0..num_rows {
+            // Safety: Writing in a memory region within `z`
+            unsafe { ctx.store512(z[r * F32_LANES..].as_mut_ptr(), ZRow(r)) };
+        }
+        out_chunk.copy_from_slice(&z[..out_chunk.len()]);
+    }
+}
+
+/// The coefficients of a biquad filter, normalized so that `a0 = 1`. The
+/// transfer function is `(b0 + b1 z⁻¹ + b2 z⁻²) / (1 + a1 z⁻¹ + a2 z⁻²)`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Biquad {
+    pub b0: f32,
+    pub b1: f32,
+    pub b2: f32,
+    pub a1: f32,
+    pub a2: f32,
+}
+
+impl Biquad {
+    /// The filter passing the input through unchanged.
+    pub const IDENTITY: Self = Self {
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+        a1: 0.0,
+        a2: 0.0,
+    };
+}
+
+/// The number of channels processed by [`BiquadBank16`].
+const BIQUAD_CHANNELS: usize = F32_LANES;
+
+/// A bank of 16 biquad filters, each applied to a channel of interleaved
+/// multi-channel audio.
+///
+/// The filters are in the transposed direct form II:
+///
+/// ```text
+/// y  = b0 * x + s1
+/// s1 = b1 * x - a1 * y + s2
+/// s2 = b2 * x - a2 * y
+/// ```
+///
+/// The 16 channels occupy the 16 lanes of vector-mode `fma32`s, so a frame
+/// is processed by five `fma32`s. Each multiply-add is fused; the result is
+/// identical to that of `f32::mul_add`.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::kernels::{Biquad, BiquadBank16};
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// // Channel 0: a one-sample delay, channel 1: a two-sample moving sum
+/// let mut filters = [Biquad::IDENTITY; 16];
+/// filters[0] = Biquad { b0: 0.0, b1: 1.0, ..Biquad::default() };
+/// filters[1] = Biquad { b0: 1.0, b1: 1.0, ..Biquad::default() };
+/// let mut bank = BiquadBank16::new(&filters);
+///
+/// let mut frames = [[1.0f32; 16], [2.0; 16], [3.0; 16]].concat();
+/// bank.process(&mut ctx, &mut frames);
+/// let channel = |ch: usize| frames.iter().skip(ch).step_by(16).copied().collect::<Vec<_>>();
+/// assert_eq!(channel(0), [0.0, 1.0, 2.0]);
+/// assert_eq!(channel(1), [1.0, 3.0, 5.0]);
+/// assert_eq!(channel(2), [1.0, 2.0, 3.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BiquadBank16 {
+    /// `b0`, `b1`, `b2`, `-a1`, and `-a2` of each channel, which are loaded
+    /// to `y[0..5]`
+    coefs: [[f32; BIQUAD_CHANNELS]; 5],
+    /// `s1` and `s2` of each channel
+    state: [[f32; BIQUAD_CHANNELS]; 2],
+}
+
+impl BiquadBank16 {
+    /// Construct a `BiquadBank16` with the specified filters and zero state.
+    pub fn new(filters: &[Biquad; BIQUAD_CHANNELS]) -> Self {
+        let mut coefs = [[0.0; BIQUAD_CHANNELS]; 5];
+        for (ch, f) in filters.iter().enumerate() {
+            for (row, value) in coefs.iter_mut().zip([f.b0, f.b1, f.b2, -f.a1, -f.a2]) {
+                row[ch] = value;
+            }
+        }
+        Self {
+            coefs,
+            state: [[0.0; BIQUAD_CHANNELS]; 2],
+        }
+    }
+
+    /// Reset the state of every filter to zero.
+    pub fn reset(&mut self) {
+        self.state = [[0.0; BIQUAD_CHANNELS]; 2];
+    }
+
+    /// Filter `frames` in place. `frames` is a sequence of frames, each
+    /// consisting of 16 samples, one for each channel.
+    ///
+    /// The state is carried over between calls, so a long signal can be
+    /// processed in pieces.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frames.len()` isn't a multiple of 16.
+    pub fn process(&mut self, ctx: &mut (impl Amx + ?Sized), frames: &mut [f32]) {
+        assert!(
+            frames.len() % BIQUAD_CHANNELS == 0,
+            "`frames.len()` must be a multiple of 16"
+        );
+        let [b0, b1, b2, neg_a1, neg_a2] = [0, 64, 128, 192, 256].map(YBytes);
+        let (input, output) = (XBytes(0), XBytes(64));
+
+        // `z[s1]` and `z[s2]` hold the state, swapping their roles after
+        // every frame
+        let (mut s1, mut s2) = (ZRow(0), ZRow(1));
+        // Safety: Reading memory regions within `self`
+        unsafe {
+            for (i, row) in self.coefs.iter().enumerate() {
+                ctx.load512(row.as_ptr(), YRow(i));
+            }
+            ctx.load512(self.state[0].as_ptr(), s1);
+            ctx.load512(self.state[1].as_ptr(), s2);
+        }
+
+        for frame in frames.chunks_exact_mut(BIQUAD_CHANNELS) {
+            // Safety: Reading a memory region within `frame`
+            unsafe { ctx.load512(frame.as_ptr(), XRow(0)) };
+
+            // `z[s1] = y = b0 * x + s1`
+            ctx.fma32(vector_fma_operand(input, Some(b0), s1, true));
+            ctx.copy_z_row_to_x(s1, XRow(1));
+            // Safety: Writing in a memory region within `frame`
+            unsafe { ctx.store512(frame.as_mut_ptr(), s1) };
+
+            // `z[s2] = s1' = b1 * x - a1 * y + s2`
+            ctx.fma32(vector_fma_operand(input, Some(b1), s2, true));
+            ctx.fma32(vector_fma_operand(output, Some(neg_a1), s2, true));
+            // `z[s1] = s2' = b2 * x - a2 * y`
+            ctx.fma32(vector_fma_operand(input, Some(b2), s1, false));
+            ctx.fma32(vector_fma_operand(output, Some(neg_a2), s1, true));
+
+            std::mem::swap(&mut s1, &mut s2);
+        }
+
+        // Safety: Writing in memory regions within `self`
+        unsafe {
+            ctx.store512(self.state[0].as_mut_ptr(), s1);
+            ctx.store512(self.state[1].as_mut_ptr(), s2);
+        }
+    }
+}
+
+/// Adding this to an `f32` in `-2^22..2^22` rounds it to the nearest integer
+/// (ties to even), which then appears in the lower bits of the mantissa.
+/// The bit pattern is `0x4b40_0000`.
+const ROUNDING_MAGIC: f32 = 12582912.0;
+
+/// Quantize `x` to `i8`s, i.e., `out[i] = round(x[i] * (1 / scale)) +
+/// zero_point`, rounded to nearest (ties to even) and saturated to the range
+/// of `i8`. The result for a NaN input is unspecified.
+/// [`dequantize_i8_to_f32`] performs the inverse conversion.
+///
+/// The scaling and rounding are done by a fused multiply-add of the magic
+/// number `1.5 * 2^23`, 16 elements at a time.
+///
+/// # Panics
+///
+/// Panics if `x` and `out` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let mut q = [0i8; 4];
+/// amx::kernels::quantize_f32_to_i8(&mut ctx, 0.5, -10, &[0.0, 1.2, -60.0, 80.0], &mut q);
+/// assert_eq!(q, [-10, -8, -128, 127]);
+/// ```
+pub fn quantize_f32_to_i8(
+    ctx: &mut (impl Amx + ?Sized),
+    scale: f32,
+    zero_point: i8,
+    x: &[f32],
+    out: &mut [i8],
+) {
+    quantize(ctx, scale, zero_point, x, out);
+}
+
+/// The `i16` version of [`quantize_f32_to_i8`].
+///
+/// # Panics
+///
+/// Panics if `x` and `out` have different lengths.
+pub fn quantize_f32_to_i16(
+    ctx: &mut (impl Amx + ?Sized),
+    scale: f32,
+    zero_point: i16,
+    x: &[f32],
+    out: &mut [i16],
+) {
+    quantize(ctx, scale, zero_point, x, out);
+}
+
+/// Dequantize `i8`s produced by [`quantize_f32_to_i8`], i.e., `out[i] =
+/// (q[i] - zero_point) * scale`.
+///
+/// The bytes are widened into `f32` lanes by a byte shuffle (`genlut`), which
+/// places them in the mantissa of the magic number `1.5 * 2^23`. The sign is
+/// then restored by a reverse table lookup, and the zero point and scale are
+/// applied by `fma32`s, 16 elements at a time.
+///
+/// # Panics
+///
+/// Panics if `q` and `out` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let mut x = [0.0; 4];
+/// amx::kernels::dequantize_i8_to_f32(&mut ctx, 0.5, -10, &[-10, -8, -128, 127], &mut x);
+/// assert_eq!(x, [0.0, 1.0, -59.0, 68.5]);
+/// ```
+pub fn dequantize_i8_to_f32(
+    ctx: &mut (impl Amx + ?Sized),
+    scale: f32,
+    zero_point: i8,
+    q: &[i8],
+    out: &mut [f32],
+) {
+    dequantize(ctx, scale, zero_point, q, out);
+}
+
+/// The `i16` version of [`dequantize_i8_to_f32`]. 8 elements are processed
+/// at a time.
+///
+/// # Panics
+///
+/// Panics if `q` and `out` have different lengths.
+pub fn dequantize_i16_to_f32(
+    ctx: &mut (impl Amx + ?Sized),
+    scale: f32,
+    zero_point: i16,
+    q: &[i16],
+    out: &mut [f32],
+) {
+    dequantize(ctx, scale, zero_point, q, out);
+}
+
+/// A quantized element type supported by [`quantize`] and [`dequantize`].
+trait QuantLane: Copy + Into<f32> {
+    /// The size in bytes.
+    const SIZE: usize;
+
+    /// Convert `x`, which is an integer or infinity, with saturation.
+    fn from_f32_saturating(x: f32) -> Self;
+
+    /// Write the little-endian representation to `out[..Self::SIZE]`.
+    fn write_le(self, out: &mut [u8]);
+}
+
+impl QuantLane for i8 {
+    const SIZE: usize = 1;
+
+    #[inline]
+    fn from_f32_saturating(x: f32) -> Self {
+        x as i8
+    }
+
+    #[inline]
+    fn write_le(self, out: &mut [u8]) {
+        out[0] = self as u8;
+    }
+}
+
+impl QuantLane for i16 {
+    const SIZE: usize = 2;
+
+    #[inline]
+    fn from_f32_saturating(x: f32) -> Self {
+        x as i16
+    }
+
+    #[inline]
+    fn write_le(self, out: &mut [u8]) {
+        out[..2].copy_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// The implementation of [`quantize_f32_to_i8`] and [`quantize_f32_to_i16`].
+fn quantize<T: QuantLane>(
+    ctx: &mut (impl Amx + ?Sized),
+    scale: f32,
+    zero_point: T,
+    x: &[f32],
+    out: &mut [T],
+) {
+    assert_eq!(
+        x.len(),
+        out.len(),
+        "`x` and `out` must have the same length"
+    );
+    let bias = [ROUNDING_MAGIC + zero_point.into(); F32_LANES];
+    let unbias = [-ROUNDING_MAGIC; F32_LANES];
+    let inv_scale = [1.0 / scale; F32_LANES];
+    // Safety: Reading memory regions within the arrays
+    unsafe {
+        ctx.load512(bias.as_ptr(), XRow(1));
+        ctx.load512(unbias.as_ptr(), XRow(2));
+        ctx.load512(inv_scale.as_ptr(), YRow(0));
+    }
+
+    let mut staging = [0.0f32; F32_LANES];
+    for (x_chunk, out_chunk) in x.chunks(F32_LANES).zip(out.chunks_mut(F32_LANES)) {
+        let x_chunk = if x_chunk.len() == F32_LANES {
+            x_chunk
+        } else {
+            staging[..x_chunk.len()].copy_from_slice(x_chunk);
+            &staging[..]
+        };
+        // Safety: Reading a memory region within `x_chunk`
+        unsafe { ctx.load512(x_chunk.as_ptr(), XRow(0)) };
+
+        // `z[0] = x * (1 / scale) + (ROUNDING_MAGIC + zero_point) -
+        // ROUNDING_MAGIC`. The first two terms are rounded to an integer by a
+        // single fused multiply-add, and the last one is subtracted exactly.
+        ctx.fma32(vector_fma_operand(XBytes(64), None, ZRow(0), false));
+        ctx.fma32(vector_fma_operand(
+            XBytes(0),
+            Some(YBytes(0)),
+            ZRow(0),
+            true,
+        ));
+        ctx.fma32(vector_fma_operand(XBytes(128), None, ZRow(0), true));
+
+        // Safety: Writing in a memory region within `staging`
+        unsafe { ctx.store512(staging.as_mut_ptr(), ZRow(0)) };
+        for (out, &value) in out_chunk.iter_mut().zip(&staging) {
+            *out = T::from_f32_saturating(value);
+        }
+    }
+}
+
+/// The implementation of [`dequantize_i8_to_f32`] and
+/// [`dequantize_i16_to_f32`].
+fn dequantize<T: QuantLane>(
+    ctx: &mut (impl Amx + ?Sized),
+    scale: f32,
+    zero_point: T,
+    q: &[T],
+    out: &mut [f32],
+) {
+    assert_eq!(
+        q.len(),
+        out.len(),
+        "`q` and `out` must have the same length"
+    );
+    // The number of elements processed at once. The input is placed in the
+    // first 16 bytes of `x[0]`, followed by the bytes of `ROUNDING_MAGIC`.
+    let lanes = 16 / T::SIZE;
+    let magic_pos = 16;
+
+    // The shuffle pattern to build `ROUNDING_MAGIC + q[i] as unsigned` in the
+    // `i`-th lane
+    let pattern: [u8; 64] = std::array::from_fn(|byte| {
+        let (lane, b) = (byte / 4, byte % 4);
+        if lane < lanes && b < T::SIZE {
+            (lane * T::SIZE + b) as u8
+        } else {
+            (magic_pos + b) as u8
+        }
+    });
+    // The reverse table to find the negative numbers, whose unsigned values
+    // are `2^(bits - 1)` or greater
+    let range = (1u32 << (T::SIZE * 8)) as f32;
+    let mut sign_table = [f32::INFINITY; F32_LANES];
+    sign_table[0] = f32::NEG_INFINITY;
+    sign_table[1] = ROUNDING_MAGIC + range / 2.0;
+    // The values to add to the unsigned values for non-negative and negative
+    // numbers, respectively
+    let mut offset_table = [0.0f32; F32_LANES];
+    offset_table[0] = -(ROUNDING_MAGIC + zero_point.into());
+    offset_table[1] = offset_table[0] - range;
+    let scale = [scale; F32_LANES];
+    // Safety: Reading memory regions within the arrays
+    unsafe {
+        ctx.load512(sign_table.as_ptr(), XRow(3));
+        ctx.load512(offset_table.as_ptr(), XRow(4));
+        ctx.load512(pack_lut_indices(&pattern, 5).as_ptr(), YRow(1));
+        ctx.load512(scale.as_ptr(), YRow(2));
+    }
+
+    let mut src = [0u8; 64];
+    src[magic_pos..][..4].copy_from_slice(&ROUNDING_MAGIC.to_le_bytes());
+    let mut staging = [0.0f32; F32_LANES];
+    for (q_chunk, out_chunk) in q.chunks(lanes).zip(out.chunks_mut(lanes)) {
+        for (i, &value) in q_chunk.iter().enumerate() {
+            value.write_le(&mut src[i * T::SIZE..]);
+        }
+        // Safety: Reading a memory region within `src`
+        unsafe { ctx.load512(src.as_ptr(), XRow(0)) };
+
+        // `x[2] = ROUNDING_MAGIC + q as unsigned`
+        ctx.shuffle_bytes(XRow(0), YBytes(64), XRow(2));
+        // `z[0] = (x[2] + offset_table[is_negative]) * scale`
+        ctx.reverse_lut(XBytes(128), XRow(3), YRow(0), F32);
+        ctx.lut_to_z(YBytes(0), XRow(4), ZRow(0), (Normal, Index4, X32));
+        ctx.fma32(vector_fma_operand(XBytes(128), None, ZRow(0), true));
+        ctx.copy_z_row_to_x(ZRow(0), XRow(5));
+        ctx.fma32(vector_fma_operand(
+            XBytes(320),
+            Some(YBytes(128)),
+            ZRow(0),
+            false,
+        ));
+
+        // Safety: Writing in a memory region within `out_chunk` or `staging`
+        unsafe {
+            if out_chunk.len() == F32_LANES {
+                ctx.store512(out_chunk.as_mut_ptr(), ZRow(0));
+            } else {
+                ctx.store512(staging.as_mut_ptr(), ZRow(0));
+                out_chunk.copy_from_slice(&staging[..out_chunk.len()]);
+            }
+        }
+    }
+}
+
+/// The number of bytes moved at once by [`amx_memcpy`] and [`amx_fill64`],
+/// which fill all rows of `x`.
+const BOUNCE_LEN: usize = 512;
+
+/// Check if `ptr` is aligned for `load1024_aligned` and `store1024_aligned`.
+#[inline]
+fn is_aligned_128<T>(ptr: *const T) -> bool {
+    ptr as usize % 128 == 0
+}
+
+/// Copy `src` to `dst` through the rows of `x`.
+///
+/// The bulk of the data is streamed 512 bytes at a time, using 128-byte
+/// loads and stores if both slices are aligned to 128-byte boundaries and
+/// 64-byte ones otherwise. The last `src.len() % 64` bytes are copied by the
+/// CPU. This is mainly useful for measuring the memory bandwidth available
+/// to AMX; [`slice::copy_from_slice`] is usually as fast or faster.
+///
+/// # Panics
+///
+/// Panics if `dst` and `src` have different lengths.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let src: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+/// let mut dst = vec![0; 1000];
+/// amx::kernels::amx_memcpy(&mut ctx, &mut dst, &src);
+/// assert_eq!(dst, src);
+/// ```
+pub fn amx_memcpy(ctx: &mut (impl Amx + ?Sized), dst: &mut [u8], src: &[u8]) {
+    assert_eq!(
+        dst.len(),
+        src.len(),
+        "`dst` and `src` must have the same length"
+    );
+    let use_pairs = is_aligned_128(dst.as_ptr()) && is_aligned_128(src.as_ptr());
+
+    let mut src_chunks = src.chunks_exact(BOUNCE_LEN);
+    let mut dst_chunks = dst.chunks_exact_mut(BOUNCE_LEN);
+    for (dst_chunk, src_chunk) in (&mut dst_chunks).zip(&mut src_chunks) {
+        // Safety: Reading and writing memory regions within `src_chunk` and
+        //         `dst_chunk`, which are aligned to 128-byte boundaries if
+        //         `use_pairs` is `true`
+        unsafe {
+            if use_pairs {
+                for i in (0..8).step_by(2) {
+                    ctx.load1024_aligned(src_chunk[i * 64..].as_ptr(), XRow(i));
+                }
+                for i in (0..8).step_by(2) {
+                    ctx.store1024_aligned(dst_chunk[i * 64..].as_mut_ptr(), XRow(i));
+                }
+            } else {
+                for i in 0..8 {
+                    ctx.load512(src_chunk[i * 64..].as_ptr(), XRow(i));
+                }
+                for i in 0..8 {
+                    ctx.store512(dst_chunk[i * 64..].as_mut_ptr(), XRow(i));
+                }
+            }
+        }
+    }
+
+    let (dst, src) = (dst_chunks.into_remainder(), src_chunks.remainder());
+    let mut src_rows = src.chunks_exact(64);
+    let mut dst_rows = dst.chunks_exact_mut(64);
+    for (dst_row, src_row) in (&mut dst_rows).zip(&mut src_rows) {
+        // Safety: Reading and writing 64-byte memory regions within
+        //         `src_row` and `dst_row`
+        unsafe {
+            ctx.load512(src_row.as_ptr(), XRow(0));
+            ctx.store512(dst_row.as_mut_ptr(), XRow(0));
+        }
+    }
+    dst_rows
+        .into_remainder()
+        .copy_from_slice(src_rows.remainder());
+}
+
+/// Fill `dst` with repetitions of `pattern` by storing the rows of `x`.
+///
+/// Like [`amx_memcpy`], 128-byte stores are used if `dst` is aligned to
+/// 128-byte boundaries, and the last `dst.len() % 64` bytes are written by
+/// the CPU. If `dst.len()` isn't a multiple of 64, the last repetition is
+/// truncated.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let pattern: [u8; 64] = std::array::from_fn(|i| i as u8);
+/// let mut dst = vec![0; 200];
+/// amx::kernels::amx_fill64(&mut ctx, &mut dst, &pattern);
+/// assert_eq!(dst[..64], pattern);
+/// assert_eq!(dst[192..], pattern[..8]);
+/// ```
+pub fn amx_fill64(ctx: &mut (impl Amx + ?Sized), dst: &mut [u8], pattern: &[u8; 64]) {
+    let use_pairs = is_aligned_128(dst.as_ptr());
+    for i in 0..2 {
+        // Safety: Reading a memory region within `pattern`
+        unsafe { ctx.load512(pattern.as_ptr(), XRow(i)) };
+    }
+
+    let mut dst_chunks = dst.chunks_exact_mut(BOUNCE_LEN);
+    for dst_chunk in &mut dst_chunks {
+        // Safety: Writing in memory regions within `dst_chunk`, which is
+        //         aligned to 128-byte boundaries if `use_pairs` is `true`
+        unsafe {
+            if use_pairs {
+                for i in (0..8).step_by(2) {
+                    ctx.store1024_aligned(dst_chunk[i * 64..].as_mut_ptr(), XRow(0));
+                }
+            } else {
+                for i in 0..8 {
+                    ctx.store512(dst_chunk[i * 64..].as_mut_ptr(), XRow(0));
+                }
+            }
+        }
+    }
+
+    let mut dst_rows = dst_chunks.into_remainder().chunks_exact_mut(64);
+    for dst_row in &mut dst_rows {
+        // Safety: Writing in a 64-byte memory region within `dst_row`
+        unsafe { ctx.store512(dst_row.as_mut_ptr(), XRow(0)) };
+    }
+    let tail = dst_rows.into_remainder();
+    tail.copy_from_slice(&pattern[..tail.len()]);
+}
@@ -0,0 +1,182 @@
+//! The kernels with the implementation selected at runtime
+//!
+//! Each function in this module runs its counterpart in
+//! [`kernels`](super) on AMX if [`is_supported`](crate::is_supported) and
+//! [`AmxCtx::new`](crate::AmxCtx::new) succeeds, and the portable version in
+//! [`fallback`] otherwise, so a library can call them unconditionally on any
+//! target. The results of the two implementations may differ by rounding
+//! errors.
+//!
+//! Only the kernels that have a portable version are covered; see
+//! [`fallback`] for which ones are AMX-only.
+//!
+//! Like `linalg::gemm_nalgebra`, these don't touch the registers of an
+//! `AmxCtx` the current thread already has; the portable version is used in
+//! that case. Call the functions in [`kernels`](super) with the existing
+//! context instead.
+//!
+//! # Example
+//!
+//! ```rust
+//! let a = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+//! let b = [1.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+//! let mut c = [0.0; 4];
+//! amx::kernels::auto::matmul_f32(2, 2, 3, &a, &b, &mut c);
+//! assert_eq!(c, [4.0, 5.0, 10.0, 11.0]);
+//! ```
+use super::{fallback, Conv2dShape};
+
+/// Evaluate `$amx` with `$ctx` bound to a new `AmxCtx` if possible, or
+/// `$fallback` otherwise.
+macro_rules! amx_or_fallback {
+    (|$ctx:ident| $amx:expr, $fallback:expr) => {{
+        #[cfg(target_arch = "aarch64")]
+        if let Ok(mut ctx) = crate::AmxCtx::new() {
+            let $ctx = &mut *ctx;
+            return $amx;
+        }
+        $fallback
+    }};
+}
+
+/// Calculate `c = a * b` like [`kernels::matmul_f32`](super::matmul_f32).
+///
+/// # Panics
+///
+/// Panics if the slice lengths don't match the specified matrix sizes.
+pub fn matmul_f32(m: usize, n: usize, k: usize, a: &[f32], b: &[f32], c: &mut [f32]) {
+    amx_or_fallback!(
+        |ctx| super::matmul_f32(ctx, m, n, k, a, b, c),
+        fallback::matmul_f32(m, n, k, a, b, c)
+    )
+}
+
+/// Calculate `c = a * b` like [`kernels::matmul_f64`](super::matmul_f64).
+///
+/// # Panics
+///
+/// Panics if the slice lengths don't match the specified matrix sizes.
+pub fn matmul_f64(m: usize, n: usize, k: usize, a: &[f64], b: &[f64], c: &mut [f64]) {
+    amx_or_fallback!(
+        |ctx| super::matmul_f64(ctx, m, n, k, a, b, c),
+        fallback::matmul_f64(m, n, k, a, b, c)
+    )
+}
+
+/// Calculate the dot product of `a` and `b` like
+/// [`kernels::dot_f32`](super::dot_f32).
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+pub fn dot_f32(a: &[f32], b: &[f32]) -> f32 {
+    amx_or_fallback!(|ctx| super::dot_f32(ctx, a, b), fallback::dot_f32(a, b))
+}
+
+/// Calculate the sum of the elements of `a` like
+/// [`kernels::sum_f32`](super::sum_f32).
+pub fn sum_f32(a: &[f32]) -> f32 {
+    amx_or_fallback!(|ctx| super::sum_f32(ctx, a), fallback::sum_f32(a))
+}
+
+/// Calculate the exact dot product of `a` and `b` like
+/// [`kernels::dot_i16`](super::dot_i16).
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+pub fn dot_i16(a: &[i16], b: &[i16]) -> i64 {
+    amx_or_fallback!(|ctx| super::dot_i16(ctx, a, b), fallback::dot_i16(a, b))
+}
+
+/// Find the index of the largest element of `x` like
+/// [`kernels::argmax_f32`](super::argmax_f32).
+///
+/// # Panics
+///
+/// Panics if `x` is empty.
+pub fn argmax_f32(x: &[f32]) -> usize {
+    amx_or_fallback!(|ctx| super::argmax_f32(ctx, x), fallback::argmax_f32(x))
+}
+
+/// Find the index of the smallest element of `x` like
+/// [`kernels::argmin_f32`](super::argmin_f32).
+///
+/// # Panics
+///
+/// Panics if `x` is empty.
+pub fn argmin_f32(x: &[f32]) -> usize {
+    amx_or_fallback!(|ctx| super::argmin_f32(ctx, x), fallback::argmin_f32(x))
+}
+
+/// Calculate `y = alpha * x + y` like [`kernels::axpy_f32`](super::axpy_f32).
+///
+/// # Panics
+///
+/// Panics if `x` and `y` have different lengths.
+pub fn axpy_f32(alpha: f32, x: &[f32], y: &mut [f32]) {
+    amx_or_fallback!(
+        |ctx| super::axpy_f32(ctx, alpha, x, y),
+        fallback::axpy_f32(alpha, x, y)
+    )
+}
+
+/// Calculate `x = alpha * x` like [`kernels::scale_f32`](super::scale_f32).
+pub fn scale_f32(alpha: f32, x: &mut [f32]) {
+    amx_or_fallback!(
+        |ctx| super::scale_f32(ctx, alpha, x),
+        fallback::scale_f32(alpha, x)
+    )
+}
+
+/// Calculate `out[i] = a[i] + b[i]` like [`kernels::add_f32`](super::add_f32).
+///
+/// # Panics
+///
+/// Panics if `a`, `b`, and `out` have different lengths.
+pub fn add_f32(a: &[f32], b: &[f32], out: &mut [f32]) {
+    amx_or_fallback!(
+        |ctx| super::add_f32(ctx, a, b, out),
+        fallback::add_f32(a, b, out)
+    )
+}
+
+/// Calculate `out[i] = a[i] * b[i]` like [`kernels::mul_f32`](super::mul_f32).
+///
+/// # Panics
+///
+/// Panics if `a`, `b`, and `out` have different lengths.
+pub fn mul_f32(a: &[f32], b: &[f32], out: &mut [f32]) {
+    amx_or_fallback!(
+        |ctx| super::mul_f32(ctx, a, b, out),
+        fallback::mul_f32(a, b, out)
+    )
+}
+
+/// Calculate the 2D convolution of `input` and `weights` like
+/// [`kernels::conv2d_f32`](super::conv2d_f32).
+///
+/// # Panics
+///
+/// Panics if the kernel is empty or larger than the input, or if the slice
+/// lengths don't match `shape`.
+pub fn conv2d_f32(shape: Conv2dShape, input: &[f32], weights: &[f32], output: &mut [f32]) {
+    amx_or_fallback!(
+        |ctx| super::conv2d_f32(ctx, shape, input, weights, output),
+        fallback::conv2d_f32(shape, input, weights, output)
+    )
+}
+
+/// Calculate the 2D convolution of `input` and `weights` with wrapping
+/// arithmetic like [`kernels::conv2d_i16`](super::conv2d_i16).
+///
+/// # Panics
+///
+/// Panics if the kernel is empty or larger than the input, or if the slice
+/// lengths don't match `shape`.
+pub fn conv2d_i16(shape: Conv2dShape, input: &[i16], weights: &[i16], output: &mut [i16]) {
+    amx_or_fallback!(
+        |ctx| super::conv2d_i16(ctx, shape, input, weights, output),
+        fallback::conv2d_i16(shape, input, weights, output)
+    )
+}
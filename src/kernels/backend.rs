@@ -0,0 +1,342 @@
+//! The hardware abstraction the blocked kernels are written against
+//!
+//! [`Backend`] exposes the small part of the AMX programming model needed by
+//! the floating-point matrix multiplications ([`matmul_f32`](super::matmul_f32)
+//! and [`matmul_f64`](super::matmul_f64)), reductions
+//! ([`dot_f32`](super::dot_f32) and [`sum_f32`](super::sum_f32)), and
+//! element-wise operations ([`axpy_f32`](super::axpy_f32),
+//! [`scale_f32`](super::scale_f32), [`add_f32`](super::add_f32), and
+//! [`mul_f32`](super::mul_f32)): eight rows of `x` and `y`, 64 rows of `z`,
+//! and the matrix- and vector-mode multiply-accumulate. It's implemented for every [`Amx`] context (i.e., the
+//! hardware and the emulator) and by [`Portable`], which keeps the registers
+//! in ordinary memory and computes them in plain Rust (which the compiler
+//! vectorizes to NEON). Since the three backends run the same code path, the
+//! blocking and scheduling logic can be tested against the emulator, and the
+//! backends can be compared in benchmarks without the differences of separate
+//! implementations getting in the way.
+//!
+//! All backends compute each multiply-accumulate as a fused operation, so
+//! they produce bit-identical results.
+//!
+//! The other kernels in [`kernels`](super) take an [`Amx`] context instead
+//! because they rely on instructions outside this subset, such as the integer
+//! multiply-accumulates, `genlut`, and the `z` reductions and shuffles.
+//!
+//! # Example
+//!
+//! ```rust
+//! use amx::kernels::{self, backend::Portable};
+//! let a = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+//! let b = [1.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+//! let (mut c_emu, mut c_portable) = ([0.0; 4], [0.0; 4]);
+//! kernels::matmul_f32(&mut amx::AmxEmuCtx::default(), 2, 2, 3, &a, &b, &mut c_emu);
+//! kernels::matmul_f32(&mut Portable::new(), 2, 2, 3, &a, &b, &mut c_portable);
+//! assert_eq!(c_emu, c_portable);
+//! ```
+use super::{vector_fma_operand, OuterProductLane};
+use crate::{Amx, Lane, XRow, YRow, ZRow};
+
+/// The number of rows in `x` and `y`.
+const XY_ROWS: usize = 8;
+
+/// The number of rows in `z`.
+const Z_ROWS: usize = 64;
+
+mod private {
+    use crate::Amx;
+
+    pub trait Sealed: Copy {
+        /// Issue the matrix-mode multiply-accumulate for `Self`.
+        fn amx_outer_product(
+            ctx: &mut (impl Amx + ?Sized),
+            x: usize,
+            y: usize,
+            tile: usize,
+            accumulate: bool,
+        );
+
+        /// Issue the vector-mode multiply-accumulate for `Self`.
+        fn amx_vector_fma(
+            ctx: &mut (impl Amx + ?Sized),
+            x: usize,
+            y: Option<usize>,
+            z: usize,
+            accumulate: bool,
+        );
+
+        /// Calculate `self * a + b` with a single rounding like AMX does.
+        fn mul_add(self, a: Self, b: Self) -> Self;
+
+        /// The multiplicative identity, which a skipped operand is replaced
+        /// with.
+        const ONE: Self;
+    }
+}
+
+/// The element types supported by [`Backend`]. This trait is sealed.
+///
+/// A row of `x`, `y`, or `z` holds [`LANES`](Lane::LANES) elements, and `z`
+/// is divided into [`TILES`](Self::TILES) interleaved tiles of
+/// `LANES × LANES` elements.
+pub trait BackendLane: Lane + Default + private::Sealed {
+    /// The number of tiles in `z`. The tile `t` occupies the rows `t`,
+    /// `t + TILES`, `t + 2 * TILES`, and so on.
+    const TILES: usize = std::mem::size_of::<Self>();
+}
+
+impl private::Sealed for f32 {
+    #[inline]
+    fn amx_outer_product(
+        ctx: &mut (impl Amx + ?Sized),
+        x: usize,
+        y: usize,
+        tile: usize,
+        accumulate: bool,
+    ) {
+        <f32 as OuterProductLane>::outer_product(
+            ctx,
+            XRow(x).offset(),
+            YRow(y).offset(),
+            ZRow(tile),
+            accumulate,
+        );
+    }
+
+    #[inline]
+    fn amx_vector_fma(
+        ctx: &mut (impl Amx + ?Sized),
+        x: usize,
+        y: Option<usize>,
+        z: usize,
+        accumulate: bool,
+    ) {
+        let y = y.map(|y| YRow(y).offset());
+        ctx.fma32(vector_fma_operand(XRow(x).offset(), y, ZRow(z), accumulate));
+    }
+
+    #[inline]
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        f32::mul_add(self, a, b)
+    }
+
+    const ONE: Self = 1.0;
+}
+
+impl private::Sealed for f64 {
+    #[inline]
+    fn amx_outer_product(
+        ctx: &mut (impl Amx + ?Sized),
+        x: usize,
+        y: usize,
+        tile: usize,
+        accumulate: bool,
+    ) {
+        <f64 as OuterProductLane>::outer_product(
+            ctx,
+            XRow(x).offset(),
+            YRow(y).offset(),
+            ZRow(tile),
+            accumulate,
+        );
+    }
+
+    #[inline]
+    fn amx_vector_fma(
+        ctx: &mut (impl Amx + ?Sized),
+        x: usize,
+        y: Option<usize>,
+        z: usize,
+        accumulate: bool,
+    ) {
+        let y = y.map(|y| YRow(y).offset());
+        ctx.fma64(vector_fma_operand(XRow(x).offset(), y, ZRow(z), accumulate));
+    }
+
+    #[inline]
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        f64::mul_add(self, a, b)
+    }
+
+    const ONE: Self = 1.0;
+}
+
+impl BackendLane for f32 {}
+impl BackendLane for f64 {}
+
+/// A target of the blocked kernels, operating on registers of `T` elements.
+///
+/// The register rows are addressed by index: `x` and `y` have eight rows
+/// each, and `z` has 64. See [`BackendLane`] for how `z` is divided into
+/// tiles.
+pub trait Backend<T: BackendLane> {
+    /// Load the first `T::LANES` elements of `src` to the row `index` of `x`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` is shorter than `T::LANES` elements.
+    fn load_x(&mut self, index: usize, src: &[T]);
+
+    /// Load the first `T::LANES` elements of `src` to the row `index` of `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` is shorter than `T::LANES` elements.
+    fn load_y(&mut self, index: usize, src: &[T]);
+
+    /// Load the first `T::LANES` elements of `src` to the row `index` of `z`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `src` is shorter than `T::LANES` elements.
+    fn load_z(&mut self, index: usize, src: &[T]);
+
+    /// Store the row `index` of `z` to the first `T::LANES` elements of
+    /// `dst`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dst` is shorter than `T::LANES` elements.
+    fn store_z(&mut self, index: usize, dst: &mut [T]);
+
+    /// Calculate the outer product of the rows `x` of `x` and `y` of `y` and
+    /// add it to (or, if `accumulate` is `false`, write it to) the tile
+    /// `tile` of `z`, i.e., `z[tile + i * T::TILES][j] += y[i] * x[j]`.
+    fn outer_product(&mut self, x: usize, y: usize, tile: usize, accumulate: bool);
+
+    /// Calculate the element-wise product of the rows `x` of `x` and `y` of
+    /// `y` (or the row of `x` itself if `y` is `None`) and add it to (or, if
+    /// `accumulate` is `false`, write it to) the row `z` of `z`.
+    fn vector_fma(&mut self, x: usize, y: Option<usize>, z: usize, accumulate: bool);
+}
+
+impl<T: BackendLane, A: Amx + ?Sized> Backend<T> for A {
+    #[inline]
+    fn load_x(&mut self, index: usize, src: &[T]) {
+        let src = &src[..T::LANES];
+        // Safety: Reading a memory region within `src`
+        unsafe { self.load512(src.as_ptr(), XRow(index)) };
+    }
+
+    #[inline]
+    fn load_y(&mut self, index: usize, src: &[T]) {
+        let src = &src[..T::LANES];
+        // Safety: Reading a memory region within `src`
+        unsafe { self.load512(src.as_ptr(), YRow(index)) };
+    }
+
+    #[inline]
+    fn load_z(&mut self, index: usize, src: &[T]) {
+        let src = &src[..T::LANES];
+        // Safety: Reading a memory region within `src`
+        unsafe { self.load512(src.as_ptr(), ZRow(index)) };
+    }
+
+    #[inline]
+    fn store_z(&mut self, index: usize, dst: &mut [T]) {
+        let dst = &mut dst[..T::LANES];
+        // Safety: Writing in a memory region within `dst`
+        unsafe { self.store512(dst.as_mut_ptr(), ZRow(index)) };
+    }
+
+    #[inline]
+    fn outer_product(&mut self, x: usize, y: usize, tile: usize, accumulate: bool) {
+        T::amx_outer_product(self, x, y, tile, accumulate);
+    }
+
+    #[inline]
+    fn vector_fma(&mut self, x: usize, y: Option<usize>, z: usize, accumulate: bool) {
+        T::amx_vector_fma(self, x, y, z, accumulate);
+    }
+}
+
+/// The portable [`Backend`], which keeps the registers in ordinary memory.
+///
+/// Unlike [`AmxEmuCtx`](crate::AmxEmuCtx), this only models the operations
+/// of `Backend` on `T` elements, which lets the compiler turn each of them
+/// into a few SIMD instructions. The row indices must be in range, or the
+/// methods panic.
+#[derive(Debug, Clone)]
+pub struct Portable<T> {
+    x: Box<[T]>,
+    y: Box<[T]>,
+    z: Box<[T]>,
+}
+
+impl<T: BackendLane> Portable<T> {
+    /// Construct a `Portable` with all registers zeroed.
+    pub fn new() -> Self {
+        let zeros = |rows: usize| vec![T::default(); rows * T::LANES].into_boxed_slice();
+        Self {
+            x: zeros(XY_ROWS),
+            y: zeros(XY_ROWS),
+            z: zeros(Z_ROWS),
+        }
+    }
+}
+
+impl<T: BackendLane> Default for Portable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get the row `index` of `regs`.
+#[inline]
+fn row<T: BackendLane>(regs: &[T], index: usize) -> &[T] {
+    &regs[index * T::LANES..][..T::LANES]
+}
+
+impl<T: BackendLane> Backend<T> for Portable<T> {
+    #[inline]
+    fn load_x(&mut self, index: usize, src: &[T]) {
+        self.x[index * T::LANES..][..T::LANES].copy_from_slice(&src[..T::LANES]);
+    }
+
+    #[inline]
+    fn load_y(&mut self, index: usize, src: &[T]) {
+        self.y[index * T::LANES..][..T::LANES].copy_from_slice(&src[..T::LANES]);
+    }
+
+    #[inline]
+    fn load_z(&mut self, index: usize, src: &[T]) {
+        self.z[index * T::LANES..][..T::LANES].copy_from_slice(&src[..T::LANES]);
+    }
+
+    #[inline]
+    fn store_z(&mut self, index: usize, dst: &mut [T]) {
+        dst[..T::LANES].copy_from_slice(row(&self.z, index));
+    }
+
+    #[inline]
+    fn outer_product(&mut self, x: usize, y: usize, tile: usize, accumulate: bool) {
+        assert!(tile < T::TILES, "tile index out of range");
+        let (x, y) = (row(&self.x, x), row(&self.y, y));
+        for (i, &y) in y.iter().enumerate() {
+            let z = &mut self.z[(tile + i * T::TILES) * T::LANES..][..T::LANES];
+            fma_row(z, x, |_| y, accumulate);
+        }
+    }
+
+    #[inline]
+    fn vector_fma(&mut self, x: usize, y: Option<usize>, z: usize, accumulate: bool) {
+        let x = row(&self.x, x);
+        let z = &mut self.z[z * T::LANES..][..T::LANES];
+        match y {
+            Some(y) => {
+                let y = row(&self.y, y);
+                fma_row(z, x, |j| y[j], accumulate);
+            }
+            None => fma_row(z, x, |_| T::ONE, accumulate),
+        }
+    }
+}
+
+/// Calculate `z[j] = x[j] * y(j) + z[j]` (or `+ 0` if `accumulate` is
+/// `false`) like the emulator does.
+#[inline]
+fn fma_row<T: BackendLane>(z: &mut [T], x: &[T], y: impl Fn(usize) -> T, accumulate: bool) {
+    for (j, (z, &x)) in z.iter_mut().zip(x).enumerate() {
+        let z_in = if accumulate { *z } else { T::default() };
+        *z = x.mul_add(y(j), z_in);
+    }
+}
@@ -0,0 +1,197 @@
+//! Portable implementations of the kernels
+//!
+//! The functions in this module compute the same results as their
+//! counterparts in [`kernels`](super) without AMX. They're written in plain
+//! Rust that the compiler vectorizes to NEON (or whatever SIMD instructions
+//! the target has), and [`kernels::auto`](super::auto) selects them when AMX
+//! isn't available.
+//!
+//! The floating-point matrix multiplications, reductions, and element-wise
+//! operations run the AMX kernels' code path on the [`Portable`] backend, so
+//! their results are bit-identical to those of the AMX versions. The
+//! convolutions may differ from them by rounding errors.
+//!
+//! The other kernels (e.g., [`matmul_c32`](super::matmul_c32), the FFT
+//! butterflies, the Cholesky tiles, [`exp_approx_f32`](super::exp_approx_f32)
+//! and the other LUT-based routines, and the quantization routines) are
+//! AMX-only. Where AMX isn't available, run them on
+//! [`AmxEmuCtx`](crate::AmxEmuCtx) or [`AnyAmxCtx`](crate::AnyAmxCtx).
+use super::{backend::Portable, Conv2dShape};
+
+/// The portable version of [`kernels::matmul_f32`](super::matmul_f32).
+///
+/// # Panics
+///
+/// Panics if the slice lengths don't match the specified matrix sizes.
+///
+/// # Example
+///
+/// ```rust
+/// let a = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+/// let b = [1.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+/// let mut c = [0.0; 4];
+/// amx::kernels::fallback::matmul_f32(2, 2, 3, &a, &b, &mut c);
+/// assert_eq!(c, [4.0, 5.0, 10.0, 11.0]);
+/// ```
+pub fn matmul_f32(m: usize, n: usize, k: usize, a: &[f32], b: &[f32], c: &mut [f32]) {
+    super::matmul_f32(&mut Portable::new(), m, n, k, a, b, c);
+}
+
+/// The portable version of [`kernels::matmul_f64`](super::matmul_f64).
+///
+/// # Panics
+///
+/// Panics if the slice lengths don't match the specified matrix sizes.
+pub fn matmul_f64(m: usize, n: usize, k: usize, a: &[f64], b: &[f64], c: &mut [f64]) {
+    super::matmul_f64(&mut Portable::new(), m, n, k, a, b, c);
+}
+
+/// The portable version of [`kernels::dot_f32`](super::dot_f32).
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+pub fn dot_f32(a: &[f32], b: &[f32]) -> f32 {
+    super::dot_f32(&mut Portable::new(), a, b)
+}
+
+/// The portable version of [`kernels::sum_f32`](super::sum_f32).
+pub fn sum_f32(a: &[f32]) -> f32 {
+    super::sum_f32(&mut Portable::new(), a)
+}
+
+/// The portable version of [`kernels::dot_i16`](super::dot_i16). The result
+/// is exact.
+///
+/// # Panics
+///
+/// Panics if `a` and `b` have different lengths.
+pub fn dot_i16(a: &[i16], b: &[i16]) -> i64 {
+    assert_eq!(a.len(), b.len(), "`a` and `b` must have the same length");
+    a.iter()
+        .zip(b)
+        .map(|(&a, &b)| a as i32 * b as i32)
+        .map(i64::from)
+        .sum()
+}
+
+/// The portable version of [`kernels::argmax_f32`](super::argmax_f32).
+///
+/// # Panics
+///
+/// Panics if `x` is empty.
+pub fn argmax_f32(x: &[f32]) -> usize {
+    arg_reduce_f32(x, |value, best| value > best)
+}
+
+/// The portable version of [`kernels::argmin_f32`](super::argmin_f32).
+///
+/// # Panics
+///
+/// Panics if `x` is empty.
+pub fn argmin_f32(x: &[f32]) -> usize {
+    arg_reduce_f32(x, |value, best| value < best)
+}
+
+/// Find the index of the first element that `improves` every preceding
+/// non-NaN element, or `0` if every element is NaN.
+fn arg_reduce_f32(x: &[f32], improves: impl Fn(f32, f32) -> bool) -> usize {
+    assert!(!x.is_empty(), "`x` must not be empty");
+    let mut best: Option<(usize, f32)> = None;
+    for (i, &value) in x.iter().enumerate() {
+        if !value.is_nan() && best.is_none_or(|(_, best)| improves(value, best)) {
+            best = Some((i, value));
+        }
+    }
+    best.map_or(0, |(i, _)| i)
+}
+
+/// The portable version of [`kernels::axpy_f32`](super::axpy_f32).
+///
+/// # Panics
+///
+/// Panics if `x` and `y` have different lengths.
+pub fn axpy_f32(alpha: f32, x: &[f32], y: &mut [f32]) {
+    super::axpy_f32(&mut Portable::new(), alpha, x, y);
+}
+
+/// The portable version of [`kernels::scale_f32`](super::scale_f32).
+pub fn scale_f32(alpha: f32, x: &mut [f32]) {
+    super::scale_f32(&mut Portable::new(), alpha, x);
+}
+
+/// The portable version of [`kernels::add_f32`](super::add_f32).
+///
+/// # Panics
+///
+/// Panics if `a`, `b`, and `out` have different lengths.
+pub fn add_f32(a: &[f32], b: &[f32], out: &mut [f32]) {
+    super::add_f32(&mut Portable::new(), a, b, out);
+}
+
+/// The portable version of [`kernels::mul_f32`](super::mul_f32).
+///
+/// # Panics
+///
+/// Panics if `a`, `b`, and `out` have different lengths.
+pub fn mul_f32(a: &[f32], b: &[f32], out: &mut [f32]) {
+    super::mul_f32(&mut Portable::new(), a, b, out);
+}
+
+/// The portable version of [`kernels::conv2d_f32`](super::conv2d_f32).
+///
+/// # Panics
+///
+/// Panics if the kernel is empty or larger than the input, or if the slice
+/// lengths don't match `shape`.
+pub fn conv2d_f32(shape: Conv2dShape, input: &[f32], weights: &[f32], output: &mut [f32]) {
+    conv2d(shape, input, weights, output, |acc, w, x| acc + w * x);
+}
+
+/// The portable version of [`kernels::conv2d_i16`](super::conv2d_i16). The
+/// products are accumulated with wrapping arithmetic.
+///
+/// # Panics
+///
+/// Panics if the kernel is empty or larger than the input, or if the slice
+/// lengths don't match `shape`.
+pub fn conv2d_i16(shape: Conv2dShape, input: &[i16], weights: &[i16], output: &mut [i16]) {
+    conv2d(shape, input, weights, output, |acc: i16, w: i16, x| {
+        acc.wrapping_add(w.wrapping_mul(x))
+    });
+}
+
+fn conv2d<T: Copy + Default>(
+    shape: Conv2dShape,
+    input: &[T],
+    weights: &[T],
+    output: &mut [T],
+    mac: impl Fn(T, T, T) -> T,
+) {
+    shape.check(input, weights, output);
+    let (out_height, out_width) = (shape.out_height(), shape.out_width());
+    let in_plane = shape.in_height * shape.in_width;
+
+    // Accumulate each tap over a whole output row at a time so that the
+    // inner loop runs over contiguous elements
+    let kernel_plane = shape.kernel_height * shape.kernel_width;
+    for (oc, out_plane) in output.chunks_exact_mut(out_height * out_width).enumerate() {
+        out_plane.fill(T::default());
+        let kernel = &weights[oc * shape.kernel_len()..][..shape.kernel_len()];
+        for (in_plane, kernel) in input
+            .chunks_exact(in_plane)
+            .zip(kernel.chunks_exact(kernel_plane))
+        {
+            for (oy, out_row) in out_plane.chunks_exact_mut(out_width).enumerate() {
+                for (ky, kernel_row) in kernel.chunks_exact(shape.kernel_width).enumerate() {
+                    let in_row = &in_plane[(oy + ky) * shape.in_width..][..shape.in_width];
+                    for (kx, &w) in kernel_row.iter().enumerate() {
+                        for (out, &x) in out_row.iter_mut().zip(&in_row[kx..]) {
+                            *out = mac(*out, w, x);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! 3×3 filters of 8-bit grayscale images
+//!
+//! The images are row-major arrays of `width × height` pixels. Pixels outside
+//! the image are taken from the nearest edge (i.e., the border is
+//! replicated), so the output has the same dimensions as the input.
+//!
+//! The filters are computed by `matint` outer products of unsigned 8-bit
+//! numbers, one 16×16 block of output pixels at a time. The input patch
+//! covering a block (18×18 pixels with the border) is copied to `x`, and each
+//! input row `i` of the patch is multiplied by a column of the kernel, placed
+//! in `y` at the lanes of the output rows `i` contributes to. The horizontal
+//! shifts of the kernel columns are done by offsetting the `x` operand, so
+//! every output row receives all of its nine taps without any data movement.
+//!
+//! `matint` only multiplies numbers of the same signedness, so the positive
+//! and negative taps of a kernel are accumulated in separate tiles of `z`
+//! and subtracted when the results are read out. The 32-bit sums are then
+//! scaled and saturated to `0..=255` by the CPU.
+use crate::{Amx, IntAccumMode, XBytes, YBytes, ZTile32};
+
+/// The width and height of an output block.
+const BLOCK: usize = 16;
+
+/// The width and height of the input patch covering an output block.
+const PATCH: usize = BLOCK + 2;
+
+/// The distance between the anchors of two weight windows in `y`. A window
+/// anchored at `a` is read in range `a - (PATCH - 1)..a + BLOCK`, which must
+/// not overlap the taps of other windows.
+const WINDOW_STRIDE: usize = PATCH;
+
+/// A 3×3 kernel, indexed by `[row][column]`. The output pixel `(r, c)` is
+/// `Σ kernel[dr][dc] * input(r + dr - 1, c + dc - 1)`.
+pub type Kernel3x3 = [[i8; 3]; 3];
+
+/// Apply `kernel` to the 8-bit image `src` and store the result in `dst`.
+///
+/// Each output pixel is the weighted sum of its 3×3 neighborhood, divided by
+/// `2^shift` (rounded to the nearest integer, with ties rounded up), and
+/// clamped to `0..=255`.
+///
+/// # Panics
+///
+/// Panics if `src.len()` or `dst.len()` isn't `width * height` or if `shift`
+/// isn't in range `0..32`.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::kernels::image;
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// // A Gaussian blur, whose weights sum up to `2^4`
+/// let gaussian = [[1, 2, 1], [2, 4, 2], [1, 2, 1]];
+/// let src = [0, 0, 160, 0, 0];
+/// let mut dst = [0; 5];
+/// image::filter3x3_u8(&mut ctx, &src, 5, 1, &gaussian, 4, &mut dst);
+/// assert_eq!(dst, [0, 40, 80, 40, 0]);
+/// ```
+pub fn filter3x3_u8(
+    ctx: &mut (impl Amx + ?Sized),
+    src: &[u8],
+    width: usize,
+    height: usize,
+    kernel: &Kernel3x3,
+    shift: u32,
+    dst: &mut [u8],
+) {
+    assert!(shift < 32, "shift amount out of range");
+    check_image_dims(src, width, height, dst);
+    let round = (1i64 << shift) >> 1;
+    filter3x3(ctx, src, width, height, [kernel], |i, [sum]| {
+        dst[i] = saturate_u8((sum as i64 + round) >> shift);
+    });
+}
+
+/// Blur the 8-bit image `src` by a 3×3 box filter and store the result in
+/// `dst`.
+///
+/// Each output pixel is the mean of its 3×3 neighborhood, rounded to the
+/// nearest integer.
+///
+/// # Panics
+///
+/// Panics if `src.len()` or `dst.len()` isn't `width * height`.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let src = [9, 0, 0, 0, 0, 0, 0, 0, 0];
+/// let mut dst = [0; 9];
+/// amx::kernels::image::box_blur3x3_u8(&mut ctx, &src, 3, 3, &mut dst);
+/// assert_eq!(dst, [4, 2, 0, 2, 1, 0, 0, 0, 0]);
+/// ```
+pub fn box_blur3x3_u8(
+    ctx: &mut (impl Amx + ?Sized),
+    src: &[u8],
+    width: usize,
+    height: usize,
+    dst: &mut [u8],
+) {
+    check_image_dims(src, width, height, dst);
+    filter3x3(ctx, src, width, height, [&[[1; 3]; 3]], |i, [sum]| {
+        dst[i] = ((sum + 4) / 9) as u8;
+    });
+}
+
+/// Detect the edges of the 8-bit image `src` by the Sobel operator and store
+/// the gradient magnitudes in `dst`.
+///
+/// Each output pixel is `|gx| + |gy|` clamped to `0..=255`, where `gx` and `gy`
+/// are the responses of the horizontal and vertical Sobel kernels.
+///
+/// # Panics
+///
+/// Panics if `src.len()` or `dst.len()` isn't `width * height`.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let src = [10, 10, 50, 50];
+/// let mut dst = [0; 4];
+/// amx::kernels::image::sobel3x3_u8(&mut ctx, &src, 4, 1, &mut dst);
+/// assert_eq!(dst, [0, 160, 160, 0]);
+/// ```
+pub fn sobel3x3_u8(
+    ctx: &mut (impl Amx + ?Sized),
+    src: &[u8],
+    width: usize,
+    height: usize,
+    dst: &mut [u8],
+) {
+    const SOBEL_X: Kernel3x3 = [[-1, 0, 1], [-2, 0, 2], [-1, 0, 1]];
+    const SOBEL_Y: Kernel3x3 = [[-1, -2, -1], [0, 0, 0], [1, 2, 1]];
+    check_image_dims(src, width, height, dst);
+    filter3x3(
+        ctx,
+        src,
+        width,
+        height,
+        [&SOBEL_X, &SOBEL_Y],
+        |i, [gx, gy]| {
+            dst[i] = saturate_u8(gx.abs() as i64 + gy.abs() as i64);
+        },
+    );
+}
+
+fn check_image_dims(src: &[u8], width: usize, height: usize, dst: &[u8]) {
+    let len = width.checked_mul(height).expect("image too large");
+    assert_eq!(src.len(), len, "`src.len()` must be `width * height`");
+    assert_eq!(dst.len(), len, "`dst.len()` must be `width * height`");
+}
+
+#[inline]
+fn saturate_u8(x: i64) -> u8 {
+    x.clamp(0, 255) as u8
+}
+
+/// Apply `N` kernels to `src` at once and call `emit(i, sums)` with the
+/// weighted sums of every pixel `i`. Each kernel uses two of the four tiles
+/// of `z`, so `N` must not exceed two.
+fn filter3x3<const N: usize>(
+    ctx: &mut (impl Amx + ?Sized),
+    src: &[u8],
+    width: usize,
+    height: usize,
+    kernels: [&Kernel3x3; N],
+    mut emit: impl FnMut(usize, [i32; N]),
+) {
+    assert!(N <= 2);
+
+    // Place a weight window in `y` for each kernel column with non-zero taps
+    // of each sign. The tap `dr` of the window anchored at `a` is at
+    // `a - dr`, so reading the window at `a - i` puts the taps applied to the
+    // patch row `i` at the lanes of the output rows `i - dr`.
+    let mut weights = [0u8; 512];
+    // `(tile, column, anchor)`
+    let mut passes = Vec::with_capacity(N * 6);
+    for (k, kernel) in kernels.iter().enumerate() {
+        for (sign, tile) in [(1, 2 * k), (-1, 2 * k + 1)] {
+            for dc in 0..3 {
+                let anchor = PATCH - 1 + passes.len() * WINDOW_STRIDE;
+                let mut any = false;
+                for (dr, row) in kernel.iter().enumerate() {
+                    let w = row[dc] as i32 * sign;
+                    if w > 0 {
+                        weights[anchor - dr] = w as u8;
+                        any = true;
+                    }
+                }
+                if any {
+                    passes.push((tile, dc, anchor));
+                }
+            }
+        }
+    }
+    ctx.load_all_y(&weights);
+
+    let mut patch = [0u8; 512];
+    let mut sums = [0i32; BLOCK];
+    for r0 in (0..height).step_by(BLOCK) {
+        for c0 in (0..width).step_by(BLOCK) {
+            // Gather the patch, replicating the border
+            for pr in 0..PATCH {
+                let sr = (r0 + pr).saturating_sub(1).min(height - 1);
+                let src_row = &src[sr * width..][..width];
+                for (pc, out) in patch[pr * PATCH..][..PATCH].iter_mut().enumerate() {
+                    *out = src_row[(c0 + pc).saturating_sub(1).min(width - 1)];
+                }
+            }
+            ctx.load_all_x(&patch);
+
+            let mut written = [false; 4];
+            for &(tile, dc, anchor) in &passes {
+                for i in 0..PATCH {
+                    let x = XBytes(dc)
+                        .checked_add(i * PATCH)
+                        .expect("the patch fits in `x`");
+                    let y = YBytes(anchor)
+                        .checked_sub(i)
+                        .expect("the window fits in `y`");
+                    ctx.outer_product_u8_to_i32(
+                        Some(x),
+                        Some(y),
+                        ZTile32 { base: tile }.z_index(),
+                        written[tile],
+                        IntAccumMode::default(),
+                    );
+                    written[tile] = true;
+                }
+            }
+
+            // `out[r][c][k]` = the sum of the kernel `k` at `(r0 + r, c0 + c)`
+            let (rows, cols) = ((height - r0).min(BLOCK), (width - c0).min(BLOCK));
+            let mut out = [[[0i32; N]; BLOCK]; BLOCK];
+            for k in 0..N {
+                for (tile, sign) in [(2 * k, 1), (2 * k + 1, -1)] {
+                    if !written[tile] {
+                        continue;
+                    }
+                    for (r, out_row) in out[..rows].iter_mut().enumerate() {
+                        // Safety: Writing in a memory region within `sums`
+                        unsafe { ctx.store512(sums.as_mut_ptr(), ZTile32 { base: tile }.row(r)) };
+                        for (out, &sum) in out_row.iter_mut().zip(&sums) {
+                            out[k] += sign * sum;
+                        }
+                    }
+                }
+            }
+
+            for (r, out_row) in out[..rows].iter().enumerate() {
+                for (c, &sums) in out_row[..cols].iter().enumerate() {
+                    emit((r0 + r) * width + c0 + c, sums);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Measuring AMX kernels with hardware performance counters
+//!
+//! This module reads the fixed performance counters (cycles and retired
+//! instructions) of the current thread through Apple's private `kperf`
+//! framework, which is loaded at runtime. Dividing the cycle count by the
+//! number of AMX instructions issued tells whether a tile loop achieves the
+//! peak throughput of the instructions it uses.
+//!
+//! Enabling the counters requires root privileges. The framework is only
+//! available on macOS; [`Profiler::new`] returns
+//! [`KperfError::Unsupported`] elsewhere.
+//!
+//! # Example
+//!
+//! ```rust
+//! use amx::{prelude::*, XBytes, YBytes, ZRow};
+//! let profiler = match amx::kperf::Profiler::new() {
+//!     Ok(x) => x,
+//!     Err(e) => {
+//!         eprintln!("performance counters are unavailable: {:?}", e);
+//!         return;
+//!     }
+//! };
+//! let mut ctx = amx::AmxCtx::new().unwrap();
+//! let count = 1_000_000;
+//! let ((), counts) = profiler.measure(|| {
+//!     for _ in 0..count {
+//!         ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), true);
+//!     }
+//! });
+//! println!("{:.2} cycles per mac16", counts.cycles as f64 / count as f64);
+//! ```
+
+/// The error type for [`Profiler::new`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum KperfError {
+    /// The target system doesn't have the `kperf` framework.
+    Unsupported,
+    /// The `kperf` framework or one of its functions couldn't be loaded.
+    Load,
+    /// A `kperf` function failed with the specified error number, e.g.,
+    /// because the process isn't running as root.
+    Os(i32),
+}
+
+/// The performance counter values accumulated during a call to
+/// [`Profiler::measure`].
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct Counts {
+    /// The number of CPU cycles.
+    pub cycles: u64,
+    /// The number of retired instructions (including AMX instructions).
+    pub instructions: u64,
+}
+
+impl Counts {
+    /// Get the number of instructions retired per cycle.
+    pub fn ipc(&self) -> f64 {
+        self.instructions as f64 / self.cycles as f64
+    }
+}
+
+/// Reads the current thread's fixed performance counters.
+pub struct Profiler {
+    kpc: sys::Kpc,
+}
+
+impl std::fmt::Debug for Profiler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Profiler").finish_non_exhaustive()
+    }
+}
+
+impl Profiler {
+    /// Load the `kperf` framework and enable the fixed performance counters.
+    pub fn new() -> Result<Self, KperfError> {
+        Ok(Self {
+            kpc: sys::Kpc::load()?,
+        })
+    }
+
+    /// Call `f`, returning its result along with the performance counter
+    /// increments of the current thread during the call.
+    pub fn measure<R>(&self, f: impl FnOnce() -> R) -> (R, Counts) {
+        let start = self.kpc.read();
+        let result = f();
+        let end = self.kpc.read();
+        let counts = Counts {
+            cycles: end.cycles.wrapping_sub(start.cycles),
+            instructions: end.instructions.wrapping_sub(start.instructions),
+        };
+        (result, counts)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use super::{Counts, KperfError};
+    use std::os::raw::{c_char, c_int, c_void};
+
+    extern "C" {
+        fn dlopen(path: *const c_char, mode: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    }
+
+    const RTLD_LAZY: c_int = 1;
+    const KPERF_PATH: &[u8] = b"/System/Library/PrivateFrameworks/kperf.framework/kperf\0";
+
+    /// The class of the fixed counters, which count cycles (counter `0`) and
+    /// retired instructions (counter `1`)
+    const KPC_CLASS_FIXED_MASK: u32 = 1;
+
+    /// The maximum number of counters in a class
+    const KPC_MAX_COUNTERS: usize = 32;
+
+    type SetCountingFn = unsafe extern "C" fn(classes: u32) -> c_int;
+    type GetCounterCountFn = unsafe extern "C" fn(classes: u32) -> u32;
+    type GetThreadCountersFn =
+        unsafe extern "C" fn(tid: u32, buf_count: u32, buf: *mut u64) -> c_int;
+
+    pub(super) struct Kpc {
+        get_thread_counters: GetThreadCountersFn,
+        counter_count: u32,
+    }
+
+    /// Look up the symbol `name` (which must be NUL-terminated) in `handle`.
+    ///
+    /// # Safety
+    ///
+    /// `F` must be a function pointer type matching the symbol's signature.
+    unsafe fn sym<F: Copy>(handle: *mut c_void, name: &[u8]) -> Result<F, KperfError> {
+        let ptr = dlsym(handle, name.as_ptr() as *const c_char);
+        if ptr.is_null() {
+            Err(KperfError::Load)
+        } else {
+            Ok(std::mem::transmute_copy(&ptr))
+        }
+    }
+
+    impl Kpc {
+        pub(super) fn load() -> Result<Self, KperfError> {
+            // Safety: The symbols have the declared signatures. The library
+            //         handle is never closed, so the function pointers remain
+            //         valid.
+            unsafe {
+                let handle = dlopen(KPERF_PATH.as_ptr() as *const c_char, RTLD_LAZY);
+                if handle.is_null() {
+                    return Err(KperfError::Load);
+                }
+                let set_counting: SetCountingFn = sym(handle, b"kpc_set_counting\0")?;
+                let set_thread_counting: SetCountingFn = sym(handle, b"kpc_set_thread_counting\0")?;
+                let get_counter_count: GetCounterCountFn = sym(handle, b"kpc_get_counter_count\0")?;
+                let get_thread_counters: GetThreadCountersFn =
+                    sym(handle, b"kpc_get_thread_counters\0")?;
+
+                for f in [set_counting, set_thread_counting] {
+                    match f(KPC_CLASS_FIXED_MASK) {
+                        0 => {}
+                        e => return Err(KperfError::Os(e)),
+                    }
+                }
+
+                let counter_count = get_counter_count(KPC_CLASS_FIXED_MASK);
+                if !(2..=KPC_MAX_COUNTERS as u32).contains(&counter_count) {
+                    return Err(KperfError::Load);
+                }
+
+                Ok(Self {
+                    get_thread_counters,
+                    counter_count,
+                })
+            }
+        }
+
+        pub(super) fn read(&self) -> Counts {
+            let mut buf = [0u64; KPC_MAX_COUNTERS];
+            // Safety: `buf` is large enough for `counter_count` counters.
+            //         `tid = 0` means the current thread.
+            let e = unsafe { (self.get_thread_counters)(0, self.counter_count, buf.as_mut_ptr()) };
+            debug_assert_eq!(e, 0, "kpc_get_thread_counters failed");
+            Counts {
+                cycles: buf[0],
+                instructions: buf[1],
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod sys {
+    use super::{Counts, KperfError};
+
+    pub(super) enum Kpc {}
+
+    impl Kpc {
+        pub(super) fn load() -> Result<Self, KperfError> {
+            Err(KperfError::Unsupported)
+        }
+
+        pub(super) fn read(&self) -> Counts {
+            match *self {}
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Lane masks of outer products
+
+/// Selects the lanes of `x` or `y` taking part in an outer product computed
+/// by one of [`Amx`](crate::Amx)'s `*_masked` methods.
+///
+/// The `x` mask selects the columns of the output tile, and the `y` mask
+/// selects its rows. The elements of `z` corresponding to a disabled lane are
+/// left unchanged, so a partial tile can be computed without masking the
+/// result after storing it. The `matint` wrappers, such as
+/// [`Amx::outer_product_i8_to_i32_masked`](crate::Amx::outer_product_i8_to_i32_masked),
+/// only take a `y` mask.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{Amx, LaneMask, XBytes, XRow, YBytes, YRow, ZRow};
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// unsafe {
+///     ctx.load512([1.0f32; 16].as_ptr(), XRow(0));
+///     ctx.load512([1.0f32; 16].as_ptr(), YRow(0));
+/// }
+/// let tile = ctx.outer_product_f32_xy_to_z_masked(
+///     Some(XBytes(0)),
+///     Some(YBytes(0)),
+///     ZRow(0),
+///     false,
+///     LaneMask::First(3),
+///     LaneMask::Only(1),
+/// );
+///
+/// let mut row = [0.0f32; 16];
+/// unsafe { ctx.store512(row.as_mut_ptr(), tile.row(1)) };
+/// assert_eq!(row[..4], [1.0, 1.0, 1.0, 0.0]);
+/// unsafe { ctx.store512(row.as_mut_ptr(), tile.row(0)) };
+/// assert_eq!(row, [0.0; 16]);
+/// ```
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LaneMask {
+    /// Enable all lanes.
+    #[default]
+    All,
+    /// Enable no lanes.
+    None,
+    /// Enable the lanes with even indices.
+    Even,
+    /// Enable the lanes with odd indices.
+    Odd,
+    /// Enable the lane with the specified index, which must be in range
+    /// `0..32`. An index beyond the last lane enables no lanes.
+    Only(usize),
+    /// Enable the specified number of lanes at the beginning.
+    First(usize),
+    /// Enable the specified number of lanes at the end.
+    Last(usize),
+}
+
+impl LaneMask {
+    /// Get the seven-bit "enable" field of an outer product operand. The
+    /// lower five bits hold the value, and the upper two bits the mode.
+    #[inline(always)]
+    #[track_caller]
+    pub(crate) fn operand_bits(self) -> u64 {
+        let (mode, value) = match self {
+            Self::All => (0, 0),
+            Self::Odd => (0, 1),
+            Self::Even => (0, 2),
+            Self::None | Self::First(0) | Self::Last(0) => (0, 3),
+            Self::Only(i) => {
+                assert!(i < 32, "lane index out of range");
+                (1, i)
+            }
+            // A count of zero in the modes 2 and 3 means all lanes
+            Self::First(n) | Self::Last(n) if n >= 32 => (0, 0),
+            Self::First(n) => (2, n),
+            Self::Last(n) => (3, n),
+        };
+        (mode << 5) | value as u64
+    }
+
+    /// Decode an "enable" field produced by
+    /// [`operand_bits`](Self::operand_bits).
+    pub(crate) fn from_operand_bits(bits: u64) -> Self {
+        let value = (bits & 0x1f) as usize;
+        match ((bits >> 5) & 0x3, value) {
+            (0, 0) | (2, 0) | (3, 0) => Self::All,
+            (0, 1) => Self::Odd,
+            (0, 2) => Self::Even,
+            (0, _) => Self::None,
+            (1, i) => Self::Only(i),
+            (2, n) => Self::First(n),
+            (_, n) => Self::Last(n),
+        }
+    }
+
+    /// Get whether the lane `i` of `lanes` lanes is enabled.
+    pub(crate) fn is_enabled(self, i: usize, lanes: usize) -> bool {
+        match self {
+            Self::All => true,
+            Self::None => false,
+            Self::Even => i % 2 == 0,
+            Self::Odd => i % 2 == 1,
+            Self::Only(k) => i == k,
+            Self::First(n) => i < n,
+            Self::Last(n) => i + n >= lanes,
+        }
+    }
+}
+
+/// The position of the `y` lane mask in an outer product operand.
+pub(crate) const Y_MASK_POS: u32 = 32;
+/// The position of the `x` lane mask in an outer product operand.
+pub(crate) const X_MASK_POS: u32 = 41;
@@ -19,7 +19,7 @@
 //!          67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82i16];
 //! unsafe { ctx.load512(x.as_ptr(), XRow(0)) };
 //! unsafe { ctx.load512(y.as_ptr(), YRow(0)) };
-//! ctx.outer_product_i16_xy_to_z(
+//! let tile = ctx.outer_product_i16_xy_to_z(
 //!     Some(XBytes(0)),    // input from X starting from byte offset 0
 //!     Some(YBytes(0)),    // input from Y starting from byte offset 0
 //!     ZRow(0),            // output to Z starting from row offset 0
@@ -28,7 +28,8 @@
 //! let z: [[i16; 32]; 64] = unsafe { std::mem::transmute(ctx.read_z()) };
 //! for (x_i, &x) in x.iter().enumerate() {
 //!     for (y_i, &y) in y.iter().enumerate() {
-//!         assert_eq!(z[y_i * 2][x_i], x * y);
+//!         // The output is in every second row (`z[y_i * 2]`)
+//!         assert_eq!(z[tile.row(y_i).0][x_i], x * y);
 //!     }
 //! }
 //! ```
@@ -45,23 +46,97 @@
 //!     z: [[u8; 64]; 64],
 //! }
 //! ```
-#![feature(asm_const)]
 #![cfg_attr(feature = "doc_cfg", feature(doc_cfg))]
 
+pub mod affinity;
+mod aligned;
+mod any;
+#[cfg(feature = "blas-shim")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "blas-shim")))]
+pub mod blas_shim;
+mod checked;
+mod disasm;
+mod dump;
+mod element;
 mod emu;
 mod genlut;
+mod int_accum;
+pub mod kernels;
+#[cfg(feature = "kperf")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "kperf")))]
+pub mod kperf;
+mod lane_mask;
+#[cfg(any(feature = "ndarray", feature = "nalgebra"))]
+#[cfg_attr(
+    feature = "doc_cfg",
+    doc(cfg(any(feature = "ndarray", feature = "nalgebra")))
+)]
+pub mod linalg;
 mod load_store;
+pub mod microkernel;
 mod ops;
-mod regs;
-pub use crate::{emu::*, genlut::*, load_store::*, ops::AmxOps, regs::*};
+#[cfg(feature = "power")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "power")))]
+pub mod power;
+pub mod prefetch;
+#[cfg(feature = "python")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "python")))]
+pub mod python;
+mod record;
+mod reduce;
+pub mod regs;
+#[cfg(feature = "serde")]
+mod serde_bytes;
+mod snapshot;
+mod support;
+mod tile_alloc;
+mod trace;
+mod transpose;
+#[cfg(feature = "unstable-ops")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "unstable-ops")))]
+pub mod unstable;
+mod write_mode;
+pub use crate::{
+    aligned::AmxAligned,
+    any::{AmxCtxBuilder, AnyAmxCtx},
+    disasm::{disasm, AmxInst},
+    dump::DumpFormat,
+    element::{AmxElement, ZLayout},
+    emu::*,
+    genlut::*,
+    int_accum::*,
+    lane_mask::LaneMask,
+    load_store::*,
+    ops::AmxOps,
+    record::{replay, AmxTrace, RecordedOp, RecordingOps},
+    reduce::Reduce,
+    regs::*,
+    snapshot::AmxStateSnapshot,
+    support::is_supported,
+    tile_alloc::{
+        OwnedZTile, PingPongTiles, PingPongTiles16, PingPongTiles32, PingPongTiles64, RegAlloc,
+    },
+    trace::{TracedOp, TracingOps},
+    write_mode::ZWriteMode,
+};
+
+// The integration tests need the opcodes regardless of `unstable-ops`
+#[doc(hidden)]
+pub use crate::ops::opcode as __opcode;
 
 cfg_if::cfg_if! {
     if #[cfg(any(doc, target_arch = "aarch64"))] {
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(target_arch = "aarch64")))]
+        pub mod contention;
         #[cfg_attr(feature = "doc_cfg", doc(cfg(target_arch = "aarch64")))]
         mod nativectx;
         #[cfg_attr(feature = "doc_cfg", doc(cfg(target_arch = "aarch64")))]
         pub mod nativeops;
-        pub use crate::nativectx::{AmxCtx, NewAmxCtxError};
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(target_arch = "aarch64")))]
+        pub mod probe;
+        pub use crate::nativectx::{
+            AmxCtx, AmxEnabled, AmxStateLost, AmxSuspended, NewAmxCtxError,
+        };
     }
 }
 
@@ -88,6 +163,10 @@ pub mod prelude {
 ///  - On any system, construct [`AmxEmuCtx`] by calling [`AmxEmuCtx::default`].
 ///    It implements [`AmxOps`], which has a blanket impl of `Amx`.
 ///
+///  - On any system, construct [`AnyAmxCtx`] by calling
+///    [`AnyAmxCtx::best_available`], which uses the hardware if possible and
+///    the emulator otherwise.
+///
 /// [`amx::nativeops::AmxOps`]: crate::nativeops::AmxOps
 /// [`amx::nativeops::AmxOps::new`]: crate::nativeops::AmxOps::new
 pub trait Amx: crate::ops::AmxOps {
@@ -124,9 +203,106 @@ pub trait Amx: crate::ops::AmxOps {
         row.store1024_aligned(self, ptr);
     }
 
+    /// Load the 64 bytes of `src` to the specified register row. Unlike
+    /// [`load512`](Self::load512), this method is safe because `src` is
+    /// known to be a valid memory region of a plain-old-data type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` isn't exactly 64 bytes large.
+    ///
+    /// ```rust
+    /// use amx::{Amx, YRow};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// ctx.load512_from(&[1.5f32; 16], YRow(2));
+    /// let mut row = [0u32; 16];
+    /// ctx.store512_into(&mut row, YRow(2));
+    /// assert_eq!(row, [1.5f32.to_bits(); 16]);
+    /// ```
+    #[cfg(feature = "bytemuck")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "bytemuck")))]
+    #[inline]
+    #[track_caller]
+    fn load512_from<T: bytemuck::Pod>(&mut self, src: &T, row: impl LoadStore) {
+        let src: &[u8; 64] = bytemuck::cast_ref(src);
+        // Safety: `src` is a memory region of 64 bytes
+        unsafe { self.load512(src.as_ptr(), row) };
+    }
+
+    /// Store the specified register row's contents to the 64 bytes of `dst`.
+    /// Unlike [`store512`](Self::store512), this method is safe because any
+    /// bit pattern is a valid `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` isn't exactly 64 bytes large.
+    #[cfg(feature = "bytemuck")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "bytemuck")))]
+    #[inline]
+    #[track_caller]
+    fn store512_into<T: bytemuck::Pod>(&mut self, dst: &mut T, row: impl LoadStore) {
+        let dst: &mut [u8; 64] = bytemuck::cast_mut(dst);
+        // Safety: `dst` is a memory region of 64 bytes
+        unsafe { self.store512(dst.as_mut_ptr(), row) };
+    }
+
+    /// Load 512 bits (64 bytes) from memory to the specified `x` row with a
+    /// caching hint. **Experimental**; see [`MemHint`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for 64-byte reads.
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn load512_x_hint<T>(&mut self, ptr: *const T, XRow(index): XRow, hint: MemHint) {
+        self.ldx(load_store::xy_hint_operand(index, hint), ptr as *mut ());
+    }
+
+    /// Load 512 bits (64 bytes) from memory to the specified `y` row with a
+    /// caching hint. **Experimental**; see [`MemHint`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for 64-byte reads.
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn load512_y_hint<T>(&mut self, ptr: *const T, YRow(index): YRow, hint: MemHint) {
+        self.ldy(load_store::xy_hint_operand(index, hint), ptr as *mut ());
+    }
+
+    /// Store 512 bits (64 bytes) the specified `x` row's contents to memory
+    /// with a caching hint. **Experimental**; see [`MemHint`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for 64-byte writes.
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn store512_x_hint<T>(&mut self, ptr: *mut T, XRow(index): XRow, hint: MemHint) {
+        self.stx(load_store::xy_hint_operand(index, hint), ptr as *mut ());
+    }
+
+    /// Store 512 bits (64 bytes) the specified `y` row's contents to memory
+    /// with a caching hint. **Experimental**; see [`MemHint`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for 64-byte writes.
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn store512_y_hint<T>(&mut self, ptr: *mut T, YRow(index): YRow, hint: MemHint) {
+        self.sty(load_store::xy_hint_operand(index, hint), ptr as *mut ());
+    }
+
     /// Load 512 bits (64 bytes) from memory to `z[index][0..64]` with interleaving.
     ///
     /// `index` must be in range `0..64`.
+    ///
+    /// It's unknown whether `ldzi` has a 1024-bit (128-byte) form like the
+    /// other loads, so this doesn't offer one, and filling a pair of rows takes
+    /// two of these. `characterize_interleaved_1024` in `tests/characterize.rs`
+    /// checks whether bit 62 of the operand, which selects the 128-byte form
+    /// of `ldz`, has any effect, but it hasn't been run on the hardware yet.
     #[inline(always)]
     #[track_caller]
     unsafe fn load512_interleaved<T>(&mut self, ptr: *const T, row: ZRow) {
@@ -136,12 +312,573 @@ pub trait Amx: crate::ops::AmxOps {
     /// Store 512 bits (64 bytes) `z[index][0..64]` to memory with interleaving.
     ///
     /// `index` must be in range `0..64`.
+    ///
+    /// Like for [`load512_interleaved`](Self::load512_interleaved), it's
+    /// unknown whether `stzi` has a 1024-bit form.
     #[inline(always)]
     #[track_caller]
     unsafe fn store512_interleaved<T>(&mut self, ptr: *mut T, row: ZRow) {
         store512_z_interleaved(self, ptr, row);
     }
 
+    /// Load a row-major matrix of `rows` rows, each 64 bytes long and
+    /// `lda_bytes` bytes apart from the previous one, to `x[0..rows]`.
+    ///
+    /// This issues one 64-byte load per row, so `lda_bytes` can be arbitrary.
+    ///
+    /// `rows` must be in range `0..=8`.
+    ///
+    /// # Safety
+    ///
+    /// `(ptr as *const u8).add(i * lda_bytes)` must be valid for 64-byte reads
+    /// for every `i` in `0..rows`.
+    #[inline]
+    #[track_caller]
+    unsafe fn load_matrix_x<T>(&mut self, ptr: *const T, rows: usize, lda_bytes: usize) {
+        assert!(rows <= 8);
+        for i in 0..rows {
+            self.load512((ptr as *const u8).add(i * lda_bytes), XRow(i));
+        }
+    }
+
+    /// Load a row-major matrix of `rows` rows, each 64 bytes long and
+    /// `lda_bytes` bytes apart from the previous one, to `y[0..rows]`.
+    ///
+    /// This issues one 64-byte load per row, so `lda_bytes` can be arbitrary.
+    ///
+    /// `rows` must be in range `0..=8`.
+    ///
+    /// # Safety
+    ///
+    /// `(ptr as *const u8).add(i * lda_bytes)` must be valid for 64-byte reads
+    /// for every `i` in `0..rows`.
+    #[inline]
+    #[track_caller]
+    unsafe fn load_matrix_y<T>(&mut self, ptr: *const T, rows: usize, lda_bytes: usize) {
+        assert!(rows <= 8);
+        for i in 0..rows {
+            self.load512((ptr as *const u8).add(i * lda_bytes), YRow(i));
+        }
+    }
+
+    /// Load `x[rows]` from consecutive 64-byte chunks starting at `src`.
+    ///
+    /// Each pair of rows starting at an even index is loaded by a single
+    /// 128-byte load if its source is aligned to 128-byte boundaries, and the
+    /// other rows by 64-byte loads, so `src` can have any alignment.
+    ///
+    /// `rows` must be a subrange of `0..8`.
+    ///
+    /// # Safety
+    ///
+    /// `src` must be valid for reading `rows.len() * 64` bytes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use amx::{Amx, AmxAligned};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// let src = AmxAligned([7u8; 320]);
+    /// // `x[1]` is loaded alone, `x[2..4]` and `x[4..6]` in pairs
+    /// unsafe { ctx.load_x_rows(src.0.as_ptr(), 1..6) };
+    /// assert_eq!(ctx.read_x()[64..384], [7u8; 320][..]);
+    /// ```
+    #[inline]
+    #[track_caller]
+    unsafe fn load_x_rows(&mut self, src: *const u8, rows: std::ops::Range<usize>) {
+        assert!(rows.start <= rows.end && rows.end <= 8);
+        load_store::load_rows(self, src, rows, XRow);
+    }
+
+    /// Load `y[rows]` from consecutive 64-byte chunks starting at `src`. See
+    /// [`load_x_rows`](Self::load_x_rows) for details.
+    ///
+    /// `rows` must be a subrange of `0..8`.
+    ///
+    /// # Safety
+    ///
+    /// `src` must be valid for reading `rows.len() * 64` bytes.
+    #[inline]
+    #[track_caller]
+    unsafe fn load_y_rows(&mut self, src: *const u8, rows: std::ops::Range<usize>) {
+        assert!(rows.start <= rows.end && rows.end <= 8);
+        load_store::load_rows(self, src, rows, YRow);
+    }
+
+    /// Store `z[0..rows]` to a row-major matrix of `rows` rows, each 64 bytes
+    /// long and `ldc_bytes` bytes apart from the previous one.
+    ///
+    /// This issues one 64-byte store per row, so `ldc_bytes` can be arbitrary.
+    ///
+    /// `rows` must be in range `0..=64`.
+    ///
+    /// # Safety
+    ///
+    /// `(ptr as *mut u8).add(i * ldc_bytes)` must be valid for 64-byte writes
+    /// for every `i` in `0..rows`.
+    #[inline]
+    #[track_caller]
+    unsafe fn store_z_matrix<T>(&mut self, ptr: *mut T, rows: usize, ldc_bytes: usize) {
+        assert!(rows <= 64);
+        for i in 0..rows {
+            self.store512((ptr as *mut u8).add(i * ldc_bytes), ZRow(i));
+        }
+    }
+
+    /// Store the result of an outer product of `E` issued with the `z` row
+    /// index `z_index` to a row-major matrix `dst` with the leading dimension
+    /// `ld` (measured in elements), converting it from the layout described
+    /// by [`E::Z_LAYOUT`](AmxElement::Z_LAYOUT).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dst` is too small to hold the matrix or if `ld` is smaller
+    /// than the number of columns.
+    ///
+    /// ```rust
+    /// use amx::{Amx, AmxElement, XBytes, XRow, YBytes, YRow, ZRow};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// unsafe {
+    ///     ctx.load512([2.0f64; 8].as_ptr(), XRow(0));
+    ///     ctx.load512([3.0f64; 8].as_ptr(), YRow(0));
+    /// }
+    /// ctx.outer_product::<f64>(Some(XBytes(0)), Some(YBytes(0)), ZRow(5), false);
+    ///
+    /// let mut c = [0.0f64; 8 * 10];
+    /// ctx.store_tile::<f64>(ZRow(5), &mut c, 10);
+    /// assert!(c.chunks(10).all(|row| row[..8] == [6.0; 8] && row[8..] == [0.0; 2]));
+    /// ```
+    #[inline]
+    #[track_caller]
+    fn store_tile<E: AmxElement>(&mut self, z_index: ZRow, dst: &mut [E], ld: usize) {
+        let layout = E::Z_LAYOUT;
+        let (rows, columns) = (layout.rows(), layout.columns());
+        assert_eq!(layout.element_bytes, std::mem::size_of::<E>());
+        assert!(ld >= columns, "leading dimension too small");
+        assert!(dst.len() >= (rows - 1) * ld + columns, "buffer too small");
+
+        let mut buf = AmxAligned([0u8; 64]);
+        for i in 0..rows {
+            for part in 0..layout.interleave {
+                let (z_row, _) = layout.locate(z_index, i, part);
+                buf.store512(self, z_row);
+                for (j, out) in dst[i * ld..][..columns]
+                    .iter_mut()
+                    .enumerate()
+                    .skip(part)
+                    .step_by(layout.interleave)
+                {
+                    let (_, offset) = layout.locate(z_index, i, j);
+                    // Safety: `offset + size_of::<E>() <= 64`, and any bit
+                    //         pattern is a valid `E`
+                    *out = unsafe { buf.0.as_ptr().add(offset).cast::<E>().read_unaligned() };
+                }
+            }
+        }
+    }
+
+    /// Combine the 16 rows of the `f32` tile `tile` element-wise by `op` and
+    /// return the resulting row. For example, this sums up the partial sums
+    /// accumulated in each row of the tile.
+    ///
+    /// The rows are combined pairwise, halving their number at every step,
+    /// which keeps the rounding errors of [`Reduce::Sum`] smaller than those
+    /// of a sequential sum.
+    ///
+    /// ```rust
+    /// use amx::{Amx, Reduce, XBytes, XRow, YBytes, YRow, ZRow, ZTile32};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// let x: Vec<f32> = (0..16).map(|i| i as f32).collect();
+    /// unsafe {
+    ///     ctx.load512(x.as_ptr(), XRow(0));
+    ///     ctx.load512([2.0f32; 16].as_ptr(), YRow(0));
+    /// }
+    /// ctx.outer_product_f32_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), false);
+    ///
+    /// let tile = ZTile32 { base: 1 };
+    /// assert_eq!(ctx.reduce_z_rows_f32(tile, Reduce::Max)[3], 6.0);
+    /// assert_eq!(ctx.reduce_z_rows_f32(tile, Reduce::Sum)[3], 96.0);
+    /// assert_eq!(ctx.reduce_z_f32(tile, Reduce::Sum), 3840.0);
+    /// ```
+    #[inline]
+    fn reduce_z_rows_f32(&mut self, tile: ZTile32, op: Reduce) -> [f32; 16] {
+        let mut rows = [[0.0f32; 16]; ZTile32::LEN];
+        let mut buf = AmxAligned([0.0f32; 16]);
+        for (out, z_row) in rows.iter_mut().zip(tile.rows()) {
+            buf.store512(self, z_row);
+            *out = buf.0;
+        }
+
+        let mut len = rows.len();
+        while len > 1 {
+            len /= 2;
+            let (lo, hi) = rows.split_at_mut(len);
+            for (a_row, b_row) in lo.iter_mut().zip(&hi[..len]) {
+                for (a, &b) in a_row.iter_mut().zip(b_row) {
+                    *a = op.apply(*a, b);
+                }
+            }
+        }
+        rows[0]
+    }
+
+    /// Combine all elements of the `f32` tile `tile` by `op`. See
+    /// [`reduce_z_rows_f32`](Self::reduce_z_rows_f32) for an example.
+    ///
+    /// The result of [`reduce_z_rows_f32`](Self::reduce_z_rows_f32) is
+    /// further combined pairwise.
+    #[inline]
+    fn reduce_z_f32(&mut self, tile: ZTile32, op: Reduce) -> f32 {
+        let mut lanes = self.reduce_z_rows_f32(tile, op);
+        let mut len = lanes.len();
+        while len > 1 {
+            len /= 2;
+            for i in 0..len {
+                lanes[i] = op.apply(lanes[i], lanes[i + len]);
+            }
+        }
+        lanes[0]
+    }
+
+    /// Load `src` to the first `src.len()` bytes of `x[row]`. The remaining
+    /// bytes are filled with zero.
+    ///
+    /// `src` is staged through an aligned buffer on the stack, so this never
+    /// reads past the end of `src`, which is useful for edge tiles.
+    ///
+    /// `src.len()` must not exceed 64.
+    #[inline]
+    #[track_caller]
+    fn load_partial_x(&mut self, src: &[u8], row: XRow) {
+        let staging = Aligned64::from_partial(src);
+        // Safety: Reading a memory region within `staging`
+        unsafe { self.load512(staging.0.as_ptr(), row) };
+    }
+
+    /// Load `src` to the first `src.len()` bytes of `y[row]`. The remaining
+    /// bytes are filled with zero.
+    ///
+    /// See [`load_partial_x`](Self::load_partial_x) for details.
+    #[inline]
+    #[track_caller]
+    fn load_partial_y(&mut self, src: &[u8], row: YRow) {
+        let staging = Aligned64::from_partial(src);
+        // Safety: Reading a memory region within `staging`
+        unsafe { self.load512(staging.0.as_ptr(), row) };
+    }
+
+    /// Store the first `dst.len()` bytes of `z[row]` to `dst`.
+    ///
+    /// `z[row]` is staged through an aligned buffer on the stack, so this
+    /// never writes past the end of `dst`, which is useful for edge tiles.
+    ///
+    /// `dst.len()` must not exceed 64.
+    #[inline]
+    #[track_caller]
+    fn store_partial_z(&mut self, dst: &mut [u8], row: ZRow) {
+        assert!(dst.len() <= 64);
+        let mut staging = Aligned64([0; 64]);
+        // Safety: Writing in a memory region within `staging`
+        unsafe { self.store512(staging.0.as_mut_ptr(), row) };
+        dst.copy_from_slice(&staging.0[..dst.len()]);
+    }
+
+    /// Gather `count` elements spaced `elem_stride` elements apart, starting
+    /// from `ptr`, and load them to the first `count` lanes of the specified
+    /// register row. The remaining lanes are filled with zero.
+    ///
+    /// This is useful for loading a column of a row-major matrix, in which
+    /// case `elem_stride` is the number of elements in a row. The elements are
+    /// gathered into a staging buffer on the stack, which is then loaded by a
+    /// single 64-byte load.
+    ///
+    /// `count * size_of::<T>()` must not exceed 64.
+    ///
+    /// # Safety
+    ///
+    /// `ptr.add(i * elem_stride)` must be valid for reads for every `i` in
+    /// `0..count`.
+    #[inline]
+    #[track_caller]
+    unsafe fn load_column_as_row<T: Copy>(
+        &mut self,
+        ptr: *const T,
+        elem_stride: usize,
+        count: usize,
+        row: impl LoadStore,
+    ) {
+        assert!(count * std::mem::size_of::<T>() <= 64);
+        let mut staging = [0u8; 64];
+        let staging_ptr = staging.as_mut_ptr() as *mut T;
+        for i in 0..count {
+            // Safety: `i < count` and `count * size_of::<T>() <= 64`. The
+            //         source pointer is assumed to be valid by the caller.
+            staging_ptr
+                .add(i)
+                .write_unaligned(ptr.add(i * elem_stride).read_unaligned());
+        }
+        self.load512(staging.as_ptr(), row);
+    }
+
+    /// Zero the whole contents of `x`.
+    ///
+    /// There's no instruction that zeroes `x` without touching other
+    /// registers, so this is done by four 128-byte loads from a static zero
+    /// buffer.
+    #[inline]
+    fn clear_x(&mut self) {
+        for i in (0..8).step_by(2) {
+            // Safety: `ZEROS` is 128 bytes long and 128-byte aligned
+            unsafe { self.load1024_aligned(ZEROS.0.as_ptr(), XRow(i)) };
+        }
+    }
+
+    /// Zero the whole contents of `y`.
+    ///
+    /// This is done in the same way as [`clear_x`](Self::clear_x).
+    #[inline]
+    fn clear_y(&mut self) {
+        for i in (0..8).step_by(2) {
+            // Safety: `ZEROS` is 128 bytes long and 128-byte aligned
+            unsafe { self.load1024_aligned(ZEROS.0.as_ptr(), YRow(i)) };
+        }
+    }
+
+    /// Zero the whole contents of `z`.
+    ///
+    /// This is done by eight `fma64` instructions with all inputs disabled,
+    /// each of which zeroes every eighth row. No memory access is involved.
+    #[inline]
+    fn clear_z(&mut self) {
+        for i in 0..8 {
+            self.fma64(FMA_SKIP_ALL | (i << 20));
+        }
+    }
+
+    /// Zero the whole contents of `x`, `y`, and `z`.
+    ///
+    /// This combines [`clear_x`](Self::clear_x),
+    /// [`clear_y`](Self::clear_y), and [`clear_z`](Self::clear_z), taking 16
+    /// instructions in total.
+    #[inline]
+    fn clear_all(&mut self) {
+        self.clear_x();
+        self.clear_y();
+        self.clear_z();
+    }
+
+    /// Zero `z[row]`.
+    ///
+    /// This is done by a single `fma64` instruction in vector mode with all
+    /// inputs disabled.
+    ///
+    /// `row` must be in range `0..64`.
+    #[inline(always)]
+    fn clear_z_row(&mut self, row: ZRow) {
+        // FIXME: rustfmt doesn't like patterns in provided trait methods
+        let row = row.0;
+        debug_assert!(row < 64);
+        self.fma64(FMA_SKIP_ALL | FMA_VECTOR | ((row as u64) << 20));
+    }
+
+    /// Copy `z[z_row]` to `x[x_row]` by a single `extrx` instruction.
+    ///
+    /// `z_row` must be in range `0..64`. `x_row` must be in range `0..8`.
+    #[inline(always)]
+    fn copy_z_row_to_x(&mut self, z_row: ZRow, x_row: XRow) {
+        // FIXME: rustfmt doesn't like patterns in provided trait methods
+        let (z_row, x_row) = (z_row.0, x_row.0);
+        debug_assert!(z_row < 64);
+        debug_assert!(x_row < 8);
+        self.extrx(((x_row << 16) | (z_row << 20)) as u64);
+    }
+
+    /// Copy `z[z_row]` to `y[y_row]` by a single `extry` instruction.
+    ///
+    /// `z_row` must be in range `0..64`. `y_row` must be in range `0..8`.
+    #[inline(always)]
+    fn copy_z_row_to_y(&mut self, z_row: ZRow, y_row: YRow) {
+        // FIXME: rustfmt doesn't like patterns in provided trait methods
+        let (z_row, y_row) = (z_row.0, y_row.0);
+        debug_assert!(z_row < 64);
+        debug_assert!(y_row < 8);
+        self.extry(((y_row << 6) | (z_row << 20)) as u64);
+    }
+
+    /// Copy `x[x_row]` to `y[y_row]` by a single `extry` instruction.
+    ///
+    /// `x_row` and `y_row` must be in range `0..8`.
+    #[inline(always)]
+    fn copy_x_to_y(&mut self, x_row: XRow, y_row: YRow) {
+        // FIXME: rustfmt doesn't like patterns in provided trait methods
+        let (x_row, y_row) = (x_row.0, y_row.0);
+        debug_assert!(x_row < 8);
+        debug_assert!(y_row < 8);
+        self.extry(((y_row << 6) | (x_row << 16) | (1 << 27)) as u64);
+    }
+
+    /// Copy the `row`-th row of the result tile of `E` selected by `z_index`
+    /// (see [`ZLayout`]) to `x[x_row]` by a single `extrx` instruction.
+    #[inline(always)]
+    fn extract_z_row_to_x<E: AmxElement>(&mut self, z_index: ZRow, row: usize, x_row: XRow) {
+        let (z_row, _) = E::Z_LAYOUT.locate(z_index, row, 0);
+        self.copy_z_row_to_x(z_row, x_row);
+    }
+
+    /// Copy the `row`-th row of the result tile of `E` selected by `z_index`
+    /// (see [`ZLayout`]) to `y[y_row]` by a single `extry` instruction.
+    #[inline(always)]
+    fn extract_z_row_to_y<E: AmxElement>(&mut self, z_index: ZRow, row: usize, y_row: YRow) {
+        let (z_row, _) = E::Z_LAYOUT.locate(z_index, row, 0);
+        self.copy_z_row_to_y(z_row, y_row);
+    }
+
+    /// Gather the `column`-th column of the result tile of `E` selected by
+    /// `z_index` (see [`ZLayout`]) to `x[x_row]` by a single `extrx`
+    /// instruction. **Experimental**: the encoding of the column variant is
+    /// based on reverse-engineering notes and only verified by the tests.
+    ///
+    /// A column of a tile of 32-bit or 64-bit results is spread over 16 or 8
+    /// `z` rows, so this is much faster than reading the rows back.
+    ///
+    /// `column` must be in range `0..E::Z_LAYOUT.columns()`. `x_row` must be
+    /// in range `0..8`.
+    ///
+    /// ```rust
+    /// use amx::{Amx, XBytes, XRow, YBytes, YRow, ZRow};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// let x: [f32; 16] = std::array::from_fn(|i| i as f32);
+    /// unsafe {
+    ///     ctx.load512(x.as_ptr(), XRow(0));
+    ///     ctx.load512(x.as_ptr(), YRow(0));
+    /// }
+    /// ctx.outer_product::<f32>(Some(XBytes(0)), Some(YBytes(0)), ZRow(2), false);
+    ///
+    /// // `z[2 + 4 * i][3] == x[3] * y[i]`
+    /// ctx.extract_z_column_to_x::<f32>(ZRow(2), 3, XRow(1));
+    /// let mut column = [0.0f32; 16];
+    /// unsafe { ctx.store512(column.as_mut_ptr(), XRow(1)) };
+    /// assert_eq!(column, x.map(|y| 3.0 * y));
+    /// ```
+    #[inline(always)]
+    fn extract_z_column_to_x<E: AmxElement>(&mut self, z_index: ZRow, column: usize, x_row: XRow) {
+        debug_assert!(x_row.0 < 8);
+        self.extrx(extr_column_operand::<E>(z_index, column) | ((x_row.0 as u64) << 16));
+    }
+
+    /// Gather the `column`-th column of the result tile of `E` selected by
+    /// `z_index` (see [`ZLayout`]) to `y[y_row]` by a single `extry`
+    /// instruction. **Experimental**; see
+    /// [`extract_z_column_to_x`](Self::extract_z_column_to_x).
+    ///
+    /// `column` must be in range `0..E::Z_LAYOUT.columns()`. `y_row` must be
+    /// in range `0..8`.
+    #[inline(always)]
+    fn extract_z_column_to_y<E: AmxElement>(&mut self, z_index: ZRow, column: usize, y_row: YRow) {
+        debug_assert!(y_row.0 < 8);
+        self.extry(extr_column_operand::<E>(z_index, column) | ((y_row.0 as u64) << 6));
+    }
+
+    /// Rotate the whole contents of `x` by `amount` bytes toward the lower
+    /// offsets, so that the byte previously found at `XBytes(amount)` ends up
+    /// at `XBytes(0)` and the byte previously found at `XBytes(0)` ends up at
+    /// `XBytes(512 - amount)`.
+    ///
+    /// This is equivalent to subtracting `amount` from every offset into `x`
+    /// held by the caller. It's implemented by a round trip through memory.
+    ///
+    /// `amount` is taken modulo 512.
+    fn rotate_x_bytes(&mut self, amount: usize) {
+        let mut buf = [0u8; 1024];
+        let x = self.read_x();
+        buf[..512].copy_from_slice(&x);
+        buf[512..].copy_from_slice(&x);
+        let amount = amount % 512;
+        for i in 0..8 {
+            // Safety: Reading a memory region within `buf`
+            unsafe { self.load512(buf[amount + i * 64..].as_ptr(), XRow(i)) };
+        }
+    }
+
+    /// Rotate the whole contents of `y` by `amount` bytes toward the lower
+    /// offsets.
+    ///
+    /// See [`rotate_x_bytes`](Self::rotate_x_bytes) for details.
+    fn rotate_y_bytes(&mut self, amount: usize) {
+        let mut buf = [0u8; 1024];
+        let y = self.read_y();
+        buf[..512].copy_from_slice(&y);
+        buf[512..].copy_from_slice(&y);
+        let amount = amount % 512;
+        for i in 0..8 {
+            // Safety: Reading a memory region within `buf`
+            unsafe { self.load512(buf[amount + i * 64..].as_ptr(), YRow(i)) };
+        }
+    }
+
+    /// Load the whole contents of `x` from `src`. This is the inverse of
+    /// [`read_x`](Self::read_x).
+    ///
+    /// If `src` is aligned to 128-byte boundaries, this method uses 128-byte
+    /// loads, halving the number of instructions.
+    #[inline]
+    fn load_all_x(&mut self, src: &[u8; 512]) {
+        load_store::load_all(self, src, XRow);
+    }
+
+    /// Load the whole contents of `y` from `src`. This is the inverse of
+    /// [`read_y`](Self::read_y).
+    ///
+    /// If `src` is aligned to 128-byte boundaries, this method uses 128-byte
+    /// loads, halving the number of instructions.
+    #[inline]
+    fn load_all_y(&mut self, src: &[u8; 512]) {
+        load_store::load_all(self, src, YRow);
+    }
+
+    /// Load the whole contents of `z` from `src`. This is the inverse of
+    /// [`read_z`](Self::read_z).
+    ///
+    /// If `src` is aligned to 128-byte boundaries, this method uses 128-byte
+    /// loads, halving the number of instructions.
+    #[inline]
+    fn load_all_z(&mut self, src: &[u8; 4096]) {
+        load_store::load_all(self, src, ZRow);
+    }
+
+    /// Store the whole contents of `x` to `dst`. Unlike
+    /// [`read_x`](Self::read_x), this method lets the caller choose the
+    /// destination.
+    ///
+    /// If `dst` is aligned to 128-byte boundaries, this method uses 128-byte
+    /// stores, halving the number of instructions.
+    #[inline]
+    fn store_all_x(&mut self, dst: &mut [u8; 512]) {
+        load_store::store_all(self, dst, XRow);
+    }
+
+    /// Store the whole contents of `y` to `dst`. Unlike
+    /// [`read_y`](Self::read_y), this method lets the caller choose the
+    /// destination.
+    ///
+    /// If `dst` is aligned to 128-byte boundaries, this method uses 128-byte
+    /// stores, halving the number of instructions.
+    #[inline]
+    fn store_all_y(&mut self, dst: &mut [u8; 512]) {
+        load_store::store_all(self, dst, YRow);
+    }
+
+    /// Store the whole contents of `z` to `dst`. Unlike
+    /// [`read_z`](Self::read_z), this method lets the caller choose the
+    /// destination.
+    ///
+    /// If `dst` is aligned to 128-byte boundaries, this method uses 128-byte
+    /// stores, halving the number of instructions.
+    #[inline]
+    fn store_all_z(&mut self, dst: &mut [u8; 4096]) {
+        load_store::store_all(self, dst, ZRow);
+    }
+
     /// Read the whole contents of `x`.
     fn read_x(&mut self) -> [u8; 512] {
         let mut ret = std::mem::MaybeUninit::uninit();
@@ -190,6 +927,114 @@ pub trait Amx: crate::ops::AmxOps {
         unsafe { ret.assume_init() }
     }
 
+    /// Read the whole contents of `x` as a value of type `T`, e.g.,
+    /// `[[f32; 16]; 8]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` isn't exactly 512 bytes large.
+    #[cfg(feature = "bytemuck")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "bytemuck")))]
+    #[inline]
+    #[track_caller]
+    fn read_x_as<T: bytemuck::Pod>(&mut self) -> T {
+        bytemuck::cast(self.read_x())
+    }
+
+    /// Read the whole contents of `y` as a value of type `T`, e.g.,
+    /// `[[f32; 16]; 8]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` isn't exactly 512 bytes large.
+    #[cfg(feature = "bytemuck")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "bytemuck")))]
+    #[inline]
+    #[track_caller]
+    fn read_y_as<T: bytemuck::Pod>(&mut self) -> T {
+        bytemuck::cast(self.read_y())
+    }
+
+    /// Read the whole contents of `z` as a value of type `T`, e.g.,
+    /// `[[f32; 16]; 64]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` isn't exactly 4096 bytes large.
+    ///
+    /// ```rust
+    /// use amx::{Amx, XBytes, XRow, YBytes, YRow, ZRow};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// ctx.load512_from(&[2.0f32; 16], XRow(0));
+    /// ctx.load512_from(&[3.0f32; 16], YRow(0));
+    /// ctx.outer_product_f32_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), false);
+    /// let z: [[f32; 16]; 64] = ctx.read_z_as();
+    /// assert_eq!(z[4], [6.0; 16]);
+    /// assert_eq!(z[5], [0.0; 16]);
+    /// ```
+    #[cfg(feature = "bytemuck")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "bytemuck")))]
+    #[inline]
+    #[track_caller]
+    fn read_z_as<T: bytemuck::Pod>(&mut self) -> T {
+        bytemuck::cast(self.read_z())
+    }
+
+    /// Save the whole contents of the AMX registers. Equivalent to
+    /// [`AmxStateSnapshot::save`].
+    fn snapshot(&mut self) -> AmxStateSnapshot {
+        AmxStateSnapshot::save(self)
+    }
+
+    /// Save the whole contents of the AMX registers, call `f`, and restore
+    /// them. The registers are restored even if `f` panics.
+    ///
+    /// Apple's libraries (e.g., vDSP and BLAS in Accelerate) use AMX
+    /// internally and don't preserve the register contents. Wrapping calls
+    /// into them with this method makes it possible to interleave them with
+    /// your own kernels without losing intermediate results. AMX must still
+    /// be enabled when `f` returns.
+    ///
+    /// ```rust
+    /// use amx::{Amx, XRow};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// unsafe { ctx.load512([42u8; 64].as_ptr(), XRow(1)) };
+    /// let len = ctx.with_state_preserved(|| {
+    ///     // Call something that might clobber the AMX registers
+    ///     vec![0.0f32; 1000].len()
+    /// });
+    /// assert_eq!(len, 1000);
+    /// assert_eq!(ctx.read_x()[64..128], [42u8; 64]);
+    /// ```
+    fn with_state_preserved<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        struct Restore<'a, T: Amx + ?Sized>(&'a mut T, AmxStateSnapshot);
+
+        impl<T: Amx + ?Sized> Drop for Restore<'_, T> {
+            fn drop(&mut self) {
+                self.1.restore(self.0);
+            }
+        }
+
+        let snapshot = self.snapshot();
+        let _restore = Restore(self, snapshot);
+        f()
+    }
+
+    /// Format the whole contents of `x`, `y`, and `z` as a human-readable
+    /// grid with one register row per line, e.g., for debugging a kernel.
+    ///
+    /// ```rust
+    /// use amx::{Amx, DumpFormat, XRow};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// unsafe { ctx.load512([1.5f32; 16].as_ptr(), XRow(1)) };
+    /// let dump = ctx.dump(DumpFormat::F32);
+    /// assert!(dump.lines().nth(1).unwrap().starts_with("x1  | 1.5 1.5"));
+    /// assert_eq!(dump.lines().count(), 8 + 8 + 64);
+    /// ```
+    fn dump(&mut self, format: DumpFormat) -> String {
+        dump::dump(&self.read_x(), &self.read_y(), &self.read_z(), format)
+    }
+
     /// Calculate the outer product of `x: [i16; 32]` and `y: [i16; 32]` and write
     /// the output to every second row of `z: [[i16; 32]; 64]`.
     ///
@@ -198,37 +1043,655 @@ pub trait Amx: crate::ops::AmxOps {
     /// multiplication).
     ///
     /// `z_index` must be in range `0..64`. Only the least significant bit of
-    /// `z_index` will be taken into consideration.
+    /// `z_index` will be taken into consideration. The returned [`ZTile16`]
+    /// describes the rows written.
+    ///
+    /// The row stride is always two; `mac16` has no operand field to change
+    /// it, and the upper bits of `z_index` don't offset the tile. Therefore,
+    /// `z` can hold at most two independent `i16` accumulators, e.g., for
+    /// alternating between them to hide the instruction latency.
+    ///
+    /// `mode` specifies whether the product is written as it is or added to
+    /// `z`. Passing a `bool` selects between the two; see [`ZWriteMode`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mode` is [`ZWriteMode::Subtract`], which `mac16` doesn't
+    /// support.
     #[inline(always)]
+    #[track_caller]
     fn outer_product_i16_xy_to_z(
         &mut self,
         x_offset_bytes: Option<XBytes>,
         y_offset_bytes: Option<YBytes>,
         z_index: ZRow,
-        accumulate: bool,
-    ) {
-        // FIXME: rustfmt doesn't like patterns in provided trait methods
-        let z_index = z_index.0;
-        debug_assert!(x_offset_bytes.unwrap_or_default().0 < 0x200);
-        debug_assert!(y_offset_bytes.unwrap_or_default().0 < 0x200);
-        debug_assert!(z_index < 64);
+        mode: impl Into<ZWriteMode>,
+    ) -> ZTile16 {
+        self.outer_product_i16_xy_to_z_masked(
+            x_offset_bytes,
+            y_offset_bytes,
+            z_index,
+            mode,
+            LaneMask::All,
+            LaneMask::All,
+        )
+    }
+
+    /// The masked version of
+    /// [`outer_product_i16_xy_to_z`](Self::outer_product_i16_xy_to_z). Only
+    /// the lanes of `x` and `y` selected by `x_mask` and `y_mask` take part
+    /// in the operation, and the other elements of the tile are left
+    /// unchanged.
+    #[inline(always)]
+    #[track_caller]
+    fn outer_product_i16_xy_to_z_masked(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        mode: impl Into<ZWriteMode>,
+        x_mask: LaneMask,
+        y_mask: LaneMask,
+    ) -> ZTile16 {
         // TODO: widening (i32 output)
         // TODO: vector output (reducing)
+        let accumulate = mode.into().reads_z_int();
         self.mac16(
-            (y_offset_bytes.unwrap_or_default().0
-                | (x_offset_bytes.unwrap_or_default().0 << 10)
-                | (z_index << 20)
-                | (((!accumulate) as usize) << 27)
-                | ((x_offset_bytes.is_none() as usize) << 28)
-                | ((y_offset_bytes.is_none() as usize) << 29)) as u64,
+            outer_product_operand(x_offset_bytes, y_offset_bytes, z_index, accumulate)
+                | lane_mask_bits(x_mask, y_mask),
         );
+        ZTile16::from_z_index(z_index)
+    }
+
+    /// Calculate the outer product of `x: [f32; 16]` and `y: [f32; 16]` and write
+    /// the output to every fourth row of `z: [[f32; 16]; 64]`.
+    ///
+    /// If `x_offset_bytes` and/or `y_offset_bytes` are `None`, the respective
+    /// registers will be excluded from the operation (not performing
+    /// multiplication).
+    ///
+    /// `z_index` must be in range `0..64`. Only the two least significant bits
+    /// of `z_index` will be taken into consideration. The returned [`ZTile32`]
+    /// describes the rows written.
+    ///
+    /// `mode` specifies whether the product is written as it is, added to
+    /// `z`, or subtracted from `z`. Passing a `bool` selects between the
+    /// first two; see [`ZWriteMode`].
+    #[inline(always)]
+    fn outer_product_f32_xy_to_z(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        mode: impl Into<ZWriteMode>,
+    ) -> ZTile32 {
+        self.outer_product_f32_xy_to_z_masked(
+            x_offset_bytes,
+            y_offset_bytes,
+            z_index,
+            mode,
+            LaneMask::All,
+            LaneMask::All,
+        )
+    }
+
+    /// The masked version of
+    /// [`outer_product_f32_xy_to_z`](Self::outer_product_f32_xy_to_z). Only
+    /// the lanes of `x` and `y` selected by `x_mask` and `y_mask` take part
+    /// in the operation, and the other elements of the tile are left
+    /// unchanged.
+    #[inline(always)]
+    #[track_caller]
+    fn outer_product_f32_xy_to_z_masked(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        mode: impl Into<ZWriteMode>,
+        x_mask: LaneMask,
+        y_mask: LaneMask,
+    ) -> ZTile32 {
+        let mode = mode.into();
+        let operand =
+            outer_product_operand(x_offset_bytes, y_offset_bytes, z_index, mode.reads_z())
+                | lane_mask_bits(x_mask, y_mask);
+        if mode == ZWriteMode::Subtract {
+            self.fms32(operand);
+        } else {
+            self.fma32(operand);
+        }
+        ZTile32::from_z_index(z_index)
+    }
+
+    /// Calculate the outer product of `x: [f64; 8]` and `y: [f64; 8]` and write
+    /// the output to every eighth row of `z: [[f64; 8]; 64]`.
+    ///
+    /// If `x_offset_bytes` and/or `y_offset_bytes` are `None`, the respective
+    /// registers will be excluded from the operation (not performing
+    /// multiplication).
+    ///
+    /// `z_index` must be in range `0..64`. Only the three least significant
+    /// bits of `z_index` will be taken into consideration. The returned
+    /// [`ZTile64`] describes the rows written.
+    ///
+    /// `mode` specifies whether the product is written as it is, added to
+    /// `z`, or subtracted from `z`. Passing a `bool` selects between the
+    /// first two; see [`ZWriteMode`].
+    #[inline(always)]
+    fn outer_product_f64_xy_to_z(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        mode: impl Into<ZWriteMode>,
+    ) -> ZTile64 {
+        self.outer_product_f64_xy_to_z_masked(
+            x_offset_bytes,
+            y_offset_bytes,
+            z_index,
+            mode,
+            LaneMask::All,
+            LaneMask::All,
+        )
+    }
+
+    /// The masked version of
+    /// [`outer_product_f64_xy_to_z`](Self::outer_product_f64_xy_to_z). Only
+    /// the lanes of `x` and `y` selected by `x_mask` and `y_mask` take part
+    /// in the operation, and the other elements of the tile are left
+    /// unchanged.
+    #[inline(always)]
+    #[track_caller]
+    fn outer_product_f64_xy_to_z_masked(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        mode: impl Into<ZWriteMode>,
+        x_mask: LaneMask,
+        y_mask: LaneMask,
+    ) -> ZTile64 {
+        let mode = mode.into();
+        let operand =
+            outer_product_operand(x_offset_bytes, y_offset_bytes, z_index, mode.reads_z())
+                | lane_mask_bits(x_mask, y_mask);
+        if mode == ZWriteMode::Subtract {
+            self.fms64(operand);
+        } else {
+            self.fma64(operand);
+        }
+        ZTile64::from_z_index(z_index)
+    }
+
+    /// Calculate the outer product of `x: [f16; 32]` and `y: [f16; 32]` and write
+    /// the output to every second row of `z: [[f16; 32]; 64]`.
+    ///
+    /// The elements are IEEE 754 binary16 numbers, which are manipulated as
+    /// `u16` bit patterns or, with the `half` feature, as `half::f16` in
+    /// memory. If `x_offset_bytes` and/or `y_offset_bytes` are `None`, the
+    /// respective registers will be excluded from the operation (not
+    /// performing multiplication).
+    ///
+    /// `z_index` must be in range `0..64`. Only the least significant bit of
+    /// `z_index` will be taken into consideration. The returned [`ZTile16`]
+    /// describes the rows written.
+    ///
+    /// `mode` specifies whether the product is written as it is, added to
+    /// `z`, or subtracted from `z`. Passing a `bool` selects between the
+    /// first two; see [`ZWriteMode`].
+    #[inline(always)]
+    fn outer_product_f16_xy_to_z(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        mode: impl Into<ZWriteMode>,
+    ) -> ZTile16 {
+        self.outer_product_f16_xy_to_z_masked(
+            x_offset_bytes,
+            y_offset_bytes,
+            z_index,
+            mode,
+            LaneMask::All,
+            LaneMask::All,
+        )
+    }
+
+    /// The masked version of
+    /// [`outer_product_f16_xy_to_z`](Self::outer_product_f16_xy_to_z). Only
+    /// the lanes of `x` and `y` selected by `x_mask` and `y_mask` take part
+    /// in the operation, and the other elements of the tile are left
+    /// unchanged.
+    #[inline(always)]
+    #[track_caller]
+    fn outer_product_f16_xy_to_z_masked(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        mode: impl Into<ZWriteMode>,
+        x_mask: LaneMask,
+        y_mask: LaneMask,
+    ) -> ZTile16 {
+        let mode = mode.into();
+        let operand =
+            outer_product_operand(x_offset_bytes, y_offset_bytes, z_index, mode.reads_z())
+                | lane_mask_bits(x_mask, y_mask);
+        if mode == ZWriteMode::Subtract {
+            self.fms16(operand);
+        } else {
+            self.fma16(operand);
+        }
+        ZTile16::from_z_index(z_index)
+    }
+
+    /// Calculate the outer product of `x: [E; E::LANES]` and
+    /// `y: [E; E::LANES]` and write the output to the tile of `z` described
+    /// by the returned `E::Tile`.
+    ///
+    /// This is a generic entry point dispatching to the element-specific
+    /// methods such as
+    /// [`outer_product_f32_xy_to_z`](Self::outer_product_f32_xy_to_z). See
+    /// [`AmxElement`] for the supported types and [`ZWriteMode`] for the
+    /// meaning of `mode`.
+    ///
+    /// ```rust
+    /// use amx::{Amx, XBytes, XRow, YBytes, YRow, ZRow, ZWriteMode};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// unsafe {
+    ///     ctx.load512([2.0f64; 8].as_ptr(), XRow(0));
+    ///     ctx.load512([3.0f64; 8].as_ptr(), YRow(0));
+    /// }
+    /// let (x, y) = (Some(XBytes(0)), Some(YBytes(0)));
+    /// let tile = ctx.outer_product::<f64>(x, y, ZRow(1), ZWriteMode::Overwrite);
+    /// ctx.outer_product::<f64>(x, y, ZRow(1), ZWriteMode::Accumulate);
+    ///
+    /// let mut row = [0.0f64; 8];
+    /// unsafe { ctx.store512(row.as_mut_ptr(), tile.row(7)) };
+    /// assert_eq!(row, [12.0; 8]);
+    /// ```
+    #[inline(always)]
+    #[track_caller]
+    fn outer_product<E: AmxElement>(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        mode: impl Into<ZWriteMode>,
+    ) -> E::Tile {
+        self.outer_product_masked::<E>(
+            x_offset_bytes,
+            y_offset_bytes,
+            z_index,
+            mode,
+            LaneMask::All,
+            LaneMask::All,
+        )
+    }
+
+    /// The masked version of [`outer_product`](Self::outer_product). Only
+    /// the lanes of `x` and `y` selected by `x_mask` and `y_mask` take part
+    /// in the operation, and the other elements of the tile are left
+    /// unchanged.
+    #[inline(always)]
+    #[track_caller]
+    fn outer_product_masked<E: AmxElement>(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        mode: impl Into<ZWriteMode>,
+        x_mask: LaneMask,
+        y_mask: LaneMask,
+    ) -> E::Tile {
+        E::outer_product_masked(
+            self,
+            x_offset_bytes,
+            y_offset_bytes,
+            z_index,
+            mode.into(),
+            x_mask,
+            y_mask,
+        )
+    }
+
+    /// Calculate the outer product of `x: [u8; 16]` and `y: [u8; 16]` and write
+    /// the output to every fourth row of `z: [[i32; 16]; 64]`.
+    ///
+    /// Only the first 16 bytes starting from `x_offset_bytes` and
+    /// `y_offset_bytes` are used. If `x_offset_bytes` and/or `y_offset_bytes`
+    /// are `None`, the respective registers will be excluded from the
+    /// operation (not performing multiplication).
+    ///
+    /// `z_index` must be in range `0..64`. Only the two least significant bits
+    /// of `z_index` will be taken into consideration. The returned [`ZTile32`]
+    /// describes the rows written.
+    ///
+    /// `write_mode` specifies whether the products are written as they are or
+    /// added to `z`. Passing a `bool` selects between the two; see
+    /// [`ZWriteMode`]. `mode` specifies how the products are shifted and
+    /// accumulated. Use `IntAccumMode::default()` for the ordinary wrapping
+    /// multiply-accumulate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `write_mode` is [`ZWriteMode::Subtract`], which `matint`
+    /// doesn't support.
+    #[inline(always)]
+    #[track_caller]
+    fn outer_product_u8_to_i32(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        write_mode: impl Into<ZWriteMode>,
+        mode: IntAccumMode,
+    ) -> ZTile32 {
+        self.outer_product_u8_to_i32_masked(
+            x_offset_bytes,
+            y_offset_bytes,
+            z_index,
+            write_mode,
+            mode,
+            LaneMask::All,
+        )
+    }
+
+    /// The masked version of
+    /// [`outer_product_u8_to_i32`](Self::outer_product_u8_to_i32). Only the
+    /// lanes of `y` selected by `y_mask`, i.e., the rows of the tile, take
+    /// part in the operation, and the other rows are left unchanged.
+    ///
+    /// `matint` has a single lane mask because the bits holding the `x` lane
+    /// mask of `fma*` hold the lane width mode instead. The mask is assumed
+    /// to be encoded in the same way as the `y` lane mask of `fma*`, which
+    /// hasn't been verified on the hardware yet;
+    /// `outer_product_8bit_to_i32_masked_native` in `tests/outer_prod.rs`
+    /// checks it.
+    #[inline(always)]
+    #[track_caller]
+    fn outer_product_u8_to_i32_masked(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        write_mode: impl Into<ZWriteMode>,
+        mode: IntAccumMode,
+        y_mask: LaneMask,
+    ) -> ZTile32 {
+        let accumulate = write_mode.into().reads_z_int();
+        self.matint(
+            outer_product_operand(x_offset_bytes, y_offset_bytes, z_index, accumulate)
+                | lane_mask_bits(LaneMask::All, y_mask)
+                | (emu::MATINT_LANES_U8_I32 << 42)
+                | mode.matint_bits(),
+        );
+        ZTile32::from_z_index(z_index)
+    }
+
+    /// Calculate the outer product of `x: [i8; 16]` and `y: [i8; 16]` and write
+    /// the output to every fourth row of `z: [[i32; 16]; 64]`.
+    ///
+    /// See [`outer_product_u8_to_i32`](Self::outer_product_u8_to_i32) for
+    /// details.
+    #[inline(always)]
+    #[track_caller]
+    fn outer_product_i8_to_i32(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        write_mode: impl Into<ZWriteMode>,
+        mode: IntAccumMode,
+    ) -> ZTile32 {
+        self.outer_product_i8_to_i32_masked(
+            x_offset_bytes,
+            y_offset_bytes,
+            z_index,
+            write_mode,
+            mode,
+            LaneMask::All,
+        )
+    }
+
+    /// The masked version of
+    /// [`outer_product_i8_to_i32`](Self::outer_product_i8_to_i32). See
+    /// [`outer_product_u8_to_i32_masked`](Self::outer_product_u8_to_i32_masked)
+    /// for details.
+    #[inline(always)]
+    #[track_caller]
+    fn outer_product_i8_to_i32_masked(
+        &mut self,
+        x_offset_bytes: Option<XBytes>,
+        y_offset_bytes: Option<YBytes>,
+        z_index: ZRow,
+        write_mode: impl Into<ZWriteMode>,
+        mode: IntAccumMode,
+        y_mask: LaneMask,
+    ) -> ZTile32 {
+        let accumulate = write_mode.into().reads_z_int();
+        self.matint(
+            outer_product_operand(x_offset_bytes, y_offset_bytes, z_index, accumulate)
+                | lane_mask_bits(LaneMask::All, y_mask)
+                | (emu::MATINT_LANES_I8_I32 << 42)
+                | mode.matint_bits(),
+        );
+        ZTile32::from_z_index(z_index)
+    }
+
+    /// Transpose each of the four interleaved 16×16 `f32` tiles held in
+    /// `z: [[f32; 16]; 64]`, i.e., the layout produced by
+    /// [`outer_product_f32_xy_to_z`](Self::outer_product_f32_xy_to_z).
+    ///
+    /// After this operation, `z[j * 4 + t][i]` holds the value previously found
+    /// in `z[i * 4 + t][j]`. This is done by a round trip through memory.
+    fn transpose_z_f32(&mut self) {
+        transpose::transpose_z(self, 4);
+    }
+
+    /// Transpose each of the two interleaved 32×32 `i16` tiles held in
+    /// `z: [[i16; 32]; 64]`, i.e., the layout produced by
+    /// [`outer_product_i16_xy_to_z`](Self::outer_product_i16_xy_to_z).
+    ///
+    /// After this operation, `z[j * 2 + t][i]` holds the value previously found
+    /// in `z[i * 2 + t][j]`. This is done by a round trip through memory.
+    fn transpose_z_i16(&mut self) {
+        transpose::transpose_z(self, 2);
     }
 
     /// Perform (reverse) table lookup.
+    ///
+    /// The indices (for [`Normal`]) or values (for [`Reverse`]) are read from
+    /// 64 bytes starting at `input`, and the table is read from `table`. The
+    /// result is written to `output`. See [`LutTy`] for the supported
+    /// combinations of types.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use amx::{Amx, Index4, Normal, XBytes, XRow, X8};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// let table: Vec<u8> = (0..64).map(|i| i * 2).collect();
+    /// let indices = [0x10u8, 0x32, 0x54, 0x76, 0x98, 0xba, 0xdc, 0xfe];
+    /// unsafe { ctx.load512(table.as_ptr(), XRow(0)) };
+    /// ctx.load_partial_x(&indices, XRow(1));
+    /// ctx.lut(XBytes(64), XRow(0), XRow(2), (Normal, Index4, X8));
+    /// assert_eq!(ctx.read_x()[128..144], table[..16]);
+    /// ```
     #[inline(always)]
     fn lut(&mut self, input: impl LutIn, table: XRow, output: impl LutOut, ty: impl LutTy) {
         genlut::lut(self, input, table, output, ty);
     }
+
+    /// For each value of type `E` in the 64 bytes starting at `input`, find
+    /// the position of the value in the table stored in `table`, and write the
+    /// results to `output` as tightly-packed `E::INDEX_BITS`-bit indices.
+    ///
+    /// When the table is sorted in ascending order, each index is that of the
+    /// last table entry less than or equal to the value (like
+    /// `searchsorted(side="right") - 1`), or all ones if the value is less
+    /// than every table entry. This is useful for quantization and
+    /// histogramming. See [`LutTy`] for the behavior with an unsorted table.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use amx::{Amx, XBytes, XRow, F32};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// // Bin boundaries
+    /// let table: Vec<f32> = (0..16).map(|i| (i * 10) as f32).collect();
+    /// let values: Vec<u8> = [-5.0f32, 0.0, 15.0, 155.0, 42.0]
+    ///     .iter()
+    ///     .flat_map(|x| x.to_le_bytes())
+    ///     .collect();
+    /// unsafe { ctx.load512(table.as_ptr(), XRow(0)) };
+    /// ctx.load_partial_x(&values, XRow(1));
+    /// ctx.reverse_lut(XBytes(64), XRow(0), XRow(2), F32);
+    /// // 4-bit indices: [0xf, 0, 1, 15, 4, 0, ...]
+    /// assert_eq!(ctx.read_x()[128..131], [0x0f, 0xf1, 0x04]);
+    /// ```
+    #[inline(always)]
+    fn reverse_lut<E: ReverseLutElem>(
+        &mut self,
+        input: impl LutIn,
+        table: XRow,
+        output: impl LutOut,
+        elem: E,
+    ) {
+        genlut::lut_raw(self, input, table, output, elem.reverse_genlut_mode());
+    }
+
+    /// Rearrange the bytes of `src`, writing `src[pattern[i]]` to the `i`-th
+    /// byte of `dst` for each `i` in `0..64`.
+    ///
+    /// `pattern` points to 64 five-bit indices packed by
+    /// [`pack_lut_indices`]. Because of the index width, only the first 32
+    /// bytes of `src` can be selected.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use amx::{Amx, XBytes, XRow};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// let src: Vec<u8> = (100..164).collect();
+    /// let pattern: Vec<u8> = (0..64).map(|i| 31 - i % 32).collect();
+    /// unsafe { ctx.load512(src.as_ptr(), XRow(0)) };
+    /// unsafe { ctx.load512(amx::pack_lut_indices(&pattern, 5).as_ptr(), XRow(1)) };
+    /// ctx.shuffle_bytes(XRow(0), XBytes(64), XRow(2));
+    /// assert_eq!(ctx.read_x()[128..131], [131, 130, 129]);
+    /// ```
+    #[inline(always)]
+    fn shuffle_bytes(&mut self, src: XRow, pattern: impl LutIn, dst: impl LutOut) {
+        self.lut(pattern, src, dst, (Normal, Index5, X8));
+    }
+
+    /// Rearrange the 16-bit lanes of `src`, writing `src[pattern[i]]` to the
+    /// `i`-th lane of `dst` for each `i` in `0..32`.
+    ///
+    /// `pattern` points to 32 five-bit indices packed by
+    /// [`pack_lut_indices`].
+    #[inline(always)]
+    fn permute_x16(&mut self, src: XRow, pattern: impl LutIn, dst: impl LutOut) {
+        self.lut(pattern, src, dst, (Normal, Index5, X16));
+    }
+
+    /// Rearrange the 32-bit lanes of `src`, writing `src[pattern[i]]` to the
+    /// `i`-th lane of `dst` for each `i` in `0..16`.
+    ///
+    /// `pattern` points to 16 four-bit indices packed by
+    /// [`pack_lut_indices`].
+    #[inline(always)]
+    fn permute_x32(&mut self, src: XRow, pattern: impl LutIn, dst: impl LutOut) {
+        self.lut(pattern, src, dst, (Normal, Index4, X32));
+    }
+
+    /// Rearrange the 64-bit lanes of `src`, writing `src[pattern[i]]` to the
+    /// `i`-th lane of `dst` for each `i` in `0..8`.
+    ///
+    /// `pattern` points to 8 four-bit indices packed by
+    /// [`pack_lut_indices`]. Only the lower three bits of each index are used.
+    #[inline(always)]
+    fn permute_x64(&mut self, src: XRow, pattern: impl LutIn, dst: impl LutOut) {
+        self.lut(pattern, src, dst, (Normal, Index4, X64));
+    }
+
+    /// Perform (reverse) table lookup and write the result to `z[output]`.
+    ///
+    /// This is equivalent to [`lut`](Self::lut) with a [`ZRow`] output. Unlike
+    /// `x` and `y`, any of the 64 rows of `z` can be specified.
+    #[inline(always)]
+    fn lut_to_z(&mut self, input: impl LutIn, table: XRow, output: ZRow, ty: impl LutTy) {
+        genlut::lut(self, input, table, output, ty);
+    }
 }
 
 impl<T: AmxOps + ?Sized> Amx for T {}
+
+/// Construct the operand of `fma*`, `mac16`, or `matint` for the outer
+/// product wrappers. A `None` offset disables the respective input. For
+/// `matint`, the lane width mode must be added separately.
+#[inline(always)]
+pub(crate) fn outer_product_operand(
+    x_offset_bytes: Option<XBytes>,
+    y_offset_bytes: Option<YBytes>,
+    ZRow(z_index): ZRow,
+    accumulate: bool,
+) -> u64 {
+    debug_assert!(z_index < ZRow::NUM_ROWS);
+    y_offset_bytes.unwrap_or_default().operand_bits()
+        | (x_offset_bytes.unwrap_or_default().operand_bits() << 10)
+        | ((z_index as u64) << 20)
+        | ((!accumulate as u64) << 27)
+        | ((x_offset_bytes.is_none() as u64) << 28)
+        | ((y_offset_bytes.is_none() as u64) << 29)
+}
+
+/// Construct the lane mask fields of the operand of `fma*` or `mac16`.
+#[inline(always)]
+#[track_caller]
+fn lane_mask_bits(x_mask: LaneMask, y_mask: LaneMask) -> u64 {
+    (x_mask.operand_bits() << lane_mask::X_MASK_POS)
+        | (y_mask.operand_bits() << lane_mask::Y_MASK_POS)
+}
+
+/// The operand bits of `fma*` and `mac16` to disable the `z`, `x`, and `y`
+/// inputs. Disabling all of them makes the instruction write zero.
+const FMA_SKIP_ALL: u64 = (1 << 27) | (1 << 28) | (1 << 29);
+
+/// The operand bit of `fma*` and `mac16` to select vector mode.
+pub(crate) const FMA_VECTOR: u64 = 1 << 63;
+
+/// Selects the column variant of `extrx` and `extry`. The lane width is
+/// encoded in bits 28–29 as `log2(8 / element_bytes)`.
+const EXTR_COLUMN: u64 = 1 << 26;
+
+/// Get the `extrx`/`extry` operand extracting the `column`-th column of the
+/// result tile of `E` selected by `z_index`.
+#[inline(always)]
+fn extr_column_operand<E: AmxElement>(ZRow(z_index): ZRow, column: usize) -> u64 {
+    let layout = E::Z_LAYOUT;
+    debug_assert!(z_index < 64);
+    debug_assert!(column < layout.columns());
+    debug_assert_eq!(layout.interleave, 1);
+    let field = column * layout.row_stride + z_index % layout.row_stride;
+    let width = (8 / layout.element_bytes).trailing_zeros() as u64;
+    EXTR_COLUMN | (width << 28) | ((field as u64) << 20)
+}
+
+#[repr(align(64))]
+struct Aligned64([u8; 64]);
+
+impl Aligned64 {
+    /// Copy `src` to the beginning of a zero-filled buffer.
+    #[inline]
+    #[track_caller]
+    fn from_partial(src: &[u8]) -> Self {
+        assert!(src.len() <= 64);
+        let mut this = Self([0; 64]);
+        this.0[..src.len()].copy_from_slice(src);
+        this
+    }
+}
+
+/// A zero buffer suitable for 128-byte loads
+static ZEROS: AmxAligned<[u8; 128]> = AmxAligned([0; 128]);
@@ -0,0 +1,282 @@
+//! Entry points to the [`kernels`] module taking `ndarray` arrays or
+//! `nalgebra` matrices
+use crate::{kernels, Amx};
+#[cfg(feature = "nalgebra")]
+use nalgebra::{
+    constraint::{AreMultipliable, SameNumberOfColumns, SameNumberOfRows, ShapeConstraint},
+    Dim, Matrix, RealField, Storage, StorageMut,
+};
+#[cfg(feature = "ndarray")]
+use ndarray::{ArrayView2, ArrayViewMut2, Zip};
+use std::borrow::Cow;
+
+/// Calculate `c = alpha * a * b + beta * c`, where `a`, `b`, and `c` are
+/// `m × k`, `k × n`, and `m × n` matrices, respectively.
+///
+/// The arrays may have arbitrary strides. Arrays not in the standard
+/// (row-major, contiguous) layout are packed into temporary buffers first. As
+/// in BLAS, `c` is not read if `beta` is zero, so NaNs in `c` don't propagate
+/// in that case.
+///
+/// # Panics
+///
+/// Panics if the array shapes are incompatible.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "ndarray")] {
+/// use ndarray::array;
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let a = array![[1.0, 2.0], [3.0, 4.0]];
+/// let b = array![[1.0, 0.0], [1.0, 1.0]];
+/// let mut c = array![[1.0, 1.0], [1.0, 1.0]];
+/// // `a.t()` is not in the standard layout
+/// amx::linalg::gemm(&mut ctx, a.t(), b.view(), c.view_mut(), 2.0, -1.0);
+/// assert_eq!(c, array![[7.0, 5.0], [11.0, 7.0]]);
+/// # }
+/// ```
+#[cfg(feature = "ndarray")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "ndarray")))]
+pub fn gemm(
+    ctx: &mut (impl Amx + ?Sized),
+    a: ArrayView2<'_, f32>,
+    b: ArrayView2<'_, f32>,
+    mut c: ArrayViewMut2<'_, f32>,
+    alpha: f32,
+    beta: f32,
+) {
+    let ((m, k), (k2, n)) = (a.dim(), b.dim());
+    assert_eq!(k, k2, "`a`'s columns and `b`'s rows must match");
+    assert_eq!(c.dim(), (m, n), "`c` must be `m × n`");
+
+    let (a, b) = (row_major(&a), row_major(&b));
+    let mut product = vec![0.0; m * n];
+    kernels::matmul_f32(ctx, m, n, k, &a, &b, &mut product);
+
+    if let Some(c) = c.as_slice_mut() {
+        if beta == 0.0 {
+            c.fill(0.0);
+        } else if beta != 1.0 {
+            kernels::scale_f32(ctx, beta, c);
+        }
+        kernels::axpy_f32(ctx, alpha, &product, c);
+    } else {
+        // `product` is in row-major order, as is the iteration order of `Zip`
+        // for a two-dimensional array in any layout
+        let product = ArrayView2::from_shape((m, n), &product).unwrap();
+        Zip::from(&mut c).and(&product).for_each(|c, &p| {
+            *c = if beta == 0.0 {
+                alpha * p
+            } else {
+                alpha * p + beta * *c
+            };
+        });
+    }
+}
+
+/// Get `x`'s elements in row-major order, copying them only if `x` is not in
+/// the standard layout.
+#[cfg(feature = "ndarray")]
+fn row_major<'a>(x: &'a ArrayView2<'_, f32>) -> Cow<'a, [f32]> {
+    match x.as_slice() {
+        Some(slice) => Cow::Borrowed(slice),
+        None => Cow::Owned(x.iter().copied().collect()),
+    }
+}
+
+/// A scalar type supported by [`gemm_nalgebra`], i.e., `f32` or `f64`.
+#[cfg(feature = "nalgebra")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "nalgebra")))]
+pub trait GemmScalar: RealField + Copy + crate::Lane {
+    #[doc(hidden)]
+    fn matmul(
+        ctx: &mut (impl Amx + ?Sized),
+        m: usize,
+        n: usize,
+        k: usize,
+        a: &[Self],
+        b: &[Self],
+        c: &mut [Self],
+    );
+}
+
+#[cfg(feature = "nalgebra")]
+impl GemmScalar for f32 {
+    #[inline]
+    fn matmul(
+        ctx: &mut (impl Amx + ?Sized),
+        m: usize,
+        n: usize,
+        k: usize,
+        a: &[Self],
+        b: &[Self],
+        c: &mut [Self],
+    ) {
+        kernels::matmul_f32(ctx, m, n, k, a, b, c);
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl GemmScalar for f64 {
+    #[inline]
+    fn matmul(
+        ctx: &mut (impl Amx + ?Sized),
+        m: usize,
+        n: usize,
+        k: usize,
+        a: &[Self],
+        b: &[Self],
+        c: &mut [Self],
+    ) {
+        kernels::matmul_f64(ctx, m, n, k, a, b, c);
+    }
+}
+
+/// The smallest `m * n * k` for which [`gemm_nalgebra`] uses AMX. Below this,
+/// enabling AMX and packing the operands outweigh the gain.
+#[cfg(all(feature = "nalgebra", target_arch = "aarch64"))]
+const NALGEBRA_MIN_AMX_WORK: usize = 16 * 16 * 16;
+
+/// Calculate `c = alpha * a * b + beta * c` using AMX if available, falling
+/// back to nalgebra's [`Matrix::gemm`] otherwise.
+///
+/// AMX is used if the product is large enough to benefit from it (which rules
+/// out most fixed-size matrices) and [`AmxCtx::new`](crate::AmxCtx::new)
+/// succeeds. In particular, this function doesn't touch the registers of an
+/// `AmxCtx` the current thread already has; nalgebra's implementation is used
+/// in that case. Use [`gemm_nalgebra_with`] to supply a context explicitly.
+///
+/// # Panics
+///
+/// Panics if the matrix shapes are incompatible.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "nalgebra")] {
+/// use nalgebra::DMatrix;
+/// let a = DMatrix::from_fn(32, 20, |i, j| (i + j) as f32);
+/// let b = DMatrix::from_fn(20, 24, |i, j| (i * j % 7) as f32);
+/// let mut c = DMatrix::zeros(32, 24);
+/// amx::linalg::gemm_nalgebra(&mut c, 1.0, &a, &b, 0.0);
+/// assert_eq!(c, &a * &b);
+/// # }
+/// ```
+#[cfg(feature = "nalgebra")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "nalgebra")))]
+pub fn gemm_nalgebra<T, R1, C1, R2, C2, R3, C3, SC, SA, SB>(
+    c: &mut Matrix<T, R1, C1, SC>,
+    alpha: T,
+    a: &Matrix<T, R2, C2, SA>,
+    b: &Matrix<T, R3, C3, SB>,
+    beta: T,
+) where
+    T: GemmScalar,
+    R1: Dim,
+    C1: Dim,
+    R2: Dim,
+    C2: Dim,
+    R3: Dim,
+    C3: Dim,
+    SC: StorageMut<T, R1, C1>,
+    SA: Storage<T, R2, C2>,
+    SB: Storage<T, R3, C3>,
+    ShapeConstraint:
+        SameNumberOfRows<R1, R2> + SameNumberOfColumns<C1, C3> + AreMultipliable<R2, C2, R3, C3>,
+{
+    #[cfg(target_arch = "aarch64")]
+    if a.nrows() * a.ncols() * b.ncols() >= NALGEBRA_MIN_AMX_WORK {
+        if let Ok(mut ctx) = crate::AmxCtx::new() {
+            gemm_nalgebra_with(&mut *ctx, c, alpha, a, b, beta);
+            return;
+        }
+    }
+
+    c.gemm(alpha, a, b, beta);
+}
+
+/// Calculate `c = alpha * a * b + beta * c` using the given context.
+///
+/// The matrices may have arbitrary strides. Matrices whose elements are not
+/// contiguous are packed into temporary buffers first. As in
+/// [`Matrix::gemm`], `c` is not read if `beta` is zero.
+///
+/// # Panics
+///
+/// Panics if the matrix shapes are incompatible.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "nalgebra")] {
+/// use nalgebra::{Matrix2, Matrix2x3, Matrix3x2};
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let a = Matrix2x3::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0);
+/// let b = Matrix3x2::new(1.0, 0.0, 0.0, 1.0, 1.0, 1.0);
+/// let mut c = Matrix2::new(1.0, 1.0, 1.0, 1.0);
+/// amx::linalg::gemm_nalgebra_with(&mut ctx, &mut c, 1.0, &a, &b, 2.0);
+/// assert_eq!(c, Matrix2::new(6.0, 7.0, 12.0, 13.0));
+/// # }
+/// ```
+#[cfg(feature = "nalgebra")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "nalgebra")))]
+pub fn gemm_nalgebra_with<T, R1, C1, R2, C2, R3, C3, SC, SA, SB>(
+    ctx: &mut (impl Amx + ?Sized),
+    c: &mut Matrix<T, R1, C1, SC>,
+    alpha: T,
+    a: &Matrix<T, R2, C2, SA>,
+    b: &Matrix<T, R3, C3, SB>,
+    beta: T,
+) where
+    T: GemmScalar,
+    R1: Dim,
+    C1: Dim,
+    R2: Dim,
+    C2: Dim,
+    R3: Dim,
+    C3: Dim,
+    SC: StorageMut<T, R1, C1>,
+    SA: Storage<T, R2, C2>,
+    SB: Storage<T, R3, C3>,
+    ShapeConstraint:
+        SameNumberOfRows<R1, R2> + SameNumberOfColumns<C1, C3> + AreMultipliable<R2, C2, R3, C3>,
+{
+    let ((m, k), (k2, n)) = (a.shape(), b.shape());
+    assert_eq!(k, k2, "`a`'s columns and `b`'s rows must match");
+    assert_eq!(c.shape(), (m, n), "`c` must be `m × n`");
+
+    // nalgebra matrices are column-major, i.e., a column-major `a` is a
+    // row-major `aᵀ`. Calculate `(a * b)ᵀ = bᵀ * aᵀ` in row-major terms,
+    // which is `a * b` in column-major terms.
+    let (a, b) = (column_major(a), column_major(b));
+    let mut product = vec![T::zero(); m * n];
+    T::matmul(ctx, n, m, k, &b, &a, &mut product);
+
+    // `Matrix::iter_mut` yields the elements in column-major order
+    for (c, &p) in c.iter_mut().zip(&product) {
+        *c = if beta == T::zero() {
+            alpha * p
+        } else {
+            alpha * p + beta * *c
+        };
+    }
+}
+
+/// Get `x`'s elements in column-major order, copying them only if they are
+/// not contiguous.
+#[cfg(feature = "nalgebra")]
+fn column_major<T, R, C, S>(x: &Matrix<T, R, C, S>) -> Cow<'_, [T]>
+where
+    T: GemmScalar,
+    R: Dim,
+    C: Dim,
+    S: Storage<T, R, C>,
+{
+    if x.data.is_contiguous() {
+        // Safety: The elements are contiguous
+        Cow::Borrowed(unsafe { x.data.as_slice_unchecked() })
+    } else {
+        Cow::Owned(x.iter().copied().collect())
+    }
+}
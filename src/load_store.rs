@@ -1,7 +1,8 @@
 use crate::{
-    regs::{XRow, YRow, ZRow},
+    regs::{XRow, XRowN, YRow, YRowN, ZRow, ZRowN},
     AmxOps,
 };
+use std::ops::Range;
 
 /// The parameters of AMX's load and store instructions.
 #[derive(Copy, Clone)]
@@ -12,13 +13,13 @@ struct MemArgs {
 }
 
 impl MemArgs {
-    #[inline]
+    #[inline(always)]
     fn encode(self) -> u64 {
         debug_assert!(self.reg_offset < 64);
 
         // The pointer is passed by a separate parameter when using `AmxOps`
         (self.reg_offset << 56)
-            // [61] - ?
+            // [61] - ? (see `MemHint`)
             | ((self.size as u64) << 62)
         // [63] - ?
     }
@@ -29,10 +30,42 @@ impl MemArgs {
 enum MemSize {
     /// 64 bytes
     _64 = 0,
-    /// 128 bytes
+    /// 128 bytes. Not known to be available to `ldzi` and `stzi`.
     _128 = 1,
 }
 
+/// A caching hint for [`Amx::load512_x_hint`] and its siblings.
+///
+/// **Experimental.** A hint is encoded in bit 61 of an `ldx`, `ldy`, `stx`,
+/// or `sty` operand. The bit isn't known to change the data transferred and
+/// is suspected of controlling caching, but this hasn't been confirmed.
+/// Compare the `load_store_hint` benchmarks on the target hardware before
+/// relying on it. Loads and stores of `z` don't take hints because bit 61 is
+/// part of their register row.
+///
+/// [`Amx::load512_x_hint`]: crate::Amx::load512_x_hint
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MemHint {
+    /// No hint, i.e., the encoding used by [`Amx::load512`].
+    ///
+    /// [`Amx::load512`]: crate::Amx::load512
+    #[default]
+    Normal,
+    /// The data is not expected to be reused soon and should bypass the
+    /// caches if possible.
+    NonTemporal,
+}
+
+impl MemHint {
+    #[inline(always)]
+    fn operand_bits(self) -> u64 {
+        match self {
+            Self::Normal => 0,
+            Self::NonTemporal => 1 << 61,
+        }
+    }
+}
+
 /// Register row types supporting 512-bit and 1024-bit operations.
 ///
 /// This trait is not meant to be used directly. Please use [`Amx`]'s methods
@@ -276,6 +309,50 @@ impl LoadStore for ZRow {
     }
 }
 
+macro_rules! impl_load_store_for_const_row {
+    ($($ty:ident),*) => {$(
+        impl<const N: usize> LoadStore for $ty<N> {
+            #[inline(always)]
+            unsafe fn load512<T>(&self, ops: &mut (impl AmxOps + ?Sized), ptr: *const T) {
+                Self::ROW.load512(ops, ptr)
+            }
+
+            #[inline(always)]
+            unsafe fn store512<T>(&self, ops: &mut (impl AmxOps + ?Sized), ptr: *mut T) {
+                Self::ROW.store512(ops, ptr)
+            }
+
+            #[inline(always)]
+            unsafe fn load1024_aligned<T>(&self, ops: &mut (impl AmxOps + ?Sized), ptr: *const T) {
+                Self::ROW.load1024_aligned(ops, ptr)
+            }
+
+            #[inline(always)]
+            unsafe fn store1024_aligned<T>(&self, ops: &mut (impl AmxOps + ?Sized), ptr: *mut T) {
+                Self::ROW.store1024_aligned(ops, ptr)
+            }
+        }
+    )*};
+}
+
+impl_load_store_for_const_row!(XRowN, YRowN, ZRowN);
+
+/// Get the operand of a 64-byte load or store of `x[index]` or `y[index]`
+/// with a caching hint.
+///
+/// `index` must be in range `0..8`.
+#[inline(always)]
+#[track_caller]
+pub(crate) fn xy_hint_operand(index: usize, hint: MemHint) -> u64 {
+    assert!(index < 8);
+    MemArgs {
+        reg_offset: index as u64,
+        size: MemSize::_64,
+    }
+    .encode()
+        | hint.operand_bits()
+}
+
 /// Load 512 bits (64 bytes) from memory to `z[index][0..64]` with interleaving.
 ///
 /// `index` must be in range `0..64`.
@@ -317,3 +394,79 @@ pub(crate) unsafe fn store512_z_interleaved<T>(
         ptr as *mut (),
     );
 }
+
+/// Load the register rows `row(i)` for `i` in `rows` from consecutive 64-byte
+/// chunks of `src`. A 128-byte load is used for each pair of rows starting at
+/// an even index whose source is aligned to 128-byte boundaries, and a 64-byte
+/// load for every other row.
+///
+/// `src` must be valid for reading `rows.len() * 64` bytes.
+#[inline]
+pub(crate) unsafe fn load_rows<R: LoadStore>(
+    ops: &mut (impl AmxOps + ?Sized),
+    src: *const u8,
+    rows: Range<usize>,
+    row: impl Fn(usize) -> R,
+) {
+    let mut i = rows.start;
+    while i < rows.end {
+        let ptr = src.add((i - rows.start) * 64);
+        if i % 2 == 0 && i + 1 < rows.end && ptr as usize % 128 == 0 {
+            row(i).load1024_aligned(ops, ptr);
+            i += 2;
+        } else {
+            row(i).load512(ops, ptr);
+            i += 1;
+        }
+    }
+}
+
+/// Load the register rows `row(0)`, `row(1)`, ... from `src`, one per 64
+/// bytes. 128-byte loads are used if `src` is aligned to 128-byte boundaries.
+///
+/// `src.len()` must be a multiple of `128`.
+#[inline]
+pub(crate) fn load_all<R: LoadStore>(
+    ops: &mut (impl AmxOps + ?Sized),
+    src: &[u8],
+    row: impl Fn(usize) -> R,
+) {
+    debug_assert_eq!(src.len() % 128, 0);
+    if src.as_ptr() as usize % 128 == 0 {
+        for (i, chunk) in src.chunks_exact(128).enumerate() {
+            // Safety: `chunk` is a 128-byte memory region aligned to 128-byte
+            //         boundaries
+            unsafe { row(i * 2).load1024_aligned(ops, chunk.as_ptr()) };
+        }
+    } else {
+        for (i, chunk) in src.chunks_exact(64).enumerate() {
+            // Safety: `chunk` is a 64-byte memory region
+            unsafe { row(i).load512(ops, chunk.as_ptr()) };
+        }
+    }
+}
+
+/// Store the register rows `row(0)`, `row(1)`, ... to `dst`, one per 64
+/// bytes. 128-byte stores are used if `dst` is aligned to 128-byte boundaries.
+///
+/// `dst.len()` must be a multiple of `128`.
+#[inline]
+pub(crate) fn store_all<R: LoadStore>(
+    ops: &mut (impl AmxOps + ?Sized),
+    dst: &mut [u8],
+    row: impl Fn(usize) -> R,
+) {
+    debug_assert_eq!(dst.len() % 128, 0);
+    if dst.as_ptr() as usize % 128 == 0 {
+        for (i, chunk) in dst.chunks_exact_mut(128).enumerate() {
+            // Safety: `chunk` is a 128-byte memory region aligned to 128-byte
+            //         boundaries
+            unsafe { row(i * 2).store1024_aligned(ops, chunk.as_mut_ptr()) };
+        }
+    } else {
+        for (i, chunk) in dst.chunks_exact_mut(64).enumerate() {
+            // Safety: `chunk` is a 64-byte memory region
+            unsafe { row(i).store512(ops, chunk.as_mut_ptr()) };
+        }
+    }
+}
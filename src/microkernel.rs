@@ -0,0 +1,480 @@
+//! Building blocks for GEMM implementations based on packed panels
+//!
+//! This module provides the packing routines and the `f32` microkernel of a
+//! BLIS-style GEMM with the calling convention of the `matrixmultiply` crate,
+//! so that GEMM drivers written for that convention can use AMX as a backend.
+//!
+//! A driver partitions `a` into row panels of [`MR`] rows and `b` into column
+//! panels of [`NR`] columns, packs them by [`pack_a`] and [`pack_b`], and
+//! calls [`kernel_f32`] for every pair of panels to update the corresponding
+//! `MR × NR` block of `c`.
+//!
+//! The `*_prefetch` variants take a prefetch distance, which may improve the
+//! throughput for large matrices, whose panels don't fit in the caches. The
+//! best distance depends on the hardware and the matrix sizes and should be
+//! found by benchmarking.
+//!
+//! [`kernel_f32`] computes `MR × NR` blocks of `f32`. Kernels for other block
+//! shapes and element types, unrolled at compile time, can be declared by
+//! [`amx_kernel!`](crate::amx_kernel); their panels have to be packed by the
+//! driver in the same layout.
+//!
+//! # Example
+//!
+//! ```rust
+//! use amx::microkernel::{self, MR, NR};
+//! let mut ctx = amx::AmxEmuCtx::default();
+//! let (m, n, k) = (20, 3, 5);
+//! let a: Vec<f32> = (0..m * k).map(|i| i as f32).collect(); // row-major
+//! let b: Vec<f32> = (0..k * n).map(|i| i as f32).collect(); // row-major
+//! let mut c = vec![0.0; m * n]; // row-major
+//!
+//! let mut a_packed = vec![0.0; microkernel::packed_a_len(k, m)];
+//! let mut b_packed = vec![0.0; microkernel::packed_b_len(k, n)];
+//! unsafe {
+//!     microkernel::pack_a(k, m, a_packed.as_mut_ptr(), a.as_ptr(), k as isize, 1);
+//!     microkernel::pack_b(k, n, b_packed.as_mut_ptr(), b.as_ptr(), n as isize, 1);
+//! }
+//!
+//! // `n <= NR`, so there's only one column panel
+//! let mut block = [0.0; MR * NR];
+//! for (i0, a_panel) in (0..m).step_by(MR).zip(a_packed.chunks(k * MR)) {
+//!     // Compute a full `MR × NR` block and copy the valid part to `c`
+//!     unsafe {
+//!         microkernel::kernel_f32(
+//!             &mut ctx,
+//!             k,
+//!             1.0,
+//!             a_panel.as_ptr(),
+//!             b_packed.as_ptr(),
+//!             0.0,
+//!             block.as_mut_ptr(),
+//!             NR as isize,
+//!             1,
+//!         );
+//!     }
+//!     for i in i0..m.min(i0 + MR) {
+//!         c[i * n..][..n].copy_from_slice(&block[(i - i0) * NR..][..n]);
+//!     }
+//! }
+//!
+//! assert_eq!(c[19 * n + 2], (0..k).map(|p| a[19 * k + p] * b[p * n + 2]).sum());
+//! ```
+use crate::{
+    kernels::backend::{Backend, BackendLane},
+    prefetch::{prefetch_read_l1, prefetch_read_l2},
+    Amx, XRow, YRow, ZRow,
+};
+use std::ops::{Add, Mul};
+
+/// The number of rows of a row panel of `a` and a block of `c`.
+pub const MR: usize = 16;
+
+/// The number of columns of a column panel of `b` and a block of `c`.
+pub const NR: usize = 16;
+
+/// Get the number of elements [`pack_a`] writes for an `mc × kc` block of
+/// `a`.
+pub const fn packed_a_len(kc: usize, mc: usize) -> usize {
+    mc.div_ceil(MR) * MR * kc
+}
+
+/// Get the number of elements [`pack_b`] writes for a `kc × nc` block of
+/// `b`.
+pub const fn packed_b_len(kc: usize, nc: usize) -> usize {
+    nc.div_ceil(NR) * NR * kc
+}
+
+/// Pack an `mc × kc` block of `a` into row panels of [`MR`] rows.
+///
+/// The element `(i, p)` of `a` is read from `a.offset(i * rsa + p * csa)`.
+/// The panel `i / MR` starts at `pack.add(i / MR * MR * kc)` and stores the
+/// element at the offset `p * MR + i % MR`, i.e., each panel is a sequence of
+/// `MR`-element columns. The last panel is padded with zeros.
+///
+/// # Safety
+///
+/// `a` must be valid for reading the elements of the block.
+/// `pack` must be valid for writing [`packed_a_len`]`(kc, mc)` elements.
+pub unsafe fn pack_a(kc: usize, mc: usize, pack: *mut f32, a: *const f32, rsa: isize, csa: isize) {
+    pack_panels::<MR>(kc, mc, pack, a, rsa, csa, 0);
+}
+
+/// The version of [`pack_a`] that prefetches the elements of `a` `distance`
+/// columns ahead into the L1 cache. A `distance` of zero disables
+/// prefetching.
+///
+/// # Safety
+///
+/// See [`pack_a`].
+pub unsafe fn pack_a_prefetch(
+    kc: usize,
+    mc: usize,
+    pack: *mut f32,
+    a: *const f32,
+    rsa: isize,
+    csa: isize,
+    distance: usize,
+) {
+    pack_panels::<MR>(kc, mc, pack, a, rsa, csa, distance);
+}
+
+/// Pack a `kc × nc` block of `b` into column panels of [`NR`] columns.
+///
+/// The element `(p, j)` of `b` is read from `b.offset(p * rsb + j * csb)`.
+/// The panel `j / NR` starts at `pack.add(j / NR * NR * kc)` and stores the
+/// element at the offset `p * NR + j % NR`, i.e., each panel is a sequence of
+/// `NR`-element rows. The last panel is padded with zeros.
+///
+/// # Safety
+///
+/// `b` must be valid for reading the elements of the block.
+/// `pack` must be valid for writing [`packed_b_len`]`(kc, nc)` elements.
+pub unsafe fn pack_b(kc: usize, nc: usize, pack: *mut f32, b: *const f32, rsb: isize, csb: isize) {
+    pack_b_prefetch(kc, nc, pack, b, rsb, csb, 0);
+}
+
+/// The version of [`pack_b`] that prefetches the elements of `b` `distance`
+/// rows ahead into the L1 cache. A `distance` of zero disables prefetching.
+///
+/// # Safety
+///
+/// See [`pack_b`].
+pub unsafe fn pack_b_prefetch(
+    kc: usize,
+    nc: usize,
+    pack: *mut f32,
+    b: *const f32,
+    rsb: isize,
+    csb: isize,
+    distance: usize,
+) {
+    // A column panel of `b` is a row panel of `bᵀ`
+    pack_panels::<NR>(kc, nc, pack, b, csb, rsb, distance);
+}
+
+/// Pack a `len × kc` matrix into panels of `W` rows, storing each panel in
+/// column-major order. The column `p + distance` is prefetched while packing
+/// the column `p` unless `distance` is zero.
+unsafe fn pack_panels<const W: usize>(
+    kc: usize,
+    len: usize,
+    pack: *mut f32,
+    src: *const f32,
+    rs: isize,
+    cs: isize,
+    distance: usize,
+) {
+    let mut out = pack;
+    for i0 in (0..len).step_by(W) {
+        for p in 0..kc {
+            if distance != 0 && p + distance < kc {
+                for i in i0..len.min(i0 + W) {
+                    prefetch_read_l1(
+                        src.wrapping_offset(i as isize * rs + (p + distance) as isize * cs),
+                    );
+                }
+            }
+            for i in i0..i0 + W {
+                *out = if i < len {
+                    *src.offset(i as isize * rs + p as isize * cs)
+                } else {
+                    0.0
+                };
+                out = out.add(1);
+            }
+        }
+    }
+}
+
+/// Calculate `c = alpha * a * b + beta * c` for an `MR × NR` block `c`, given
+/// a row panel of `a` packed by [`pack_a`] and a column panel of `b` packed by
+/// [`pack_b`], both of depth `k`.
+///
+/// The element `(i, j)` of `c` is at `c.offset(i * rsc + j * csc)`. If `beta`
+/// is zero, `c` is not read (so it may be uninitialized).
+///
+/// This function uses all `x` and `y` registers and the `z` rows `0, 4, ...,
+/// 60`.
+///
+/// # Safety
+///
+/// `a` and `b` must be valid for reading `k * MR` and `k * NR` elements,
+/// respectively. `c` must be valid for reading and writing the elements of
+/// the block.
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn kernel_f32(
+    ctx: &mut (impl Amx + ?Sized),
+    k: usize,
+    alpha: f32,
+    a: *const f32,
+    b: *const f32,
+    beta: f32,
+    c: *mut f32,
+    rsc: isize,
+    csc: isize,
+) {
+    kernel_f32_prefetch(ctx, k, alpha, a, b, beta, c, rsc, csc, 0);
+}
+
+/// The version of [`kernel_f32`] that prefetches the panels `distance` steps
+/// of `k` ahead into the L2 cache, from which AMX loads them. A `distance`
+/// of zero disables prefetching.
+///
+/// # Safety
+///
+/// See [`kernel_f32`].
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn kernel_f32_prefetch(
+    ctx: &mut (impl Amx + ?Sized),
+    k: usize,
+    alpha: f32,
+    a: *const f32,
+    b: *const f32,
+    beta: f32,
+    c: *mut f32,
+    rsc: isize,
+    csc: isize,
+    distance: usize,
+) {
+    // Load up to eight columns of `a` to `y` and as many rows of `b` to `x`,
+    // and accumulate their outer products. `z[i * 4]` holds the row `i` of
+    // the result.
+    for p0 in (0..k).step_by(8) {
+        let len = (k - p0).min(8);
+        for q in 0..len {
+            // Each step of `k` consumes one cache line from each panel
+            let ahead = p0 + q + distance;
+            if distance != 0 && ahead < k {
+                prefetch_read_l2(a.add(ahead * MR));
+                prefetch_read_l2(b.add(ahead * NR));
+            }
+            ctx.load512(a.add((p0 + q) * MR), YRow(q));
+            ctx.load512(b.add((p0 + q) * NR), XRow(q));
+        }
+        for q in 0..len {
+            ctx.outer_product_f32_xy_to_z(
+                Some(XRow(q).offset()),
+                Some(YRow(q).offset()),
+                ZRow(0),
+                p0 + q != 0,
+            );
+        }
+    }
+
+    let mut row = [0.0f32; NR];
+    for i in 0..MR {
+        if k != 0 {
+            // Safety: Writing in a memory region within `row`
+            ctx.store512(row.as_mut_ptr(), ZRow(i * 4));
+        }
+        for (j, &ab) in row.iter().enumerate() {
+            let c = c.offset(i as isize * rsc + j as isize * csc);
+            *c = if beta == 0.0 {
+                alpha * ab
+            } else {
+                alpha * ab + beta * *c
+            };
+        }
+    }
+}
+
+/// The number of rows in `x` and `y`.
+const XY_ROWS: usize = 8;
+
+/// The generic version of [`kernel_f32`] for a `ROWS × COLS` block of `T`
+/// elements, which processes `UNROLL` steps of `k` at once. The panels are
+/// packed in the same layout as [`pack_a`] and [`pack_b`] produce, but with
+/// `ROWS` and `COLS` in place of [`MR`] and [`NR`].
+///
+/// The block is held in `(ROWS / T::LANES) × (COLS / T::LANES)` tiles of `z`,
+/// and each unrolled group of steps loads `UNROLL` columns of `a` to `y` and
+/// as many rows of `b` to `x` before issuing all of their outer products.
+/// All loop bounds except `k` are constants, so the compiler unrolls each
+/// group into straight-line instructions.
+///
+/// An instantiation fails to compile unless `ROWS` and `COLS` are non-zero
+/// multiples of `T::LANES`, the block fits in `z`, and the registers used by
+/// a group of steps fit in `x` and `y`. [`amx_kernel!`](crate::amx_kernel)
+/// declares a named function calling this.
+///
+/// # Safety
+///
+/// `a` and `b` must be valid for reading `k * ROWS` and `k * COLS` elements,
+/// respectively. `c` must be valid for reading and writing the elements of
+/// the block.
+#[allow(clippy::too_many_arguments)]
+#[inline]
+pub unsafe fn kernel_unrolled<T, const ROWS: usize, const COLS: usize, const UNROLL: usize>(
+    ctx: &mut (impl Amx + ?Sized),
+    k: usize,
+    alpha: T,
+    a: *const T,
+    b: *const T,
+    beta: T,
+    c: *mut T,
+    rsc: isize,
+    csc: isize,
+) where
+    T: BackendLane + Add<Output = T> + Mul<Output = T> + PartialEq,
+{
+    const {
+        assert!(
+            ROWS != 0 && COLS != 0 && ROWS % T::LANES == 0 && COLS % T::LANES == 0,
+            "the block size must be a multiple of the tile size"
+        );
+        assert!(
+            (ROWS / T::LANES) * (COLS / T::LANES) <= T::TILES,
+            "the block doesn't fit in `z`"
+        );
+        assert!(
+            UNROLL != 0 && UNROLL * (ROWS / T::LANES) <= XY_ROWS,
+            "the unrolled columns of `a` don't fit in `y`"
+        );
+        assert!(
+            UNROLL * (COLS / T::LANES) <= XY_ROWS,
+            "the unrolled rows of `b` don't fit in `x`"
+        );
+    }
+
+    let full = k - k % UNROLL;
+    for p0 in (0..full).step_by(UNROLL) {
+        kernel_steps::<T, ROWS, COLS, UNROLL>(ctx, p0, a, b);
+    }
+    for p in full..k {
+        kernel_steps::<T, ROWS, COLS, 1>(ctx, p, a, b);
+    }
+
+    // The tile `ti * col_tiles + tj` holds the elements `(ti * lanes + i,
+    // tj * lanes + j)` of the block in its row `i`
+    let lanes = T::LANES;
+    let col_tiles = COLS / lanes;
+    let mut row = [T::default(); 64];
+    for i in 0..ROWS {
+        let (ti, i_in_tile) = (i / lanes, i % lanes);
+        for tj in 0..col_tiles {
+            if k != 0 {
+                ctx.store_z(i_in_tile * T::TILES + ti * col_tiles + tj, &mut row);
+            }
+            for (j, &ab) in row[..lanes].iter().enumerate() {
+                let j = tj * lanes + j;
+                let c = c.offset(i as isize * rsc + j as isize * csc);
+                *c = if beta == T::default() {
+                    alpha * ab
+                } else {
+                    alpha * ab + beta * *c
+                };
+            }
+        }
+    }
+}
+
+/// Accumulate the outer products of `STEPS` columns of `a` and rows of `b`,
+/// starting from the step `p0`, to the tiles of [`kernel_unrolled`].
+#[inline(always)]
+unsafe fn kernel_steps<T: BackendLane, const ROWS: usize, const COLS: usize, const STEPS: usize>(
+    ctx: &mut (impl Amx + ?Sized),
+    p0: usize,
+    a: *const T,
+    b: *const T,
+) {
+    let lanes = T::LANES;
+    let (row_tiles, col_tiles) = (ROWS / lanes, COLS / lanes);
+    for q in 0..STEPS {
+        for ti in 0..row_tiles {
+            ctx.load512(
+                a.add((p0 + q) * ROWS + ti * lanes),
+                YRow(q * row_tiles + ti),
+            );
+        }
+        for tj in 0..col_tiles {
+            ctx.load512(
+                b.add((p0 + q) * COLS + tj * lanes),
+                XRow(q * col_tiles + tj),
+            );
+        }
+    }
+    for q in 0..STEPS {
+        for ti in 0..row_tiles {
+            for tj in 0..col_tiles {
+                Backend::<T>::outer_product(
+                    ctx,
+                    q * col_tiles + tj,
+                    q * row_tiles + ti,
+                    ti * col_tiles + tj,
+                    p0 + q != 0,
+                );
+            }
+        }
+    }
+}
+
+/// Declare microkernels of the specified shapes.
+///
+/// Each declaration `fn name(T, mr = ROWS, nr = COLS, unroll = UNROLL);`
+/// expands to an `unsafe fn` with the same parameters as
+/// [`microkernel::kernel_f32`](crate::microkernel::kernel_f32), which
+/// calculates `c = alpha * a * b + beta * c` for a `ROWS × COLS` block by
+/// [`microkernel::kernel_unrolled`](crate::microkernel::kernel_unrolled).
+/// `T` is `f32` or `f64`. The shape is checked at compile time.
+///
+/// Hand-unrolling the loops of a microkernel is the only way to issue AMX
+/// instructions at the peak rate; this produces the same straight-line code
+/// for any shape, so shapes can be compared by benchmarking.
+///
+/// # Example
+///
+/// ```rust
+/// amx::amx_kernel! {
+///     /// A 32 × 32 `f32` kernel, which takes all four tiles of `z`
+///     pub fn kernel_32x32(f32, mr = 32, nr = 32, unroll = 4);
+///     fn kernel_16x32(f64, mr = 16, nr = 32, unroll = 2);
+/// }
+///
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let k = 3;
+/// let a = vec![1.0f32; k * 32]; // packed like `pack_a` with `MR = 32`
+/// let b = vec![2.0f32; k * 32]; // packed like `pack_b` with `NR = 32`
+/// let mut c = vec![0.0f32; 32 * 32];
+/// unsafe {
+///     kernel_32x32(&mut ctx, k, 1.0, a.as_ptr(), b.as_ptr(), 0.0, c.as_mut_ptr(), 32, 1);
+/// }
+/// assert!(c.iter().all(|&x| x == 6.0));
+/// ```
+///
+/// An unsupported shape fails to compile:
+///
+/// ```compile_fail
+/// amx::amx_kernel! {
+///     fn kernel_64x64(f32, mr = 64, nr = 64, unroll = 1);
+/// }
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let mut c = [0.0f32];
+/// unsafe { kernel_64x64(&mut ctx, 0, 1.0, &0.0, &0.0, 0.0, c.as_mut_ptr(), 0, 0) };
+/// ```
+#[macro_export]
+macro_rules! amx_kernel {
+    ($(
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident($ty:ty, mr = $mr:expr, nr = $nr:expr, unroll = $unroll:expr);
+    )*) => {$(
+        $(#[$attr])*
+        #[allow(clippy::too_many_arguments, clippy::missing_safety_doc)]
+        #[inline]
+        $vis unsafe fn $name(
+            ctx: &mut (impl $crate::Amx + ?Sized),
+            k: usize,
+            alpha: $ty,
+            a: *const $ty,
+            b: *const $ty,
+            beta: $ty,
+            c: *mut $ty,
+            rsc: isize,
+            csc: isize,
+        ) {
+            $crate::microkernel::kernel_unrolled::<$ty, { $mr }, { $nr }, { $unroll }>(
+                ctx, k, alpha, a, b, beta, c, rsc, csc,
+            );
+        }
+    )*};
+}
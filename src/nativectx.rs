@@ -1,14 +1,70 @@
 //! Safely manages AMX's actiavtion state.
 use std::{
     cell::Cell,
+    marker::PhantomData,
     ops::{Deref, DerefMut},
 };
 
-use crate::nativeops::AmxOps;
+use crate::{nativeops::AmxOps, Amx, AmxStateSnapshot, ZRow};
 
 /// Represents the current thread's AMX context.
+///
+/// AMX is enabled while at least one `AmxCtx` exists in the current thread.
+/// [`AmxCtx::new`] fails if the thread already has one, guaranteeing that the
+/// created `AmxCtx` has exclusive access to the AMX registers.
+/// [`AmxCtx::new_nested`] and [`AmxCtx::scope`], on the other hand, share the
+/// existing activation (and thus the register contents) if there is one, and
+/// AMX is disabled only when the outermost `AmxCtx` is dropped.
+///
+/// [`AmxCtxBuilder`](crate::AmxCtxBuilder) constructs an `AmxCtx` with
+/// options such as [the canary](AmxCtx::enable_state_canary) and tracing
+/// composed.
+///
+/// # Asynchronous code
+///
+/// The AMX state belongs to a thread, not to a task, so it must not be held
+/// across an `.await`; the task might be resumed on another thread, where the
+/// registers hold someone else's data (or AMX isn't even enabled).
+///
+/// `AmxCtx` is not `Send`, so a future holding one across an `.await` is not
+/// `Send` either and is rejected by multi-threaded executors. Even so, prefer
+/// [`AmxCtx::run`] in asynchronous code. Its closure is synchronous and thus
+/// can't `.await`, which rules out the problem by construction:
+///
+/// ```rust
+/// # async fn next_block() -> [f32; 16] { [1.0; 16] }
+/// use amx::{prelude::*, XRow};
+/// async fn process() {
+///     let block = next_block().await;
+///     let mut out = [0.0f32; 16];
+///     amx::AmxCtx::run(|amx| unsafe {
+///         amx.load512(block.as_ptr(), XRow(0));
+///         amx.store512(out.as_mut_ptr(), XRow(0));
+///         // `next_block().await` isn't allowed here
+///     })
+///     .unwrap();
+/// }
+/// ```
+///
+/// Clippy can catch the remaining cases (e.g., futures driven by a
+/// single-threaded executor) if the following is added to `clippy.toml`:
+///
+/// ```toml
+/// await-holding-invalid-types = ["amx::AmxCtx", "amx::nativeops::AmxOps"]
+/// ```
+///
+/// In debug builds, `AmxCtx` also asserts that it's used and dropped on the
+/// thread that created it, catching a context moved to another thread by an
+/// unsound `Send` impl.
 pub struct AmxCtx {
     ops: AmxOps<'static>,
+    /// The thread that created `self`.
+    #[cfg(debug_assertions)]
+    thread: std::thread::ThreadId,
+    /// The `z` row holding [`CANARY`], if enabled.
+    canary: Option<ZRow>,
+    /// Zero the registers on drop.
+    zero_on_drop: bool,
 }
 
 /// The error type for [`AmxCtx::new`]
@@ -16,44 +72,409 @@ pub struct AmxCtx {
 pub enum NewAmxCtxError {
     /// The current thread already has an active `AmxCtx`.
     AlreadyActive,
-    /// AMX is not supported by the target system.
-    ///
-    /// TODO: Report this error if it's unsupported
+    /// AMX is not supported by the target system. See
+    /// [`is_supported`](crate::is_supported).
     Unsupported,
 }
 
+/// The error type for [`AmxCtx::check_state_canary`], indicating that the
+/// register contents have been lost.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct AmxStateLost {
+    /// The `z` row that was supposed to hold the canary.
+    pub row: ZRow,
+}
+
+/// A proof that AMX is enabled for the current thread, obtained by
+/// [`AmxCtx::enabled`].
+///
+/// A helper function taking `AmxEnabled` can issue AMX instructions without
+/// creating an `AmxCtx` of its own or trusting that the caller has one. The
+/// token borrows the `AmxCtx` it was obtained from, so it can't outlive the
+/// activation, and it's not `Send`, so it can't leave the thread. It's `Copy`
+/// and can be passed down freely.
+///
+/// ```rust
+/// use amx::{prelude::*, AmxEnabled, XRow};
+/// fn fill_x0(amx: AmxEnabled<'_>, value: u8) {
+///     unsafe { amx.ops().load512([value; 64].as_ptr(), XRow(0)) };
+/// }
+///
+/// let mut ctx = amx::AmxCtx::new().unwrap();
+/// fill_x0(ctx.enabled(), 42);
+/// assert_eq!(ctx.read_x()[..64], [42; 64]);
+/// ```
+///
+/// The token can't outlive the `AmxCtx`:
+///
+/// ```rust,compile_fail
+/// let token = {
+///     let ctx = amx::AmxCtx::new().unwrap();
+///     ctx.enabled()
+/// };
+/// ```
+///
+/// And it can't be sent to another thread:
+///
+/// ```rust,compile_fail
+/// let ctx = amx::AmxCtx::new().unwrap();
+/// let token = ctx.enabled();
+/// std::thread::scope(|s| {
+///     s.spawn(move || token.ops());
+/// });
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct AmxEnabled<'a>(PhantomData<(&'a AmxCtx, *mut ())>);
+
+impl<'a> AmxEnabled<'a> {
+    /// Get a handle to issue AMX instructions.
+    ///
+    /// The handle shares the registers with the `AmxCtx` the token was
+    /// obtained from, like [`AmxCtx::new_nested`] does.
+    #[inline]
+    pub fn ops(self) -> AmxOps<'a> {
+        // Safety: AMX is supported, as proven by the existence of an `AmxCtx`
+        unsafe { AmxOps::new() }
+    }
+}
+
+/// The pattern written by [`AmxCtx::enable_state_canary`]. Zeros (which a
+/// reset register would hold) and repeated bytes are avoided.
+static CANARY: [u8; 64] = {
+    let mut x = [0u8; 64];
+    let mut i = 0;
+    while i < 64 {
+        x[i] = (i as u8).wrapping_mul(0x9d) ^ 0x5a;
+        i += 1;
+    }
+    x
+};
+
 thread_local! {
-    static CTX_ACTIVE: Cell<bool> = Cell::new(false);
+    /// The number of `AmxCtx`s existing in the current thread.
+    static CTX_DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// Whether the current thread's `AmxCtx` is suspended by
+    /// [`AmxCtx::suspend`].
+    static SUSPENDED: Cell<bool> = const { Cell::new(false) };
 }
 
 impl AmxCtx {
     /// Construct a brand new instance of `AmxCtx` by enabling AMX for the
     /// current thread.
+    ///
+    /// Returns [`NewAmxCtxError::AlreadyActive`] if the current thread already
+    /// has an `AmxCtx` and [`NewAmxCtxError::Unsupported`] if AMX isn't
+    /// available (see [`is_supported`](crate::is_supported)).
     pub fn new() -> Result<Self, NewAmxCtxError> {
-        if CTX_ACTIVE.with(|x| x.get()) {
+        if CTX_DEPTH.with(|x| x.get()) != 0 {
             Err(NewAmxCtxError::AlreadyActive)
         } else {
-            // TODO: Don't assume AMX is always supported
+            Self::new_nested()
+        }
+    }
+
+    /// Construct an instance of `AmxCtx`, enabling AMX for the current thread
+    /// if it hasn't been enabled yet.
+    ///
+    /// If the current thread already has an `AmxCtx`, the new one shares its
+    /// register contents, and dropping the new one doesn't disable AMX.
+    ///
+    /// Returns [`NewAmxCtxError::AlreadyActive`] if the current thread's
+    /// `AmxCtx` is [suspended](AmxCtx::suspend).
+    pub fn new_nested() -> Result<Self, NewAmxCtxError> {
+        if !crate::is_supported() {
+            return Err(NewAmxCtxError::Unsupported);
+        }
+        if SUSPENDED.with(|x| x.get()) {
+            return Err(NewAmxCtxError::AlreadyActive);
+        }
+        CTX_DEPTH.with(|depth| {
+            if depth.get() == 0 {
+                // Enable AMX for the current thread
+                // Safety: AMX is supported
+                unsafe { crate::nativeops::set() };
+            }
+            depth.set(depth.get() + 1);
+        });
 
-            // Enable AMX for the current thread
+        Ok(Self {
             // Safety: AMX is supported
-            unsafe { crate::nativeops::set() };
+            ops: unsafe { AmxOps::new() },
+            #[cfg(debug_assertions)]
+            thread: std::thread::current().id(),
+            canary: None,
+            zero_on_drop: false,
+        })
+    }
 
-            Ok(Self {
-                // Safety: AMX is supported
-                ops: unsafe { AmxOps::new() },
-            })
+    /// Construct a brand new instance of `AmxCtx` like [`AmxCtx::new`], which
+    /// zeroes the registers when dropped, before disabling AMX.
+    ///
+    /// The AMX registers are not cleared by the OS or the hardware when AMX
+    /// is disabled, so sensitive data left in them may be read by other code
+    /// running on the same thread later. Use this constructor when handling
+    /// such data, e.g., key material.
+    ///
+    /// The registers are zeroed even when the context is dropped during
+    /// unwinding. Contexts created by [`AmxCtx::new_nested`] while this one
+    /// exists share its registers; they see the registers zeroed if they
+    /// outlive this one.
+    ///
+    /// ```rust
+    /// use amx::{prelude::*, XRow};
+    /// {
+    ///     let mut ctx = amx::AmxCtx::new_zeroing().unwrap();
+    ///     unsafe { ctx.load512([42u8; 64].as_ptr(), XRow(0)) };
+    /// }
+    /// let mut ctx = amx::AmxCtx::new().unwrap();
+    /// assert_eq!(ctx.read_x(), [0; 512]);
+    /// ```
+    pub fn new_zeroing() -> Result<Self, NewAmxCtxError> {
+        let mut ctx = Self::new()?;
+        ctx.zero_on_drop = true;
+        Ok(ctx)
+    }
+
+    /// Enable AMX for the current thread, call the given closure with a
+    /// handle to issue AMX instructions, and disable AMX again.
+    ///
+    /// The handle is borrowed for the duration of the closure call and can't
+    /// escape from it. AMX is disabled when the closure returns or unwinds.
+    ///
+    /// This method can be nested. Like [`AmxCtx::new_nested`], it shares the
+    /// activation of an existing `AmxCtx` (if any), in which case AMX remains
+    /// enabled after the closure returns. Note that the register contents are
+    /// shared as well.
+    ///
+    /// ```rust
+    /// use amx::{prelude::*, XRow};
+    /// let x = amx::AmxCtx::scope(|amx| {
+    ///     let mut x = [0u8; 64];
+    ///     unsafe {
+    ///         amx.load512([42u8; 64].as_ptr(), XRow(1));
+    ///         amx.store512(x.as_mut_ptr(), XRow(1));
+    ///     }
+    ///     x
+    /// })
+    /// .unwrap();
+    /// assert_eq!(x, [42u8; 64]);
+    /// ```
+    pub fn scope<R>(f: impl FnOnce(&mut AmxOps<'_>) -> R) -> Result<R, NewAmxCtxError> {
+        let mut ctx = Self::new_nested()?;
+        // `ctx` is dropped (thus disabling AMX) even if `f` panics
+        Ok(f(&mut ctx.ops.borrow_mut()))
+    }
+
+    /// Enable AMX for the current thread, call the given closure with
+    /// exclusive access to AMX, and disable AMX again.
+    ///
+    /// Unlike [`AmxCtx::scope`], this method returns
+    /// [`NewAmxCtxError::AlreadyActive`] if the current thread already has an
+    /// `AmxCtx`, so the closure never clobbers another context's registers.
+    /// This is the recommended way to use AMX in asynchronous code (see
+    /// [the type-level documentation](AmxCtx#asynchronous-code)).
+    pub fn run<R>(f: impl FnOnce(&mut AmxOps<'_>) -> R) -> Result<R, NewAmxCtxError> {
+        let mut ctx = Self::new()?;
+        Ok(f(&mut ctx.ops.borrow_mut()))
+    }
+
+    /// Get a token proving that AMX is enabled for the current thread while
+    /// it's alive. See [`AmxEnabled`] for an example.
+    #[inline]
+    pub fn enabled(&self) -> AmxEnabled<'_> {
+        self.assert_same_thread();
+        AmxEnabled(PhantomData)
+    }
+
+    /// Disable AMX for the current thread until the returned guard is dropped
+    /// (or [`resume`](AmxSuspended::resume)d), saving the register contents
+    /// to restore them on resumption.
+    ///
+    /// This can be used to release AMX while doing something else for a
+    /// while, e.g., to save power or to hand AMX over to other code on the
+    /// same thread that enables and disables it on its own. The guard
+    /// borrows `self` mutably, so no AMX instructions can be issued through
+    /// it while suspended. [`AmxCtx::new`], [`AmxCtx::new_nested`], and the
+    /// methods built on them fail with [`NewAmxCtxError::AlreadyActive`]
+    /// while suspended.
+    ///
+    /// # Panics
+    ///
+    /// Panics if other `AmxCtx`s share the activation (i.e., `self` or
+    /// another context was created by [`AmxCtx::new_nested`] while one
+    /// existed), because they would lose access to AMX.
+    ///
+    /// ```rust
+    /// use amx::{prelude::*, XRow};
+    /// let mut ctx = amx::AmxCtx::new().unwrap();
+    /// unsafe { ctx.load512([42u8; 64].as_ptr(), XRow(0)) };
+    ///
+    /// let suspended = ctx.suspend();
+    /// // AMX is disabled here
+    /// suspended.resume();
+    ///
+    /// assert_eq!(ctx.read_x()[..64], [42; 64]);
+    /// ```
+    ///
+    /// Instructions can't be issued while suspended:
+    ///
+    /// ```rust,compile_fail
+    /// use amx::{prelude::*, XRow};
+    /// let mut ctx = amx::AmxCtx::new().unwrap();
+    /// let suspended = ctx.suspend();
+    /// ctx.clear_x();
+    /// suspended.resume();
+    /// ```
+    pub fn suspend(&mut self) -> AmxSuspended<'_> {
+        self.assert_same_thread();
+        assert_eq!(
+            CTX_DEPTH.with(|x| x.get()),
+            1,
+            "can't suspend an `AmxCtx` sharing the activation with others"
+        );
+        let snapshot = Box::new(self.ops.snapshot());
+        SUSPENDED.with(|x| x.set(true));
+        // Disable AMX for the current thread
+        // Safety: AMX is supported
+        unsafe { crate::nativeops::clr() };
+        AmxSuspended {
+            ctx: self,
+            snapshot,
         }
     }
+
+    /// Return the registers to the all-zero state, e.g., to scrub the data of
+    /// one computation before an unrelated one, or to start every iteration
+    /// of a benchmark from the same state.
+    ///
+    /// This is done by explicit zeroing ([`Amx::clear_all`]) rather than by
+    /// disabling and re-enabling AMX, which isn't known to clear the
+    /// registers and would affect the other `AmxCtx`s sharing the
+    /// activation. Note that those contexts share the register contents and
+    /// are reset as well.
+    ///
+    /// The canary written by [`enable_state_canary`](Self::enable_state_canary)
+    /// is rewritten, so it stays intact.
+    ///
+    /// ```rust
+    /// use amx::{prelude::*, XRow};
+    /// let mut ctx = amx::AmxCtx::new().unwrap();
+    /// unsafe { ctx.load512([42u8; 64].as_ptr(), XRow(1)) };
+    /// ctx.reset();
+    /// assert_eq!(ctx.read_x(), [0; 512]);
+    /// ```
+    pub fn reset(&mut self) {
+        self.ops.clear_all();
+        if let Some(row) = self.canary {
+            self.enable_state_canary(row);
+        }
+    }
+
+    /// Write a known pattern to the `z` row `row`, which can be checked later
+    /// by [`check_state_canary`](Self::check_state_canary) to detect the loss
+    /// of the register contents.
+    ///
+    /// The AMX state is not part of the architecturally defined thread state,
+    /// and code outside of your control (e.g., Accelerate, or a signal
+    /// handler) may use AMX on the same thread. If it doesn't preserve the
+    /// registers, your data is silently replaced. The canary turns this into
+    /// an error, which helps to diagnose such an interaction.
+    ///
+    /// `row` must not be used for anything else while the canary is enabled.
+    /// Calling this method again moves the canary to a new row.
+    pub fn enable_state_canary(&mut self, row: ZRow) {
+        // Safety: Reading a memory region within `CANARY`
+        unsafe { self.ops.load512(CANARY.as_ptr(), row) };
+        self.canary = Some(row);
+    }
+
+    /// Stop checking the canary written by
+    /// [`enable_state_canary`](Self::enable_state_canary), making its row
+    /// available for other uses.
+    pub fn disable_state_canary(&mut self) {
+        self.canary = None;
+    }
+
+    /// Check if the canary written by
+    /// [`enable_state_canary`](Self::enable_state_canary) is intact. Call this
+    /// before reading results that must not be corrupted.
+    ///
+    /// Returns `Ok(())` if the canary is not enabled.
+    ///
+    /// ```rust
+    /// use amx::{prelude::*, ZRow};
+    /// let mut ctx = amx::AmxCtx::new().unwrap();
+    /// ctx.enable_state_canary(ZRow(63));
+    /// // ... compute something in other rows ...
+    /// ctx.check_state_canary().expect("AMX state was clobbered");
+    /// ```
+    pub fn check_state_canary(&mut self) -> Result<(), AmxStateLost> {
+        let row = match self.canary {
+            Some(row) => row,
+            None => return Ok(()),
+        };
+        let mut got = [0u8; 64];
+        // Safety: Writing in a memory region within `got`
+        unsafe { self.ops.store512(got.as_mut_ptr(), row) };
+        if got == CANARY {
+            Ok(())
+        } else {
+            Err(AmxStateLost { row })
+        }
+    }
+
+    /// Panic if the current thread isn't the one that created `self`.
+    #[inline]
+    #[track_caller]
+    fn assert_same_thread(&self) {
+        #[cfg(debug_assertions)]
+        assert_eq!(
+            std::thread::current().id(),
+            self.thread,
+            "`AmxCtx` was moved to another thread, whose AMX state it doesn't own"
+        );
+    }
 }
 
 impl Drop for AmxCtx {
     fn drop(&mut self) {
-        // Disable AMX for the current thread
-        // Safety: AMX is supported
-        unsafe { crate::nativeops::clr() };
+        self.assert_same_thread();
+        if self.zero_on_drop {
+            self.ops.clear_all();
+        }
+        CTX_DEPTH.with(|depth| {
+            depth.set(depth.get() - 1);
+            if depth.get() == 0 {
+                // Disable AMX for the current thread
+                // Safety: AMX is supported
+                unsafe { crate::nativeops::clr() };
+            }
+        });
+    }
+}
+
+/// The guard returned by [`AmxCtx::suspend`], which re-enables AMX and
+/// restores the register contents when dropped.
+pub struct AmxSuspended<'a> {
+    ctx: &'a mut AmxCtx,
+    snapshot: Box<AmxStateSnapshot>,
+}
+
+impl AmxSuspended<'_> {
+    /// Re-enable AMX and restore the register contents. This is equivalent
+    /// to dropping `self`.
+    #[inline]
+    pub fn resume(self) {}
+}
 
-        CTX_ACTIVE.with(|x| x.set(false));
+impl Drop for AmxSuspended<'_> {
+    fn drop(&mut self) {
+        self.ctx.assert_same_thread();
+        // Enable AMX for the current thread
+        // Safety: AMX is supported
+        unsafe { crate::nativeops::set() };
+        SUSPENDED.with(|x| x.set(false));
+        self.snapshot.restore(&mut self.ctx.ops);
     }
 }
 
@@ -67,6 +488,7 @@ impl Deref for AmxCtx {
 
 impl DerefMut for AmxCtx {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.assert_same_thread();
         &mut self.ops
     }
 }
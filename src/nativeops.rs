@@ -1,27 +1,38 @@
 //! Low-level operations (modeled after [Apple compiler intrinsics])
 //!
 //! [Apple compiler intrinsics]: https://www.realworldtech.com/forum/?threadid=187087&curpostid=187120
+use crate::ops::opcode::*;
 use std::{arch::asm, marker::PhantomData};
 
+/// The register through which [`op_in`] passes the operand.
+///
+/// The register number has to be known when assembling the instruction word,
+/// so we pin the operand to a specific register rather than letting the
+/// register allocator choose one.
+const OPERAND_REG: u8 = 0;
+
+/// Calculate the instruction word for the AMX operation `op` with the 5-bit
+/// register number or immediate `operand`.
+const fn encode(op: u8, operand: u8) -> u32 {
+    0x0020_1000 | ((op as u32) << 5) | operand as u32
+}
+
 /// Emit an AMX instruction with an input register.
 #[inline(always)]
 pub unsafe fn op_in<const OP: u8>(operand: u64) {
+    #[cfg(feature = "checked")]
+    crate::checked::validate(OP, operand);
+    op_in_unchecked::<OP>(operand);
+}
+
+/// Emit an AMX instruction with an input register, skipping the validation
+/// by the `checked` feature.
+#[inline(always)]
+pub(crate) unsafe fn op_in_unchecked<const OP: u8>(operand: u64) {
     asm!(
-        // Most AMX instructions take a 64-bit register number (e.g., `x25`) as
-        // the operand. The problem is how to encode the register number in the
-        // `.word` directive. We could use a fixed register number, but then we
-        // would have to move a value into that register, which is utterly
-        // inefficient.
-        //
-        // The trick used here is to prepend `0` to the register name to make it
-        // look like a hexadecimal number (e.g., `0x25`). The encoding is still
-        // wrong at this point because the register number is a decimal number,
-        // but it's being interpreted as a hexadecimal number (`0x25 = 37`). So
-        // we have to split it into digits (`2` and `5`) and reconstitute the
-        // intended register number (`2 * 10 + 5`).
-        ".word 0x00201000 + ({op} << 5) + (0{operand} & 0xf) + (0{operand} >> 4) * 10",
-        op = const OP,
-        operand = in(reg) operand,
+        ".word {word}",
+        word = const encode(OP, OPERAND_REG),
+        in("x0") operand,
         options(nostack, preserves_flags),
     );
 }
@@ -30,131 +41,130 @@ pub unsafe fn op_in<const OP: u8>(operand: u64) {
 #[inline(always)]
 pub unsafe fn op_imm<const OP: u8, const OPERAND: u8>() {
     asm!(
-        ".word 0x00201000 + ({op} << 5) + {operand}",
-        op = const OP,
-        operand = const OPERAND,
+        ".word {word}",
+        word = const encode(OP, OPERAND),
         options(nostack, preserves_flags),
     );
 }
 
 #[inline(always)]
 pub unsafe fn ldx(x: u64) {
-    op_in::<0>(x);
+    op_in::<LDX>(x);
 }
 
 #[inline(always)]
 pub unsafe fn ldy(x: u64) {
-    op_in::<1>(x);
+    op_in::<LDY>(x);
 }
 
 #[inline(always)]
 pub unsafe fn stx(x: u64) {
-    op_in::<2>(x);
+    op_in::<STX>(x);
 }
 
 #[inline(always)]
 pub unsafe fn sty(x: u64) {
-    op_in::<3>(x);
+    op_in::<STY>(x);
 }
 
 #[inline(always)]
 pub unsafe fn ldz(x: u64) {
-    op_in::<4>(x);
+    op_in::<LDZ>(x);
 }
 
 #[inline(always)]
 pub unsafe fn stz(x: u64) {
-    op_in::<5>(x);
+    op_in::<STZ>(x);
 }
 
 #[inline(always)]
 pub unsafe fn ldzi(x: u64) {
-    op_in::<6>(x);
+    op_in::<LDZI>(x);
 }
 
 #[inline(always)]
 pub unsafe fn stzi(x: u64) {
-    op_in::<7>(x);
+    op_in::<STZI>(x);
 }
 
 #[inline(always)]
 pub unsafe fn extrx(x: u64) {
-    op_in::<8>(x);
+    op_in::<EXTRX>(x);
 }
 
 #[inline(always)]
 pub unsafe fn extry(x: u64) {
-    op_in::<9>(x);
+    op_in::<EXTRY>(x);
 }
 
 #[inline(always)]
 pub unsafe fn fma64(x: u64) {
-    op_in::<10>(x);
+    op_in::<FMA64>(x);
 }
 
 #[inline(always)]
 pub unsafe fn fms64(x: u64) {
-    op_in::<11>(x);
+    op_in::<FMS64>(x);
 }
 
 #[inline(always)]
 pub unsafe fn fma32(x: u64) {
-    op_in::<12>(x);
+    op_in::<FMA32>(x);
 }
 
 #[inline(always)]
 pub unsafe fn fms32(x: u64) {
-    op_in::<13>(x);
+    op_in::<FMS32>(x);
 }
 
 #[inline(always)]
 pub unsafe fn mac16(x: u64) {
-    op_in::<14>(x);
+    op_in::<MAC16>(x);
 }
 
 #[inline(always)]
 pub unsafe fn fma16(x: u64) {
-    op_in::<15>(x);
+    op_in::<FMA16>(x);
 }
 
 #[inline(always)]
 pub unsafe fn fms16(x: u64) {
-    op_in::<16>(x);
+    op_in::<FMS16>(x);
 }
 
 #[inline(always)]
 pub unsafe fn set() {
-    op_imm::<17, 0>();
+    op_imm::<SET_CLR, 0>();
 }
 
 #[inline(always)]
 pub unsafe fn clr() {
-    op_imm::<17, 1>();
+    op_imm::<SET_CLR, 1>();
 }
 
 #[inline(always)]
 pub unsafe fn vecint(x: u64) {
-    op_in::<18>(x);
+    op_in::<VECINT>(x);
 }
 
 #[inline(always)]
 pub unsafe fn vecfp(x: u64) {
-    op_in::<19>(x);
+    op_in::<VECFP>(x);
 }
 
 #[inline(always)]
 pub unsafe fn matint(x: u64) {
-    op_in::<20>(x);
+    op_in::<MATINT>(x);
 }
 
 #[inline(always)]
 pub unsafe fn matfp(x: u64) {
-    op_in::<21>(x);
+    op_in::<MATFP>(x);
 }
 
 #[inline(always)]
 pub unsafe fn genlut(x: u64) {
-    op_in::<22>(x);
+    op_in::<GENLUT>(x);
 }
 
 /// Exposes the target processor's AMX support by implementing [`AmxOps`] trait.
@@ -2,6 +2,22 @@
 ///
 /// Load and store operations receive a pointer by the additional parameter to
 /// allow emulation on a system with a different pointer size.
+///
+/// This trait is dyn-compatible. `dyn AmxOps`, `&mut T`, and `Box<T>` (where
+/// `T: AmxOps + ?Sized`) all implement `AmxOps` and thus [`Amx`](crate::Amx),
+/// so the high-level methods and the [`kernels`](crate::kernels) can be used
+/// through trait objects, e.g., ones passed across a plugin boundary.
+///
+/// ```rust
+/// use amx::{Amx, AmxOps, XRow};
+/// fn fill_x(ctx: &mut dyn AmxOps, value: u8) {
+///     unsafe { ctx.load512([value; 64].as_ptr(), XRow(0)) };
+/// }
+///
+/// let mut ctx: Box<dyn AmxOps> = Box::new(amx::AmxEmuCtx::default());
+/// fill_x(&mut *ctx, 42);
+/// assert_eq!(ctx.read_x()[..64], [42; 64]);
+/// ```
 pub unsafe trait AmxOps {
     unsafe fn ldx(&mut self, x: u64, ptr: *mut ());
     unsafe fn ldy(&mut self, x: u64, ptr: *mut ());
@@ -96,3 +112,109 @@ unsafe impl<T: ?Sized + AmxOps> AmxOps for &'_ mut T {
         (**self).genlut(x)
     }
 }
+
+// Safety: Just forwarding the calls
+unsafe impl<T: ?Sized + AmxOps> AmxOps for Box<T> {
+    unsafe fn ldx(&mut self, x: u64, ptr: *mut ()) {
+        (**self).ldx(x, ptr)
+    }
+    unsafe fn ldy(&mut self, x: u64, ptr: *mut ()) {
+        (**self).ldy(x, ptr)
+    }
+    unsafe fn stx(&mut self, x: u64, ptr: *mut ()) {
+        (**self).stx(x, ptr)
+    }
+    unsafe fn sty(&mut self, x: u64, ptr: *mut ()) {
+        (**self).sty(x, ptr)
+    }
+    unsafe fn ldz(&mut self, x: u64, ptr: *mut ()) {
+        (**self).ldz(x, ptr)
+    }
+    unsafe fn stz(&mut self, x: u64, ptr: *mut ()) {
+        (**self).stz(x, ptr)
+    }
+    unsafe fn ldzi(&mut self, x: u64, ptr: *mut ()) {
+        (**self).ldzi(x, ptr)
+    }
+    unsafe fn stzi(&mut self, x: u64, ptr: *mut ()) {
+        (**self).stzi(x, ptr)
+    }
+    fn extrx(&mut self, x: u64) {
+        (**self).extrx(x)
+    }
+    fn extry(&mut self, x: u64) {
+        (**self).extry(x)
+    }
+    fn fma64(&mut self, x: u64) {
+        (**self).fma64(x)
+    }
+    fn fms64(&mut self, x: u64) {
+        (**self).fms64(x)
+    }
+    fn fma32(&mut self, x: u64) {
+        (**self).fma32(x)
+    }
+    fn fms32(&mut self, x: u64) {
+        (**self).fms32(x)
+    }
+    fn mac16(&mut self, x: u64) {
+        (**self).mac16(x)
+    }
+    fn fma16(&mut self, x: u64) {
+        (**self).fma16(x)
+    }
+    fn fms16(&mut self, x: u64) {
+        (**self).fms16(x)
+    }
+    fn vecint(&mut self, x: u64) {
+        (**self).vecint(x)
+    }
+    fn vecfp(&mut self, x: u64) {
+        (**self).vecfp(x)
+    }
+    fn matint(&mut self, x: u64) {
+        (**self).matint(x)
+    }
+    fn matfp(&mut self, x: u64) {
+        (**self).matfp(x)
+    }
+    fn genlut(&mut self, x: u64) {
+        (**self).genlut(x)
+    }
+}
+
+/// The operation numbers of AMX instructions.
+pub mod opcode {
+    pub const LDX: u8 = 0;
+    pub const LDY: u8 = 1;
+    pub const STX: u8 = 2;
+    pub const STY: u8 = 3;
+    pub const LDZ: u8 = 4;
+    pub const STZ: u8 = 5;
+    pub const LDZI: u8 = 6;
+    pub const STZI: u8 = 7;
+    pub const EXTRX: u8 = 8;
+    pub const EXTRY: u8 = 9;
+    pub const FMA64: u8 = 10;
+    pub const FMS64: u8 = 11;
+    pub const FMA32: u8 = 12;
+    pub const FMS32: u8 = 13;
+    pub const MAC16: u8 = 14;
+    pub const FMA16: u8 = 15;
+    pub const FMS16: u8 = 16;
+    pub const SET_CLR: u8 = 17;
+    pub const VECINT: u8 = 18;
+    pub const VECFP: u8 = 19;
+    pub const MATINT: u8 = 20;
+    pub const MATFP: u8 = 21;
+    pub const GENLUT: u8 = 22;
+
+    /// Get the mnemonic of the AMX instruction `op`.
+    pub fn mnemonic(op: u8) -> &'static str {
+        [
+            "ldx", "ldy", "stx", "sty", "ldz", "stz", "ldzi", "stzi", "extrx", "extry", "fma64",
+            "fms64", "fma32", "fms32", "mac16", "fma16", "fms16", "set/clr", "vecint", "vecfp",
+            "matint", "matfp", "genlut",
+        ][op as usize]
+    }
+}
@@ -0,0 +1,396 @@
+//! Measuring energy consumption
+//!
+//! This module samples the energy counters of the SoC through Apple's private
+//! `IOReport` library (which `powermetrics` is built on), loaded at runtime.
+//! Dividing the energy consumed by the CPU during an AMX workload by the
+//! number of operations performed gives its energy efficiency, e.g., in
+//! joules per GFLOP.
+//!
+//! The counters cover the whole system, so the energy consumed by other
+//! activity during a measurement is included. They're also updated at
+//! intervals of a few milliseconds, so a measurement should last at least
+//! several hundred milliseconds to be accurate. Root privileges aren't
+//! required. The library is only available on macOS; [`EnergyMeter::new`]
+//! returns [`PowerError::Unsupported`] elsewhere.
+//!
+//! # Example
+//!
+//! ```rust
+//! use amx::{prelude::*, XBytes, YBytes, ZRow};
+//! let meter = match amx::power::EnergyMeter::new() {
+//!     Ok(x) => x,
+//!     Err(e) => {
+//!         eprintln!("energy counters are unavailable: {:?}", e);
+//!         return;
+//!     }
+//! };
+//! let mut ctx = amx::AmxCtx::new().unwrap();
+//! let count = 100_000_000;
+//! let ((), report) = meter.measure(|| {
+//!     for _ in 0..count {
+//!         ctx.outer_product_f32_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), true);
+//!     }
+//! });
+//! // Each `fma32` performs 16 × 16 multiply-adds
+//! let gflop = (count * 512) as f64 * 1e-9;
+//! if let Some(joules) = report.cpu_joules() {
+//!     println!("{:.3} J/GFLOP", joules / gflop);
+//! }
+//! ```
+use std::time::{Duration, Instant};
+
+/// The error type for [`EnergyMeter::new`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PowerError {
+    /// The target system doesn't have the `IOReport` library.
+    Unsupported,
+    /// The `IOReport` library or one of its functions couldn't be loaded.
+    Load,
+    /// The system doesn't report any energy counters.
+    NoChannels,
+}
+
+/// The energy consumed during a call to [`EnergyMeter::measure`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnergyReport {
+    /// The wall-clock time taken by the call.
+    pub elapsed: Duration,
+    /// The energy consumed by each component, measured in joules, in the
+    /// order reported by the system. The channel names depend on the SoC,
+    /// e.g., `"CPU Energy"`, `"GPU Energy"`, `"ANE"`, and `"DRAM"`.
+    pub channels: Vec<(String, f64)>,
+}
+
+impl EnergyReport {
+    /// Get the energy consumed by the component `channel`, measured in
+    /// joules.
+    pub fn joules(&self, channel: &str) -> Option<f64> {
+        self.channels
+            .iter()
+            .find(|(name, _)| name == channel)
+            .map(|&(_, joules)| joules)
+    }
+
+    /// Get the average power drawn by the component `channel`, measured in
+    /// watts.
+    pub fn watts(&self, channel: &str) -> Option<f64> {
+        Some(self.joules(channel)? / self.elapsed.as_secs_f64())
+    }
+
+    /// Get the energy consumed by the CPU clusters (including AMX), measured
+    /// in joules. This is the channel `"CPU Energy"`.
+    pub fn cpu_joules(&self) -> Option<f64> {
+        self.joules("CPU Energy")
+    }
+}
+
+/// Samples the system's energy counters.
+pub struct EnergyMeter {
+    meter: sys::Meter,
+}
+
+impl std::fmt::Debug for EnergyMeter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnergyMeter").finish_non_exhaustive()
+    }
+}
+
+impl EnergyMeter {
+    /// Load the `IOReport` library and subscribe to the energy counters.
+    pub fn new() -> Result<Self, PowerError> {
+        Ok(Self {
+            meter: sys::Meter::load()?,
+        })
+    }
+
+    /// Call `f`, returning its result along with the energy consumed by the
+    /// system during the call.
+    pub fn measure<R>(&self, f: impl FnOnce() -> R) -> (R, EnergyReport) {
+        let start = self.meter.sample();
+        let start_time = Instant::now();
+        let result = f();
+        let elapsed = start_time.elapsed();
+        let end = self.meter.sample();
+        let report = EnergyReport {
+            elapsed,
+            channels: self.meter.delta(&start, &end),
+        };
+        (result, report)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use super::PowerError;
+    use std::os::raw::{c_char, c_int, c_void};
+
+    extern "C" {
+        fn dlopen(path: *const c_char, mode: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    }
+
+    const RTLD_LAZY: c_int = 1;
+    const CORE_FOUNDATION_PATH: &[u8] =
+        b"/System/Library/Frameworks/CoreFoundation.framework/CoreFoundation\0";
+    const IOREPORT_PATH: &[u8] = b"/usr/lib/libIOReport.dylib\0";
+
+    /// The channel group containing the energy counters
+    const ENERGY_GROUP: &[u8] = b"Energy Model\0";
+    /// The key of a sample dictionary holding the array of channels
+    const CHANNELS_KEY: &[u8] = b"IOReportChannels\0";
+
+    const K_CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+
+    type CFTypeRef = *const c_void;
+    type CFIndex = isize;
+
+    /// The functions of CoreFoundation and `IOReport` used by this module
+    struct Api {
+        cf_release: unsafe extern "C" fn(CFTypeRef),
+        cf_string_create_with_c_string:
+            unsafe extern "C" fn(CFTypeRef, *const c_char, u32) -> CFTypeRef,
+        cf_string_get_c_string: unsafe extern "C" fn(CFTypeRef, *mut c_char, CFIndex, u32) -> u8,
+        cf_dictionary_get_value: unsafe extern "C" fn(CFTypeRef, CFTypeRef) -> CFTypeRef,
+        cf_array_get_count: unsafe extern "C" fn(CFTypeRef) -> CFIndex,
+        cf_array_get_value_at_index: unsafe extern "C" fn(CFTypeRef, CFIndex) -> CFTypeRef,
+        copy_channels_in_group:
+            unsafe extern "C" fn(CFTypeRef, CFTypeRef, u64, u64, u64) -> CFTypeRef,
+        create_subscription: unsafe extern "C" fn(
+            *const c_void,
+            CFTypeRef,
+            *mut CFTypeRef,
+            u64,
+            CFTypeRef,
+        ) -> CFTypeRef,
+        create_samples: unsafe extern "C" fn(CFTypeRef, CFTypeRef, CFTypeRef) -> CFTypeRef,
+        create_samples_delta: unsafe extern "C" fn(CFTypeRef, CFTypeRef, CFTypeRef) -> CFTypeRef,
+        channel_get_channel_name: unsafe extern "C" fn(CFTypeRef) -> CFTypeRef,
+        channel_get_unit_label: unsafe extern "C" fn(CFTypeRef) -> CFTypeRef,
+        simple_get_integer_value: unsafe extern "C" fn(CFTypeRef, i32) -> i64,
+    }
+
+    /// Look up the symbol `name` (which must be NUL-terminated) in `handle`.
+    ///
+    /// # Safety
+    ///
+    /// `F` must be a function pointer type matching the symbol's signature.
+    unsafe fn sym<F: Copy>(handle: *mut c_void, name: &[u8]) -> Result<F, PowerError> {
+        let ptr = dlsym(handle, name.as_ptr() as *const c_char);
+        if ptr.is_null() {
+            Err(PowerError::Load)
+        } else {
+            Ok(std::mem::transmute_copy(&ptr))
+        }
+    }
+
+    /// Open the library at `path` (which must be NUL-terminated).
+    fn open(path: &[u8]) -> Result<*mut c_void, PowerError> {
+        // Safety: `path` is NUL-terminated
+        let handle = unsafe { dlopen(path.as_ptr() as *const c_char, RTLD_LAZY) };
+        if handle.is_null() {
+            Err(PowerError::Load)
+        } else {
+            Ok(handle)
+        }
+    }
+
+    pub(super) struct Meter {
+        api: Api,
+        subscription: CFTypeRef,
+        /// The subscribed channels
+        channels: CFTypeRef,
+        /// `CHANNELS_KEY` as a `CFString`
+        channels_key: CFTypeRef,
+    }
+
+    /// A set of counter values
+    pub(super) struct Sample {
+        ptr: CFTypeRef,
+        cf_release: unsafe extern "C" fn(CFTypeRef),
+    }
+
+    impl Meter {
+        pub(super) fn load() -> Result<Self, PowerError> {
+            let (cf, ior) = (open(CORE_FOUNDATION_PATH)?, open(IOREPORT_PATH)?);
+            // Safety: The symbols have the declared signatures. The library
+            //         handles are never closed, so the function pointers
+            //         remain valid.
+            let api = unsafe {
+                Api {
+                    cf_release: sym(cf, b"CFRelease\0")?,
+                    cf_string_create_with_c_string: sym(cf, b"CFStringCreateWithCString\0")?,
+                    cf_string_get_c_string: sym(cf, b"CFStringGetCString\0")?,
+                    cf_dictionary_get_value: sym(cf, b"CFDictionaryGetValue\0")?,
+                    cf_array_get_count: sym(cf, b"CFArrayGetCount\0")?,
+                    cf_array_get_value_at_index: sym(cf, b"CFArrayGetValueAtIndex\0")?,
+                    copy_channels_in_group: sym(ior, b"IOReportCopyChannelsInGroup\0")?,
+                    create_subscription: sym(ior, b"IOReportCreateSubscription\0")?,
+                    create_samples: sym(ior, b"IOReportCreateSamples\0")?,
+                    create_samples_delta: sym(ior, b"IOReportCreateSamplesDelta\0")?,
+                    channel_get_channel_name: sym(ior, b"IOReportChannelGetChannelName\0")?,
+                    channel_get_unit_label: sym(ior, b"IOReportChannelGetUnitLabel\0")?,
+                    simple_get_integer_value: sym(ior, b"IOReportSimpleGetIntegerValue\0")?,
+                }
+            };
+
+            // Safety: The arguments are valid, and every object created here
+            //         is released exactly once (the ones stored in `Meter` by
+            //         its `Drop` impl)
+            unsafe {
+                let new_string = |s: &[u8]| {
+                    (api.cf_string_create_with_c_string)(
+                        std::ptr::null(),
+                        s.as_ptr() as *const c_char,
+                        K_CF_STRING_ENCODING_UTF8,
+                    )
+                };
+                let group = new_string(ENERGY_GROUP);
+                let desired = (api.copy_channels_in_group)(group, std::ptr::null(), 0, 0, 0);
+                (api.cf_release)(group);
+                if desired.is_null() {
+                    return Err(PowerError::NoChannels);
+                }
+
+                let mut channels = std::ptr::null();
+                let subscription = (api.create_subscription)(
+                    std::ptr::null(),
+                    desired,
+                    &mut channels,
+                    0,
+                    std::ptr::null(),
+                );
+                (api.cf_release)(desired);
+                if subscription.is_null() || channels.is_null() {
+                    return Err(PowerError::NoChannels);
+                }
+
+                let channels_key = new_string(CHANNELS_KEY);
+                Ok(Self {
+                    api,
+                    subscription,
+                    channels,
+                    channels_key,
+                })
+            }
+        }
+
+        pub(super) fn sample(&self) -> Sample {
+            // Safety: `subscription` and `channels` are valid
+            let ptr = unsafe {
+                (self.api.create_samples)(self.subscription, self.channels, std::ptr::null())
+            };
+            Sample {
+                ptr,
+                cf_release: self.api.cf_release,
+            }
+        }
+
+        /// Get the energy consumed by each channel between `start` and
+        /// `end`, measured in joules.
+        pub(super) fn delta(&self, start: &Sample, end: &Sample) -> Vec<(String, f64)> {
+            let api = &self.api;
+            let mut out = Vec::new();
+            if start.ptr.is_null() || end.ptr.is_null() {
+                return out;
+            }
+            // Safety: The samples are valid, and the objects obtained by the
+            //         "get" functions are owned by `delta`, which is released
+            //         last
+            unsafe {
+                let delta = (api.create_samples_delta)(start.ptr, end.ptr, std::ptr::null());
+                if delta.is_null() {
+                    return out;
+                }
+                let items = (api.cf_dictionary_get_value)(delta, self.channels_key);
+                let len = if items.is_null() {
+                    0
+                } else {
+                    (api.cf_array_get_count)(items)
+                };
+                for i in 0..len {
+                    let item = (api.cf_array_get_value_at_index)(items, i);
+                    let name = self.string((api.channel_get_channel_name)(item));
+                    let unit = self.string((api.channel_get_unit_label)(item));
+                    let scale = match unit.as_deref().map(str::trim) {
+                        Some("mJ") => 1e-3,
+                        Some("uJ" | "µJ") => 1e-6,
+                        Some("nJ") => 1e-9,
+                        _ => continue,
+                    };
+                    if let Some(name) = name {
+                        let value = (api.simple_get_integer_value)(item, 0);
+                        out.push((name, value as f64 * scale));
+                    }
+                }
+                (api.cf_release)(delta);
+            }
+            out
+        }
+
+        /// Convert a `CFString` to `String`.
+        ///
+        /// # Safety
+        ///
+        /// `s` must be null or a valid `CFString`.
+        unsafe fn string(&self, s: CFTypeRef) -> Option<String> {
+            if s.is_null() {
+                return None;
+            }
+            let mut buf = [0u8; 256];
+            let ok = (self.api.cf_string_get_c_string)(
+                s,
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len() as CFIndex,
+                K_CF_STRING_ENCODING_UTF8,
+            );
+            if ok == 0 {
+                return None;
+            }
+            let len = buf.iter().position(|&b| b == 0)?;
+            String::from_utf8(buf[..len].to_vec()).ok()
+        }
+    }
+
+    impl Drop for Meter {
+        fn drop(&mut self) {
+            // Safety: These objects are owned by `self`
+            unsafe {
+                (self.api.cf_release)(self.channels_key);
+                (self.api.cf_release)(self.channels);
+                (self.api.cf_release)(self.subscription);
+            }
+        }
+    }
+
+    impl Drop for Sample {
+        fn drop(&mut self) {
+            if !self.ptr.is_null() {
+                // Safety: The sample is owned by `self`
+                unsafe { (self.cf_release)(self.ptr) };
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod sys {
+    use super::PowerError;
+
+    pub(super) enum Meter {}
+
+    pub(super) struct Sample;
+
+    impl Meter {
+        pub(super) fn load() -> Result<Self, PowerError> {
+            Err(PowerError::Unsupported)
+        }
+
+        pub(super) fn sample(&self) -> Sample {
+            match *self {}
+        }
+
+        pub(super) fn delta(&self, _: &Sample, _: &Sample) -> Vec<(String, f64)> {
+            match *self {}
+        }
+    }
+}
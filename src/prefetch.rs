@@ -0,0 +1,48 @@
+//! Software prefetching
+//!
+//! The hardware prefetchers keep up with sequential accesses, but a GEMM
+//! kernel streaming through several panels or a packing routine reading a
+//! strided matrix can outrun them, leaving AMX waiting for memory. The
+//! functions in this module issue `prfm` instructions to request cache lines
+//! ahead of their use.
+//!
+//! AMX is attached to the shared L2 cache of a CPU cluster, so data consumed
+//! by AMX loads should be prefetched by [`prefetch_read_l2`], and data read by
+//! the CPU (e.g., by a packing routine) by [`prefetch_read_l1`].
+//!
+//! A prefetch is merely a hint and never faults, so these functions are safe
+//! to call with any pointer. They do nothing on targets other than AArch64.
+
+/// Request the cache line containing `ptr` to be brought into the L1 data
+/// cache for reading.
+#[inline(always)]
+pub fn prefetch_read_l1<T>(ptr: *const T) {
+    #[cfg(target_arch = "aarch64")]
+    // Safety: `prfm` doesn't access memory architecturally
+    unsafe {
+        std::arch::asm!(
+            "prfm pldl1keep, [{ptr}]",
+            ptr = in(reg) ptr,
+            options(nostack, preserves_flags, readonly),
+        );
+    }
+    #[cfg(not(target_arch = "aarch64"))]
+    let _ = ptr;
+}
+
+/// Request the cache line containing `ptr` to be brought into the L2 cache
+/// for reading.
+#[inline(always)]
+pub fn prefetch_read_l2<T>(ptr: *const T) {
+    #[cfg(target_arch = "aarch64")]
+    // Safety: `prfm` doesn't access memory architecturally
+    unsafe {
+        std::arch::asm!(
+            "prfm pldl2keep, [{ptr}]",
+            ptr = in(reg) ptr,
+            options(nostack, preserves_flags, readonly),
+        );
+    }
+    #[cfg(not(target_arch = "aarch64"))]
+    let _ = ptr;
+}
@@ -0,0 +1,252 @@
+//! Probing which AMX instructions the processor accepts
+//!
+//! The AMX instruction set has changed between processor generations, and
+//! the opcodes following `genlut` may be assigned on newer ones. [`scan_ops`]
+//! issues every opcode with a few operand variants and records whether it
+//! executes, raises `SIGILL`, or crashes otherwise, so the capabilities of
+//! an unfamiliar processor can be surveyed at a glance. The result can be
+//! printed as CSV (through [`Display`](std::fmt::Display)) or serialized
+//! with the `serde` feature.
+//!
+//! Each probe runs in a child process created by `fork`, which enables AMX,
+//! issues the instruction, and exits, so a faulting instruction only takes
+//! the child down. On macOS, each crashing child may produce a crash report,
+//! which makes a scan take a few seconds.
+//!
+//! # Example
+//!
+//! ```rust
+//! let report = amx::probe::scan_ops().unwrap();
+//! print!("{}", report);
+//! for result in report.accepted() {
+//!     println!("{:?} executes with operand {:#x}", result.mnemonic(), result.operand);
+//! }
+//! ```
+use crate::ops::opcode::{self, GENLUT, LDX, SET_CLR, STZI};
+use std::fmt;
+
+/// The error type for [`scan_ops`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ProbeError {
+    /// The target system doesn't provide a way to do this.
+    Unsupported,
+    /// The OS reported the specified error number.
+    Os(i32),
+}
+
+/// How a probed instruction behaved.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProbeOutcome {
+    /// The instruction executed without faulting.
+    Accepted,
+    /// The instruction raised `SIGILL`, i.e., the processor doesn't recognize
+    /// it (or the operand).
+    IllegalInstruction,
+    /// The instruction raised the specified signal, e.g., `SIGSEGV` or
+    /// `SIGBUS` by accessing memory at an address derived from the operand.
+    Signal(i32),
+    /// The child process exited with the specified unexpected status.
+    Exited(i32),
+}
+
+/// The outcome of issuing an instruction in [`scan_ops`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProbeResult {
+    /// The opcode of the instruction.
+    pub opcode: u8,
+    /// The operand of the instruction, excluding the pointer.
+    pub operand: u64,
+    /// Whether the lower 56 bits of the operand held the address of a valid
+    /// buffer (of 4096 bytes, aligned to 128-byte boundaries).
+    pub pointer: bool,
+    /// How the instruction behaved.
+    pub outcome: ProbeOutcome,
+}
+
+/// The result of [`scan_ops`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProbeReport {
+    /// The probed instructions, in the order of opcodes.
+    pub results: Vec<ProbeResult>,
+}
+
+/// The operand bits probed in addition to an all-zero operand. Bit 62 and 63
+/// select the variants of several known instructions.
+const VARIANT_BITS: [u64; 2] = [1 << 62, 1 << 63];
+
+/// The number of opcodes representable in an instruction word.
+const NUM_OPCODES: u8 = 32;
+
+/// The size of the buffer given to instructions with a pointer.
+const BUFFER_LEN: usize = 4096;
+
+impl ProbeResult {
+    /// Get the mnemonic of the instruction, or `None` if the opcode isn't
+    /// known.
+    pub fn mnemonic(&self) -> Option<&'static str> {
+        (self.opcode <= GENLUT).then(|| opcode::mnemonic(self.opcode))
+    }
+}
+
+impl ProbeReport {
+    /// Get the results of the instructions that executed without faulting.
+    pub fn accepted(&self) -> impl Iterator<Item = &ProbeResult> + '_ {
+        self.results
+            .iter()
+            .filter(|r| r.outcome == ProbeOutcome::Accepted)
+    }
+}
+
+/// Formats the report as CSV with the columns `opcode`, `mnemonic`,
+/// `operand`, `pointer`, and `outcome`.
+impl fmt::Display for ProbeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "opcode,mnemonic,operand,pointer,outcome")?;
+        for r in &self.results {
+            write!(
+                f,
+                "{},{},{:#018x},{},",
+                r.opcode,
+                r.mnemonic().unwrap_or(""),
+                r.operand,
+                r.pointer
+            )?;
+            match r.outcome {
+                ProbeOutcome::Accepted => writeln!(f, "accepted")?,
+                ProbeOutcome::IllegalInstruction => writeln!(f, "sigill")?,
+                ProbeOutcome::Signal(sig) => writeln!(f, "signal {}", sig)?,
+                ProbeOutcome::Exited(status) => writeln!(f, "exit {}", status)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Issue every opcode except `set`/`clr` with an all-zero operand and with
+/// each of a few variant-selecting bits set, and record how it behaves.
+///
+/// The load and store instructions (`ldx`–`stzi`) are given a pointer to a
+/// valid buffer. The unknown opcodes are probed both with and without one
+/// because they might access memory. The other instructions don't receive a
+/// pointer because the lower bits of their operands hold register offsets.
+///
+/// Returns [`ProbeError::Unsupported`] on non-Unix systems.
+pub fn scan_ops() -> Result<ProbeReport, ProbeError> {
+    let mut buffer = Box::new(crate::AmxAligned([0u8; BUFFER_LEN]));
+    let address = buffer.as_mut_ptr() as u64;
+
+    let mut results = Vec::new();
+    for opcode in (0..NUM_OPCODES).filter(|&op| op != SET_CLR) {
+        let pointers: &[bool] = match opcode {
+            LDX..=STZI => &[true],
+            _ if opcode > GENLUT => &[false, true],
+            _ => &[false],
+        };
+        for operand in std::iter::once(0).chain(VARIANT_BITS) {
+            for &pointer in pointers {
+                let full_operand = if pointer { operand | address } else { operand };
+                results.push(ProbeResult {
+                    opcode,
+                    operand,
+                    pointer,
+                    outcome: sys::probe(opcode, full_operand)?,
+                });
+            }
+        }
+    }
+
+    Ok(ProbeReport { results })
+}
+
+/// Issue the AMX instruction `op` (which must be in range `0..32`) with
+/// `operand`, skipping the validation by the `checked` feature.
+///
+/// # Safety
+///
+/// See [`nativeops::op_in_unchecked`](crate::nativeops::op_in_unchecked).
+#[cfg(unix)]
+unsafe fn issue(op: u8, operand: u64) {
+    macro_rules! issue {
+        ($($op:literal)*) => {
+            match op {
+                $($op => crate::nativeops::op_in_unchecked::<$op>(operand),)*
+                _ => unreachable!(),
+            }
+        };
+    }
+    issue!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31);
+}
+
+#[cfg(unix)]
+mod sys {
+    use super::{ProbeError, ProbeOutcome};
+    use std::os::raw::c_int;
+
+    extern "C" {
+        fn fork() -> c_int;
+        fn waitpid(pid: c_int, status: *mut c_int, options: c_int) -> c_int;
+        fn _exit(status: c_int) -> !;
+    }
+
+    /// `SIGILL` from `<signal.h>`, which is the same on Linux and macOS
+    const SIGILL: i32 = 4;
+
+    fn last_os_error() -> ProbeError {
+        ProbeError::Os(std::io::Error::last_os_error().raw_os_error().unwrap_or(0))
+    }
+
+    pub(super) fn probe(op: u8, operand: u64) -> Result<ProbeOutcome, ProbeError> {
+        // Safety: The child only issues AMX instructions and calls `_exit`,
+        //         which is safe even if other threads of the parent held
+        //         locks at the time of `fork`
+        let pid = unsafe { fork() };
+        if pid < 0 {
+            return Err(last_os_error());
+        }
+        if pid == 0 {
+            // Safety: The child process owns the AMX state of its only
+            //         thread. The instruction may fault, which only kills the
+            //         child.
+            unsafe {
+                crate::nativeops::set();
+                super::issue(op, operand);
+                crate::nativeops::clr();
+                _exit(0);
+            }
+        }
+
+        let mut status = 0;
+        loop {
+            // Safety: `status` is a valid pointer
+            if unsafe { waitpid(pid, &mut status, 0) } >= 0 {
+                break;
+            }
+            let e = last_os_error();
+            // Retry on `EINTR`
+            if e != ProbeError::Os(4) {
+                return Err(e);
+            }
+        }
+
+        // Decode the status like `WIFEXITED`, `WEXITSTATUS`, and `WTERMSIG`
+        let signal = status & 0x7f;
+        Ok(match signal {
+            0 if (status >> 8) & 0xff == 0 => ProbeOutcome::Accepted,
+            0 => ProbeOutcome::Exited((status >> 8) & 0xff),
+            SIGILL => ProbeOutcome::IllegalInstruction,
+            _ => ProbeOutcome::Signal(signal),
+        })
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use super::{ProbeError, ProbeOutcome};
+
+    pub(super) fn probe(_: u8, _: u64) -> Result<ProbeOutcome, ProbeError> {
+        Err(ProbeError::Unsupported)
+    }
+}
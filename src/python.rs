@@ -0,0 +1,216 @@
+//! Python bindings
+//!
+//! This module defines a Python extension module named `amx`, which wraps
+//! [`AnyAmxCtx`] as the class `amx.Context`. Register rows are loaded from and
+//! stored to one-dimensional NumPy `uint8` arrays; arrays of other element
+//! types can be passed without copying by `array.view(numpy.uint8)`.
+//!
+//! To build the extension module, create a `cdylib` crate (e.g., with
+//! [maturin]) that depends on this crate with the `python` feature and
+//! contains `use amx as _;`. The module initialization function is exported
+//! from this crate.
+//!
+//! ```python
+//! import numpy as np
+//! import amx
+//!
+//! ctx = amx.Context()
+//! ctx.load("x", 0, np.arange(16, dtype=np.float32).view(np.uint8))
+//! print(ctx.store("x", 0).view(np.float32))
+//! print(ctx.matmul_f32(np.eye(4, dtype=np.float32), np.ones((4, 2), dtype=np.float32)))
+//! ```
+//!
+//! [maturin]: https://www.maturin.rs/
+use crate::{kernels, Amx, AnyAmxCtx, XRow, YRow, ZRow};
+use numpy::{
+    PyArray1, PyArray2, PyArrayMethods, PyReadonlyArray1, PyReadonlyArray2, PyUntypedArrayMethods,
+};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::borrow::Cow;
+
+/// An AMX context, which uses the hardware if available and the emulator
+/// otherwise.
+#[pyclass(unsendable, name = "Context", module = "amx")]
+pub struct PyAmxCtx {
+    ctx: AnyAmxCtx,
+}
+
+/// A register row specified by a register set name (`"x"`, `"y"`, or `"z"`)
+/// and a row index.
+enum PyRow {
+    X(XRow),
+    Y(YRow),
+    Z(ZRow),
+}
+
+impl PyRow {
+    /// Parse a register row. `num_rows` is the number of rows accessed by
+    /// the operation.
+    fn parse(register: &str, row: usize, num_rows: usize) -> PyResult<Self> {
+        let (row, len) = match register {
+            "x" => (Self::X(XRow(row)), 8),
+            "y" => (Self::Y(YRow(row)), 8),
+            "z" => (Self::Z(ZRow(row)), 64),
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown register set {:?}; expected \"x\", \"y\", or \"z\"",
+                    register
+                )))
+            }
+        };
+        if row_index(&row) + num_rows > len {
+            return Err(PyValueError::new_err(format!(
+                "row {} is out of range for `{}`",
+                row_index(&row),
+                register
+            )));
+        }
+        Ok(row)
+    }
+}
+
+fn row_index(row: &PyRow) -> usize {
+    match *row {
+        PyRow::X(XRow(i)) | PyRow::Y(YRow(i)) | PyRow::Z(ZRow(i)) => i,
+    }
+}
+
+/// Evaluate `$e` with `$r` bound to the row of the appropriate type.
+macro_rules! with_row {
+    ($row:expr, |$r:ident| $e:expr) => {
+        match $row {
+            PyRow::X($r) => $e,
+            PyRow::Y($r) => $e,
+            PyRow::Z($r) => $e,
+        }
+    };
+}
+
+/// Get a contiguous array's contents without copying.
+fn contiguous<'a>(data: &'a PyReadonlyArray1<'_, u8>, len: usize) -> PyResult<&'a [u8]> {
+    let slice = data
+        .as_slice()
+        .map_err(|_| PyValueError::new_err("the array must be contiguous"))?;
+    if slice.len() != len {
+        return Err(PyValueError::new_err(format!(
+            "the array must have {} bytes, not {}",
+            len,
+            slice.len()
+        )));
+    }
+    Ok(slice)
+}
+
+/// Get a two-dimensional array's contents in row-major order, copying them
+/// only if the array is not C-contiguous.
+fn row_major<'a>(x: &'a PyReadonlyArray2<'_, f32>) -> Cow<'a, [f32]> {
+    match x.as_slice() {
+        Ok(slice) => Cow::Borrowed(slice),
+        Err(_) => Cow::Owned(x.as_array().iter().copied().collect()),
+    }
+}
+
+#[pymethods]
+impl PyAmxCtx {
+    #[new]
+    fn new() -> Self {
+        Self {
+            ctx: AnyAmxCtx::best_available(),
+        }
+    }
+
+    /// Whether this context uses the hardware.
+    #[getter]
+    fn is_native(&self) -> bool {
+        self.ctx.is_native()
+    }
+
+    /// Load 64 bytes from a contiguous `uint8` array to a register row.
+    fn load(&mut self, register: &str, row: usize, data: PyReadonlyArray1<'_, u8>) -> PyResult<()> {
+        let row = PyRow::parse(register, row, 1)?;
+        let data = contiguous(&data, 64)?;
+        // Safety: `data` is 64 bytes long
+        with_row!(row, |r| unsafe { self.ctx.load512(data.as_ptr(), r) });
+        Ok(())
+    }
+
+    /// Load 128 bytes from a contiguous `uint8` array to a register row and
+    /// the next one. The array must be aligned to 128 bytes.
+    fn load_pair(
+        &mut self,
+        register: &str,
+        row: usize,
+        data: PyReadonlyArray1<'_, u8>,
+    ) -> PyResult<()> {
+        let row = PyRow::parse(register, row, 2)?;
+        let data = contiguous(&data, 128)?;
+        if data.as_ptr() as usize % 128 != 0 {
+            return Err(PyValueError::new_err(
+                "the array must be aligned to 128 bytes",
+            ));
+        }
+        // Safety: `data` is 128 bytes long and suitably aligned
+        with_row!(row, |r| unsafe {
+            self.ctx.load1024_aligned(data.as_ptr(), r)
+        });
+        Ok(())
+    }
+
+    /// Store a register row to a new `uint8` array of 64 bytes.
+    fn store<'py>(
+        &mut self,
+        py: Python<'py>,
+        register: &str,
+        row: usize,
+    ) -> PyResult<Bound<'py, PyArray1<u8>>> {
+        let row = PyRow::parse(register, row, 1)?;
+        let mut out = [0u8; 64];
+        // Safety: `out` is 64 bytes long
+        with_row!(row, |r| unsafe { self.ctx.store512(out.as_mut_ptr(), r) });
+        Ok(PyArray1::from_slice(py, &out))
+    }
+
+    /// Get the contents of `x` as a `uint8` array of 512 bytes.
+    fn read_x<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyArray1<u8>> {
+        PyArray1::from_slice(py, &self.ctx.read_x())
+    }
+
+    /// Get the contents of `y` as a `uint8` array of 512 bytes.
+    fn read_y<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyArray1<u8>> {
+        PyArray1::from_slice(py, &self.ctx.read_y())
+    }
+
+    /// Get the contents of `z` as a `uint8` array of 4096 bytes.
+    fn read_z<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyArray1<u8>> {
+        PyArray1::from_slice(py, &self.ctx.read_z())
+    }
+
+    /// Multiply two-dimensional `float32` arrays `a` and `b`. C-contiguous
+    /// arrays are read without copying.
+    fn matmul_f32<'py>(
+        &mut self,
+        py: Python<'py>,
+        a: PyReadonlyArray2<'py, f32>,
+        b: PyReadonlyArray2<'py, f32>,
+    ) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        let (m, k) = (a.shape()[0], a.shape()[1]);
+        let (k2, n) = (b.shape()[0], b.shape()[1]);
+        if k != k2 {
+            return Err(PyValueError::new_err(format!(
+                "shape mismatch: ({}, {}) @ ({}, {})",
+                m, k, k2, n
+            )));
+        }
+        let (a_data, b_data) = (row_major(&a), row_major(&b));
+
+        let mut c = vec![0.0; m * n];
+        kernels::matmul_f32(&mut self.ctx, m, n, k, &a_data, &b_data, &mut c);
+        PyArray1::from_vec(py, c).reshape([m, n])
+    }
+}
+
+/// The Python extension module `amx`.
+#[pymodule]
+pub fn amx(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyAmxCtx>()
+}
@@ -0,0 +1,317 @@
+//! Recording and replaying instruction streams
+use crate::{
+    ops::{opcode::*, AmxOps},
+    AmxAligned,
+};
+use std::io::{self, Read, Write};
+
+/// An AMX instruction captured by [`RecordingOps`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordedOp {
+    /// The operation number, e.g., `0` for `ldx`.
+    pub opcode: u8,
+    /// The operand, excluding the pointer of a load/store instruction.
+    pub operand: u64,
+    /// The bytes read by a load instruction. Empty for other instructions.
+    pub data: Vec<u8>,
+}
+
+/// A sequence of AMX instructions recorded by [`RecordingOps`], which can be
+/// fed to another [`AmxOps`] by [`replay`].
+///
+/// Loads are recorded with a copy of the memory they read, so a trace is
+/// self-contained and can be saved by [`write_to`](Self::write_to) and loaded
+/// later (possibly on another machine) by [`read_from`](Self::read_from).
+/// With the `serde` feature, traces also implement `Serialize` and
+/// `Deserialize`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AmxTrace {
+    /// The recorded instructions, in the order they were issued.
+    pub ops: Vec<RecordedOp>,
+}
+
+/// The magic number at the beginning of a serialized [`AmxTrace`].
+const TRACE_MAGIC: [u8; 8] = *b"AMXTRC01";
+
+impl AmxTrace {
+    /// Serialize `self` in a compact binary format.
+    pub fn write_to(&self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(&TRACE_MAGIC)?;
+        w.write_all(&(self.ops.len() as u64).to_le_bytes())?;
+        for op in &self.ops {
+            w.write_all(&[op.opcode])?;
+            w.write_all(&op.operand.to_le_bytes())?;
+            w.write_all(&(op.data.len() as u32).to_le_bytes())?;
+            w.write_all(&op.data)?;
+        }
+        Ok(())
+    }
+
+    /// Deserialize an `AmxTrace` written by [`write_to`](Self::write_to).
+    pub fn read_from(mut r: impl Read) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if magic != TRACE_MAGIC {
+            return Err(invalid("not an AMX trace"));
+        }
+
+        let mut buf = [0u8; 8];
+        r.read_exact(&mut buf)?;
+        let len = u64::from_le_bytes(buf);
+
+        let mut ops = Vec::new();
+        for _ in 0..len {
+            let mut opcode = [0u8];
+            r.read_exact(&mut opcode)?;
+            let opcode = opcode[0];
+            if opcode > GENLUT || opcode == SET_CLR {
+                return Err(invalid("unknown opcode"));
+            }
+
+            let mut buf = [0u8; 8];
+            r.read_exact(&mut buf)?;
+            let operand = u64::from_le_bytes(buf);
+
+            let mut buf = [0u8; 4];
+            r.read_exact(&mut buf)?;
+            let data_len = u32::from_le_bytes(buf) as usize;
+            if data_len != load_len(opcode, operand) {
+                return Err(invalid("load data length mismatch"));
+            }
+            let mut data = vec![0u8; data_len];
+            r.read_exact(&mut data)?;
+
+            ops.push(RecordedOp {
+                opcode,
+                operand,
+                data,
+            });
+        }
+
+        Ok(Self { ops })
+    }
+}
+
+/// Get the number of bytes read by the instruction. Returns `0` if it's not a
+/// load instruction.
+fn load_len(opcode: u8, operand: u64) -> usize {
+    let pair = operand & (1 << 62) != 0;
+    match opcode {
+        LDX | LDY | LDZ if pair => 128,
+        LDX | LDY | LDZ | LDZI => 64,
+        _ => 0,
+    }
+}
+
+/// Wraps an [`AmxOps`] and records every instruction issued through it into
+/// an [`AmxTrace`] before forwarding it to the inner `AmxOps`.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{Amx, AmxTrace, RecordingOps, XRow};
+/// let mut ctx = RecordingOps::new(amx::AmxEmuCtx::default());
+/// unsafe { ctx.load512([42u8; 64].as_ptr(), XRow(1)) };
+/// let (mut emu, trace) = ctx.into_parts();
+///
+/// // Replay the trace on another backend and compare the results
+/// let mut emu2 = amx::AmxEmuCtx::default();
+/// amx::replay(&trace, &mut emu2);
+/// assert_eq!(emu2.read_x()[..], emu.read_x()[..]);
+/// ```
+#[derive(Debug, Default)]
+pub struct RecordingOps<T> {
+    inner: T,
+    trace: AmxTrace,
+}
+
+impl<T> RecordingOps<T> {
+    /// Wrap `inner` with an empty trace.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            trace: AmxTrace::default(),
+        }
+    }
+
+    /// Get a reference to the instructions recorded so far.
+    pub fn trace(&self) -> &AmxTrace {
+        &self.trace
+    }
+
+    /// Get a mutable reference to the inner `AmxOps`. Instructions issued
+    /// through it are not recorded.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner `AmxOps` and the recorded trace.
+    pub fn into_parts(self) -> (T, AmxTrace) {
+        (self.inner, self.trace)
+    }
+
+    /// Append an instruction to the trace. `ptr` must be valid for the read
+    /// performed by the instruction.
+    unsafe fn record(&mut self, opcode: u8, operand: u64, ptr: *mut ()) {
+        let len = load_len(opcode, operand);
+        let data = if len == 0 {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(ptr as *const u8, len).to_vec()
+        };
+        self.trace.ops.push(RecordedOp {
+            opcode,
+            operand,
+            data,
+        });
+    }
+}
+
+// Safety: Just forwarding the calls
+unsafe impl<T: AmxOps> AmxOps for RecordingOps<T> {
+    unsafe fn ldx(&mut self, x: u64, ptr: *mut ()) {
+        self.record(LDX, x, ptr);
+        self.inner.ldx(x, ptr)
+    }
+    unsafe fn ldy(&mut self, x: u64, ptr: *mut ()) {
+        self.record(LDY, x, ptr);
+        self.inner.ldy(x, ptr)
+    }
+    unsafe fn stx(&mut self, x: u64, ptr: *mut ()) {
+        self.record(STX, x, ptr);
+        self.inner.stx(x, ptr)
+    }
+    unsafe fn sty(&mut self, x: u64, ptr: *mut ()) {
+        self.record(STY, x, ptr);
+        self.inner.sty(x, ptr)
+    }
+    unsafe fn ldz(&mut self, x: u64, ptr: *mut ()) {
+        self.record(LDZ, x, ptr);
+        self.inner.ldz(x, ptr)
+    }
+    unsafe fn stz(&mut self, x: u64, ptr: *mut ()) {
+        self.record(STZ, x, ptr);
+        self.inner.stz(x, ptr)
+    }
+    unsafe fn ldzi(&mut self, x: u64, ptr: *mut ()) {
+        self.record(LDZI, x, ptr);
+        self.inner.ldzi(x, ptr)
+    }
+    unsafe fn stzi(&mut self, x: u64, ptr: *mut ()) {
+        self.record(STZI, x, ptr);
+        self.inner.stzi(x, ptr)
+    }
+    fn extrx(&mut self, x: u64) {
+        unsafe { self.record(EXTRX, x, std::ptr::null_mut()) };
+        self.inner.extrx(x)
+    }
+    fn extry(&mut self, x: u64) {
+        unsafe { self.record(EXTRY, x, std::ptr::null_mut()) };
+        self.inner.extry(x)
+    }
+    fn fma64(&mut self, x: u64) {
+        unsafe { self.record(FMA64, x, std::ptr::null_mut()) };
+        self.inner.fma64(x)
+    }
+    fn fms64(&mut self, x: u64) {
+        unsafe { self.record(FMS64, x, std::ptr::null_mut()) };
+        self.inner.fms64(x)
+    }
+    fn fma32(&mut self, x: u64) {
+        unsafe { self.record(FMA32, x, std::ptr::null_mut()) };
+        self.inner.fma32(x)
+    }
+    fn fms32(&mut self, x: u64) {
+        unsafe { self.record(FMS32, x, std::ptr::null_mut()) };
+        self.inner.fms32(x)
+    }
+    fn mac16(&mut self, x: u64) {
+        unsafe { self.record(MAC16, x, std::ptr::null_mut()) };
+        self.inner.mac16(x)
+    }
+    fn fma16(&mut self, x: u64) {
+        unsafe { self.record(FMA16, x, std::ptr::null_mut()) };
+        self.inner.fma16(x)
+    }
+    fn fms16(&mut self, x: u64) {
+        unsafe { self.record(FMS16, x, std::ptr::null_mut()) };
+        self.inner.fms16(x)
+    }
+    fn vecint(&mut self, x: u64) {
+        unsafe { self.record(VECINT, x, std::ptr::null_mut()) };
+        self.inner.vecint(x)
+    }
+    fn vecfp(&mut self, x: u64) {
+        unsafe { self.record(VECFP, x, std::ptr::null_mut()) };
+        self.inner.vecfp(x)
+    }
+    fn matint(&mut self, x: u64) {
+        unsafe { self.record(MATINT, x, std::ptr::null_mut()) };
+        self.inner.matint(x)
+    }
+    fn matfp(&mut self, x: u64) {
+        unsafe { self.record(MATFP, x, std::ptr::null_mut()) };
+        self.inner.matfp(x)
+    }
+    fn genlut(&mut self, x: u64) {
+        unsafe { self.record(GENLUT, x, std::ptr::null_mut()) };
+        self.inner.genlut(x)
+    }
+}
+
+/// Issue the instructions in `trace` on `ops`.
+///
+/// Loads read the memory contents captured in `trace`. Stores write to a
+/// scratch buffer, whose contents are discarded; use [`Amx`](crate::Amx)'s
+/// methods to inspect the resulting register state.
+///
+/// # Panics
+///
+/// Panics if a load in `trace` doesn't have as many bytes of data as it
+/// reads.
+pub fn replay(trace: &AmxTrace, ops: &mut (impl AmxOps + ?Sized)) {
+    let mut buf = AmxAligned([0u8; 128]);
+    for op in &trace.ops {
+        let (opcode, x) = (op.opcode, op.operand);
+        assert_eq!(
+            op.data.len(),
+            load_len(opcode, x),
+            "load data length mismatch"
+        );
+        buf.0[..op.data.len()].copy_from_slice(&op.data);
+        let ptr = buf.0.as_mut_ptr() as *mut ();
+        // Safety: `buf` is large enough and suitably aligned for any
+        //         load/store
+        unsafe {
+            match opcode {
+                LDX => ops.ldx(x, ptr),
+                LDY => ops.ldy(x, ptr),
+                STX => ops.stx(x, ptr),
+                STY => ops.sty(x, ptr),
+                LDZ => ops.ldz(x, ptr),
+                STZ => ops.stz(x, ptr),
+                LDZI => ops.ldzi(x, ptr),
+                STZI => ops.stzi(x, ptr),
+                EXTRX => ops.extrx(x),
+                EXTRY => ops.extry(x),
+                FMA64 => ops.fma64(x),
+                FMS64 => ops.fms64(x),
+                FMA32 => ops.fma32(x),
+                FMS32 => ops.fms32(x),
+                MAC16 => ops.mac16(x),
+                FMA16 => ops.fma16(x),
+                FMS16 => ops.fms16(x),
+                VECINT => ops.vecint(x),
+                VECFP => ops.vecfp(x),
+                MATINT => ops.matint(x),
+                MATFP => ops.matfp(x),
+                GENLUT => ops.genlut(x),
+                _ => panic!("unknown opcode {}", opcode),
+            }
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Reduction operations
+
+/// Specifies how the elements are combined by a reduction such as
+/// [`Amx::reduce_z_rows_f32`](crate::Amx::reduce_z_rows_f32).
+///
+/// # Example
+///
+/// ```rust
+/// use amx::Reduce;
+/// assert_eq!(Reduce::Sum.apply(2.0, 3.0), 5.0);
+/// assert_eq!(Reduce::Max.apply(2.0, 3.0), 3.0);
+/// assert_eq!(Reduce::Min.apply(2.0, f32::NAN), 2.0);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Reduce {
+    /// The sum of the elements
+    Sum,
+    /// The maximum element. NaNs are ignored unless every element is NaN.
+    Max,
+    /// The minimum element. NaNs are ignored unless every element is NaN.
+    Min,
+}
+
+impl Reduce {
+    /// Combine two elements.
+    #[inline]
+    pub fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            Self::Sum => a + b,
+            Self::Max => a.max(b),
+            Self::Min => a.min(b),
+        }
+    }
+}
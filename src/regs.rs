@@ -1,31 +1,677 @@
 //! AMX registers
+//!
+//! This module defines the types referring to the parts of the AMX register
+//! state:
+//!
+//!  - [`XRow`], [`YRow`], and [`ZRow`] (and their compile-time-checked
+//!    counterparts [`XRowN`], [`YRowN`], and [`ZRowN`]) refer to a 64-byte
+//!    row of a register set.
+//!  - [`XBytes`](type@XBytes), [`YBytes`](type@YBytes), and
+//!    [`ZBytes`](type@ZBytes) refer to a byte offset in a register set.
+//!  - [`ZTile`] describes the `z` rows holding the result of an outer
+//!    product.
+//!
+//! The items are also re-exported at the crate root.
+//!
+//! ```rust
+//! use amx::regs::{XBytes, XRow, ZBytes, ZRow};
+//! use std::convert::TryFrom;
+//! assert_eq!(XRow(3).offset(), XBytes(192));
+//! assert_eq!(ZBytes::from(ZRow(2)), ZBytes(128));
+//! assert_eq!(XRow::try_from(7), Ok(XRow(7)));
+//! assert!(XRow::try_from(8).is_err());
+//! assert_eq!(XRow(7).checked_add(1), None);
+//! ```
+use std::{
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::{Add, AddAssign, Sub, SubAssign},
+};
 
 /// Refers to a row (register) in the `x` register set.
 ///
 /// The row index must be in range `0..8`.
-#[derive(Default, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Default, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct XRow(pub usize);
 
 /// Refers to a row (register) in the `y` register set.
 ///
 /// The row index must be in range `0..8`.
-#[derive(Default, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Default, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct YRow(pub usize);
 
 /// Refers to a row (register) in the `z` register set.
 ///
 /// The row index must be in range `0..64`.
-#[derive(Default, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Default, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct ZRow(pub usize);
 
+/// The error type for the `TryFrom<usize>` implementations of [`XRow`],
+/// [`YRow`], and [`ZRow`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RowOutOfRange {
+    /// The rejected row index.
+    pub index: usize,
+}
+
+macro_rules! impl_row {
+    ($($row:ident => $regs:ident, $num_rows:literal;)*) => {$(
+        impl $row {
+            /// The number of rows in the register set.
+            pub const NUM_ROWS: usize = $num_rows;
+
+            /// Get the byte offset of the first byte of the row.
+            #[inline]
+            pub fn offset(self) -> ByteOffset<$regs> {
+                ByteOffset::from_row_index(self.0)
+            }
+
+            /// Add `rhs` rows, returning `None` if the result is out of range.
+            #[inline]
+            pub fn checked_add(self, rhs: usize) -> Option<Self> {
+                self.0
+                    .checked_add(rhs)
+                    .filter(|&x| x < $num_rows)
+                    .map(Self)
+            }
+
+            /// Subtract `rhs` rows, returning `None` if the result is out of
+            /// range.
+            #[inline]
+            pub fn checked_sub(self, rhs: usize) -> Option<Self> {
+                self.0
+                    .checked_sub(rhs)
+                    .filter(|&x| x < $num_rows)
+                    .map(Self)
+            }
+
+            /// Add `rhs` rows, wrapping around at the end of the register set.
+            #[inline]
+            pub fn wrapping_add(self, rhs: usize) -> Self {
+                Self((self.0 % $num_rows + rhs % $num_rows) % $num_rows)
+            }
+
+            /// Subtract `rhs` rows, wrapping around at the start of the
+            /// register set.
+            #[inline]
+            pub fn wrapping_sub(self, rhs: usize) -> Self {
+                Self((self.0 % $num_rows + $num_rows - rhs % $num_rows) % $num_rows)
+            }
+        }
+
+        impl TryFrom<usize> for $row {
+            type Error = RowOutOfRange;
+
+            /// Construct a row reference, checking that `index` is in range.
+            #[inline]
+            fn try_from(index: usize) -> Result<Self, RowOutOfRange> {
+                if index < $num_rows {
+                    Ok(Self(index))
+                } else {
+                    Err(RowOutOfRange { index })
+                }
+            }
+        }
+    )*};
+}
+
+impl_row! {
+    XRow => XRegs, 8;
+    YRow => YRegs, 8;
+    ZRow => ZRegs, 64;
+}
+
+impl ZRow {
+    /// Get an iterator over the `z` rows holding the 16-bit results of an
+    /// outer product, which is given `self` as the `z` row index.
+    ///
+    /// This is a shorthand for `ZTile16::from_z_index(self).rows()`. See
+    /// [`ZTile`].
+    ///
+    /// ```rust
+    /// use amx::ZRow;
+    /// let rows: Vec<_> = ZRow(1).tile_rows_16bit().take(3).collect();
+    /// assert_eq!(rows, [ZRow(1), ZRow(3), ZRow(5)]);
+    /// assert_eq!(ZRow(1).tile_rows_16bit().len(), 32);
+    /// ```
+    #[inline]
+    pub fn tile_rows_16bit(self) -> impl ExactSizeIterator<Item = ZRow> + DoubleEndedIterator {
+        ZTile16::from_z_index(self).rows()
+    }
+
+    /// Get an iterator over the `z` rows holding the 32-bit results of an
+    /// outer product, which is given `self` as the `z` row index.
+    ///
+    /// This is a shorthand for `ZTile32::from_z_index(self).rows()`. See
+    /// [`ZTile`].
+    #[inline]
+    pub fn tile_rows_32bit(self) -> impl ExactSizeIterator<Item = ZRow> + DoubleEndedIterator {
+        ZTile32::from_z_index(self).rows()
+    }
+
+    /// Get an iterator over the `z` rows holding the 64-bit results of an
+    /// outer product, which is given `self` as the `z` row index.
+    ///
+    /// This is a shorthand for `ZTile64::from_z_index(self).rows()`. See
+    /// [`ZTile`].
+    #[inline]
+    pub fn tile_rows_64bit(self) -> impl ExactSizeIterator<Item = ZRow> + DoubleEndedIterator {
+        ZTile64::from_z_index(self).rows()
+    }
+}
+
+macro_rules! define_const_row {
+    ($(
+        $(#[$meta:meta])*
+        $name:ident => $row:ident, $set:literal, $num_rows:literal;
+    )*) => {$(
+        $(#[$meta])*
+        #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        pub struct $name<const N: usize>;
+
+        impl<const N: usize> $name<N> {
+            /// The equivalent runtime row index. Referring to this constant
+            /// causes a compile error if `N` is out of range.
+            pub const ROW: $row = {
+                assert!(N < $num_rows, concat!("`", $set, "` row index out of range"));
+                $row(N)
+            };
+        }
+
+        impl<const N: usize> From<$name<N>> for $row {
+            #[inline(always)]
+            fn from(_: $name<N>) -> Self {
+                $name::<N>::ROW
+            }
+        }
+    )*};
+}
+
+define_const_row! {
+    /// Refers to the row `N` in the `x` register set, which is checked to be
+    /// in range `0..8` at compile time.
+    ///
+    /// This type can be used in place of [`XRow`] wherever a row index is
+    /// known at compile time. An out-of-range index is rejected when the code
+    /// is monomorphized:
+    ///
+    /// ```rust,compile_fail
+    /// let _ = amx::XRowN::<8>::ROW;
+    /// ```
+    ///
+    /// ```rust
+    /// use amx::{Amx, XRowN};
+    /// let mut ctx = amx::AmxEmuCtx::default();
+    /// unsafe { ctx.load512([1u8; 64].as_ptr(), XRowN::<7>) };
+    /// assert_eq!(ctx.read_x()[448..], [1; 64]);
+    /// ```
+    XRowN => XRow, "x", 8;
+
+    /// Refers to the row `N` in the `y` register set, which is checked to be
+    /// in range `0..8` at compile time. See [`XRowN`].
+    YRowN => YRow, "y", 8;
+
+    /// Refers to the row `N` in the `z` register set, which is checked to be
+    /// in range `0..64` at compile time. See [`XRowN`].
+    ZRowN => ZRow, "z", 64;
+}
+
+/// Describes the `z` rows holding a tile produced by an outer product, which
+/// occupies every `STRIDE`-th row starting from `base`.
+///
+/// Outer products of `N`-byte elements write their results to every `N`-th row
+/// of `z`. [`ZTile16`], [`ZTile32`], and [`ZTile64`] describe the results of
+/// 16-bit, 32-bit, and 64-bit outer products, respectively.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{ZRow, ZTile16};
+/// let tile = ZTile16::from_z_index(ZRow(1));
+/// assert_eq!(tile.row(0), ZRow(1));
+/// assert_eq!(tile.row(5), ZRow(11));
+/// assert_eq!(tile.rows().take(3).collect::<Vec<_>>(), [ZRow(1), ZRow(3), ZRow(5)]);
+/// ```
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ZTile<const STRIDE: usize> {
+    /// The first row of the tile. Must be in range `0..STRIDE`.
+    pub base: usize,
+}
+
+/// The tile of 16-bit results, occupying every second row of `z`.
+pub type ZTile16 = ZTile<2>;
+
+/// The tile of 32-bit results, occupying every fourth row of `z`.
+pub type ZTile32 = ZTile<4>;
+
+/// The tile of 64-bit results, occupying every eighth row of `z`.
+pub type ZTile64 = ZTile<8>;
+
+impl<const STRIDE: usize> ZTile<STRIDE> {
+    /// The number of rows in the tile.
+    pub const LEN: usize = 64 / STRIDE;
+
+    /// Get the tile selected by the `z` row index passed to an outer product
+    /// instruction. Only the lower bits of the index are taken into
+    /// consideration.
+    #[inline]
+    pub const fn from_z_index(ZRow(index): ZRow) -> Self {
+        Self {
+            base: index % STRIDE,
+        }
+    }
+
+    /// Get the `z` row holding the `i`-th row of the tile.
+    ///
+    /// `i` must be in range `0..Self::LEN`.
+    #[inline]
+    pub fn row(self, i: usize) -> ZRow {
+        debug_assert!(i < Self::LEN);
+        ZRow(i * STRIDE + self.base)
+    }
+
+    /// Get an iterator over the `z` rows of the tile, in order.
+    #[inline]
+    pub fn rows(self) -> impl ExactSizeIterator<Item = ZRow> + DoubleEndedIterator {
+        (0..Self::LEN).map(move |i| ZRow(i * STRIDE + self.base))
+    }
+
+    /// Get the `z` row index to pass to an outer product instruction to write
+    /// to this tile.
+    #[inline]
+    pub const fn z_index(self) -> ZRow {
+        ZRow(self.base)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// The trait for marker types identifying a register set. This trait is
+/// sealed.
+pub trait RegSet: private::Sealed {
+    /// The row type of this register set.
+    type Row;
+    /// The name of the corresponding [`ByteOffset`] type alias, used by its
+    /// `Debug` implementation.
+    const BYTES_NAME: &'static str;
+    /// The size of the register set, measured in bytes.
+    const SIZE: usize;
+
+    /// Construct a row reference from a row index.
+    fn row(index: usize) -> Self::Row;
+}
+
+/// Identifies the `x` register set.
+#[derive(Debug)]
+pub enum XRegs {}
+
+/// Identifies the `y` register set.
+#[derive(Debug)]
+pub enum YRegs {}
+
+/// Identifies the `z` register set.
+#[derive(Debug)]
+pub enum ZRegs {}
+
+impl private::Sealed for XRegs {}
+impl private::Sealed for YRegs {}
+impl private::Sealed for ZRegs {}
+
+impl RegSet for XRegs {
+    type Row = XRow;
+    const BYTES_NAME: &'static str = "XBytes";
+    const SIZE: usize = 512;
+
+    #[inline]
+    fn row(index: usize) -> XRow {
+        XRow(index)
+    }
+}
+
+impl RegSet for YRegs {
+    type Row = YRow;
+    const BYTES_NAME: &'static str = "YBytes";
+    const SIZE: usize = 512;
+
+    #[inline]
+    fn row(index: usize) -> YRow {
+        YRow(index)
+    }
+}
+
+impl RegSet for ZRegs {
+    type Row = ZRow;
+    const BYTES_NAME: &'static str = "ZBytes";
+    const SIZE: usize = 4096;
+
+    #[inline]
+    fn row(index: usize) -> ZRow {
+        ZRow(index)
+    }
+}
+
+/// The size of a register row, measured in bytes.
+const ROW_SIZE: usize = 64;
+
+/// The trait for element types that can occupy a lane of a register row. This
+/// trait is sealed.
+///
+/// With the `half` feature, this trait is also implemented for `half::f16`
+/// and `half::bf16`, so 16-bit floating-point numbers can be loaded and
+/// stored (e.g., by [`AmxAligned`](crate::AmxAligned)) without bit-casting
+/// them to `u16`.
+pub trait Lane: private::Sealed + Copy {
+    /// The number of lanes in a register row.
+    const LANES: usize = ROW_SIZE / std::mem::size_of::<Self>();
+}
+
+macro_rules! impl_lane {
+    ($($ty:ty),*) => {$(
+        impl private::Sealed for $ty {}
+        impl Lane for $ty {}
+    )*};
+}
+
+impl_lane!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+#[cfg(feature = "half")]
+impl_lane!(half::f16, half::bf16);
+
+/// A byte offset in the register set `R`.
+///
+/// The byte offset must be in range `0..R::SIZE`. Instructions taking a byte
+/// offset wrap around at the end of the register set, and so do the `+` and
+/// `-` operators of this type.
+///
+/// The type parameter prevents offsets into different register sets from
+/// being mixed up:
+///
+/// ```compile_fail
+/// use amx::{XBytes, YBytes};
+/// let _: YBytes = XBytes(64) + 64;
+/// ```
+pub struct ByteOffset<R>(pub usize, PhantomData<R>);
+
 /// A byte offset in `x` register set.
 ///
 /// The byte offset must be in range `0..512`.
-#[derive(Default, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
-pub struct XBytes(pub usize);
+pub type XBytes = ByteOffset<XRegs>;
 
 /// A byte offset in `y` register set.
 ///
 /// The byte offset must be in range `0..512`.
-#[derive(Default, Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
-pub struct YBytes(pub usize);
+pub type YBytes = ByteOffset<YRegs>;
+
+/// A byte offset in `z` register set.
+///
+/// The byte offset must be in range `0..4096`. No instruction takes a byte
+/// offset into `z`, but this type is useful for locating an element in the
+/// contents of `z` as a whole, e.g., as returned by
+/// [`Amx::read_z`](crate::Amx::read_z).
+pub type ZBytes = ByteOffset<ZRegs>;
+
+/// Construct an [`XBytes`](type@XBytes).
+#[allow(non_snake_case)]
+#[inline]
+pub const fn XBytes(offset: usize) -> XBytes {
+    ByteOffset::new(offset)
+}
+
+/// Construct a [`YBytes`](type@YBytes).
+#[allow(non_snake_case)]
+#[inline]
+pub const fn YBytes(offset: usize) -> YBytes {
+    ByteOffset::new(offset)
+}
+
+/// Construct a [`ZBytes`](type@ZBytes).
+#[allow(non_snake_case)]
+#[inline]
+pub const fn ZBytes(offset: usize) -> ZBytes {
+    ByteOffset::new(offset)
+}
+
+impl<R> ByteOffset<R> {
+    /// Construct a `ByteOffset` from a raw byte offset.
+    #[inline]
+    pub const fn new(offset: usize) -> Self {
+        Self(offset, PhantomData)
+    }
+}
+
+impl<R: RegSet> ByteOffset<R> {
+    /// Get the byte offset of the first byte of the specified row.
+    #[inline]
+    pub fn from_row_index(index: usize) -> Self {
+        Self::new(index * ROW_SIZE)
+    }
+
+    /// Get the byte offset of the `lane`-th `lane_bytes`-byte element of the
+    /// specified row.
+    ///
+    /// `lane_bytes` must be a power of two not greater than 64, and `lane`
+    /// must be less than `64 / lane_bytes`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use amx::XBytes;
+    /// // The sixth `f32` in `x[2]`
+    /// assert_eq!(XBytes::from_row_and_lane(2, 5, 4), XBytes(148));
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn from_row_and_lane(row: usize, lane: usize, lane_bytes: usize) -> Self {
+        debug_assert!(lane_bytes.is_power_of_two() && lane_bytes <= ROW_SIZE);
+        debug_assert!(lane < ROW_SIZE / lane_bytes);
+        Self::new(row * ROW_SIZE + lane * lane_bytes)
+    }
+
+    /// Get the byte offset of the `lane`-th element of type `T` of the
+    /// specified row.
+    ///
+    /// Unlike [`from_row_and_lane`](Self::from_row_and_lane), the lane width
+    /// is determined by the type parameter, so it can't be invalid.
+    ///
+    /// `lane` must be less than `T::LANES`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use amx::XBytes;
+    /// assert_eq!(XBytes::of_element::<f32>(2, 5), XBytes(148));
+    /// assert_eq!(XBytes::of_element::<i16>(7, 31), XBytes(510));
+    /// ```
+    ///
+    /// Types that don't fit in a lane are rejected at compile time:
+    ///
+    /// ```compile_fail
+    /// use amx::XBytes;
+    /// let _ = XBytes::of_element::<u128>(0, 0);
+    /// ```
+    #[inline]
+    #[track_caller]
+    pub fn of_element<T: Lane>(row: usize, lane: usize) -> Self {
+        debug_assert!(lane < T::LANES);
+        Self::new(row * ROW_SIZE + lane * std::mem::size_of::<T>())
+    }
+
+    /// Get the index of the `lane_bytes`-byte element containing the byte at
+    /// this offset within its row. This is the inverse of
+    /// [`from_row_and_lane`](Self::from_row_and_lane) when combined with
+    /// [`row_index`](Self::row_index).
+    #[inline]
+    pub fn lane_index(self, lane_bytes: usize) -> usize {
+        self.offset_in_row() / lane_bytes
+    }
+
+    /// Get the index of the row containing the byte at this offset.
+    #[inline]
+    pub fn row_index(self) -> usize {
+        self.0 / ROW_SIZE
+    }
+
+    /// Get the row containing the byte at this offset.
+    #[inline]
+    pub fn row(self) -> R::Row {
+        R::row(self.row_index())
+    }
+
+    /// Get the position of the byte at this offset within its row.
+    #[inline]
+    pub fn offset_in_row(self) -> usize {
+        self.0 % ROW_SIZE
+    }
+
+    /// Get the byte offset as an operand field of an instruction.
+    #[inline(always)]
+    #[track_caller]
+    pub(crate) fn operand_bits(self) -> u64 {
+        debug_assert!(self.0 < R::SIZE, "{:?} is out of range", self);
+        self.0 as u64
+    }
+
+    /// Add `rhs` bytes, returning `None` if the result is out of range.
+    #[inline]
+    pub fn checked_add(self, rhs: usize) -> Option<Self> {
+        self.0
+            .checked_add(rhs)
+            .filter(|&x| x < R::SIZE)
+            .map(Self::new)
+    }
+
+    /// Subtract `rhs` bytes, returning `None` if the result is out of range.
+    #[inline]
+    pub fn checked_sub(self, rhs: usize) -> Option<Self> {
+        self.0
+            .checked_sub(rhs)
+            .filter(|&x| x < R::SIZE)
+            .map(Self::new)
+    }
+
+    /// Add `rhs` bytes, wrapping around at the end of the register set.
+    #[inline]
+    pub fn wrapping_add(self, rhs: usize) -> Self {
+        Self::new((self.0 % R::SIZE + rhs % R::SIZE) % R::SIZE)
+    }
+
+    /// Subtract `rhs` bytes, wrapping around at the start of the register set.
+    #[inline]
+    pub fn wrapping_sub(self, rhs: usize) -> Self {
+        Self::new((self.0 % R::SIZE + R::SIZE - rhs % R::SIZE) % R::SIZE)
+    }
+}
+
+impl<R: RegSet> Add<usize> for ByteOffset<R> {
+    type Output = Self;
+
+    /// Equivalent to [`wrapping_add`](Self::wrapping_add).
+    #[inline]
+    fn add(self, rhs: usize) -> Self {
+        self.wrapping_add(rhs)
+    }
+}
+
+impl<R: RegSet> Sub<usize> for ByteOffset<R> {
+    type Output = Self;
+
+    /// Equivalent to [`wrapping_sub`](Self::wrapping_sub).
+    #[inline]
+    fn sub(self, rhs: usize) -> Self {
+        self.wrapping_sub(rhs)
+    }
+}
+
+impl<R: RegSet> AddAssign<usize> for ByteOffset<R> {
+    #[inline]
+    fn add_assign(&mut self, rhs: usize) {
+        *self = *self + rhs;
+    }
+}
+
+impl<R: RegSet> SubAssign<usize> for ByteOffset<R> {
+    #[inline]
+    fn sub_assign(&mut self, rhs: usize) {
+        *self = *self - rhs;
+    }
+}
+
+impl From<XRow> for XBytes {
+    #[inline]
+    fn from(x: XRow) -> Self {
+        Self::from_row_index(x.0)
+    }
+}
+
+impl From<YRow> for YBytes {
+    #[inline]
+    fn from(x: YRow) -> Self {
+        Self::from_row_index(x.0)
+    }
+}
+
+impl From<ZRow> for ZBytes {
+    #[inline]
+    fn from(x: ZRow) -> Self {
+        Self::from_row_index(x.0)
+    }
+}
+
+// The following impls are written by hand because `#[derive(...)]` would
+// place unnecessary bounds on `R`.
+
+impl<R> Default for ByteOffset<R> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<R: RegSet> fmt::Debug for ByteOffset<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(R::BYTES_NAME).field(&self.0).finish()
+    }
+}
+
+impl<R> Clone for ByteOffset<R> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R> Copy for ByteOffset<R> {}
+
+impl<R> PartialEq for ByteOffset<R> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<R> Eq for ByteOffset<R> {}
+
+impl<R> PartialOrd for ByteOffset<R> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<R> Ord for ByteOffset<R> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<R> Hash for ByteOffset<R> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
@@ -0,0 +1,50 @@
+//! (De)serializing byte arrays as byte strings, for use with
+//! `#[serde(with = "crate::serde_bytes")]`
+//!
+//! `serde` only implements its traits for arrays of up to 32 elements, and
+//! would encode them as sequences anyway, which is wasteful for register
+//! contents.
+use serde::{de, Deserializer, Serializer};
+use std::{convert::TryInto, fmt};
+
+pub(crate) fn serialize<S: Serializer, const N: usize>(
+    bytes: &[u8; N],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(bytes)
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    deserializer.deserialize_bytes(ByteArrayVisitor::<N>)
+}
+
+struct ByteArrayVisitor<const N: usize>;
+
+impl<'de, const N: usize> de::Visitor<'de> for ByteArrayVisitor<N> {
+    type Value = [u8; N];
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes", N)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        v.try_into().map_err(|_| E::invalid_length(v.len(), &self))
+    }
+
+    // Self-describing formats without a byte string type (e.g., JSON) produce
+    // a sequence instead
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut out = [0u8; N];
+        for (i, b) in out.iter_mut().enumerate() {
+            *b = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(de::Error::invalid_length(N + 1, &self));
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,183 @@
+//! Saving and restoring the AMX state
+use crate::{Amx, XRow, YRow, ZRow};
+use std::{
+    convert::TryInto,
+    fmt,
+    ops::{Index, IndexMut},
+};
+
+/// A copy of the whole contents of the AMX registers.
+///
+/// The AMX state is per-thread, so a computation can't be moved to another
+/// thread while it has intermediate values in the registers. A snapshot, on
+/// the other hand, is an ordinary value that can be sent to another thread,
+/// making it possible to suspend a computation in one thread by
+/// [`save`](Self::save) and resume it in another thread by
+/// [`restore`](Self::restore).
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{Amx, AmxStateSnapshot, XRow};
+/// let mut ctx = amx::AmxCtx::new().unwrap();
+/// unsafe { ctx.load512([42u8; 64].as_ptr(), XRow(1)) };
+/// let snapshot = AmxStateSnapshot::save(&mut *ctx);
+/// drop(ctx);
+///
+/// std::thread::spawn(move || {
+///     let mut ctx = amx::AmxCtx::new().unwrap();
+///     snapshot.restore(&mut *ctx);
+///     assert_eq!(ctx.read_x()[64..128], [42u8; 64]);
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+///
+/// Snapshots can also be compared with each other, which is handy for
+/// checking the outcome of a sequence of instructions. Individual register
+/// rows can be accessed by indexing with [`XRow`], [`YRow`], or [`ZRow`]:
+///
+/// ```rust
+/// use amx::{Amx, AmxStateSnapshot, XRow, ZRow};
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// unsafe { ctx.load512([7u8; 64].as_ptr(), ZRow(3)) };
+///
+/// let mut expected = AmxStateSnapshot::default();
+/// expected[ZRow(3)] = [7; 64];
+/// assert_eq!(ctx.snapshot(), expected);
+/// assert_eq!(expected[XRow(0)], [0; 64]);
+/// ```
+///
+/// With the `serde` feature, snapshots implement `Serialize` and
+/// `Deserialize`, so a register state captured on a device can be loaded into
+/// an emulator-based test.
+///
+/// With the `bytemuck` feature, snapshots implement `Copy` and `Pod`, so they
+/// can be reinterpreted as raw bytes (`x`, `y`, and `z` in this order) and
+/// back.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bytemuck", derive(Copy))]
+#[repr(C)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AmxStateSnapshot {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_bytes"))]
+    x: [u8; 512],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_bytes"))]
+    y: [u8; 512],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_bytes"))]
+    z: [u8; 4096],
+}
+
+// Safety: `AmxStateSnapshot` is `repr(C)` and consists only of `u8` arrays,
+//         so it has no padding and any bit pattern is valid
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Zeroable for AmxStateSnapshot {}
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Pod for AmxStateSnapshot {}
+
+// FIXME: Large arrays do not implement `Default` yet
+impl Default for AmxStateSnapshot {
+    /// Construct a snapshot of all-zero registers.
+    fn default() -> Self {
+        Self {
+            x: [0; 512],
+            y: [0; 512],
+            z: [0; 4096],
+        }
+    }
+}
+
+impl AmxStateSnapshot {
+    /// Read the whole contents of the AMX registers.
+    pub fn save(ctx: &mut (impl Amx + ?Sized)) -> Self {
+        Self {
+            x: ctx.read_x(),
+            y: ctx.read_y(),
+            z: ctx.read_z(),
+        }
+    }
+
+    /// Overwrite the whole contents of the AMX registers with this snapshot.
+    pub fn restore(&self, ctx: &mut (impl Amx + ?Sized)) {
+        ctx.load_all_x(&self.x);
+        ctx.load_all_y(&self.y);
+        ctx.load_all_z(&self.z);
+    }
+
+    /// Get the saved contents of `x`.
+    pub fn x(&self) -> &[u8; 512] {
+        &self.x
+    }
+
+    /// Get the saved contents of `y`.
+    pub fn y(&self) -> &[u8; 512] {
+        &self.y
+    }
+
+    /// Get the saved contents of `z`.
+    pub fn z(&self) -> &[u8; 4096] {
+        &self.z
+    }
+}
+
+/// Get the `i`-th 64-byte row of a register set.
+fn row(regs: &[u8], i: usize) -> &[u8; 64] {
+    regs[i * 64..][..64].try_into().unwrap()
+}
+
+fn row_mut(regs: &mut [u8], i: usize) -> &mut [u8; 64] {
+    (&mut regs[i * 64..][..64]).try_into().unwrap()
+}
+
+macro_rules! impl_index {
+    ($($ty:ident => $field:ident),*$(,)*) => {$(
+        impl Index<$ty> for AmxStateSnapshot {
+            type Output = [u8; 64];
+
+            #[track_caller]
+            fn index(&self, $ty(i): $ty) -> &[u8; 64] {
+                row(&self.$field, i)
+            }
+        }
+
+        impl IndexMut<$ty> for AmxStateSnapshot {
+            #[track_caller]
+            fn index_mut(&mut self, $ty(i): $ty) -> &mut [u8; 64] {
+                row_mut(&mut self.$field, i)
+            }
+        }
+    )*};
+}
+
+impl_index! {
+    XRow => x,
+    YRow => y,
+    ZRow => z,
+}
+
+/// Formats a register row as a hexadecimal string.
+struct HexRow<'a>(&'a [u8]);
+
+impl fmt::Debug for HexRow<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints one register row per entry so that `{:#?}` (and thus a failed
+/// `assert_eq!`) shows a row-by-row view.
+impl fmt::Debug for AmxStateSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn rows(regs: &[u8]) -> Vec<HexRow<'_>> {
+            regs.chunks_exact(64).map(HexRow).collect()
+        }
+        f.debug_struct("AmxStateSnapshot")
+            .field("x", &rows(&self.x))
+            .field("y", &rows(&self.y))
+            .field("z", &rows(&self.z))
+            .finish()
+    }
+}
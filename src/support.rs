@@ -0,0 +1,71 @@
+//! Runtime detection of AMX support
+
+/// Check if AMX is available on the current system, i.e., if
+/// [`AmxCtx::new`](crate::AmxCtx::new) can succeed.
+///
+/// AMX is only usable on Apple silicon running macOS, which preserves the
+/// AMX state of each thread across context switches. This returns `false`
+/// elsewhere, including on other operating systems running on Apple silicon.
+/// On macOS, the `hw.optional.amx_version` sysctl is queried on the first
+/// call, and AMX is deemed unavailable if it's missing or zero (e.g., in a
+/// virtual machine that doesn't expose AMX). The result is cached.
+///
+/// [`kernels::auto`](crate::kernels::auto) uses this to choose between AMX
+/// and portable implementations.
+///
+/// ```rust
+/// if !amx::is_supported() {
+///     println!("AMX is unavailable; falling back to portable code");
+/// }
+/// ```
+#[inline]
+pub fn is_supported() -> bool {
+    #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+    {
+        static SUPPORTED: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+        *SUPPORTED.get_or_init(|| amx_version().is_some_and(|version| version != 0))
+    }
+
+    #[cfg(not(all(target_arch = "aarch64", target_os = "macos")))]
+    {
+        false
+    }
+}
+
+/// Get the value of the `hw.optional.amx_version` sysctl.
+#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+fn amx_version() -> Option<u32> {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    extern "C" {
+        fn sysctlbyname(
+            name: *const c_char,
+            oldp: *mut c_void,
+            oldlenp: *mut usize,
+            newp: *mut c_void,
+            newlen: usize,
+        ) -> c_int;
+    }
+
+    let mut version = 0u32;
+    let mut len = std::mem::size_of_val(&version);
+    // Safety: `name` is a NUL-terminated string, and `version` is valid for
+    //         writing `len` bytes
+    let result = unsafe {
+        sysctlbyname(
+            b"hw.optional.amx_version\0".as_ptr() as *const c_char,
+            &mut version as *mut u32 as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if result != 0 {
+        log::debug!(
+            "sysctlbyname(\"hw.optional.amx_version\") failed: {}",
+            std::io::Error::last_os_error()
+        );
+        return None;
+    }
+    Some(version)
+}
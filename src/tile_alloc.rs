@@ -0,0 +1,358 @@
+//! Runtime-checked ownership of registers
+use crate::regs::{XRow, YRow, ZRow, ZTile};
+use std::{cell::Cell, fmt, marker::PhantomData, ops::Deref};
+
+thread_local! {
+    /// The `z` rows claimed by live `OwnedZTile`s in the current thread, one
+    /// bit per row.
+    static Z_ROWS_IN_USE: Cell<u64> = const { Cell::new(0) };
+}
+
+impl<const STRIDE: usize> ZTile<STRIDE> {
+    /// Get the bit mask of the `z` rows of the tile, where bit `i` represents
+    /// `z[i]`.
+    #[inline]
+    pub fn row_mask(self) -> u64 {
+        self.rows().fold(0, |mask, ZRow(i)| mask | (1 << i))
+    }
+}
+
+/// An exclusive claim on the `z` rows of a [`ZTile`], released when dropped.
+///
+/// The accumulators of independently written kernels can't be told apart by
+/// the hardware. By allocating their tiles through this type, the kernels can
+/// be composed without clobbering each other's accumulators.
+///
+/// Claims are tracked per thread because the AMX state is per-thread, so this
+/// type is not `Send`. Note that this is purely a cooperative mechanism;
+/// nothing prevents code from writing to rows it hasn't claimed.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{OwnedZTile, ZTile16, ZTile32};
+/// let a = OwnedZTile::<2>::alloc().unwrap();
+/// assert_eq!(*a, ZTile16 { base: 0 });
+///
+/// // `ZTile32 { base: 0 }` and `ZTile32 { base: 2 }` overlap with `a`
+/// let b = OwnedZTile::<4>::alloc().unwrap();
+/// assert_eq!(*b, ZTile32 { base: 1 });
+/// let c = OwnedZTile::<4>::alloc().unwrap();
+/// assert_eq!(*c, ZTile32 { base: 3 });
+/// assert!(OwnedZTile::<4>::alloc().is_none());
+///
+/// drop(a);
+/// assert_eq!(*OwnedZTile::<4>::alloc().unwrap(), ZTile32 { base: 0 });
+/// ```
+pub struct OwnedZTile<const STRIDE: usize> {
+    tile: ZTile<STRIDE>,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl<const STRIDE: usize> OwnedZTile<STRIDE> {
+    /// Claim the first tile whose rows are all unclaimed. Returns `None` if
+    /// there's no such tile.
+    pub fn alloc() -> Option<Self> {
+        (0..STRIDE).find_map(|base| Self::alloc_at(ZTile { base }))
+    }
+
+    /// Claim the rows of `tile`. Returns `None` if any of them are already
+    /// claimed.
+    pub fn alloc_at(tile: ZTile<STRIDE>) -> Option<Self> {
+        let mask = tile.row_mask();
+        Z_ROWS_IN_USE.with(|in_use| {
+            if in_use.get() & mask != 0 {
+                return None;
+            }
+            in_use.set(in_use.get() | mask);
+            Some(Self {
+                tile,
+                _not_send: PhantomData,
+            })
+        })
+    }
+
+    /// Get the claimed tile.
+    #[inline]
+    pub fn tile(&self) -> ZTile<STRIDE> {
+        self.tile
+    }
+}
+
+impl<const STRIDE: usize> Deref for OwnedZTile<STRIDE> {
+    type Target = ZTile<STRIDE>;
+
+    #[inline]
+    fn deref(&self) -> &ZTile<STRIDE> {
+        &self.tile
+    }
+}
+
+impl<const STRIDE: usize> Drop for OwnedZTile<STRIDE> {
+    fn drop(&mut self) {
+        let mask = self.tile.row_mask();
+        // Ignore the error if the thread-local storage has already been
+        // destroyed during thread exit
+        let _ = Z_ROWS_IN_USE.try_with(|in_use| in_use.set(in_use.get() & !mask));
+    }
+}
+
+impl<const STRIDE: usize> fmt::Debug for OwnedZTile<STRIDE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OwnedZTile").field(&self.tile).finish()
+    }
+}
+
+/// A pair of disjoint [`OwnedZTile`]s used alternately, so that a pipelined
+/// kernel can store the result held in one tile while the next result
+/// accumulates in the other.
+///
+/// The *front* tile is the one being accumulated into, and the *back* tile
+/// holds the previous result. [`swap`](Self::swap) exchanges their roles
+/// once the front tile is complete.
+///
+/// The two tiles occupy different row residues modulo `STRIDE`, e.g., the
+/// even and odd rows for `PingPongTiles<2>` (16-bit lanes) and the rows `4i`
+/// and `4i + 1` for `PingPongTiles<4>` (32-bit lanes). Pass
+/// [`z_index`](ZTile::z_index) of [`front`](Self::front) to the outer product
+/// so that the hardware writes to the right tile.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{Amx, PingPongTiles, XBytes, XRow, YBytes, YRow};
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// let mut tiles = PingPongTiles::<4>::alloc().unwrap();
+/// let mut out = vec![[0.0f32; 16 * 16]; 3];
+/// for k in 0..=out.len() {
+///     if k < out.len() {
+///         unsafe {
+///             ctx.load512([k as f32; 16].as_ptr(), XRow(0));
+///             ctx.load512([1.0f32; 16].as_ptr(), YRow(0));
+///         }
+///         let z = tiles.front().z_index();
+///         ctx.outer_product_f32_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), z, false);
+///     }
+///     if k > 0 {
+///         // Drain the previous result
+///         unsafe { tiles.store_back(&mut ctx, out[k - 1].as_mut_ptr(), 64) };
+///     }
+///     tiles.swap();
+/// }
+/// assert_eq!(out[2], [2.0; 16 * 16]);
+/// ```
+pub struct PingPongTiles<const STRIDE: usize> {
+    tiles: [OwnedZTile<STRIDE>; 2],
+    /// The index of the front tile in `tiles`.
+    front: usize,
+}
+
+/// [`PingPongTiles`] for 16-bit lanes, using the even and odd rows of `z`.
+pub type PingPongTiles16 = PingPongTiles<2>;
+
+/// [`PingPongTiles`] for 32-bit lanes.
+pub type PingPongTiles32 = PingPongTiles<4>;
+
+/// [`PingPongTiles`] for 64-bit lanes.
+pub type PingPongTiles64 = PingPongTiles<8>;
+
+impl<const STRIDE: usize> PingPongTiles<STRIDE> {
+    /// Claim two unclaimed tiles. Returns `None` if there aren't two such
+    /// tiles.
+    pub fn alloc() -> Option<Self> {
+        let first = OwnedZTile::alloc()?;
+        let second = OwnedZTile::alloc()?;
+        Some(Self::from_tiles(first, second))
+    }
+
+    /// Construct a `PingPongTiles` from two claimed tiles. `front` will be
+    /// accumulated into first.
+    pub fn from_tiles(front: OwnedZTile<STRIDE>, back: OwnedZTile<STRIDE>) -> Self {
+        // `OwnedZTile` guarantees that the tiles are disjoint
+        Self {
+            tiles: [front, back],
+            front: 0,
+        }
+    }
+
+    /// Get the tile being accumulated into.
+    #[inline]
+    pub fn front(&self) -> ZTile<STRIDE> {
+        self.tiles[self.front].tile()
+    }
+
+    /// Get the tile holding the previous result.
+    #[inline]
+    pub fn back(&self) -> ZTile<STRIDE> {
+        self.tiles[self.front ^ 1].tile()
+    }
+
+    /// Exchange the front and back tiles.
+    #[inline]
+    pub fn swap(&mut self) {
+        self.front ^= 1;
+    }
+
+    /// Store the rows of the back tile to memory, the row `i` to
+    /// `(ptr as *mut u8).add(i * ld_bytes)`.
+    ///
+    /// # Safety
+    ///
+    /// `(ptr as *mut u8).add(i * ld_bytes)` must be valid for 64-byte writes
+    /// for every `i` in `0..ZTile::<STRIDE>::LEN`.
+    #[inline]
+    pub unsafe fn store_back<T>(
+        &self,
+        ctx: &mut (impl crate::Amx + ?Sized),
+        ptr: *mut T,
+        ld_bytes: usize,
+    ) {
+        for (i, row) in self.back().rows().enumerate() {
+            ctx.store512((ptr as *mut u8).add(i * ld_bytes), row);
+        }
+    }
+
+    /// Release the tiles, returning the front and back tiles in this order.
+    pub fn into_tiles(self) -> (OwnedZTile<STRIDE>, OwnedZTile<STRIDE>) {
+        let [a, b] = self.tiles;
+        if self.front == 0 {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+}
+
+impl<const STRIDE: usize> fmt::Debug for PingPongTiles<STRIDE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PingPongTiles")
+            .field("front", &self.front())
+            .field("back", &self.back())
+            .finish()
+    }
+}
+
+/// Tracks which of the `x` and `y` rows are in use, so that helper routines
+/// needing scratch rows can be composed without stepping on each other.
+///
+/// Unlike [`OwnedZTile`], this is a plain value owned by the caller, who
+/// passes it (along with an [`Amx`](crate::Amx)) to the routines that need
+/// scratch rows.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{RegAlloc, XRow};
+/// let mut regs = RegAlloc::new();
+/// regs.claim_x(XRow(0));
+/// assert_eq!(regs.alloc_x(), Some(XRow(1)));
+/// regs.free_x(XRow(0));
+/// assert_eq!(regs.alloc_x(), Some(XRow(0)));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RegAlloc {
+    /// The `x` rows in use, one bit per row.
+    x: u8,
+    /// The `y` rows in use, one bit per row.
+    y: u8,
+}
+
+impl RegAlloc {
+    /// Construct a `RegAlloc` with every row free.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { x: 0, y: 0 }
+    }
+
+    /// Allocate the lowest free `x` row. Returns `None` if every row is in
+    /// use.
+    pub fn alloc_x(&mut self) -> Option<XRow> {
+        alloc(&mut self.x).map(XRow)
+    }
+
+    /// Allocate the lowest free `y` row. Returns `None` if every row is in
+    /// use.
+    pub fn alloc_y(&mut self) -> Option<YRow> {
+        alloc(&mut self.y).map(YRow)
+    }
+
+    /// Mark `row` as in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` is already in use or out of range.
+    #[track_caller]
+    pub fn claim_x(&mut self, row: XRow) {
+        claim(&mut self.x, row.0, "x");
+    }
+
+    /// Mark `row` as in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` is already in use or out of range.
+    #[track_caller]
+    pub fn claim_y(&mut self, row: YRow) {
+        claim(&mut self.y, row.0, "y");
+    }
+
+    /// Mark `row` as free.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` is not in use.
+    #[track_caller]
+    pub fn free_x(&mut self, row: XRow) {
+        free(&mut self.x, row.0, "x");
+    }
+
+    /// Mark `row` as free.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` is not in use.
+    #[track_caller]
+    pub fn free_y(&mut self, row: YRow) {
+        free(&mut self.y, row.0, "y");
+    }
+
+    /// Check if `row` is in use.
+    #[inline]
+    pub fn is_x_in_use(&self, row: XRow) -> bool {
+        row.0 < 8 && self.x & (1 << row.0) != 0
+    }
+
+    /// Check if `row` is in use.
+    #[inline]
+    pub fn is_y_in_use(&self, row: YRow) -> bool {
+        row.0 < 8 && self.y & (1 << row.0) != 0
+    }
+}
+
+fn alloc(in_use: &mut u8) -> Option<usize> {
+    let i = (!*in_use).trailing_zeros() as usize;
+    if i < 8 {
+        *in_use |= 1 << i;
+        Some(i)
+    } else {
+        None
+    }
+}
+
+#[track_caller]
+fn claim(in_use: &mut u8, i: usize, regs: &str) {
+    assert!(i < 8, "{}[{}] is out of range", regs, i);
+    assert!(*in_use & (1 << i) == 0, "{}[{}] is already in use", regs, i);
+    *in_use |= 1 << i;
+}
+
+#[track_caller]
+fn free(in_use: &mut u8, i: usize, regs: &str) {
+    assert!(
+        i < 8 && *in_use & (1 << i) != 0,
+        "{}[{}] is not in use",
+        regs,
+        i
+    );
+    *in_use &= !(1 << i);
+}
@@ -0,0 +1,303 @@
+//! Instruction tracing
+use crate::{
+    lane_mask::{LaneMask, X_MASK_POS, Y_MASK_POS},
+    ops::{opcode::*, AmxOps},
+};
+use std::fmt;
+
+/// An AMX instruction observed by [`TracingOps`].
+///
+/// The [`Display`](fmt::Display) impl prints the mnemonic and the decoded
+/// fields of the operand, e.g., `fma32 z_row=5 x_offset=64 y_offset=0
+/// (0x0000000000510000)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TracedOp {
+    /// The operation number, e.g., `0` for `ldx`.
+    pub opcode: u8,
+    /// The raw operand.
+    pub operand: u64,
+    /// The pointer passed to a load/store instruction.
+    pub ptr: Option<*mut ()>,
+}
+
+impl TracedOp {
+    /// Get the mnemonic of the instruction, e.g., `"ldx"`.
+    pub fn mnemonic(&self) -> &'static str {
+        mnemonic(self.opcode)
+    }
+}
+
+impl fmt::Display for TracedOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let x = self.operand;
+        let field = |lo: u32, len: u32| (x >> lo) & ((1 << len) - 1);
+        let flag = |bit: u32| x & (1 << bit) != 0;
+
+        f.write_str(self.mnemonic())?;
+        match self.opcode {
+            LDX | LDY | STX | STY => {
+                write!(f, " row={}", field(56, 5))?;
+                if flag(62) {
+                    f.write_str(" pair")?;
+                }
+                if flag(61) {
+                    f.write_str(" non_temporal")?;
+                }
+                if let Some(ptr) = self.ptr {
+                    write!(f, " ptr={:p}", ptr)?;
+                }
+            }
+            LDZ | STZ | LDZI | STZI => {
+                write!(f, " row={}", field(56, 6))?;
+                if flag(62) && !matches!(self.opcode, LDZI | STZI) {
+                    f.write_str(" pair")?;
+                }
+                if let Some(ptr) = self.ptr {
+                    write!(f, " ptr={:p}", ptr)?;
+                }
+            }
+            EXTRX | EXTRY if flag(26) && !(self.opcode == EXTRY && flag(27)) => {
+                write!(f, " z_column={} width={}", field(20, 6), 8 >> field(28, 2))?;
+                if self.opcode == EXTRX {
+                    write!(f, " x_row={}", field(16, 3))?;
+                } else {
+                    write!(f, " y_row={}", field(6, 3))?;
+                }
+            }
+            EXTRX => write!(f, " z_row={} x_row={}", field(20, 6), field(16, 3))?,
+            EXTRY => {
+                if flag(27) {
+                    write!(f, " z_row={} x_row={}", field(20, 6), field(16, 3))?;
+                } else {
+                    write!(f, " z_row={} y_row={}", field(20, 6), field(6, 3))?;
+                }
+            }
+            FMA64 | FMS64 | FMA32 | FMS32 | MAC16 | FMA16 | FMS16 | MATINT => {
+                write!(
+                    f,
+                    " z_row={} x_offset={} y_offset={}",
+                    field(20, 6),
+                    field(10, 9),
+                    field(0, 9)
+                )?;
+                for &(bit, name) in &[(27, "skip_z"), (28, "skip_x"), (29, "skip_y")] {
+                    if flag(bit) {
+                        write!(f, " {}", name)?;
+                    }
+                }
+                if self.opcode == MATINT {
+                    write!(
+                        f,
+                        " lanes={} alu={} shift={}",
+                        field(42, 4),
+                        field(47, 6),
+                        field(58, 5)
+                    )?;
+                    if flag(56) {
+                        f.write_str(" round")?;
+                    }
+                    if flag(57) {
+                        f.write_str(" saturate")?;
+                    }
+                } else {
+                    for &(pos, name) in &[(X_MASK_POS, "x_mask"), (Y_MASK_POS, "y_mask")] {
+                        let mask = LaneMask::from_operand_bits(x >> pos);
+                        if mask != LaneMask::All {
+                            write!(f, " {}={:?}", name, mask)?;
+                        }
+                    }
+                    if flag(62) {
+                        f.write_str(" widen")?;
+                    }
+                    if flag(63) {
+                        f.write_str(" vector")?;
+                    }
+                }
+            }
+            GENLUT => {
+                let input = if flag(10) { 'y' } else { 'x' };
+                write!(f, " input={}[{}]", input, field(0, 9))?;
+                if flag(26) {
+                    write!(f, " output=z[{}]", field(20, 6))?;
+                } else {
+                    let output = if flag(25) { 'y' } else { 'x' };
+                    write!(f, " output={}[{}]", output, field(20, 3))?;
+                }
+                write!(f, " table=x[{}] mode={}", field(60, 3), field(53, 4))?;
+            }
+            // The operand layouts of the remaining instructions aren't
+            // modeled yet; the raw operand below is all we can show
+            _ => {}
+        }
+        write!(f, " ({:#018x})", x)
+    }
+}
+
+/// Wraps an [`AmxOps`] and reports every instruction issued through it before
+/// forwarding it to the inner `AmxOps`.
+///
+/// By default, instructions are logged at the `trace` level through the [`log`]
+/// crate. Use [`with_callback`](Self::with_callback) to handle them in some
+/// other way.
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{Amx, TracingOps, XBytes, YBytes, ZRow};
+/// let mut trace = Vec::new();
+/// let mut ctx = TracingOps::with_callback(amx::AmxEmuCtx::default(), |op| {
+///     trace.push(op.to_string());
+/// });
+/// ctx.outer_product_f32_xy_to_z(Some(XBytes(64)), Some(YBytes(0)), ZRow(1), false);
+/// assert_eq!(
+///     trace,
+///     ["fma32 z_row=1 x_offset=64 y_offset=0 skip_z (0x0000000008110000)"],
+/// );
+/// ```
+pub struct TracingOps<T, F = fn(&TracedOp)> {
+    inner: T,
+    callback: F,
+}
+
+impl<T> TracingOps<T> {
+    /// Wrap `inner`, logging every instruction through the [`log`] crate.
+    pub fn new(inner: T) -> Self {
+        Self::with_callback(inner, log_op)
+    }
+}
+
+impl<T, F: FnMut(&TracedOp)> TracingOps<T, F> {
+    /// Wrap `inner`, calling `callback` for every instruction.
+    pub fn with_callback(inner: T, callback: F) -> Self {
+        Self { inner, callback }
+    }
+
+    /// Get a reference to the inner `AmxOps`.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner `AmxOps`. Instructions issued
+    /// through it bypass tracing.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner `AmxOps`.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    #[inline]
+    fn emit(&mut self, opcode: u8, operand: u64, ptr: Option<*mut ()>) {
+        (self.callback)(&TracedOp {
+            opcode,
+            operand,
+            ptr,
+        });
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for TracingOps<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TracingOps")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+pub(crate) fn log_op(op: &TracedOp) {
+    log::trace!("{}", op);
+}
+
+// Safety: Just forwarding the calls
+unsafe impl<T: AmxOps, F: FnMut(&TracedOp)> AmxOps for TracingOps<T, F> {
+    unsafe fn ldx(&mut self, x: u64, ptr: *mut ()) {
+        self.emit(LDX, x, Some(ptr));
+        self.inner.ldx(x, ptr)
+    }
+    unsafe fn ldy(&mut self, x: u64, ptr: *mut ()) {
+        self.emit(LDY, x, Some(ptr));
+        self.inner.ldy(x, ptr)
+    }
+    unsafe fn stx(&mut self, x: u64, ptr: *mut ()) {
+        self.emit(STX, x, Some(ptr));
+        self.inner.stx(x, ptr)
+    }
+    unsafe fn sty(&mut self, x: u64, ptr: *mut ()) {
+        self.emit(STY, x, Some(ptr));
+        self.inner.sty(x, ptr)
+    }
+    unsafe fn ldz(&mut self, x: u64, ptr: *mut ()) {
+        self.emit(LDZ, x, Some(ptr));
+        self.inner.ldz(x, ptr)
+    }
+    unsafe fn stz(&mut self, x: u64, ptr: *mut ()) {
+        self.emit(STZ, x, Some(ptr));
+        self.inner.stz(x, ptr)
+    }
+    unsafe fn ldzi(&mut self, x: u64, ptr: *mut ()) {
+        self.emit(LDZI, x, Some(ptr));
+        self.inner.ldzi(x, ptr)
+    }
+    unsafe fn stzi(&mut self, x: u64, ptr: *mut ()) {
+        self.emit(STZI, x, Some(ptr));
+        self.inner.stzi(x, ptr)
+    }
+    fn extrx(&mut self, x: u64) {
+        self.emit(EXTRX, x, None);
+        self.inner.extrx(x)
+    }
+    fn extry(&mut self, x: u64) {
+        self.emit(EXTRY, x, None);
+        self.inner.extry(x)
+    }
+    fn fma64(&mut self, x: u64) {
+        self.emit(FMA64, x, None);
+        self.inner.fma64(x)
+    }
+    fn fms64(&mut self, x: u64) {
+        self.emit(FMS64, x, None);
+        self.inner.fms64(x)
+    }
+    fn fma32(&mut self, x: u64) {
+        self.emit(FMA32, x, None);
+        self.inner.fma32(x)
+    }
+    fn fms32(&mut self, x: u64) {
+        self.emit(FMS32, x, None);
+        self.inner.fms32(x)
+    }
+    fn mac16(&mut self, x: u64) {
+        self.emit(MAC16, x, None);
+        self.inner.mac16(x)
+    }
+    fn fma16(&mut self, x: u64) {
+        self.emit(FMA16, x, None);
+        self.inner.fma16(x)
+    }
+    fn fms16(&mut self, x: u64) {
+        self.emit(FMS16, x, None);
+        self.inner.fms16(x)
+    }
+    fn vecint(&mut self, x: u64) {
+        self.emit(VECINT, x, None);
+        self.inner.vecint(x)
+    }
+    fn vecfp(&mut self, x: u64) {
+        self.emit(VECFP, x, None);
+        self.inner.vecfp(x)
+    }
+    fn matint(&mut self, x: u64) {
+        self.emit(MATINT, x, None);
+        self.inner.matint(x)
+    }
+    fn matfp(&mut self, x: u64) {
+        self.emit(MATFP, x, None);
+        self.inner.matfp(x)
+    }
+    fn genlut(&mut self, x: u64) {
+        self.emit(GENLUT, x, None);
+        self.inner.genlut(x)
+    }
+}
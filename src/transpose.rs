@@ -0,0 +1,30 @@
+//! Transposition of the matrices held in `z`
+use crate::{Amx, ZRow};
+
+/// Transpose each of the interleaved tiles of `elem_size`-byte elements held
+/// in `z`.
+///
+/// The outer product instructions producing `elem_size`-byte elements lay out
+/// `elem_size` square tiles in `z`, each occupying every `elem_size`-th row.
+/// Element `(i, j)` of tile `t` is located at `z[i * elem_size + t][j]`.
+///
+/// There's no instruction to move data across `z` rows, so this is done by a
+/// round trip through memory.
+pub(crate) fn transpose_z(ctx: &mut (impl Amx + ?Sized), elem_size: usize) {
+    let lanes = 64 / elem_size;
+    let z = ctx.read_z();
+    let mut out = [0u8; 4096];
+    for t in 0..elem_size {
+        for i in 0..lanes {
+            for j in 0..lanes {
+                let src = (i * elem_size + t) * 64 + j * elem_size;
+                let dst = (j * elem_size + t) * 64 + i * elem_size;
+                out[dst..][..elem_size].copy_from_slice(&z[src..][..elem_size]);
+            }
+        }
+    }
+    for (i, row) in out.chunks_exact(64).enumerate() {
+        // Safety: Reading a memory region within `out`
+        unsafe { ctx.load512(row.as_ptr(), ZRow(i)) };
+    }
+}
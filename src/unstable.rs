@@ -0,0 +1,123 @@
+//! Instructions and operand bits not modeled by this crate
+//!
+//! **This module is exempt from semantic versioning.** Its items may change
+//! or disappear in any release as the instructions become better understood
+//! (at which point they graduate to the stable API).
+//!
+//! The operand layouts of `vecint`, `vecfp`, and `matfp` aren't modeled (the
+//! emulator doesn't execute them), the other instructions have operand bits
+//! with unknown meanings, and the opcodes following `genlut` aren't known to
+//! be assigned on any processor. This module provides a way to experiment
+//! with them:
+//!
+//!  - [`known_fields`] tells which operand bits are understood, i.e., which
+//!    ones are worth flipping to discover new behavior.
+//!  - [`dispatch`] issues an instruction selected at runtime, e.g., to sweep
+//!    operand bits across all instructions.
+//!  - [`issue`] emits an arbitrary opcode with an arbitrary operand on the
+//!    hardware, bypassing the validation by the `checked` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use amx::unstable::{self, opcode};
+//! let unknown = !unstable::known_fields(opcode::FMA32).unwrap();
+//! println!("unknown `fma32` operand bits: {:#018x}", unknown);
+//! assert_eq!(unstable::known_fields(opcode::VECFP), None);
+//! ```
+use crate::{checked, AmxOps};
+
+/// The opcodes of AMX instructions.
+///
+/// An instruction word is `0x00201000 | (opcode << 5) | register`, so there
+/// are 32 opcodes, of which `0..=22` are known.
+pub mod opcode {
+    pub use crate::ops::opcode::{
+        EXTRX, EXTRY, FMA16, FMA32, FMA64, FMS16, FMS32, FMS64, GENLUT, LDX, LDY, LDZ, LDZI, MAC16,
+        MATFP, MATINT, SET_CLR, STX, STY, STZ, STZI, VECFP, VECINT,
+    };
+
+    /// The number of opcodes representable in an instruction word.
+    pub const COUNT: u8 = 32;
+
+    /// Get the mnemonic of the instruction `op`, or `None` if `op` isn't
+    /// known.
+    pub fn mnemonic(op: u8) -> Option<&'static str> {
+        (op <= GENLUT).then(|| crate::ops::opcode::mnemonic(op))
+    }
+}
+
+/// Get the operand bits of the instruction `op` whose meanings are known.
+///
+/// Returns `None` if the operand layout of `op` isn't modeled at all
+/// (`vecint`, `vecfp`, `matfp`, `set`/`clr`, and the unknown opcodes). For
+/// load and store instructions, the pointer (the lower 56 bits) is included.
+/// The `checked` feature rejects operands with any other bits set.
+pub fn known_fields(op: u8) -> Option<u64> {
+    checked::known_fields(op)
+}
+
+/// Issue the instruction `op` with `operand` through `ctx`.
+///
+/// `ptr` is the pointer operand of a load or store and ignored otherwise.
+///
+/// # Safety
+///
+/// If `op` is a load or store, `ptr` must be valid for the access described
+/// by `operand`. An operand with unknown bits set may make the access larger
+/// than expected.
+///
+/// # Panics
+///
+/// Panics if `op` is `set`/`clr` or isn't a known instruction.
+pub unsafe fn dispatch(ctx: &mut (impl AmxOps + ?Sized), op: u8, operand: u64, ptr: *mut ()) {
+    use opcode::*;
+    match op {
+        LDX => ctx.ldx(operand, ptr),
+        LDY => ctx.ldy(operand, ptr),
+        STX => ctx.stx(operand, ptr),
+        STY => ctx.sty(operand, ptr),
+        LDZ => ctx.ldz(operand, ptr),
+        STZ => ctx.stz(operand, ptr),
+        LDZI => ctx.ldzi(operand, ptr),
+        STZI => ctx.stzi(operand, ptr),
+        EXTRX => ctx.extrx(operand),
+        EXTRY => ctx.extry(operand),
+        FMA64 => ctx.fma64(operand),
+        FMS64 => ctx.fms64(operand),
+        FMA32 => ctx.fma32(operand),
+        FMS32 => ctx.fms32(operand),
+        MAC16 => ctx.mac16(operand),
+        FMA16 => ctx.fma16(operand),
+        FMS16 => ctx.fms16(operand),
+        VECINT => ctx.vecint(operand),
+        VECFP => ctx.vecfp(operand),
+        MATINT => ctx.matint(operand),
+        MATFP => ctx.matfp(operand),
+        GENLUT => ctx.genlut(operand),
+        _ => panic!("opcode {} can't be dispatched", op),
+    }
+}
+
+/// Emit the AMX instruction `OP` with `operand` in a register, skipping the
+/// validation by the `checked` feature.
+///
+/// `OP` must be in range `0..32` and must not be `set`/`clr`, which takes an
+/// immediate. Unlike [`dispatch`], this accepts the unknown opcodes.
+///
+/// # Safety
+///
+/// AMX must be enabled in the current thread (e.g., by
+/// [`AmxCtx`](crate::AmxCtx)). The instruction may access memory at the
+/// address in `operand` or have any other effect, so everything about it is
+/// the caller's responsibility.
+#[cfg(any(doc, target_arch = "aarch64"))]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(target_arch = "aarch64")))]
+#[inline(always)]
+pub unsafe fn issue<const OP: u8>(operand: u64) {
+    const {
+        assert!(OP < opcode::COUNT, "opcode out of range");
+        assert!(OP != opcode::SET_CLR, "`set`/`clr` takes an immediate");
+    }
+    crate::nativeops::op_in_unchecked::<OP>(operand);
+}
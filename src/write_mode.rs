@@ -0,0 +1,77 @@
+//! Write modes of outer products
+
+/// Specifies how the result of an outer product is written to `z`.
+///
+/// [`Subtract`] is only supported by the floating-point outer products. The
+/// integer ones (`mac16` and `matint`) can't subtract and panic if it's
+/// specified.
+///
+/// `bool` converts to this type, `true` meaning [`Accumulate`] and `false`
+/// meaning [`Overwrite`], so the outer product wrappers also accept a plain
+/// "accumulate" flag.
+///
+/// [`Accumulate`]: Self::Accumulate
+/// [`Overwrite`]: Self::Overwrite
+/// [`Subtract`]: Self::Subtract
+///
+/// # Example
+///
+/// ```rust
+/// use amx::{Amx, XBytes, XRow, YBytes, YRow, ZRow, ZWriteMode};
+/// let mut ctx = amx::AmxEmuCtx::default();
+/// unsafe {
+///     ctx.load512([2.0f32; 16].as_ptr(), XRow(0));
+///     ctx.load512([3.0f32; 16].as_ptr(), YRow(0));
+/// }
+/// let (x, y) = (Some(XBytes(0)), Some(YBytes(0)));
+/// ctx.outer_product_f32_xy_to_z(x, y, ZRow(0), ZWriteMode::Overwrite);
+/// ctx.outer_product_f32_xy_to_z(x, y, ZRow(0), ZWriteMode::Accumulate);
+/// ctx.outer_product_f32_xy_to_z(x, y, ZRow(0), ZWriteMode::Subtract);
+///
+/// let mut row = [0.0f32; 16];
+/// unsafe { ctx.store512(row.as_mut_ptr(), ZRow(0)) };
+/// assert_eq!(row, [6.0; 16]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ZWriteMode {
+    /// `z = x * y`
+    Overwrite,
+    /// `z = z + x * y`, performed by `fma*`
+    Accumulate,
+    /// `z = z - x * y`, performed by `fms*` (floating-point only)
+    Subtract,
+}
+
+impl ZWriteMode {
+    /// Get whether the existing values of `z` are read.
+    #[inline(always)]
+    pub(crate) fn reads_z(self) -> bool {
+        self != Self::Overwrite
+    }
+
+    /// Get whether the existing values of `z` are read by an integer outer
+    /// product, which can't subtract.
+    ///
+    /// Panics if `self` is [`Subtract`](Self::Subtract).
+    #[inline(always)]
+    #[track_caller]
+    pub(crate) fn reads_z_int(self) -> bool {
+        assert!(
+            self != Self::Subtract,
+            "integer outer products don't support `ZWriteMode::Subtract`"
+        );
+        self.reads_z()
+    }
+}
+
+impl From<bool> for ZWriteMode {
+    /// Convert an "accumulate" flag.
+    #[inline]
+    fn from(accumulate: bool) -> Self {
+        if accumulate {
+            Self::Accumulate
+        } else {
+            Self::Overwrite
+        }
+    }
+}
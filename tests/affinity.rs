@@ -0,0 +1,28 @@
+use amx::affinity::{self, PinError};
+
+#[test]
+fn pin_to_performance_core() {
+    let result = affinity::pin_to_performance_core();
+    if cfg!(target_os = "macos") {
+        assert_eq!(result, Ok(()));
+    } else {
+        assert_eq!(result, Err(PinError::Unsupported));
+    }
+}
+
+#[test]
+fn pin_to_core() {
+    // Run in a separate thread not to affect other tests
+    let (first, out_of_range) =
+        std::thread::spawn(|| (affinity::pin_to_core(0), affinity::pin_to_core(4096)))
+            .join()
+            .unwrap();
+    if cfg!(target_os = "linux") {
+        // Core 0 may be excluded from the process's CPU set
+        assert_ne!(first, Err(PinError::Unsupported));
+        assert_eq!(out_of_range, Err(PinError::Os(22)));
+    } else {
+        assert_eq!(first, Err(PinError::Unsupported));
+        assert_eq!(out_of_range, Err(PinError::Unsupported));
+    }
+}
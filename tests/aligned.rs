@@ -0,0 +1,40 @@
+use amx::{Amx, AmxAligned, XRow, YRow, ZRow};
+
+fn check_aligned_load_store(ctx: &mut impl Amx) {
+    let src = AmxAligned(std::array::from_fn::<u16, 64, _>(|i| i as u16 * 3));
+    assert_eq!(&src.0 as *const _ as usize % 128, 0);
+    src.load1024(ctx, ZRow(10));
+    src.load512(ctx, YRow(3));
+
+    let mut dst = AmxAligned([0u16; 64]);
+    dst.store1024(ctx, ZRow(10));
+    assert_eq!(dst, src);
+
+    let mut dst = AmxAligned([0u32; 16]);
+    dst.store512(ctx, YRow(3));
+    assert_eq!(
+        dst.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>(),
+        src[..32]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn aligned_load_store_emu() {
+    check_aligned_load_store(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn aligned_load_store_native() {
+    check_aligned_load_store(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic(expected = "buffer too small")]
+fn aligned_too_small() {
+    let buf = AmxAligned([0u8; 64]);
+    buf.load1024(&mut amx::AmxEmuCtx::default(), XRow(0));
+}
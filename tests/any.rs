@@ -0,0 +1,89 @@
+use amx::{Amx, AmxCtxBuilder, AnyAmxCtx, XBytes, XRow, YBytes, YRow, ZRow};
+
+fn check_ops(ctx: &mut impl Amx) {
+    let x: Vec<f32> = (0..16).map(|i| i as f32).collect();
+    let y: Vec<f32> = (0..16).map(|i| 2.0 + i as f32).collect();
+    unsafe {
+        ctx.load512(x.as_ptr(), XRow(0));
+        ctx.load512(y.as_ptr(), YRow(0));
+    }
+    ctx.outer_product_f32_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), false);
+    let mut z = [0.0f32; 16];
+    unsafe { ctx.store512(z.as_mut_ptr(), ZRow(12)) };
+    assert_eq!(z[5], x[5] * y[3]);
+}
+
+#[test]
+fn best_available() {
+    let mut ctx = AnyAmxCtx::best_available();
+    assert_eq!(ctx.is_native(), cfg!(target_arch = "aarch64"));
+    check_ops(&mut ctx);
+}
+
+#[test]
+fn from_emu() {
+    let mut ctx = AnyAmxCtx::from(amx::AmxEmuCtx::default());
+    assert!(!ctx.is_native());
+    check_ops(&mut ctx);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn fallback_if_already_active() {
+    let _native = amx::AmxCtx::new().unwrap();
+    let mut ctx = AnyAmxCtx::best_available();
+    assert!(!ctx.is_native());
+    check_ops(&mut ctx);
+}
+
+#[test]
+fn trait_objects() {
+    let mut boxed: Box<dyn amx::AmxOps> = Box::new(amx::AmxEmuCtx::default());
+    check_ops(&mut boxed);
+
+    let dyn_ref: &mut dyn amx::AmxOps = &mut *boxed;
+    check_ops(&mut &mut *dyn_ref);
+    let mut c = [0.0f32; 1];
+    amx::kernels::matmul_f32(dyn_ref, 1, 1, 1, &[3.0], &[4.0], &mut c);
+    assert_eq!(c, [12.0]);
+
+    // Wrappers can hold trait objects, too
+    let mut ctx = amx::RecordingOps::new(boxed);
+    check_ops(&mut ctx);
+    assert_eq!(ctx.trace().ops.len(), 4);
+}
+
+#[test]
+fn builder_or_emulate() {
+    let mut ctx = AmxCtxBuilder::new().zero_on_drop(true).build_or_emulate();
+    assert_eq!(ctx.is_native(), cfg!(target_arch = "aarch64"));
+    check_ops(&mut ctx);
+}
+
+#[test]
+fn builder_trace() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let mut ctx = AmxCtxBuilder::new()
+        .trace(|_| {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+        })
+        .build_or_emulate();
+    assert!(matches!(ctx, AnyAmxCtx::Traced(_)));
+    assert_eq!(ctx.is_native(), cfg!(target_arch = "aarch64"));
+    check_ops(&mut ctx);
+    assert_eq!(COUNT.load(Ordering::Relaxed), 4);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn builder_native() {
+    let mut ctx = AmxCtxBuilder::new()
+        .state_canary(ZRow(63))
+        .trace_log()
+        .build()
+        .unwrap();
+    assert!(AmxCtxBuilder::new().build().is_err());
+    check_ops(&mut ctx);
+    ctx.native_mut().unwrap().check_state_canary().unwrap();
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use amx::{
+    kernels::{
+        self,
+        backend::{Backend, BackendLane, Portable},
+    },
+    AmxEmuCtx,
+};
+use common::Xorshift32;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+impl Xorshift32 {
+    /// Generate a value in range `-1..1` with a full mantissa, so that
+    /// unfused multiply-adds would round differently
+    fn next_f32(&mut self) -> f32 {
+        self.next() as f32 / 2147483648.0 - 1.0
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.next() as f64 / 2147483648.0 - 1.0
+    }
+}
+
+/// Run a sequence of `Backend` operations on `backend` and return the
+/// contents of `z`.
+fn run_ops<T: BackendLane>(backend: &mut impl Backend<T>, data: &[T]) -> Vec<T> {
+    for i in 0..8 {
+        backend.load_x(i, &data[i * T::LANES..]);
+        backend.load_y(i, &data[(i + 8) * T::LANES..]);
+    }
+    for t in 0..T::TILES {
+        backend.outer_product(t % 8, (t + 3) % 8, t, false);
+        backend.outer_product((t + 1) % 8, t % 8, t, true);
+    }
+    for z in [0, 5, 63] {
+        backend.vector_fma(z % 8, Some((z + 1) % 8), z, true);
+        backend.vector_fma((z + 2) % 8, None, z, true);
+    }
+    backend.vector_fma(7, None, 1, false);
+
+    let mut z = vec![T::default(); 64 * T::LANES];
+    for (i, z_row) in z.chunks_exact_mut(T::LANES).enumerate() {
+        backend.store_z(i, z_row);
+    }
+    z
+}
+
+#[test]
+fn ops_emu_f32() {
+    init();
+    let mut rng = Xorshift32(0x1234567);
+    let data: Vec<f32> = (0..16 * 16).map(|_| rng.next_f32()).collect();
+    let expected = run_ops(&mut AmxEmuCtx::default(), &data);
+    let got = run_ops(&mut Portable::new(), &data);
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn ops_emu_f64() {
+    init();
+    let mut rng = Xorshift32(0x2345678);
+    let data: Vec<f64> = (0..16 * 8).map(|_| rng.next_f64()).collect();
+    let expected = run_ops(&mut AmxEmuCtx::default(), &data);
+    let got = run_ops(&mut Portable::new(), &data);
+    assert_eq!(got, expected);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn ops_native() {
+    init();
+    let mut rng = Xorshift32(0x3456789);
+    let data: Vec<f32> = (0..16 * 16).map(|_| rng.next_f32()).collect();
+    let expected = run_ops(&mut Portable::new(), &data);
+    let got = run_ops(&mut *amx::AmxCtx::new().unwrap(), &data);
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn kernels_emu() {
+    init();
+    let mut rng = Xorshift32(0x4567890);
+    let mut emu = AmxEmuCtx::default();
+    for &(m, n, k) in &[(1, 1, 1), (17, 70, 9), (33, 64, 40)] {
+        let a: Vec<f32> = (0..m * k).map(|_| rng.next_f32()).collect();
+        let b: Vec<f32> = (0..k * n).map(|_| rng.next_f32()).collect();
+        let mut expected = vec![0.0; m * n];
+        let mut got = vec![0.0; m * n];
+        kernels::matmul_f32(&mut emu, m, n, k, &a, &b, &mut expected);
+        kernels::matmul_f32(&mut Portable::new(), m, n, k, &a, &b, &mut got);
+        assert_eq!(got, expected, "{:?}", (m, n, k));
+
+        let a: Vec<f64> = a.iter().map(|&x| x as f64 * 1.1).collect();
+        let b: Vec<f64> = b.iter().map(|&x| x as f64 * 0.9).collect();
+        let mut expected = vec![0.0; m * n];
+        let mut got = vec![0.0; m * n];
+        kernels::matmul_f64(&mut emu, m, n, k, &a, &b, &mut expected);
+        kernels::matmul_f64(&mut Portable::new(), m, n, k, &a, &b, &mut got);
+        assert_eq!(got, expected, "{:?}", (m, n, k));
+    }
+
+    for &len in &[0, 1, 127, 128, 1000] {
+        let a: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+        let b: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+        let mut portable = Portable::new();
+        assert_eq!(
+            kernels::dot_f32(&mut portable, &a, &b),
+            kernels::dot_f32(&mut emu, &a, &b),
+            "{}",
+            len
+        );
+        assert_eq!(
+            kernels::sum_f32(&mut portable, &a),
+            kernels::sum_f32(&mut emu, &a),
+            "{}",
+            len
+        );
+
+        let (mut expected, mut got) = (b.clone(), b.clone());
+        kernels::axpy_f32(&mut emu, -0.5, &a, &mut expected);
+        kernels::axpy_f32(&mut portable, -0.5, &a, &mut got);
+        assert_eq!(got, expected, "{}", len);
+        kernels::scale_f32(&mut emu, 0.0, &mut expected);
+        kernels::scale_f32(&mut portable, 0.0, &mut got);
+        assert_eq!(got, expected, "{}", len);
+        kernels::mul_f32(&mut emu, &a, &b, &mut expected);
+        kernels::mul_f32(&mut portable, &a, &b, &mut got);
+        assert_eq!(got, expected, "{}", len);
+        kernels::add_f32(&mut emu, &a, &b, &mut expected);
+        kernels::add_f32(&mut portable, &a, &b, &mut got);
+        assert_eq!(got, expected, "{}", len);
+    }
+}
+
+#[test]
+#[should_panic]
+fn load_short_slice() {
+    Backend::<f32>::load_x(&mut Portable::new(), 0, &[0.0; 15]);
+}
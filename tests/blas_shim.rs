@@ -0,0 +1,130 @@
+#![cfg(feature = "blas-shim")]
+mod common;
+
+use amx::blas_shim::{
+    cblas_dgemm, cblas_sgemm, CBLAS_COL_MAJOR, CBLAS_CONJ_TRANS, CBLAS_NO_TRANS, CBLAS_ROW_MAJOR,
+    CBLAS_TRANS,
+};
+use common::Xorshift32;
+use std::os::raw::c_int;
+
+impl Xorshift32 {
+    /// Generate a small integer so that the products and their sums are
+    /// exact.
+    fn next_small(&mut self) -> f64 {
+        (self.next() % 17) as f64 - 8.0
+    }
+}
+
+/// Get the index of the element `(i, j)` of a matrix stored in the given
+/// order, optionally transposed.
+fn index(row_major: bool, trans: bool, ld: usize, i: usize, j: usize) -> usize {
+    if row_major != trans {
+        i * ld + j
+    } else {
+        i + j * ld
+    }
+}
+
+#[test]
+fn gemm_layouts() {
+    let mut rng = Xorshift32(0x1234567);
+    let (m, n, k) = (5, 19, 7);
+    for &order in &[CBLAS_ROW_MAJOR, CBLAS_COL_MAJOR] {
+        for &trans_a in &[CBLAS_NO_TRANS, CBLAS_TRANS, CBLAS_CONJ_TRANS] {
+            for &trans_b in &[CBLAS_NO_TRANS, CBLAS_TRANS] {
+                let row_major = order == CBLAS_ROW_MAJOR;
+                let (ta, tb) = (trans_a != CBLAS_NO_TRANS, trans_b != CBLAS_NO_TRANS);
+                // Padded leading dimensions
+                let (lda, ldb, ldc) = (k.max(m) + 3, k.max(n) + 1, m.max(n) + 2);
+                let a: Vec<f64> = (0..lda * k.max(m)).map(|_| rng.next_small()).collect();
+                let b: Vec<f64> = (0..ldb * k.max(n)).map(|_| rng.next_small()).collect();
+                let c: Vec<f64> = (0..ldc * m.max(n)).map(|_| rng.next_small()).collect();
+
+                let mut expected = c.clone();
+                for i in 0..m {
+                    for j in 0..n {
+                        let ab: f64 = (0..k)
+                            .map(|p| {
+                                a[index(row_major, ta, lda, i, p)]
+                                    * b[index(row_major, tb, ldb, p, j)]
+                            })
+                            .sum();
+                        let c = &mut expected[index(row_major, false, ldc, i, j)];
+                        *c = 2.0 * ab - *c;
+                    }
+                }
+
+                let dims = [m, n, k, lda, ldb, ldc].map(|x| x as c_int);
+                let mut got = c.clone();
+                unsafe {
+                    cblas_dgemm(
+                        order,
+                        trans_a,
+                        trans_b,
+                        dims[0],
+                        dims[1],
+                        dims[2],
+                        2.0,
+                        a.as_ptr(),
+                        dims[3],
+                        b.as_ptr(),
+                        dims[4],
+                        -1.0,
+                        got.as_mut_ptr(),
+                        dims[5],
+                    )
+                };
+                assert_eq!(got, expected, "{:?}", (order, trans_a, trans_b));
+
+                let narrow = |x: &[f64]| -> Vec<f32> { x.iter().map(|&x| x as f32).collect() };
+                let mut got = narrow(&c);
+                unsafe {
+                    cblas_sgemm(
+                        order,
+                        trans_a,
+                        trans_b,
+                        dims[0],
+                        dims[1],
+                        dims[2],
+                        2.0,
+                        narrow(&a).as_ptr(),
+                        dims[3],
+                        narrow(&b).as_ptr(),
+                        dims[4],
+                        -1.0,
+                        got.as_mut_ptr(),
+                        dims[5],
+                    )
+                };
+                assert_eq!(got, narrow(&expected), "{:?}", (order, trans_a, trans_b));
+            }
+        }
+    }
+}
+
+#[test]
+fn gemm_invalid_params() {
+    // `lda` is too small; `C` must be left untouched
+    let (a, b) = ([1.0f32; 4], [1.0f32; 4]);
+    let mut c = [f32::NAN; 4];
+    unsafe {
+        cblas_sgemm(
+            CBLAS_ROW_MAJOR,
+            CBLAS_NO_TRANS,
+            CBLAS_NO_TRANS,
+            2,
+            2,
+            2,
+            1.0,
+            a.as_ptr(),
+            1,
+            b.as_ptr(),
+            2,
+            0.0,
+            c.as_mut_ptr(),
+            2,
+        )
+    };
+    assert!(c.iter().all(|x| x.is_nan()));
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "bytemuck")]
+use amx::{Amx, AmxStateSnapshot, XBytes, XRow, YBytes, YRow, ZRow};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+fn check_typed_load_store(ctx: &mut impl Amx) {
+    let x: [f32; 16] = std::array::from_fn(|i| i as f32);
+    let y: [f32; 16] = std::array::from_fn(|i| 1.0 - i as f32);
+    ctx.load512_from(&x, XRow(3));
+    ctx.load512_from(&y, YRow(5));
+    ctx.outer_product_f32_xy_to_z(Some(XBytes(192)), Some(YBytes(320)), ZRow(1), false);
+
+    let mut row = [0.0f32; 16];
+    ctx.store512_into(&mut row, ZRow(9));
+    assert_eq!(row, x.map(|x| x * y[2]));
+
+    let z: [[f32; 16]; 64] = ctx.read_z_as();
+    for i in 0..16 {
+        assert_eq!(z[i * 4 + 1], x.map(|x| x * y[i]));
+    }
+    let xs: [[f32; 16]; 8] = ctx.read_x_as();
+    assert_eq!(xs[3], x);
+    let ys: [[f32; 16]; 8] = ctx.read_y_as();
+    assert_eq!(ys[5], y);
+}
+
+#[test]
+fn typed_load_store_emu() {
+    init();
+    check_typed_load_store(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn typed_load_store_native() {
+    init();
+    check_typed_load_store(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic]
+fn typed_load_size_mismatch() {
+    amx::AmxEmuCtx::default().load512_from(&[0u8; 32], XRow(0));
+}
+
+#[test]
+fn snapshot_pod() {
+    init();
+    let mut ctx = amx::AmxEmuCtx::default();
+    ctx.load512_from(&[7u8; 64], YRow(1));
+    ctx.load512_from(&[9u8; 64], ZRow(63));
+    let snapshot = ctx.snapshot();
+
+    let bytes = bytemuck::bytes_of(&snapshot);
+    assert_eq!(bytes.len(), 512 + 512 + 4096);
+    assert_eq!(bytes[512 + 64..][..64], [7; 64]);
+    assert_eq!(bytes[1024 + 63 * 64..], [9; 64]);
+    assert!(*bytemuck::from_bytes::<AmxStateSnapshot>(bytes) == snapshot);
+    assert!(<AmxStateSnapshot as bytemuck::Zeroable>::zeroed() == AmxStateSnapshot::default());
+}
@@ -0,0 +1,268 @@
+//! Characterization of the operand bits whose meanings are unknown.
+//!
+//! For every instruction and every operand bit that isn't part of a field
+//! modeled by this crate, the instruction is executed with and without the
+//! bit set, starting from the same register and memory state, and the
+//! differences are recorded. A bit with no observable effect is probably
+//! ignored; the others are leads for reverse engineering.
+//!
+//! The tests in this file are ignored by default. Run them on the hardware
+//! by:
+//!
+//! ```text
+//! cargo test --release --test characterize -- --ignored --nocapture
+//! ```
+//!
+//! The report is printed and written to `AMX_CHARACTERIZE_REPORT` (by default,
+//! `characterize.txt` in Cargo's temporary directory for integration tests).
+#![cfg(target_arch = "aarch64")]
+mod common;
+
+use amx::{__opcode::*, Amx, AmxAligned};
+use common::Xorshift32;
+use std::{convert::TryInto, fmt::Write};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+/// Get a mask of `len` bits starting from bit `lo`.
+const fn bits(lo: u32, len: u32) -> u64 {
+    ((1u64 << len) - 1) << lo
+}
+
+/// The pointer and the register row of a load/store operand, which aren't
+/// flipped because doing so would access arbitrary memory.
+const MEM_FIXED: u64 = bits(0, 56) | bits(56, 3);
+
+/// The known fields of an `fma*` or `mac16` operand.
+const FMA_KNOWN: u64 =
+    bits(0, 9) | bits(10, 9) | bits(20, 6) | bits(27, 3) | bits(32, 7) | bits(41, 7) | bits(63, 1);
+
+/// An instruction to characterize.
+struct Subject {
+    name: &'static str,
+    opcode: u8,
+    /// The operand executed as it is for the reference state, excluding the
+    /// pointer of a load/store.
+    base: u64,
+    /// The bits not to flip, either because they're known or because they
+    /// can't be flipped safely.
+    known: u64,
+}
+
+fn subjects() -> Vec<Subject> {
+    let subject = |name, opcode, base, known| Subject {
+        name,
+        opcode,
+        base,
+        known,
+    };
+    // `z` row 1, `x` offset 64, `y` offset 128
+    let outer = (1 << 20) | (64 << 10) | 128;
+    vec![
+        subject("ldx", LDX, 1 << 56, MEM_FIXED | bits(62, 1)),
+        subject("ldy", LDY, 1 << 56, MEM_FIXED | bits(62, 1)),
+        subject("stx", STX, 1 << 56, MEM_FIXED | bits(62, 1)),
+        subject("sty", STY, 1 << 56, MEM_FIXED | bits(62, 1)),
+        subject("ldz", LDZ, 5 << 56, MEM_FIXED | bits(59, 3) | bits(62, 1)),
+        subject("stz", STZ, 5 << 56, MEM_FIXED | bits(59, 3) | bits(62, 1)),
+        subject("ldzi", LDZI, 5 << 56, MEM_FIXED | bits(59, 3)),
+        subject("stzi", STZI, 5 << 56, MEM_FIXED | bits(59, 3)),
+        subject(
+            "extrx",
+            EXTRX,
+            (1 << 16) | (3 << 20),
+            bits(16, 3) | bits(20, 7) | bits(28, 2),
+        ),
+        subject(
+            "extry",
+            EXTRY,
+            (1 << 6) | (3 << 20),
+            bits(6, 3) | bits(16, 3) | bits(20, 7) | bits(27, 3),
+        ),
+        subject("fma64", FMA64, outer, FMA_KNOWN),
+        subject("fms64", FMS64, outer, FMA_KNOWN),
+        subject("fma32", FMA32, outer, FMA_KNOWN),
+        subject("fms32", FMS32, outer, FMA_KNOWN),
+        subject("mac16", MAC16, outer, FMA_KNOWN | bits(62, 1)),
+        subject("fma16", FMA16, outer, FMA_KNOWN | bits(62, 1)),
+        subject("fms16", FMS16, outer, FMA_KNOWN | bits(62, 1)),
+        subject(
+            "matint",
+            MATINT,
+            outer | (10 << 42),
+            bits(0, 9)
+                | bits(10, 9)
+                | bits(20, 6)
+                | bits(27, 3)
+                | bits(42, 4)
+                | bits(47, 6)
+                | bits(56, 2)
+                | bits(58, 5),
+        ),
+        subject(
+            "genlut",
+            GENLUT,
+            64 | (1 << 26) | (2 << 20) | (7 << 60),
+            bits(0, 9) | bits(10, 1) | bits(20, 7) | bits(53, 4) | bits(60, 3),
+        ),
+        // The operand layouts of these aren't modeled at all
+        subject("vecint", VECINT, outer, 0),
+        subject("vecfp", VECFP, outer, 0),
+        subject("matfp", MATFP, outer, 0),
+    ]
+}
+
+/// The register state and the memory buffer after executing an instruction.
+#[derive(PartialEq)]
+struct Outcome {
+    x: [u8; 512],
+    y: [u8; 512],
+    z: [u8; 4096],
+    mem: Vec<u8>,
+}
+
+/// The size of the memory buffer. The pointer passed to a load/store points
+/// to its middle, leaving room for accesses larger than expected.
+const MEM_LEN: usize = 2048;
+
+/// Execute `opcode` with `operand` from a fixed initial state.
+fn execute(ctx: &mut impl Amx, opcode: u8, operand: u64) -> Outcome {
+    let mut rng = Xorshift32(0x2545f491);
+    let mut gen = |len: usize| -> Vec<u8> { (0..len).map(|_| (rng.next() % 0x40) as u8).collect() };
+    let (x, y, z) = (gen(512), gen(512), gen(4096));
+    ctx.load_all_x(x[..].try_into().unwrap());
+    ctx.load_all_y(y[..].try_into().unwrap());
+    ctx.load_all_z(z[..].try_into().unwrap());
+    let mut mem = Box::new(AmxAligned([0u8; MEM_LEN]));
+    mem.0.copy_from_slice(&gen(MEM_LEN));
+
+    let ptr = mem.0[MEM_LEN / 2..].as_mut_ptr() as *mut ();
+    // Safety: `ptr` is valid for any access of the known sizes, with a
+    //         generous margin for unknown ones
+    unsafe {
+        match opcode {
+            LDX => ctx.ldx(operand, ptr),
+            LDY => ctx.ldy(operand, ptr),
+            STX => ctx.stx(operand, ptr),
+            STY => ctx.sty(operand, ptr),
+            LDZ => ctx.ldz(operand, ptr),
+            STZ => ctx.stz(operand, ptr),
+            LDZI => ctx.ldzi(operand, ptr),
+            STZI => ctx.stzi(operand, ptr),
+            EXTRX => ctx.extrx(operand),
+            EXTRY => ctx.extry(operand),
+            FMA64 => ctx.fma64(operand),
+            FMS64 => ctx.fms64(operand),
+            FMA32 => ctx.fma32(operand),
+            FMS32 => ctx.fms32(operand),
+            MAC16 => ctx.mac16(operand),
+            FMA16 => ctx.fma16(operand),
+            FMS16 => ctx.fms16(operand),
+            VECINT => ctx.vecint(operand),
+            VECFP => ctx.vecfp(operand),
+            MATINT => ctx.matint(operand),
+            MATFP => ctx.matfp(operand),
+            GENLUT => ctx.genlut(operand),
+            _ => unreachable!(),
+        }
+    }
+
+    Outcome {
+        x: ctx.read_x(),
+        y: ctx.read_y(),
+        z: ctx.read_z(),
+        mem: mem.0.to_vec(),
+    }
+}
+
+/// Describe how `got` differs from `reference`.
+fn describe_diff(reference: &Outcome, got: &Outcome) -> String {
+    let rows = |a: &[u8], b: &[u8]| -> Vec<usize> {
+        (0..a.len() / 64)
+            .filter(|&i| a[i * 64..][..64] != b[i * 64..][..64])
+            .collect()
+    };
+    let mut out = String::new();
+    for &(name, a, b) in &[
+        ("x", &reference.x[..], &got.x[..]),
+        ("y", &reference.y[..], &got.y[..]),
+        ("z", &reference.z[..], &got.z[..]),
+    ] {
+        let rows = rows(a, b);
+        if !rows.is_empty() {
+            write!(out, " {} rows {:?};", name, rows).unwrap();
+        }
+    }
+    let mem: Vec<usize> = (0..MEM_LEN)
+        .filter(|&i| reference.mem[i] != got.mem[i])
+        .collect();
+    if let (Some(first), Some(last)) = (mem.first(), mem.last()) {
+        let base = MEM_LEN as isize / 2;
+        write!(
+            out,
+            " memory bytes {}..={} relative to the pointer;",
+            *first as isize - base,
+            *last as isize - base
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[test]
+#[ignore]
+fn characterize_unknown_bits() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    let mut report = String::new();
+
+    for subject in subjects() {
+        let reference = execute(&mut *ctx, subject.opcode, subject.base);
+        for bit in (0..64).filter(|&bit| subject.known & (1 << bit) == 0) {
+            let operand = subject.base ^ (1 << bit);
+            log::debug!("{} {:#018x}", subject.name, operand);
+            let got = execute(&mut *ctx, subject.opcode, operand);
+            let effect = if got == reference {
+                " no effect".to_owned()
+            } else {
+                describe_diff(&reference, &got)
+            };
+            writeln!(report, "{} bit {}:{}", subject.name, bit, effect).unwrap();
+        }
+    }
+
+    print!("{}", report);
+    let path = std::env::var("AMX_CHARACTERIZE_REPORT")
+        .unwrap_or_else(|_| concat!(env!("CARGO_TARGET_TMPDIR"), "/characterize.txt").to_owned());
+    std::fs::write(&path, report).unwrap();
+    println!("The report was written to {}", path);
+}
+
+/// Check whether bit 62, which selects the 128-byte form of the other loads
+/// and stores, has any effect on `ldzi` and `stzi`, i.e., whether they have a
+/// 128-byte form. This fails if they do. It hasn't been run on the hardware
+/// yet, so `Amx` doesn't expose such a form, and the `checked` feature still
+/// allows the bit.
+#[test]
+#[ignore]
+fn characterize_interleaved_1024() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    for &(name, opcode) in &[("ldzi", LDZI), ("stzi", STZI)] {
+        // Both halves of the row pair `z[4..6]`
+        for row in [4u64, 5] {
+            let operand = row << 56;
+            let reference = execute(&mut *ctx, opcode, operand);
+            let got = execute(&mut *ctx, opcode, operand | (1 << 62));
+            assert!(
+                got == reference,
+                "{} z row {} bit 62:{}",
+                name,
+                row,
+                describe_diff(&reference, &got)
+            );
+        }
+    }
+}
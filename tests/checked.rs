@@ -0,0 +1,54 @@
+#![cfg(feature = "checked")]
+use amx::{prelude::*, XBytes, XRow, YBytes, ZRow};
+
+#[test]
+#[should_panic(expected = "reserved bits set")]
+fn fma_reserved_bits() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    // Bit 9 lies between the `y` and `x` offsets
+    ctx.fma32(1 << 9);
+}
+
+#[test]
+#[should_panic(expected = "register row (9) is out of range")]
+fn load_row_out_of_range() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    let buf = [0u8; 64];
+    unsafe { ctx.ldx((9 << 56) | buf.as_ptr() as u64, buf.as_ptr() as *mut ()) };
+}
+
+#[test]
+fn interleaved_load_1024() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    let buf = [0u8; 128];
+    // Bit 62 selects the 128-byte form of `ldz`. Whether `ldzi` has one is
+    // unverified, so it isn't rejected.
+    unsafe { ctx.ldzi(1 << 62, buf.as_ptr() as *mut ()) };
+}
+
+#[test]
+#[should_panic(expected = "`genlut` operand")]
+fn genlut_output_row_out_of_range() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    ctx.genlut(8 << 20);
+}
+
+#[test]
+fn wrappers_pass_validation() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    let buf = [1u8; 128];
+    unsafe {
+        ctx.load512(buf.as_ptr(), XRow(7));
+        ctx.load512(buf.as_ptr(), amx::YRow(7));
+    }
+    ctx.clear_z();
+    ctx.outer_product_f32_xy_to_z(Some(XBytes(448)), Some(YBytes(448)), ZRow(63), true);
+    ctx.outer_product_i16_xy_to_z(None, Some(YBytes(0)), ZRow(0), false);
+    ctx.copy_z_row_to_x(ZRow(63), XRow(7));
+    ctx.lut(
+        XBytes(0),
+        XRow(1),
+        ZRow(5),
+        (amx::Normal, amx::Index4, amx::X8),
+    );
+}
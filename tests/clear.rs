@@ -0,0 +1,81 @@
+use amx::{Amx, XRow, YRow, ZRow};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+/// Fill every register with `0xff`.
+fn fill_state(ctx: &mut impl Amx) {
+    let ones = [0xffu8; 64];
+    for i in 0..8 {
+        unsafe {
+            ctx.load512(ones.as_ptr(), XRow(i));
+            ctx.load512(ones.as_ptr(), YRow(i));
+        }
+    }
+    for i in 0..64 {
+        unsafe { ctx.load512(ones.as_ptr(), ZRow(i)) };
+    }
+}
+
+fn check_clear(ctx: &mut impl Amx) {
+    fill_state(ctx);
+    ctx.clear_x();
+    assert_eq!(ctx.read_x(), [0; 512]);
+    assert_eq!(ctx.read_y(), [0xff; 512]);
+    assert_eq!(ctx.read_z(), [0xff; 4096]);
+
+    fill_state(ctx);
+    ctx.clear_y();
+    assert_eq!(ctx.read_x(), [0xff; 512]);
+    assert_eq!(ctx.read_y(), [0; 512]);
+    assert_eq!(ctx.read_z(), [0xff; 4096]);
+
+    fill_state(ctx);
+    ctx.clear_z();
+    assert_eq!(ctx.read_x(), [0xff; 512]);
+    assert_eq!(ctx.read_y(), [0xff; 512]);
+    assert_eq!(ctx.read_z(), [0; 4096]);
+
+    fill_state(ctx);
+    ctx.clear_all();
+    assert_eq!(ctx.read_x(), [0; 512]);
+    assert_eq!(ctx.read_y(), [0; 512]);
+    assert_eq!(ctx.read_z(), [0; 4096]);
+
+    for &row in &[0, 1, 7, 8, 42, 63] {
+        log::debug!("row = {}", row);
+        fill_state(ctx);
+        ctx.clear_z_row(ZRow(row));
+        let mut expected_z = [0xffu8; 4096];
+        expected_z[row * 64..][..64].fill(0);
+        assert_eq!(ctx.read_x(), [0xff; 512]);
+        assert_eq!(ctx.read_y(), [0xff; 512]);
+        assert_eq!(ctx.read_z(), expected_z);
+    }
+}
+
+#[test]
+fn clear_emu() {
+    init();
+    check_clear(&mut amx::AmxEmuCtx::default());
+}
+
+#[test]
+fn reset_emu() {
+    init();
+    let mut ctx = amx::AmxEmuCtx::with_strictness(amx::EmuStrictness::Strict);
+    fill_state(&mut ctx);
+    ctx.reset();
+    assert_eq!(ctx.read_x(), [0; 512]);
+    assert_eq!(ctx.read_y(), [0; 512]);
+    assert_eq!(ctx.read_z(), [0; 4096]);
+    assert_eq!(ctx.strictness(), amx::EmuStrictness::Strict);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn clear_native() {
+    init();
+    check_clear(&mut *amx::AmxCtx::new().unwrap());
+}
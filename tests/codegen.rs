@@ -0,0 +1,91 @@
+//! Checks that the single-instruction `Amx` methods instantiated in
+//! `examples/codegen.rs` compile to exactly one AMX instruction without any
+//! calls, i.e., that the wrapper layer is zero-overhead. A missed inlining
+//! wouldn't cause any functional failures, so we have to look at the
+//! generated code to catch it.
+#![cfg(target_arch = "aarch64")]
+use std::{fs, path::Path, process::Command};
+
+/// Build `examples/codegen.rs` in release mode and get the assembly listing.
+fn example_asm() -> String {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("codegen");
+    let status = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()))
+        .args([
+            "rustc",
+            "--release",
+            "--example",
+            "codegen",
+            "--manifest-path",
+        ])
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .args(["--", "--emit", "asm", "-C", "codegen-units=1"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let examples_dir = target_dir.join("release/examples");
+    let asm_path = fs::read_dir(&examples_dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| {
+            let name = p.file_name().unwrap().to_str().unwrap();
+            name.starts_with("codegen-") && name.ends_with(".s")
+        })
+        .max_by_key(|p| fs::metadata(p).unwrap().modified().unwrap())
+        .expect("assembly listing not found");
+    fs::read_to_string(asm_path).unwrap()
+}
+
+/// Split the listing into the bodies of `amx_codegen_*` functions.
+fn functions(asm: &str) -> Vec<(&str, Vec<&str>)> {
+    let mut out: Vec<(&str, Vec<&str>)> = Vec::new();
+    let mut current = None;
+    for line in asm.lines() {
+        let line = line.trim();
+        if let Some(label) = line.strip_suffix(':') {
+            // Mach-O prefixes symbol names with an underscore
+            let name = label.trim_start_matches('_');
+            if name.starts_with("amx_codegen_") {
+                out.push((name, Vec::new()));
+                current = Some(out.len() - 1);
+                continue;
+            }
+        }
+        if line.starts_with(".cfi_endproc") {
+            current = None;
+        }
+        if let Some(i) = current {
+            out[i].1.push(line);
+        }
+    }
+    out
+}
+
+#[test]
+fn wrappers_compile_to_single_instruction() {
+    let asm = example_asm();
+    let functions = functions(&asm);
+
+    let expected = include_str!("../examples/codegen.rs")
+        .matches("fn amx_codegen_")
+        .count();
+    assert_eq!(functions.len(), expected, "missing functions in:\n{}", asm);
+
+    for (name, body) in &functions {
+        let listing = body.join("\n");
+        let num_amx_insts = body.iter().filter(|l| l.starts_with(".word")).count();
+        assert_eq!(
+            num_amx_insts, 1,
+            "`{}` should contain exactly one AMX instruction:\n{}",
+            name, listing
+        );
+
+        let has_call = body.iter().any(|l| {
+            let mnemonic = l.split_whitespace().next().unwrap_or("");
+            matches!(mnemonic, "bl" | "blr" | "br") || (mnemonic == "b" && !l.contains("LBB"))
+        });
+        assert!(!has_call, "`{}` contains a call:\n{}", name, listing);
+    }
+}
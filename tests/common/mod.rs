@@ -0,0 +1,14 @@
+//! Utilities shared by the integration tests
+
+/// A xorshift32 pseudorandom number generator. The tests add their own
+/// methods to generate the values they need.
+pub struct Xorshift32(pub u32);
+
+impl Xorshift32 {
+    pub fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+}
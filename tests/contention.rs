@@ -0,0 +1,45 @@
+#![cfg(target_arch = "aarch64")]
+use amx::contention::{ContentionBench, ContentionOp, Placement};
+use std::time::Duration;
+
+#[test]
+fn run() {
+    let report = ContentionBench::new(ContentionOp::Mac16)
+        .duration(Duration::from_millis(50))
+        .thread(Placement::Any)
+        .threads(2, Placement::Performance)
+        .run();
+    assert_eq!(report.op, ContentionOp::Mac16);
+    assert!(report.elapsed >= Duration::from_millis(50), "{:?}", report);
+
+    let placements: Vec<_> = report.threads.iter().map(|t| t.placement).collect();
+    assert_eq!(
+        placements,
+        [
+            Placement::Any,
+            Placement::Performance,
+            Placement::Performance
+        ]
+    );
+    for thread in &report.threads {
+        assert!(thread.instructions > 0, "{:?}", thread);
+    }
+    assert!(report.gops() > 0.0, "{:?}", report);
+}
+
+#[test]
+fn sweep() {
+    let reports = ContentionBench::new(ContentionOp::Fma32)
+        .duration(Duration::from_millis(20))
+        .sweep(3, Placement::Any);
+    let counts: Vec<_> = reports.iter().map(|r| r.threads.len()).collect();
+    assert_eq!(counts, [1, 2, 3]);
+}
+
+#[test]
+fn ops_per_instruction() {
+    // 32 × 32 multiply-adds of 16-bit integers
+    assert_eq!(ContentionOp::Mac16.ops_per_instruction(), 2048);
+    assert_eq!(ContentionOp::Fma32.ops_per_instruction(), 512);
+    assert_eq!(ContentionOp::Fma64.ops_per_instruction(), 128);
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use amx::{Amx, AmxElement, XRow, YRow, ZRow};
+use common::Xorshift32;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+/// Fill every register row with a distinct byte pattern. `x[i]` is filled
+/// with `i + 1`, `y[i]` with `i + 0x11`, and `z[i]` with `i + 0x41`.
+fn fill_state(ctx: &mut impl Amx) {
+    for i in 0..8 {
+        unsafe {
+            ctx.load512([i as u8 + 0x01; 64].as_ptr(), XRow(i));
+            ctx.load512([i as u8 + 0x11; 64].as_ptr(), YRow(i));
+        }
+    }
+    for i in 0..64 {
+        unsafe { ctx.load512([i as u8 + 0x41; 64].as_ptr(), ZRow(i)) };
+    }
+}
+
+fn expected_state() -> ([u8; 512], [u8; 512], [u8; 4096]) {
+    let mut x = [0; 512];
+    let mut y = [0; 512];
+    let mut z = [0; 4096];
+    for i in 0..8 {
+        x[i * 64..][..64].fill(i as u8 + 0x01);
+        y[i * 64..][..64].fill(i as u8 + 0x11);
+    }
+    for i in 0..64 {
+        z[i * 64..][..64].fill(i as u8 + 0x41);
+    }
+    (x, y, z)
+}
+
+fn check_copy(ctx: &mut impl Amx) {
+    for &(z_row, xy_row) in &[(0, 0), (9, 3), (63, 7)] {
+        log::debug!("(z_row, xy_row) = {:?}", (z_row, xy_row));
+
+        fill_state(ctx);
+        ctx.copy_z_row_to_x(ZRow(z_row), XRow(xy_row));
+        let (mut x, y, z) = expected_state();
+        x[xy_row * 64..][..64].fill(z_row as u8 + 0x41);
+        assert_eq!((ctx.read_x(), ctx.read_y(), ctx.read_z()), (x, y, z));
+
+        fill_state(ctx);
+        ctx.copy_z_row_to_y(ZRow(z_row), YRow(xy_row));
+        let (x, mut y, z) = expected_state();
+        y[xy_row * 64..][..64].fill(z_row as u8 + 0x41);
+        assert_eq!((ctx.read_x(), ctx.read_y(), ctx.read_z()), (x, y, z));
+    }
+
+    for &(x_row, y_row) in &[(0, 0), (2, 5), (7, 1)] {
+        log::debug!("(x_row, y_row) = {:?}", (x_row, y_row));
+
+        fill_state(ctx);
+        ctx.copy_x_to_y(XRow(x_row), YRow(y_row));
+        let (x, mut y, z) = expected_state();
+        y[y_row * 64..][..64].fill(x_row as u8 + 0x01);
+        assert_eq!((ctx.read_x(), ctx.read_y(), ctx.read_z()), (x, y, z));
+    }
+}
+
+#[test]
+fn copy_emu() {
+    init();
+    check_copy(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn copy_native() {
+    init();
+    check_copy(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn check_extract_element<E: AmxElement>(ctx: &mut impl Amx) {
+    let layout = E::Z_LAYOUT;
+    let mut rng = Xorshift32(0x1234567);
+    let mut z = [0u8; 4096];
+    z.iter_mut().for_each(|b| *b = rng.next() as u8);
+    ctx.load_all_z(&z);
+
+    for z_index in [0, 1, 7, 63] {
+        for (i, &column) in [0, 1, layout.columns() - 1].iter().enumerate() {
+            log::debug!("(z_index, column) = {:?}", (z_index, column));
+            let mut expected = [0u8; 64];
+            for (row, out) in expected.chunks_exact_mut(layout.element_bytes).enumerate() {
+                let (ZRow(z_row), offset) = layout.locate(ZRow(z_index), row, column);
+                out.copy_from_slice(&z[z_row * 64 + offset..][..layout.element_bytes]);
+            }
+
+            ctx.extract_z_column_to_x::<E>(ZRow(z_index), column, XRow(i));
+            assert_eq!(ctx.read_x()[i * 64..][..64], expected);
+            ctx.extract_z_column_to_y::<E>(ZRow(z_index), column, YRow(7 - i));
+            assert_eq!(ctx.read_y()[(7 - i) * 64..][..64], expected);
+
+            let row = column % layout.rows();
+            let (ZRow(z_row), _) = layout.locate(ZRow(z_index), row, 0);
+            ctx.extract_z_row_to_x::<E>(ZRow(z_index), row, XRow(i));
+            assert_eq!(ctx.read_x()[i * 64..][..64], z[z_row * 64..][..64]);
+            ctx.extract_z_row_to_y::<E>(ZRow(z_index), row, YRow(i));
+            assert_eq!(ctx.read_y()[i * 64..][..64], z[z_row * 64..][..64]);
+        }
+    }
+    assert_eq!(ctx.read_z(), z);
+}
+
+fn check_extract(ctx: &mut impl Amx) {
+    check_extract_element::<i16>(ctx);
+    check_extract_element::<f32>(ctx);
+    check_extract_element::<f64>(ctx);
+}
+
+#[test]
+fn extract_emu() {
+    init();
+    check_extract(&mut amx::AmxEmuCtx::default());
+}
+
+/// Also characterizes the column variant of `extrx` and `extry`, whose
+/// encoding is only known from reverse-engineering notes.
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn extract_native() {
+    init();
+    check_extract(&mut *amx::AmxCtx::new().unwrap());
+}
@@ -0,0 +1,242 @@
+#![cfg(target_arch = "aarch64")]
+use amx::{prelude::*, XRow, ZRow};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+#[test]
+fn scope_returns_value() {
+    init();
+    let got = amx::AmxCtx::scope(|amx| {
+        let mut out = [0u32; 16];
+        unsafe {
+            amx.load512([0x12345678u32; 16].as_ptr(), XRow(5));
+            amx.store512(out.as_mut_ptr(), XRow(5));
+        }
+        out
+    })
+    .unwrap();
+    assert_eq!(got, [0x12345678u32; 16]);
+}
+
+#[test]
+fn scope_disables_amx_on_panic() {
+    init();
+    let result = std::panic::catch_unwind(|| {
+        amx::AmxCtx::scope(|_| panic!("oops")).unwrap();
+    });
+    assert!(result.is_err());
+
+    // The context must be usable again
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    let mut out = [0u8; 64];
+    unsafe {
+        ctx.load512([1u8; 64].as_ptr(), XRow(0));
+        ctx.store512(out.as_mut_ptr(), XRow(0));
+    }
+    assert_eq!(out, [1u8; 64]);
+}
+
+/// Check that AMX is enabled by doing a round-trip through `x[0]`.
+fn check_enabled(ctx: &mut amx::AmxCtx) {
+    let mut out = [0u8; 64];
+    unsafe {
+        ctx.load512([7u8; 64].as_ptr(), XRow(0));
+        ctx.store512(out.as_mut_ptr(), XRow(0));
+    }
+    assert_eq!(out, [7u8; 64]);
+}
+
+#[test]
+fn new_fails_while_active() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    assert_eq!(
+        amx::AmxCtx::new().err(),
+        Some(amx::NewAmxCtxError::AlreadyActive)
+    );
+    check_enabled(&mut ctx);
+    drop(ctx);
+
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    check_enabled(&mut ctx);
+}
+
+#[test]
+fn nested_ctx_keeps_amx_enabled() {
+    init();
+    let mut outer = amx::AmxCtx::new().unwrap();
+    let mut inner = amx::AmxCtx::new_nested().unwrap();
+    check_enabled(&mut inner);
+    drop(inner);
+
+    // Dropping the inner context must not disable AMX
+    check_enabled(&mut outer);
+
+    // A nested scope must not disable AMX either
+    amx::AmxCtx::scope(|_| {}).unwrap();
+    check_enabled(&mut outer);
+
+    drop(outer);
+
+    // The outermost context has been dropped, so AMX can be exclusively
+    // activated again
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    check_enabled(&mut ctx);
+}
+
+#[test]
+fn run_is_exclusive() {
+    init();
+    let got = amx::AmxCtx::run(|amx| {
+        let mut out = [0u8; 64];
+        unsafe {
+            amx.load512([3u8; 64].as_ptr(), XRow(2));
+            amx.store512(out.as_mut_ptr(), XRow(2));
+        }
+        out
+    });
+    assert_eq!(got, Ok([3u8; 64]));
+
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    assert_eq!(
+        amx::AmxCtx::run(|_| ()),
+        Err(amx::NewAmxCtxError::AlreadyActive)
+    );
+    check_enabled(&mut ctx);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn moving_to_another_thread_is_detected() {
+    init();
+    struct Smuggled(amx::AmxCtx);
+    // Safety: Not really, which is what this test is about
+    unsafe impl Send for Smuggled {}
+
+    let ctx = Smuggled(amx::AmxCtx::new().unwrap());
+    let result = std::thread::spawn(move || drop(ctx.0)).join();
+    assert!(result.is_err());
+}
+
+#[test]
+fn state_canary() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    assert_eq!(ctx.check_state_canary(), Ok(()));
+
+    ctx.enable_state_canary(ZRow(63));
+    unsafe { ctx.load512([1u8; 64].as_ptr(), ZRow(62)) };
+    assert_eq!(ctx.check_state_canary(), Ok(()));
+
+    // Simulate another library clobbering the registers
+    unsafe { ctx.load512([0u8; 64].as_ptr(), ZRow(63)) };
+    assert_eq!(
+        ctx.check_state_canary(),
+        Err(amx::AmxStateLost { row: ZRow(63) })
+    );
+
+    ctx.disable_state_canary();
+    assert_eq!(ctx.check_state_canary(), Ok(()));
+}
+
+#[test]
+fn reset_keeps_canary() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    ctx.enable_state_canary(ZRow(63));
+    unsafe {
+        ctx.load512([1u8; 64].as_ptr(), XRow(3));
+        ctx.load512([1u8; 64].as_ptr(), ZRow(62));
+    }
+    ctx.reset();
+    assert_eq!(ctx.read_x(), [0; 512]);
+    assert_eq!(ctx.read_z()[..63 * 64], [0; 63 * 64][..]);
+    assert_eq!(ctx.check_state_canary(), Ok(()));
+}
+
+#[test]
+fn zero_on_drop() {
+    init();
+    {
+        let mut ctx = amx::AmxCtx::new_zeroing().unwrap();
+        assert!(amx::AmxCtx::new().is_err());
+        unsafe {
+            ctx.load512([1u8; 64].as_ptr(), XRow(3));
+            ctx.load512([1u8; 64].as_ptr(), amx::YRow(7));
+            ctx.load512([1u8; 64].as_ptr(), ZRow(42));
+        }
+    }
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    assert_eq!(ctx.read_x(), [0; 512]);
+    assert_eq!(ctx.read_y(), [0; 512]);
+    assert_eq!(ctx.read_z(), [0; 4096]);
+}
+
+#[test]
+fn zero_on_drop_when_unwinding() {
+    init();
+    let result = std::panic::catch_unwind(|| {
+        let mut ctx = amx::AmxCtx::new_zeroing().unwrap();
+        unsafe { ctx.load512([1u8; 64].as_ptr(), ZRow(0)) };
+        panic!("oops");
+    });
+    assert!(result.is_err());
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    assert_eq!(ctx.read_z(), [0; 4096]);
+}
+
+/// A helper function requiring AMX to be enabled.
+fn store_x0(amx: amx::AmxEnabled<'_>) -> [u8; 64] {
+    let mut out = [0u8; 64];
+    unsafe { amx.ops().store512(out.as_mut_ptr(), XRow(0)) };
+    out
+}
+
+#[test]
+fn enabled_token() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    unsafe { ctx.load512([3u8; 64].as_ptr(), XRow(0)) };
+    let token = ctx.enabled();
+    assert_eq!(store_x0(token), [3u8; 64]);
+    // The token is `Copy`
+    assert_eq!(store_x0(token), [3u8; 64]);
+}
+
+#[test]
+fn suspend_restores_registers() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    unsafe { ctx.load512([5u8; 64].as_ptr(), ZRow(17)) };
+    let expected = ctx.snapshot();
+
+    let suspended = ctx.suspend();
+    assert_eq!(
+        amx::AmxCtx::new_nested().err(),
+        Some(amx::NewAmxCtxError::AlreadyActive)
+    );
+    // Other code enabling AMX on its own and clobbering the registers
+    amx::AmxCtx::scope(|_| ()).unwrap_err();
+    unsafe {
+        amx::nativeops::set();
+        amx::nativeops::AmxOps::new().clear_all();
+        amx::nativeops::clr();
+    }
+    suspended.resume();
+
+    assert!(ctx.snapshot() == expected);
+    check_enabled(&mut ctx);
+    drop(ctx);
+    check_enabled(&mut amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic(expected = "can't suspend an `AmxCtx` sharing the activation with others")]
+fn suspend_nested() {
+    init();
+    let _ctx = amx::AmxCtx::new().unwrap();
+    let mut nested = amx::AmxCtx::new_nested().unwrap();
+    nested.suspend();
+}
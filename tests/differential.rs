@@ -0,0 +1,192 @@
+//! Differential testing of the emulator against the hardware.
+//!
+//! Random instruction sequences are run on both `AmxEmuCtx` and the backend
+//! under test, comparing the whole register state after every instruction.
+//! The operands are constrained to the subset modeled by the emulator, so a
+//! mismatch means the emulator's idea of an instruction's semantics is wrong.
+//!
+//! Set `AMX_DIFF_SEED` to reproduce a failure and `AMX_DIFF_ITERATIONS` to
+//! change the number of sequences tried.
+mod common;
+
+use amx::{__opcode::*, Amx, AmxTrace, RecordedOp, TracedOp};
+use common::Xorshift32;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+impl Xorshift32 {
+    fn bits(&mut self, len: u32) -> u64 {
+        (self.next() & ((1 << len) - 1)) as u64
+    }
+
+    fn flag(&mut self) -> bool {
+        self.next() & 1 != 0
+    }
+}
+
+/// Generate `len` bytes to load. Every byte is in `0..0x40`, so any
+/// floating-point number made of them is finite and there won't be NaNs,
+/// whose payloads would be hard to compare.
+fn gen_data(rng: &mut Xorshift32, len: usize) -> Vec<u8> {
+    (0..len).map(|_| (rng.next() % 0x40) as u8).collect()
+}
+
+fn load(rng: &mut Xorshift32, opcode: u8, row: u64, pair: bool) -> RecordedOp {
+    RecordedOp {
+        opcode,
+        operand: (row << 56) | ((pair as u64) << 62),
+        data: gen_data(rng, if pair { 128 } else { 64 }),
+    }
+}
+
+fn gen_op(rng: &mut Xorshift32) -> RecordedOp {
+    let op = |opcode, operand| RecordedOp {
+        opcode,
+        operand,
+        data: Vec::new(),
+    };
+    // `y` offset, `x` offset, `z` row, and skip bits
+    let outer_product_operand =
+        |rng: &mut Xorshift32| rng.bits(9) | (rng.bits(9) << 10) | (rng.bits(6) << 20);
+    match rng.next() % 14 {
+        0 => {
+            let (opcode, row, pair) = ([LDX, LDY][rng.bits(1) as usize], rng.bits(3), rng.flag());
+            load(rng, opcode, row, pair)
+        }
+        1 => {
+            let (row, pair) = (rng.bits(6), rng.flag());
+            load(rng, LDZ, row, pair)
+        }
+        2 => {
+            let row = rng.bits(6);
+            load(rng, LDZI, row, false)
+        }
+        3 => {
+            let opcode = [STX, STY, STZ, STZI][rng.bits(2) as usize];
+            let row = if opcode == STX || opcode == STY {
+                rng.bits(3)
+            } else {
+                rng.bits(6)
+            };
+            op(opcode, row << 56)
+        }
+        4 => op(EXTRX, (rng.bits(3) << 16) | (rng.bits(6) << 20)),
+        5 => op(
+            EXTRY,
+            (rng.bits(3) << 6) | (rng.bits(3) << 16) | (rng.bits(6) << 20) | (rng.bits(1) << 27),
+        ),
+        6..=10 => {
+            let opcode =
+                [FMA64, FMS64, FMA32, FMS32, MAC16, FMA16, FMS16][rng.bits(3) as usize % 7];
+            let operand = outer_product_operand(rng) | (rng.bits(3) << 27);
+            // Lane masks are only modeled in matrix mode
+            let operand = if rng.flag() {
+                operand | (1 << 63)
+            } else {
+                operand | (rng.bits(7) << 32) | (rng.bits(7) << 41)
+            };
+            op(opcode, operand)
+        }
+        11 | 12 => {
+            let lanes = [10, 11][rng.bits(1) as usize];
+            let operand = outer_product_operand(rng)
+                | (rng.bits(3) << 27)
+                | (lanes << 42)
+                | (rng.bits(2) << 56)
+                | (rng.bits(5) << 58);
+            op(MATINT, operand)
+        }
+        _ => {
+            let output = if rng.flag() {
+                // `z` row
+                (1 << 26) | (rng.bits(6) << 20)
+            } else {
+                // `x` or `y` row
+                (rng.bits(1) << 25) | (rng.bits(3) << 20)
+            };
+            let operand = rng.bits(9)
+                | (rng.bits(1) << 10)
+                | output
+                | (rng.bits(4) << 53)
+                | (rng.bits(3) << 60);
+            op(GENLUT, operand)
+        }
+    }
+}
+
+/// The number of loads at the beginning of a sequence generated by
+/// [`gen_trace`], which initialize every register.
+const NUM_INIT_OPS: usize = 8 * 2 + 64;
+
+/// Generate a sequence starting with loads that initialize every register.
+fn gen_trace(rng: &mut Xorshift32, len: usize) -> AmxTrace {
+    let mut ops: Vec<_> = (0..8)
+        .flat_map(|row| vec![(LDX, row), (LDY, row)])
+        .chain((0..64).map(|row| (LDZ, row)))
+        .map(|(opcode, row)| load(rng, opcode, row, false))
+        .collect();
+    ops.extend((0..len).map(|_| gen_op(rng)));
+    AmxTrace { ops }
+}
+
+fn check_differential(ctx: &mut impl Amx) {
+    init();
+    let seed = std::env::var("AMX_DIFF_SEED").map_or(0x2545f491, |s| s.parse().unwrap());
+    let iterations = std::env::var("AMX_DIFF_ITERATIONS").map_or(100, |s| s.parse().unwrap());
+    let mut rng = Xorshift32(seed);
+
+    for iteration in 0..iterations {
+        let trace = gen_trace(&mut rng, 64);
+        let mut emu = amx::AmxEmuCtx::default();
+        for (i, op) in trace.ops.iter().enumerate() {
+            let step = AmxTrace {
+                ops: vec![op.clone()],
+            };
+            amx::replay(&step, &mut *ctx);
+            amx::replay(&step, &mut emu);
+            if i + 1 < NUM_INIT_OPS {
+                // The backend under test still has leftovers from the
+                // previous iteration
+                continue;
+            }
+
+            let got = [ctx.read_x(), ctx.read_y()];
+            let expected = [emu.read_x(), emu.read_y()];
+            let mismatch = (0..2)
+                .find_map(|k| {
+                    let pos = (0..512).find(|&b| got[k][b] != expected[k][b])?;
+                    Some((["x", "y"][k], pos))
+                })
+                .or_else(|| {
+                    let (got, expected) = (ctx.read_z(), emu.read_z());
+                    let pos = (0..4096).find(|&b| got[b] != expected[b])?;
+                    Some(("z", pos))
+                });
+            if let Some((regs, pos)) = mismatch {
+                let op = TracedOp {
+                    opcode: op.opcode,
+                    operand: op.operand,
+                    ptr: None,
+                };
+                panic!(
+                    "register state diverged at byte {} of `{}` after `{}` \
+                     (iteration {}, step {}, seed {:#x})",
+                    pos, regs, op, iteration, i, seed
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn differential_emu() {
+    check_differential(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn differential_native() {
+    check_differential(&mut *amx::AmxCtx::new().unwrap());
+}
@@ -0,0 +1,40 @@
+use amx::{disasm, AmxInst};
+
+#[test]
+fn disasm_round_trip() {
+    let mut num_valid = 0;
+    for word in 0x0020_1000..0x0020_1400 {
+        if let Some(inst) = disasm(word) {
+            assert_eq!(inst.encode(), word);
+            num_valid += 1;
+        }
+    }
+    // 22 instructions with a register operand, `set`, and `clr`
+    assert_eq!(num_valid, 22 * 32 + 2);
+}
+
+#[test]
+fn disasm_mnemonics() {
+    let show = |word| disasm(word).map(|inst| inst.to_string());
+    assert_eq!(show(0x0020_1000).as_deref(), Some("ldx x0"));
+    assert_eq!(show(0x0020_10bf).as_deref(), Some("stz x31"));
+    assert_eq!(show(0x0020_12c3).as_deref(), Some("genlut x3"));
+    assert_eq!(show(0x0020_1220).as_deref(), Some("set"));
+    assert_eq!(show(0x0020_1221).as_deref(), Some("clr"));
+    assert_eq!(
+        disasm(0x0020_1281),
+        Some(AmxInst {
+            opcode: 20,
+            operand: 1
+        })
+    );
+}
+
+#[test]
+fn disasm_rejects_other_words() {
+    assert_eq!(disasm(0xd503_201f), None); // `nop`
+    assert_eq!(disasm(0x0020_1222), None); // `set`/`clr` with an unknown immediate
+    assert_eq!(disasm(0x0020_12e0), None); // operation 23
+    assert_eq!(disasm(0x0020_1800), None);
+    assert_eq!(disasm(0x0030_1000), None);
+}
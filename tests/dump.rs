@@ -0,0 +1,41 @@
+use amx::{Amx, DumpFormat, XRow, YRow, ZRow};
+
+#[test]
+fn dump_formats() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    let bytes: Vec<u8> = (0..64).collect();
+    let words: Vec<i16> = (0..32).map(|i| -i * 100).collect();
+    unsafe {
+        ctx.load512(bytes.as_ptr(), XRow(0));
+        ctx.load512(words.as_ptr(), YRow(7));
+        ctx.load512([-0.25f64; 8].as_ptr(), ZRow(63));
+    }
+
+    let dump = ctx.dump(DumpFormat::Hex);
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines.len(), 80);
+    assert_eq!(
+        lines[0],
+        format!(
+            "x0  | {}",
+            (0..64)
+                .map(|i| format!("{:02x}", i))
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+    );
+    assert_eq!(
+        lines[79],
+        format!("z63 |{}", " 00 00 00 00 00 00 d0 bf".repeat(8))
+    );
+
+    // Lanes are right-aligned to the widest one (`-16432` from `z63`)
+    let dump = ctx.dump(DumpFormat::I16);
+    let lines: Vec<&str> = dump.lines().collect();
+    assert!(lines[15].starts_with("y7  |      0   -100   -200"));
+    assert!(lines[0].starts_with("x0  |    256    770"));
+
+    let dump = ctx.dump(DumpFormat::F64);
+    let last: Vec<&str> = dump.lines().last().unwrap().split_whitespace().collect();
+    assert_eq!(last, [&["z63", "|"][..], &["-0.25"; 8]].concat());
+}
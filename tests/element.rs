@@ -0,0 +1,160 @@
+mod common;
+
+use amx::{Amx, AmxElement, AmxEmuCtx, LaneMask, XBytes, XRow, YBytes, YRow, ZRow, ZWriteMode};
+use common::Xorshift32;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+/// Fill `x` and `y` with random bytes below `limit` (`0x40` keeps
+/// floating-point numbers finite).
+fn random_state(limit: u32) -> AmxEmuCtx {
+    let mut rng = Xorshift32(0x2545f491);
+    let mut ctx = AmxEmuCtx::default();
+    for i in 0..8 {
+        let row: Vec<u8> = (0..64).map(|_| (rng.next() % limit) as u8).collect();
+        let row2: Vec<u8> = (0..64).map(|_| (rng.next() % limit) as u8).collect();
+        unsafe {
+            ctx.load512(row.as_ptr(), XRow(i));
+            ctx.load512(row2.as_ptr(), YRow(i));
+        }
+    }
+    ctx
+}
+
+/// Check that `outer_product_masked::<E>` does the same as `specific`.
+fn check_element<E: AmxElement>(
+    limit: u32,
+    modes: &[ZWriteMode],
+    specific: impl Fn(&mut AmxEmuCtx, ZWriteMode, LaneMask) -> E::Tile,
+) where
+    E::Tile: PartialEq + std::fmt::Debug,
+{
+    for &mode in modes {
+        for &mask in &[LaneMask::All, LaneMask::Odd, LaneMask::First(3)] {
+            let mut expected = random_state(limit);
+            let expected_tile = specific(&mut expected, mode, mask);
+
+            let mut got = random_state(limit);
+            let got_tile = got.outer_product_masked::<E>(
+                Some(XBytes(66)),
+                Some(YBytes(130)),
+                ZRow(3),
+                mode,
+                mask,
+                LaneMask::Even,
+            );
+            assert_eq!(got_tile, expected_tile);
+            assert!(got.snapshot() == expected.snapshot());
+        }
+    }
+}
+
+/// `mac16` can't subtract
+const INT_MODES: [ZWriteMode; 2] = [ZWriteMode::Overwrite, ZWriteMode::Accumulate];
+
+const FLOAT_MODES: [ZWriteMode; 3] = [
+    ZWriteMode::Overwrite,
+    ZWriteMode::Accumulate,
+    ZWriteMode::Subtract,
+];
+
+#[test]
+fn element_dispatch() {
+    init();
+    let (x, y, z) = (Some(XBytes(66)), Some(YBytes(130)), ZRow(3));
+    check_element::<i16>(256, &INT_MODES, |ctx, mode, mask| {
+        ctx.outer_product_i16_xy_to_z_masked(x, y, z, mode, mask, LaneMask::Even)
+    });
+    check_element::<u16>(256, &INT_MODES, |ctx, mode, mask| {
+        ctx.outer_product_i16_xy_to_z_masked(x, y, z, mode, mask, LaneMask::Even)
+    });
+    check_element::<f32>(0x40, &FLOAT_MODES, |ctx, mode, mask| {
+        ctx.outer_product_f32_xy_to_z_masked(x, y, z, mode, mask, LaneMask::Even)
+    });
+    check_element::<f64>(0x40, &FLOAT_MODES, |ctx, mode, mask| {
+        ctx.outer_product_f64_xy_to_z_masked(x, y, z, mode, mask, LaneMask::Even)
+    });
+    #[cfg(feature = "half")]
+    check_element::<half::f16>(0x40, &FLOAT_MODES, |ctx, mode, mask| {
+        ctx.outer_product_f16_xy_to_z_masked(x, y, z, mode, mask, LaneMask::Even)
+    });
+}
+
+#[test]
+fn element_bool_mode() {
+    init();
+    let mut ctx = AmxEmuCtx::default();
+    unsafe {
+        ctx.load512([2.0f32; 16].as_ptr(), XRow(0));
+        ctx.load512([3.0f32; 16].as_ptr(), YRow(0));
+    }
+    let (x, y) = (Some(XBytes(0)), Some(YBytes(0)));
+    ctx.outer_product::<f32>(x, y, ZRow(2), false);
+    let tile = ctx.outer_product::<f32>(x, y, ZRow(2), true);
+    let mut row = [0.0f32; 16];
+    unsafe { ctx.store512(row.as_mut_ptr(), tile.row(15)) };
+    assert_eq!(row, [12.0; 16]);
+    assert_eq!(tile.row(15), ZRow(62));
+}
+
+#[test]
+#[should_panic]
+fn element_int_subtract() {
+    AmxEmuCtx::default().outer_product::<i16>(None, None, ZRow(0), ZWriteMode::Subtract);
+}
+
+fn check_store_tile<E: AmxElement + Default + PartialEq + std::fmt::Debug>() {
+    let layout = E::Z_LAYOUT;
+    assert_eq!(layout.element_bytes, std::mem::size_of::<E>());
+    assert_eq!(layout.columns(), E::LANES);
+
+    let mut ctx = random_state(256);
+    let mut z = [0u8; 4096];
+    let mut rng = Xorshift32(0xcafe);
+    z.iter_mut().for_each(|b| *b = rng.next() as u8);
+    ctx.load_all_z(&z);
+
+    for &z_index in &[0, 5, 63] {
+        let ld = layout.columns() + 3;
+        let mut dst = vec![E::default(); layout.rows() * ld];
+        ctx.store_tile::<E>(ZRow(z_index), &mut dst, ld);
+        for i in 0..layout.rows() {
+            for j in 0..ld {
+                let got = &dst[i * ld + j];
+                if j >= layout.columns() {
+                    assert_eq!(*got, E::default());
+                    continue;
+                }
+                let (ZRow(row), offset) = layout.locate(ZRow(z_index), i, j);
+                let expected = &z[row * 64 + offset..][..layout.element_bytes];
+                let got = unsafe {
+                    std::slice::from_raw_parts(got as *const E as *const u8, layout.element_bytes)
+                };
+                assert_eq!(got, expected, "z_index = {}, ({}, {})", z_index, i, j);
+            }
+        }
+    }
+}
+
+#[test]
+fn store_tile() {
+    init();
+    check_store_tile::<i16>();
+    check_store_tile::<u16>();
+    check_store_tile::<f32>();
+    check_store_tile::<f64>();
+}
+
+#[test]
+fn z_layout() {
+    let layout = f64::Z_LAYOUT;
+    assert_eq!(
+        (layout.row_stride, layout.interleave, layout.element_bytes),
+        (8, 1, 8)
+    );
+    assert_eq!((layout.rows(), layout.columns()), (8, 8));
+    assert_eq!(layout.locate(ZRow(13), 7, 7), (ZRow(61), 56));
+    assert_eq!(i16::Z_LAYOUT.locate(ZRow(1), 31, 31), (ZRow(63), 62));
+}
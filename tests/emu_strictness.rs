@@ -0,0 +1,44 @@
+use amx::{prelude::*, AmxEmuCtx, EmuStrictness};
+
+#[test]
+#[should_panic(expected = "reserved bits set")]
+fn strict_rejects_reserved_bits() {
+    let mut ctx = AmxEmuCtx::with_strictness(EmuStrictness::Strict);
+    // Bit 9 lies between the `y` and `x` offsets
+    ctx.fma32(1 << 9);
+}
+
+#[test]
+fn permissive_ignores_reserved_bits() {
+    let mut ctx = AmxEmuCtx::with_strictness(EmuStrictness::Permissive);
+    ctx.load_all_x(&[0x3f; 512]);
+    ctx.load_all_y(&[0x3f; 512]);
+    let mut reference = ctx;
+
+    ctx.fma32(1 << 9);
+    reference.fma32(0);
+    assert_eq!(ctx.read_z()[..], reference.read_z()[..]);
+}
+
+#[test]
+fn strictness_is_per_context() {
+    let mut strict = AmxEmuCtx::with_strictness(EmuStrictness::Strict);
+    let mut permissive = strict;
+    permissive.set_strictness(EmuStrictness::Permissive);
+    assert_eq!(strict.strictness(), EmuStrictness::Strict);
+    assert_eq!(permissive.strictness(), EmuStrictness::Permissive);
+
+    permissive.genlut(8 << 20);
+    let result = std::panic::catch_unwind(move || strict.genlut(8 << 20));
+    assert!(result.is_err());
+}
+
+#[test]
+fn default_follows_checked_feature() {
+    let expected = if cfg!(feature = "checked") {
+        EmuStrictness::Strict
+    } else {
+        EmuStrictness::Permissive
+    };
+    assert_eq!(AmxEmuCtx::default().strictness(), expected);
+}
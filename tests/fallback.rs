@@ -0,0 +1,180 @@
+mod common;
+
+use amx::{
+    kernels::{self, auto, fallback, Conv2dShape},
+    AmxEmuCtx,
+};
+use common::Xorshift32;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+impl Xorshift32 {
+    /// Generate an integer in range `-8..8`, whose products and sums are
+    /// exact in `f32`
+    fn next_f32(&mut self) -> f32 {
+        (self.next() % 16) as f32 - 8.0
+    }
+
+    fn next_i16(&mut self) -> i16 {
+        self.next() as i16
+    }
+}
+
+#[test]
+fn is_supported() {
+    let supported = amx::is_supported();
+    if !cfg!(all(target_arch = "aarch64", target_os = "macos")) {
+        assert!(!supported);
+    }
+    // The result is cached
+    assert_eq!(amx::is_supported(), supported);
+}
+
+#[test]
+fn matmul() {
+    init();
+    let mut rng = Xorshift32(0x1234567);
+    let mut ctx = AmxEmuCtx::default();
+    for &(m, n, k) in &[(1, 1, 1), (3, 5, 0), (17, 33, 9), (40, 20, 70)] {
+        let a: Vec<f32> = (0..m * k).map(|_| rng.next_f32()).collect();
+        let b: Vec<f32> = (0..k * n).map(|_| rng.next_f32()).collect();
+        let mut expected = vec![1.0; m * n];
+        let mut got = vec![1.0; m * n];
+        let mut got_auto = vec![1.0; m * n];
+        kernels::matmul_f32(&mut ctx, m, n, k, &a, &b, &mut expected);
+        fallback::matmul_f32(m, n, k, &a, &b, &mut got);
+        auto::matmul_f32(m, n, k, &a, &b, &mut got_auto);
+        assert_eq!(got, expected, "{:?}", (m, n, k));
+        assert_eq!(got_auto, expected, "{:?}", (m, n, k));
+
+        let a: Vec<f64> = a.iter().map(|&x| x as f64).collect();
+        let b: Vec<f64> = b.iter().map(|&x| x as f64).collect();
+        let expected: Vec<f64> = expected.iter().map(|&x| x as f64).collect();
+        let mut got = vec![1.0; m * n];
+        fallback::matmul_f64(m, n, k, &a, &b, &mut got);
+        assert_eq!(got, expected, "{:?}", (m, n, k));
+    }
+}
+
+#[test]
+fn dot() {
+    init();
+    let mut rng = Xorshift32(0x2345678);
+    let mut ctx = AmxEmuCtx::default();
+    for &len in &[0, 1, 15, 16, 17, 300, 1000] {
+        let a: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+        let b: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+        let expected = kernels::dot_f32(&mut ctx, &a, &b);
+        assert_eq!(fallback::dot_f32(&a, &b), expected, "{}", len);
+        assert_eq!(auto::dot_f32(&a, &b), expected, "{}", len);
+        let expected = kernels::sum_f32(&mut ctx, &a);
+        assert_eq!(fallback::sum_f32(&a), expected, "{}", len);
+        assert_eq!(auto::sum_f32(&a), expected, "{}", len);
+
+        let a: Vec<i16> = (0..len).map(|_| rng.next_i16()).collect();
+        let b: Vec<i16> = (0..len).map(|_| rng.next_i16()).collect();
+        let expected = kernels::dot_i16(&mut ctx, &a, &b);
+        assert_eq!(fallback::dot_i16(&a, &b), expected, "{}", len);
+        assert_eq!(auto::dot_i16(&a, &b), expected, "{}", len);
+    }
+}
+
+#[test]
+fn elementwise() {
+    init();
+    let mut rng = Xorshift32(0x4567890);
+    let mut ctx = AmxEmuCtx::default();
+    for &len in &[0, 1, 15, 16, 17, 300] {
+        let a: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+        let b: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+        let alpha = rng.next_f32();
+
+        let mut expected = b.clone();
+        let mut got = b.clone();
+        let mut got_auto = b.clone();
+        kernels::axpy_f32(&mut ctx, alpha, &a, &mut expected);
+        fallback::axpy_f32(alpha, &a, &mut got);
+        auto::axpy_f32(alpha, &a, &mut got_auto);
+        assert_eq!(got, expected, "{}", len);
+        assert_eq!(got_auto, expected, "{}", len);
+
+        let mut expected = a.clone();
+        let mut got = a.clone();
+        kernels::scale_f32(&mut ctx, alpha, &mut expected);
+        fallback::scale_f32(alpha, &mut got);
+        assert_eq!(got, expected, "{}", len);
+
+        let mut expected = vec![0.0; len];
+        let mut got = vec![1.0; len];
+        kernels::add_f32(&mut ctx, &a, &b, &mut expected);
+        fallback::add_f32(&a, &b, &mut got);
+        assert_eq!(got, expected, "{}", len);
+        kernels::mul_f32(&mut ctx, &a, &b, &mut expected);
+        fallback::mul_f32(&a, &b, &mut got);
+        assert_eq!(got, expected, "{}", len);
+    }
+}
+
+#[test]
+fn arg_reduce() {
+    init();
+    let mut rng = Xorshift32(0x5678901);
+    let mut ctx = AmxEmuCtx::default();
+    for &len in &[1, 17, 300, 1000] {
+        // Few distinct values so that ties are common
+        let mut x: Vec<f32> = (0..len).map(|_| (rng.next() % 16) as f32).collect();
+        x[len / 2] = f32::NAN;
+        let expected = kernels::argmax_f32(&mut ctx, &x);
+        assert_eq!(fallback::argmax_f32(&x), expected, "{}", len);
+        assert_eq!(auto::argmax_f32(&x), expected, "{}", len);
+        let expected = kernels::argmin_f32(&mut ctx, &x);
+        assert_eq!(fallback::argmin_f32(&x), expected, "{}", len);
+        assert_eq!(auto::argmin_f32(&x), expected, "{}", len);
+    }
+    assert_eq!(fallback::argmax_f32(&[f32::NAN; 3]), 0);
+}
+
+#[test]
+fn conv2d() {
+    init();
+    let mut rng = Xorshift32(0x3456789);
+    let mut ctx = AmxEmuCtx::default();
+    let shape = Conv2dShape {
+        in_channels: 3,
+        out_channels: 5,
+        in_height: 11,
+        in_width: 20,
+        kernel_height: 3,
+        kernel_width: 4,
+    };
+    let out_len = shape.out_channels * shape.out_height() * shape.out_width();
+    let in_len = shape.in_channels * shape.in_height * shape.in_width;
+    let w_len = shape.out_channels * shape.in_channels * shape.kernel_height * shape.kernel_width;
+
+    let input: Vec<f32> = (0..in_len).map(|_| rng.next_f32()).collect();
+    let weights: Vec<f32> = (0..w_len).map(|_| rng.next_f32()).collect();
+    let mut expected = vec![0.0; out_len];
+    let mut got = vec![1.0; out_len];
+    let mut got_auto = vec![1.0; out_len];
+    kernels::conv2d_f32(&mut ctx, shape, &input, &weights, &mut expected);
+    fallback::conv2d_f32(shape, &input, &weights, &mut got);
+    auto::conv2d_f32(shape, &input, &weights, &mut got_auto);
+    assert_eq!(got, expected);
+    assert_eq!(got_auto, expected);
+
+    let input: Vec<i16> = (0..in_len).map(|_| rng.next_i16()).collect();
+    let weights: Vec<i16> = (0..w_len).map(|_| rng.next_i16()).collect();
+    let mut expected = vec![0; out_len];
+    let mut got = vec![1; out_len];
+    kernels::conv2d_i16(&mut ctx, shape, &input, &weights, &mut expected);
+    fallback::conv2d_i16(shape, &input, &weights, &mut got);
+    assert_eq!(got, expected);
+}
+
+#[test]
+#[should_panic(expected = "`a` and `b` must have the same length")]
+fn dot_length_mismatch() {
+    fallback::dot_f32(&[1.0; 3], &[1.0; 4]);
+}
@@ -1,15 +1,24 @@
-use amx::{prelude::*, Index4, Normal, XBytes, XRow, YBytes, YRow, X8};
+mod common;
+
+use amx::{
+    Amx, Index2, Index4, Index5, LutIn, LutOut, Normal, Reverse, XBytes, XRow, YBytes, YRow, ZRow,
+    F16, F32, F64, I16, I32, U16, U32, X16, X32, X64, X8,
+};
+use common::Xorshift32;
 use either::{Left, Right};
+#[cfg(target_arch = "aarch64")]
 use quickcheck::TestResult;
 
 fn init() {
     let _ = env_logger::builder().is_test(true).try_init();
 }
 
+#[cfg(target_arch = "aarch64")]
 fn overlaps(x: std::ops::Range<usize>, y: std::ops::Range<usize>) -> bool {
     x.start < y.end && x.end > y.start
 }
 
+#[cfg(target_arch = "aarch64")]
 #[quickcheck_macros::quickcheck]
 fn qc_genlut_lut8x16(
     table_row: usize,
@@ -78,7 +87,7 @@ fn qc_genlut_lut8x16(
 
     // Read the result
     unsafe { ctx.store512(got.as_mut_ptr(), XRow(out_row)) };
-    let all_x = unsafe { std::mem::transmute::<_, [[u64; 8]; 8]>(ctx.read_x()) };
+    let all_x = unsafe { std::mem::transmute::<[u8; 512], [[u64; 8]; 8]>(ctx.read_x()) };
 
     let expected: Vec<u8> = (0..64)
         .map(|i| {
@@ -101,3 +110,483 @@ fn qc_genlut_lut8x16(
 
     TestResult::passed()
 }
+
+/// The element type of a reverse LUT mode
+#[derive(Debug, Copy, Clone)]
+enum Elem {
+    F16,
+    F32,
+    F64,
+    I16,
+    I32,
+    U16,
+    U32,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Mode {
+    Normal {
+        index_bits: usize,
+        value_size: usize,
+    },
+    Reverse {
+        index_bits: usize,
+        elem: Elem,
+    },
+}
+
+/// `(mode number, description)` for every entry of the LUT type table
+const MODES: [(u64, Mode); 16] = [
+    (
+        0,
+        Mode::Reverse {
+            index_bits: 4,
+            elem: Elem::F32,
+        },
+    ),
+    (
+        1,
+        Mode::Reverse {
+            index_bits: 5,
+            elem: Elem::F16,
+        },
+    ),
+    (
+        2,
+        Mode::Reverse {
+            index_bits: 4,
+            elem: Elem::F64,
+        },
+    ),
+    (
+        3,
+        Mode::Reverse {
+            index_bits: 4,
+            elem: Elem::I32,
+        },
+    ),
+    (
+        4,
+        Mode::Reverse {
+            index_bits: 5,
+            elem: Elem::I16,
+        },
+    ),
+    (
+        5,
+        Mode::Reverse {
+            index_bits: 4,
+            elem: Elem::U32,
+        },
+    ),
+    (
+        6,
+        Mode::Reverse {
+            index_bits: 5,
+            elem: Elem::U16,
+        },
+    ),
+    (
+        7,
+        Mode::Normal {
+            index_bits: 2,
+            value_size: 4,
+        },
+    ),
+    (
+        8,
+        Mode::Normal {
+            index_bits: 2,
+            value_size: 2,
+        },
+    ),
+    (
+        9,
+        Mode::Normal {
+            index_bits: 2,
+            value_size: 1,
+        },
+    ),
+    (
+        10,
+        Mode::Normal {
+            index_bits: 4,
+            value_size: 8,
+        },
+    ),
+    (
+        11,
+        Mode::Normal {
+            index_bits: 4,
+            value_size: 4,
+        },
+    ),
+    (
+        12,
+        Mode::Normal {
+            index_bits: 4,
+            value_size: 2,
+        },
+    ),
+    (
+        13,
+        Mode::Normal {
+            index_bits: 4,
+            value_size: 1,
+        },
+    ),
+    (
+        14,
+        Mode::Normal {
+            index_bits: 5,
+            value_size: 2,
+        },
+    ),
+    (
+        15,
+        Mode::Normal {
+            index_bits: 5,
+            value_size: 1,
+        },
+    ),
+];
+
+/// Call `Amx::lut` with the LUT type tag corresponding to `mode`.
+fn lut_by_mode(ctx: &mut impl Amx, mode: u64, input: impl LutIn, table: XRow, output: impl LutOut) {
+    match mode {
+        0 => ctx.lut(input, table, output, (Reverse, Index4, F32)),
+        1 => ctx.lut(input, table, output, (Reverse, Index5, F16)),
+        2 => ctx.lut(input, table, output, (Reverse, Index4, F64)),
+        3 => ctx.lut(input, table, output, (Reverse, Index4, I32)),
+        4 => ctx.lut(input, table, output, (Reverse, Index5, I16)),
+        5 => ctx.lut(input, table, output, (Reverse, Index4, U32)),
+        6 => ctx.lut(input, table, output, (Reverse, Index5, U16)),
+        7 => ctx.lut(input, table, output, (Normal, Index2, X32)),
+        8 => ctx.lut(input, table, output, (Normal, Index2, X16)),
+        9 => ctx.lut(input, table, output, (Normal, Index2, X8)),
+        10 => ctx.lut(input, table, output, (Normal, Index4, X64)),
+        11 => ctx.lut(input, table, output, (Normal, Index4, X32)),
+        12 => ctx.lut(input, table, output, (Normal, Index4, X16)),
+        13 => ctx.lut(input, table, output, (Normal, Index4, X8)),
+        14 => ctx.lut(input, table, output, (Normal, Index5, X16)),
+        15 => ctx.lut(input, table, output, (Normal, Index5, X8)),
+        _ => unreachable!(),
+    }
+}
+
+/// Convert an IEEE 754 binary16 value to `f64`.
+fn f16_to_f64(bits: u16) -> f64 {
+    let mag = match (bits >> 10) & 0x1f {
+        0 => (bits & 0x3ff) as f64 / (1 << 24) as f64,
+        exp => ((bits & 0x3ff) | 0x400) as f64 * 2f64.powi(exp as i32 - 25),
+    };
+    if bits & 0x8000 != 0 {
+        -mag
+    } else {
+        mag
+    }
+}
+
+impl Elem {
+    fn size(self) -> usize {
+        match self {
+            Elem::F16 | Elem::I16 | Elem::U16 => 2,
+            Elem::F32 | Elem::I32 | Elem::U32 => 4,
+            Elem::F64 => 8,
+        }
+    }
+
+    fn value(self, b: &[u8]) -> f64 {
+        match self {
+            Elem::F16 => f16_to_f64(u16::from_le_bytes([b[0], b[1]])),
+            Elem::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Elem::F64 => f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]),
+            Elem::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Elem::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Elem::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Elem::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+        }
+    }
+
+    /// Generate a random finite value in a narrow range, so that the inputs
+    /// and the table entries are likely to interleave.
+    fn random(self, rng: &mut Xorshift32) -> Vec<u8> {
+        let v = (rng.next() % 64) as i32 - 16;
+        match self {
+            // Small integers are exactly representable. 0x3c00 is `1.0`, so
+            // `0x3c00 + v * 0x400` is `2^v`.
+            Elem::F16 => ((0x3c00 + (v % 15) * 0x400) as u16 | (rng.next() & 0x83ff) as u16)
+                .to_le_bytes()
+                .to_vec(),
+            Elem::F32 => (v as f32 * 0.75).to_le_bytes().to_vec(),
+            Elem::F64 => (v as f64 * 0.75).to_le_bytes().to_vec(),
+            Elem::I16 => (v as i16).to_le_bytes().to_vec(),
+            Elem::I32 => (v * 0x10000).to_le_bytes().to_vec(),
+            Elem::U16 => (v as u16 & 0x3f).to_le_bytes().to_vec(),
+            Elem::U32 => (v as u32 & 0x3f).to_le_bytes().to_vec(),
+        }
+    }
+}
+
+fn bit_field(bytes: &[u8], index: usize, bits: usize) -> usize {
+    let mut value = 0;
+    for i in 0..bits {
+        let pos = index * bits + i;
+        value |= ((bytes[pos / 8] as usize >> (pos % 8)) & 1) << i;
+    }
+    value
+}
+
+/// Calculate the expected output of `genlut`.
+fn expected_lut_output(mode: Mode, input: &[u8], table: &[u8]) -> Vec<u8> {
+    match mode {
+        Mode::Normal {
+            index_bits,
+            value_size,
+        } => (0..64 / value_size)
+            .flat_map(|i| {
+                // A table row can hold only eight 64-bit entries
+                let index = bit_field(input, i, index_bits) % (64 / value_size);
+                table[index * value_size..][..value_size].to_vec()
+            })
+            .collect(),
+        Mode::Reverse { index_bits, elem } => {
+            let size = elem.size();
+            let table: Vec<f64> = table.chunks(size).map(|x| elem.value(x)).collect();
+            let mut out = vec![0u8; 64];
+            for (i, x) in input.chunks(size).enumerate() {
+                let x = elem.value(x);
+                // "the minimum index whose element is greater than the given
+                // value" minus 1
+                let index = match table.iter().position(|&e| e > x) {
+                    Some(i) => i.wrapping_sub(1),
+                    None => table.len() - 1,
+                } & ((1 << index_bits) - 1);
+                for bit in 0..index_bits {
+                    let pos = i * index_bits + bit;
+                    out[pos / 8] |= (((index >> bit) & 1) as u8) << (pos % 8);
+                }
+            }
+            out
+        }
+    }
+}
+
+fn check_genlut_mode_matrix(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0xdeadbeef);
+
+    for &(mode_num, mode) in MODES.iter() {
+        for iteration in 0..32 {
+            // Fill the registers with random values
+            let mut x = [0u8; 512];
+            let mut y = [0u8; 512];
+            let z: Vec<u8> = (0..4096).map(|_| rng.next() as u8).collect();
+            let table_row = rng.next() as usize % 8;
+            let index_in_y = rng.next() % 2 == 0;
+            let index_offset = rng.next() as usize % 512;
+            match mode {
+                Mode::Normal { .. } => {
+                    x.iter_mut().for_each(|x| *x = rng.next() as u8);
+                    y.iter_mut().for_each(|x| *x = rng.next() as u8);
+                }
+                Mode::Reverse { elem, .. } => {
+                    for chunk in x.chunks_mut(elem.size()) {
+                        chunk.copy_from_slice(&elem.random(&mut rng));
+                    }
+                    for chunk in y.chunks_mut(elem.size()) {
+                        chunk.copy_from_slice(&elem.random(&mut rng));
+                    }
+                    // Sort the table in every second iteration
+                    if iteration % 2 == 0 {
+                        let table = &mut x[table_row * 64..][..64];
+                        let mut entries: Vec<Vec<u8>> =
+                            table.chunks(elem.size()).map(|x| x.to_vec()).collect();
+                        entries.sort_by(|a, b| elem.value(a).partial_cmp(&elem.value(b)).unwrap());
+                        table.copy_from_slice(&entries.concat());
+                    }
+                }
+            }
+            for i in 0..8 {
+                unsafe {
+                    ctx.load512(x[i * 64..].as_ptr(), XRow(i));
+                    ctx.load512(y[i * 64..].as_ptr(), YRow(i));
+                }
+            }
+            for i in 0..64 {
+                unsafe { ctx.load512(z[i * 64..].as_ptr(), ZRow(i)) };
+            }
+
+            let input_regs = if index_in_y { &y } else { &x };
+            let input: Vec<u8> = (0..64)
+                .map(|i| input_regs[(index_offset + i) % 512])
+                .collect();
+            let table = &x[table_row * 64..][..64];
+            let expected_out = expected_lut_output(mode, &input, table);
+
+            let input = if index_in_y {
+                Left(YBytes(index_offset))
+            } else {
+                Right(XBytes(index_offset))
+            };
+
+            let (mut expected_x, mut expected_y, mut expected_z) = (x, y, z.clone());
+            let out_kind = rng.next() % 3;
+            let out_row = rng.next() as usize % if out_kind == 2 { 64 } else { 8 };
+
+            log::debug!(
+                "mode = {} ({:?}), iteration = {}, table_row = {}, index_in_y = {}, \
+                index_offset = {}, out = {}[{}]",
+                mode_num,
+                mode,
+                iteration,
+                table_row,
+                index_in_y,
+                index_offset,
+                ["x", "y", "z"][out_kind as usize],
+                out_row
+            );
+
+            match out_kind {
+                0 => {
+                    lut_by_mode(ctx, mode_num, input, XRow(table_row), XRow(out_row));
+                    expected_x[out_row * 64..][..64].copy_from_slice(&expected_out);
+                }
+                1 => {
+                    lut_by_mode(ctx, mode_num, input, XRow(table_row), YRow(out_row));
+                    expected_y[out_row * 64..][..64].copy_from_slice(&expected_out);
+                }
+                _ => {
+                    lut_by_mode(ctx, mode_num, input, XRow(table_row), ZRow(out_row));
+                    expected_z[out_row * 64..][..64].copy_from_slice(&expected_out);
+                }
+            }
+
+            assert_eq!(ctx.read_x()[..], expected_x[..], "mode {:?}", mode);
+            assert_eq!(ctx.read_y()[..], expected_y[..], "mode {:?}", mode);
+            assert_eq!(ctx.read_z()[..], expected_z[..], "mode {:?}", mode);
+        }
+    }
+}
+
+#[test]
+fn genlut_mode_matrix_emu() {
+    init();
+    check_genlut_mode_matrix(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn genlut_mode_matrix_native() {
+    init();
+    check_genlut_mode_matrix(&mut *amx::AmxCtx::new().unwrap());
+}
+
+/// Check that every `z` row, including the ones that don't exist in `x` and
+/// `y`, can be specified as the output.
+fn check_genlut_z_rows(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0x2545f491);
+    let x: Vec<u8> = (0..512).map(|_| rng.next() as u8).collect();
+    for i in 0..8 {
+        unsafe { ctx.load512(x[i * 64..].as_ptr(), XRow(i)) };
+    }
+    let input = &x[64..128];
+    let table = &x[3 * 64..][..64];
+
+    for &(mode_num, mode) in MODES
+        .iter()
+        .filter(|(_, m)| matches!(m, Mode::Normal { .. }))
+    {
+        let expected_out = expected_lut_output(mode, input, table);
+        for out_row in 0..64 {
+            log::debug!("mode = {}, out_row = {}", mode_num, out_row);
+            ctx.clear_z();
+            lut_by_mode(ctx, mode_num, XBytes(64), XRow(3), ZRow(out_row));
+            let mut expected_z = [0u8; 4096];
+            expected_z[out_row * 64..][..64].copy_from_slice(&expected_out);
+            assert_eq!(ctx.read_z()[..], expected_z[..], "mode {:?}", mode);
+        }
+    }
+
+    // `lut_to_z` is a shorthand for the above
+    ctx.clear_z();
+    ctx.lut_to_z(XBytes(64), XRow(3), ZRow(45), (Normal, Index5, X8));
+    let mut expected_z = [0u8; 4096];
+    expected_z[45 * 64..][..64].copy_from_slice(&expected_lut_output(
+        Mode::Normal {
+            index_bits: 5,
+            value_size: 1,
+        },
+        input,
+        table,
+    ));
+    assert_eq!(ctx.read_z()[..], expected_z[..]);
+}
+
+#[test]
+fn genlut_z_rows_emu() {
+    init();
+    check_genlut_z_rows(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn genlut_z_rows_native() {
+    init();
+    check_genlut_z_rows(&mut *amx::AmxCtx::new().unwrap());
+}
+
+/// Call `Amx::reverse_lut` with the element type tag corresponding to `elem`.
+fn reverse_lut_by_elem(ctx: &mut impl Amx, elem: Elem, input: XBytes, table: XRow, out: YRow) {
+    match elem {
+        Elem::F16 => ctx.reverse_lut(input, table, out, F16),
+        Elem::F32 => ctx.reverse_lut(input, table, out, F32),
+        Elem::F64 => ctx.reverse_lut(input, table, out, F64),
+        Elem::I16 => ctx.reverse_lut(input, table, out, I16),
+        Elem::I32 => ctx.reverse_lut(input, table, out, I32),
+        Elem::U16 => ctx.reverse_lut(input, table, out, U16),
+        Elem::U32 => ctx.reverse_lut(input, table, out, U32),
+    }
+}
+
+fn check_reverse_lut(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0x9e3779b9);
+    for &(_, mode) in MODES.iter() {
+        let (index_bits, elem) = match mode {
+            Mode::Reverse { index_bits, elem } => (index_bits, elem),
+            Mode::Normal { .. } => continue,
+        };
+        for _ in 0..16 {
+            let mut entries: Vec<Vec<u8>> = (0..64 / elem.size())
+                .map(|_| elem.random(&mut rng))
+                .collect();
+            entries.sort_by(|a, b| elem.value(a).partial_cmp(&elem.value(b)).unwrap());
+            let table = entries.concat();
+            let input: Vec<u8> = (0..64 / elem.size())
+                .flat_map(|_| elem.random(&mut rng))
+                .collect();
+            unsafe {
+                ctx.load512(table.as_ptr(), XRow(5));
+                ctx.load512(input.as_ptr(), XRow(2));
+            }
+
+            log::debug!("elem = {:?}", elem);
+            reverse_lut_by_elem(ctx, elem, XBytes(128), XRow(5), YRow(6));
+
+            let expected = expected_lut_output(Mode::Reverse { index_bits, elem }, &input, &table);
+            assert_eq!(ctx.read_y()[384..448], expected[..], "elem = {:?}", elem);
+        }
+    }
+}
+
+#[test]
+fn reverse_lut_emu() {
+    init();
+    check_reverse_lut(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn reverse_lut_native() {
+    init();
+    check_reverse_lut(&mut *amx::AmxCtx::new().unwrap());
+}
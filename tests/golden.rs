@@ -0,0 +1,247 @@
+//! Golden-trace regression tests.
+//!
+//! Each case in [`corpus`] is a fixed instruction sequence. Its trace and the
+//! register state the hardware ends up in after executing it are recorded
+//! under `tests/golden` as `<name>.trace` (in the format of
+//! [`AmxTrace::write_to`]) and `<name>.txt` (a hexadecimal
+//! [`Amx::dump`]). [`golden_emu`] replays the traces on the emulator and
+//! compares the results, so the files double as a record of the hardware's
+//! behavior. A case without recorded files fails the test.
+//!
+//! The files must come from the hardware; checking in the emulator's own
+//! output would make [`golden_emu`] pass trivially. After adding or changing
+//! a case, (re-)record the files on the hardware by:
+//!
+//! ```text
+//! cargo test --test golden -- --ignored record_golden_native
+//! ```
+//!
+//! The first line of each `.txt` file tells where it was recorded, which can
+//! be changed by setting `AMX_GOLDEN_ORIGIN`.
+//!
+//! No files have been recorded yet, so [`golden_emu`] is ignored until they
+//! are checked in.
+mod common;
+
+use amx::{
+    Amx, AmxEmuCtx, AmxTrace, DumpFormat, EmuStrictness, Index4, IntAccumMode, LaneMask, Normal,
+    RecordingOps, XBytes, XRow, YBytes, YRow, ZRow, ZWriteMode, X8,
+};
+use common::Xorshift32;
+use std::path::PathBuf;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+impl Xorshift32 {
+    /// Generate 64 bytes. Every byte is less than `limit`; `0x40` keeps
+    /// floating-point numbers finite.
+    fn row(&mut self, limit: u32) -> [u8; 64] {
+        let mut row = [0u8; 64];
+        for x in row.iter_mut() {
+            *x = (self.next() % limit) as u8;
+        }
+        row
+    }
+}
+
+type Ctx = RecordingOps<AmxEmuCtx>;
+type Case = fn(&mut Ctx);
+
+/// The instruction sequences to check. The names are used as file names.
+fn corpus() -> Vec<(&'static str, Case)> {
+    vec![
+        ("load_store", load_store),
+        ("fma32", fma32),
+        ("fma64", fma64),
+        ("fma16", fma16),
+        ("mac16", mac16),
+        ("matint", matint),
+        ("genlut", genlut),
+    ]
+}
+
+/// Load `x` and `y` with random 64-byte rows below `limit`.
+fn load_xy(ctx: &mut Ctx, rng: &mut Xorshift32, limit: u32) {
+    for i in 0..8 {
+        // Safety: Reading 64-byte regions within the rows
+        unsafe {
+            ctx.load512(rng.row(limit).as_ptr(), XRow(i));
+            ctx.load512(rng.row(limit).as_ptr(), YRow(i));
+        }
+    }
+}
+
+fn load_store(ctx: &mut Ctx) {
+    let mut rng = Xorshift32(0x2545f491);
+    let mut pair = amx::AmxAligned([0u8; 128]);
+    pair.0[..64].copy_from_slice(&rng.row(256));
+    pair.0[64..].copy_from_slice(&rng.row(256));
+    // Safety: Reading and writing regions within `pair` and the rows
+    unsafe {
+        ctx.load1024_aligned(pair.0.as_ptr(), XRow(6));
+        ctx.load1024_aligned(pair.0.as_ptr(), YRow(1));
+        ctx.load1024_aligned(pair.0.as_ptr(), ZRow(40));
+        ctx.load512(rng.row(256).as_ptr(), ZRow(63));
+        ctx.load512_interleaved(rng.row(256).as_ptr(), ZRow(10));
+        ctx.store512(pair.0.as_mut_ptr(), ZRow(63));
+        ctx.store512_interleaved(pair.0.as_mut_ptr(), ZRow(10));
+    }
+    ctx.copy_z_row_to_x(ZRow(41), XRow(2));
+    ctx.copy_z_row_to_y(ZRow(63), YRow(5));
+    ctx.copy_x_to_y(XRow(7), YRow(0));
+}
+
+fn fma32(ctx: &mut Ctx) {
+    let mut rng = Xorshift32(0x1234567);
+    load_xy(ctx, &mut rng, 0x40);
+    let (x, y) = (Some(XBytes(64)), Some(YBytes(200)));
+    ctx.outer_product_f32_xy_to_z(x, y, ZRow(0), ZWriteMode::Overwrite);
+    ctx.outer_product_f32_xy_to_z(Some(XBytes(4)), y, ZRow(0), ZWriteMode::Accumulate);
+    ctx.outer_product_f32_xy_to_z(x, Some(YBytes(0)), ZRow(0), ZWriteMode::Subtract);
+    ctx.outer_product_f32_xy_to_z(None, y, ZRow(1), ZWriteMode::Overwrite);
+    ctx.outer_product_f32_xy_to_z(x, None, ZRow(2), ZWriteMode::Accumulate);
+    ctx.outer_product_f32_xy_to_z_masked(x, y, ZRow(3), false, LaneMask::Last(5), LaneMask::Even);
+}
+
+fn fma64(ctx: &mut Ctx) {
+    let mut rng = Xorshift32(0x7654321);
+    load_xy(ctx, &mut rng, 0x40);
+    let (x, y) = (Some(XBytes(8)), Some(YBytes(448)));
+    ctx.outer_product_f64_xy_to_z(x, y, ZRow(0), ZWriteMode::Overwrite);
+    ctx.outer_product_f64_xy_to_z(x, Some(YBytes(96)), ZRow(0), ZWriteMode::Subtract);
+    ctx.outer_product_f64_xy_to_z(x, y, ZRow(5), ZWriteMode::Accumulate);
+    ctx.outer_product_f64_xy_to_z_masked(x, y, ZRow(7), false, LaneMask::Odd, LaneMask::First(6));
+}
+
+fn fma16(ctx: &mut Ctx) {
+    let mut rng = Xorshift32(0xdeadbeef);
+    load_xy(ctx, &mut rng, 0x40);
+    let (x, y) = (Some(XBytes(130)), Some(YBytes(2)));
+    ctx.outer_product_f16_xy_to_z(x, y, ZRow(0), ZWriteMode::Overwrite);
+    ctx.outer_product_f16_xy_to_z(x, y, ZRow(0), ZWriteMode::Accumulate);
+    ctx.outer_product_f16_xy_to_z(Some(XBytes(0)), y, ZRow(1), ZWriteMode::Subtract);
+    ctx.outer_product_f16_xy_to_z_masked(x, y, ZRow(1), true, LaneMask::Only(17), LaneMask::None);
+}
+
+fn mac16(ctx: &mut Ctx) {
+    let mut rng = Xorshift32(0xcafe);
+    load_xy(ctx, &mut rng, 256);
+    let (x, y) = (Some(XBytes(34)), Some(YBytes(320)));
+    ctx.outer_product_i16_xy_to_z(x, y, ZRow(0), false);
+    ctx.outer_product_i16_xy_to_z(x, Some(YBytes(2)), ZRow(0), true);
+    ctx.outer_product_i16_xy_to_z(None, y, ZRow(1), false);
+    ctx.outer_product_i16_xy_to_z_masked(
+        x,
+        y,
+        ZRow(1),
+        true,
+        LaneMask::First(9),
+        LaneMask::Last(30),
+    );
+}
+
+fn matint(ctx: &mut Ctx) {
+    let mut rng = Xorshift32(0xf00d);
+    load_xy(ctx, &mut rng, 256);
+    let (x, y) = (Some(XBytes(16)), Some(YBytes(48)));
+    let saturate = IntAccumMode {
+        shift: 3,
+        saturate: true,
+        ..IntAccumMode::default()
+    };
+    ctx.outer_product_i8_to_i32(x, y, ZRow(0), false, IntAccumMode::default());
+    ctx.outer_product_i8_to_i32(x, y, ZRow(0), true, saturate);
+    ctx.outer_product_u8_to_i32(x, y, ZRow(1), false, IntAccumMode::default());
+    ctx.outer_product_u8_to_i32(Some(XBytes(500)), y, ZRow(1), true, saturate);
+}
+
+fn genlut(ctx: &mut Ctx) {
+    let mut rng = Xorshift32(0xbeef);
+    load_xy(ctx, &mut rng, 256);
+    ctx.lut(XBytes(64), XRow(0), XRow(2), (Normal, Index4, X8));
+    ctx.lut(YBytes(128), XRow(3), YRow(4), (Normal, Index4, X8));
+    ctx.lut(XBytes(320), XRow(7), ZRow(9), (Normal, Index4, X8));
+}
+
+/// Record `case` from the all-zero register state.
+fn record(case: Case) -> AmxTrace {
+    let mut ctx = RecordingOps::new(AmxEmuCtx::with_strictness(EmuStrictness::Strict));
+    ctx.load_all_x(&[0; 512]);
+    ctx.load_all_y(&[0; 512]);
+    ctx.load_all_z(&[0; 4096]);
+    case(&mut ctx);
+    ctx.into_parts().1
+}
+
+fn golden_dir() -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", "golden"]
+        .iter()
+        .collect()
+}
+
+fn golden_path(name: &str, ext: &str) -> PathBuf {
+    golden_dir().join(name).with_extension(ext)
+}
+
+#[test]
+#[ignore = "the golden files haven't been recorded on the hardware yet"]
+fn golden_emu() {
+    init();
+    for (name, case) in corpus() {
+        log::info!("{}", name);
+        let read = |ext| {
+            let path = golden_path(name, ext);
+            std::fs::read(&path).unwrap_or_else(|e| {
+                panic!(
+                    "could not read {} ({}); record `{}` on the hardware",
+                    path.display(),
+                    e,
+                    name
+                )
+            })
+        };
+        let (trace, expected) = (read("trace"), read("txt"));
+
+        let trace = AmxTrace::read_from(&trace[..]).unwrap();
+        assert!(
+            trace == record(case),
+            "the golden trace of `{}` is stale; re-record it on the hardware",
+            name
+        );
+
+        let expected = String::from_utf8(expected).unwrap();
+        let expected: String = expected
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(|line| vec![line, "\n"])
+            .collect();
+
+        let mut ctx = AmxEmuCtx::with_strictness(EmuStrictness::Strict);
+        amx::replay(&trace, &mut ctx);
+        assert_eq!(ctx.dump(DumpFormat::Hex), expected, "`{}`", name);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+#[ignore]
+fn record_golden_native() {
+    init();
+    let origin = std::env::var("AMX_GOLDEN_ORIGIN")
+        .unwrap_or_else(|_| format!("hardware ({})", std::env::consts::OS));
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    std::fs::create_dir_all(golden_dir()).unwrap();
+    for (name, case) in corpus() {
+        let trace = record(case);
+        amx::replay(&trace, &mut *ctx);
+
+        let mut bytes = Vec::new();
+        trace.write_to(&mut bytes).unwrap();
+        std::fs::write(golden_path(name, "trace"), bytes).unwrap();
+
+        let dump = format!("# Recorded on {}\n{}", origin, ctx.dump(DumpFormat::Hex));
+        std::fs::write(golden_path(name, "txt"), dump).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "half")]
+use amx::{Amx, AmxAligned, XBytes, XRow, YBytes, YRow, ZRow};
+use half::{bf16, f16};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+fn check_f16_outer_product(ctx: &mut impl Amx) {
+    let x = AmxAligned(std::array::from_fn::<f16, 32, _>(|i| {
+        f16::from_f32(i as f32)
+    }));
+    let y = AmxAligned(std::array::from_fn::<f16, 32, _>(|i| {
+        f16::from_f32(0.5 - i as f32)
+    }));
+    x.load512(ctx, XRow(0));
+    y.load512(ctx, YRow(0));
+    let tile = ctx.outer_product_f16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(0), false);
+
+    let mut out = AmxAligned([f16::ZERO; 32]);
+    for (i, row) in tile.rows().enumerate() {
+        out.store512(ctx, row);
+        for j in 0..32 {
+            assert_eq!(out[j], x[j] * y[i], "z[{}][{}]", i, j);
+        }
+    }
+}
+
+#[test]
+fn f16_outer_product_emu() {
+    init();
+    check_f16_outer_product(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn f16_outer_product_native() {
+    init();
+    check_f16_outer_product(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+fn bf16_lanes() {
+    init();
+    let mut ctx = amx::AmxEmuCtx::default();
+    let src = AmxAligned(std::array::from_fn::<bf16, 64, _>(|i| {
+        bf16::from_f32(i as f32 * 1.5)
+    }));
+    src.load1024(&mut ctx, YRow(2));
+
+    let mut dst = AmxAligned([bf16::ZERO; 64]);
+    dst.store1024(&mut ctx, YRow(2));
+    assert_eq!(dst, src);
+    assert_eq!(YBytes::of_element::<bf16>(2, 5), YBytes(138));
+}
+
+fn check_exp_softmax_f16(ctx: &mut impl Amx) {
+    let x: Vec<f16> = (0..=1600)
+        .map(|i| f16::from_f32(-i as f32 / 100.0))
+        .chain([f16::NEG_INFINITY, f16::MIN, f16::ZERO, f16::from_f32(5.0)])
+        .collect();
+    let mut out = vec![f16::NAN; x.len()];
+    amx::kernels::exp_approx_f16(ctx, &x, &mut out);
+    for (&x, &got) in x.iter().zip(&out) {
+        let expected = (x.to_f64().min(0.0)).exp();
+        let error = (got.to_f64() - expected).abs();
+        assert!(
+            error <= expected * 2e-3 || error < 1e-4,
+            "exp({}): got {}, expected {}",
+            x,
+            got,
+            expected
+        );
+    }
+
+    for &len in &[1, 32, 33, 100] {
+        let x: Vec<f16> = (0..len)
+            .map(|i| f16::from_f32((i * 7 % 23) as f32 * 0.25 + 100.0))
+            .collect();
+        let mut out = vec![f16::NAN; len];
+        amx::kernels::softmax_f16(ctx, &x, &mut out);
+
+        let max = x
+            .iter()
+            .map(|x| x.to_f64())
+            .fold(f64::NEG_INFINITY, f64::max);
+        let sum: f64 = x.iter().map(|x| (x.to_f64() - max).exp()).sum();
+        for (&x, &got) in x.iter().zip(&out) {
+            let expected = (x.to_f64() - max).exp() / sum;
+            assert!(
+                (got.to_f64() - expected).abs() <= expected * 4e-3 + 1e-4,
+                "len = {}: got {}, expected {}",
+                len,
+                got,
+                expected
+            );
+        }
+    }
+
+    let mut out = [f16::NAN; 4];
+    amx::kernels::softmax_f16(ctx, &[f16::NEG_INFINITY; 4], &mut out);
+    assert_eq!(out, [f16::from_f32(0.25); 4]);
+}
+
+#[test]
+fn exp_softmax_f16_emu() {
+    init();
+    check_exp_softmax_f16(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn exp_softmax_f16_native() {
+    init();
+    check_exp_softmax_f16(&mut *amx::AmxCtx::new().unwrap());
+}
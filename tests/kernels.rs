@@ -0,0 +1,1083 @@
+mod common;
+
+use amx::{
+    kernels::{self, Conv2dShape},
+    Amx, ZRow, ZTile64,
+};
+use common::Xorshift32;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+impl Xorshift32 {
+    /// Generate a small integer as `f32` so that the products and their sums
+    /// are exact.
+    fn next_f32(&mut self) -> f32 {
+        (self.next() % 17) as f32 - 8.0
+    }
+
+    fn next_i16(&mut self) -> i16 {
+        self.next() as i16
+    }
+}
+
+/// The matrix sizes `(m, n, k)` to test, including ones that aren't multiples
+/// of the tile size
+const SIZES: &[(usize, usize, usize)] = &[
+    (1, 1, 1),
+    (16, 16, 16),
+    (3, 5, 7),
+    (17, 65, 9),
+    (40, 130, 33),
+    (64, 64, 1),
+    (5, 4, 0),
+    (0, 4, 5),
+    (4, 0, 5),
+];
+
+/// `a`, `b`, and the expected product
+type Problem = (Vec<f32>, Vec<f32>, Vec<f32>);
+
+fn random_problem(m: usize, n: usize, k: usize) -> Problem {
+    let mut rng = Xorshift32(0x5eed + (m * 10000 + n * 100 + k) as u32);
+    let a: Vec<f32> = (0..m * k).map(|_| rng.next_f32()).collect();
+    let b: Vec<f32> = (0..k * n).map(|_| rng.next_f32()).collect();
+    let mut expected = vec![0.0; m * n];
+    for i in 0..m {
+        for j in 0..n {
+            expected[i * n + j] = (0..k).map(|p| a[i * k + p] * b[p * n + j]).sum();
+        }
+    }
+    (a, b, expected)
+}
+
+fn check_matmul_f32(ctx: &mut impl Amx) {
+    for &(m, n, k) in SIZES {
+        log::debug!("(m, n, k) = {:?}", (m, n, k));
+        let (a, b, expected) = random_problem(m, n, k);
+        let mut c = vec![f32::NAN; m * n];
+        kernels::matmul_f32(ctx, m, n, k, &a, &b, &mut c);
+        assert_eq!(c, expected, "(m, n, k) = {:?}", (m, n, k));
+    }
+}
+
+#[test]
+fn matmul_f32_emu() {
+    init();
+    check_matmul_f32(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn matmul_f32_native() {
+    init();
+    check_matmul_f32(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn check_matmul_f64(ctx: &mut impl Amx) {
+    let widen = |x: Vec<f32>| -> Vec<f64> { x.into_iter().map(f64::from).collect() };
+    for &(m, n, k) in SIZES {
+        log::debug!("(m, n, k) = {:?}", (m, n, k));
+        let (a, b, expected) = random_problem(m, n, k);
+        let (a, b, expected) = (widen(a), widen(b), widen(expected));
+        let mut c = vec![f64::NAN; m * n];
+        kernels::matmul_f64(ctx, m, n, k, &a, &b, &mut c);
+        assert_eq!(c, expected, "(m, n, k) = {:?}", (m, n, k));
+    }
+}
+
+#[test]
+fn matmul_f64_emu() {
+    init();
+    check_matmul_f64(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn matmul_f64_native() {
+    init();
+    check_matmul_f64(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn check_matmul_c32(ctx: &mut impl Amx) {
+    for &(m, n, k) in SIZES {
+        log::debug!("(m, n, k) = {:?}", (m, n, k));
+        let mut rng = Xorshift32(0xc32 + (m * 10000 + n * 100 + k) as u32);
+        let a: Vec<f32> = (0..2 * m * k).map(|_| rng.next_f32()).collect();
+        let b: Vec<f32> = (0..2 * k * n).map(|_| rng.next_f32()).collect();
+        let mut expected = vec![0.0; 2 * m * n];
+        for i in 0..m {
+            for j in 0..n {
+                for p in 0..k {
+                    let (a_re, a_im) = (a[2 * (i * k + p)], a[2 * (i * k + p) + 1]);
+                    let (b_re, b_im) = (b[2 * (p * n + j)], b[2 * (p * n + j) + 1]);
+                    expected[2 * (i * n + j)] += a_re * b_re - a_im * b_im;
+                    expected[2 * (i * n + j) + 1] += a_re * b_im + a_im * b_re;
+                }
+            }
+        }
+
+        let mut c = vec![f32::NAN; 2 * m * n];
+        kernels::matmul_c32(ctx, m, n, k, &a, &b, &mut c);
+        assert_eq!(c, expected);
+    }
+}
+
+#[test]
+fn matmul_c32_emu() {
+    init();
+    check_matmul_c32(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn matmul_c32_native() {
+    init();
+    check_matmul_c32(&mut *amx::AmxCtx::new().unwrap());
+}
+
+/// Calculate the reference result of `fft_butterfly{radix}_c32` in `f64`.
+fn fft_butterfly_ref(radix: usize, data: &[f32], twiddles: Option<&[f32]>) -> Vec<f64> {
+    let n = data.len() / (2 * radix);
+    let mut out = vec![0.0; data.len()];
+    for j in 0..radix {
+        for b in 0..n {
+            for p in 0..radix {
+                let i = 2 * (p * n + b);
+                let (mut re, mut im) = (data[i] as f64, data[i + 1] as f64);
+                if let Some(t) = twiddles {
+                    let (t_re, t_im) = (t[i] as f64, t[i + 1] as f64);
+                    (re, im) = (re * t_re - im * t_im, re * t_im + im * t_re);
+                }
+                let angle = -2.0 * std::f64::consts::PI * (j * p) as f64 / radix as f64;
+                let (w_re, w_im) = (angle.cos(), angle.sin());
+                out[2 * (j * n + b)] += w_re * re - w_im * im;
+                out[2 * (j * n + b) + 1] += w_re * im + w_im * re;
+            }
+        }
+    }
+    out
+}
+
+fn check_fft_butterfly(ctx: &mut impl Amx) {
+    for &(radix, n) in &[(4, 1), (4, 5), (4, 40), (8, 1), (8, 17), (8, 64)] {
+        log::debug!("(radix, n) = {:?}", (radix, n));
+        let mut rng = Xorshift32(0xff7 + (radix * 100 + n) as u32);
+        let data: Vec<f32> = (0..2 * radix * n).map(|_| rng.next_f32()).collect();
+        let twiddles: Vec<f32> = (0..2 * radix * n).map(|_| rng.next_f32()).collect();
+
+        for twiddles in [None, Some(&twiddles[..])] {
+            let mut got = data.clone();
+            if radix == 4 {
+                kernels::fft_butterfly4_c32(ctx, &mut got, twiddles);
+            } else {
+                kernels::fft_butterfly8_c32(ctx, &mut got, twiddles);
+            }
+            let expected = fft_butterfly_ref(radix, &data, twiddles);
+            for (i, (&got, &expected)) in got.iter().zip(&expected).enumerate() {
+                assert!(
+                    (got as f64 - expected).abs() <= 1e-3 * expected.abs().max(1.0),
+                    "[{}]: got {}, expected {}",
+                    i,
+                    got,
+                    expected
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn fft_butterfly_emu() {
+    init();
+    check_fft_butterfly(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn fft_butterfly_native() {
+    init();
+    check_fft_butterfly(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic]
+fn fft_butterfly_length_mismatch() {
+    kernels::fft_butterfly8_c32(&mut amx::AmxEmuCtx::default(), &mut [0.0; 12], None);
+}
+
+#[cfg(all(feature = "parallel", target_arch = "aarch64"))]
+#[test]
+fn matmul_f32_par() {
+    init();
+    for &(m, n, k) in SIZES.iter().chain(&[(300, 200, 100)]) {
+        log::debug!("(m, n, k) = {:?}", (m, n, k));
+        let (a, b, expected) = random_problem(m, n, k);
+        let mut c = vec![f32::NAN; m * n];
+        kernels::matmul_f32_par(m, n, k, &a, &b, &mut c);
+        assert_eq!(c, expected, "(m, n, k) = {:?}", (m, n, k));
+    }
+}
+
+/// A strip running on a thread that already has an `AmxCtx` must not share
+/// (and clobber) its registers
+#[cfg(all(feature = "parallel", target_arch = "aarch64"))]
+#[test]
+#[should_panic]
+fn matmul_f32_par_from_worker_with_ctx() {
+    init();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        let _ctx = amx::AmxCtx::new().unwrap();
+        let (a, b, _) = random_problem(32, 32, 32);
+        let mut c = vec![0.0; 32 * 32];
+        kernels::matmul_f32_par(32, 32, 32, &a, &b, &mut c);
+    });
+}
+
+/// The convolution shapes `(in_channels, out_channels, in_height, in_width,
+/// kernel_height, kernel_width)` to test
+const CONV_SHAPES: &[(usize, usize, usize, usize, usize, usize)] = &[
+    (1, 1, 1, 1, 1, 1),
+    (1, 1, 5, 5, 3, 3),
+    (3, 16, 6, 66, 3, 3),
+    (2, 33, 4, 130, 2, 5),
+    (4, 7, 3, 64, 3, 1),
+    (0, 5, 4, 4, 2, 2),
+    (2, 0, 4, 4, 2, 2),
+];
+
+fn conv_shape(
+    &(in_channels, out_channels, in_height, in_width, kernel_height, kernel_width): &(
+        usize,
+        usize,
+        usize,
+        usize,
+        usize,
+        usize,
+    ),
+) -> Conv2dShape {
+    Conv2dShape {
+        in_channels,
+        out_channels,
+        in_height,
+        in_width,
+        kernel_height,
+        kernel_width,
+    }
+}
+
+/// Calculate the convolution without AMX. `mul_add(acc, a, b)` calculates
+/// `acc + a * b`.
+fn conv2d_ref<T: Copy + Default>(
+    s: Conv2dShape,
+    input: &[T],
+    weights: &[T],
+    mul_add: impl Fn(T, T, T) -> T,
+) -> Vec<T> {
+    let (oh, ow) = (s.out_height(), s.out_width());
+    let mut output = vec![T::default(); s.out_channels * oh * ow];
+    for co in 0..s.out_channels {
+        for oy in 0..oh {
+            for ox in 0..ow {
+                let mut acc = T::default();
+                for ci in 0..s.in_channels {
+                    for dy in 0..s.kernel_height {
+                        for dx in 0..s.kernel_width {
+                            let i = (ci * s.in_height + oy + dy) * s.in_width + ox + dx;
+                            let wi = ((co * s.in_channels + ci) * s.kernel_height + dy)
+                                * s.kernel_width
+                                + dx;
+                            acc = mul_add(acc, input[i], weights[wi]);
+                        }
+                    }
+                }
+                output[(co * oh + oy) * ow + ox] = acc;
+            }
+        }
+    }
+    output
+}
+
+fn check_conv2d(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0xc0ffee);
+    for dims in CONV_SHAPES {
+        log::debug!("shape = {:?}", dims);
+        let s = conv_shape(dims);
+        let out_len = s.out_channels * s.out_height() * s.out_width();
+        let in_len = s.in_channels * s.in_height * s.in_width;
+        let w_len = s.out_channels * s.in_channels * s.kernel_height * s.kernel_width;
+
+        let input: Vec<f32> = (0..in_len).map(|_| rng.next_f32()).collect();
+        let weights: Vec<f32> = (0..w_len).map(|_| rng.next_f32()).collect();
+        let mut output = vec![f32::NAN; out_len];
+        kernels::conv2d_f32(ctx, s, &input, &weights, &mut output);
+        let expected = conv2d_ref(s, &input, &weights, |acc, a, b| acc + a * b);
+        assert_eq!(output, expected, "shape = {:?}", dims);
+
+        let input: Vec<i16> = (0..in_len).map(|_| rng.next_i16()).collect();
+        let weights: Vec<i16> = (0..w_len).map(|_| rng.next_i16()).collect();
+        let mut output = vec![0x5555; out_len];
+        kernels::conv2d_i16(ctx, s, &input, &weights, &mut output);
+        let expected = conv2d_ref(s, &input, &weights, |acc, a, b| {
+            acc.wrapping_add(a.wrapping_mul(b))
+        });
+        assert_eq!(output, expected, "shape = {:?}", dims);
+    }
+}
+
+#[test]
+fn conv2d_emu() {
+    init();
+    check_conv2d(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn conv2d_native() {
+    init();
+    check_conv2d(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic(expected = "the kernel must be non-empty and fit in the input")]
+fn conv2d_kernel_too_large() {
+    let s = conv_shape(&(1, 1, 2, 2, 3, 1));
+    kernels::conv2d_f32(
+        &mut amx::AmxEmuCtx::default(),
+        s,
+        &[0.0; 4],
+        &[0.0; 3],
+        &mut [],
+    );
+}
+
+/// The vector lengths to test, including ones that aren't multiples of the
+/// chunk size
+const VECTOR_LENS: &[usize] = &[0, 1, 15, 64, 128, 129, 1000];
+
+fn check_reduce(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0xd07);
+    for &len in VECTOR_LENS {
+        let a: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+        let b: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+        let expected: f32 = a.iter().zip(&b).map(|(x, y)| x * y).sum();
+        assert_eq!(kernels::dot_f32(ctx, &a, &b), expected, "len = {}", len);
+        assert_eq!(
+            kernels::sum_f32(ctx, &a),
+            a.iter().sum::<f32>(),
+            "len = {}",
+            len
+        );
+
+        let a: Vec<i16> = (0..len).map(|_| rng.next_i16()).collect();
+        let b: Vec<i16> = (0..len).map(|_| rng.next_i16()).collect();
+        let expected: i64 = a.iter().zip(&b).map(|(&x, &y)| x as i64 * y as i64).sum();
+        assert_eq!(kernels::dot_i16(ctx, &a, &b), expected, "len = {}", len);
+    }
+}
+
+#[test]
+fn reduce_emu() {
+    init();
+    check_reduce(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn reduce_native() {
+    init();
+    check_reduce(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic(expected = "`a` and `b` must have the same length")]
+fn dot_length_mismatch() {
+    kernels::dot_f32(&mut amx::AmxEmuCtx::default(), &[0.0; 3], &[0.0; 4]);
+}
+
+/// The scalar reference of `kernels::fingerprint_i16`, following its
+/// documentation.
+fn fingerprint_i16_ref(data: &[i16]) -> u64 {
+    let mut multipliers = [0u16; 32];
+    let mut state = 0x9e37_79b9u32;
+    for m in multipliers.iter_mut() {
+        state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+        *m = (state >> 16) as u16 | 1;
+    }
+
+    let mut streams = [[0u16; 32]; 8];
+    for (i, block) in data.chunks(32).enumerate() {
+        for (j, s) in streams[i % 8].iter_mut().enumerate() {
+            let value = block.get(j).map_or(0, |&x| x as u16);
+            *s = s.wrapping_mul(multipliers[j]).wrapping_add(value);
+        }
+    }
+    streams
+        .iter()
+        .flatten()
+        .fold(data.len() as u64, |hash, &lane| {
+            (hash ^ lane as u64).wrapping_mul(0x100_0000_01b3)
+        })
+}
+
+fn check_fingerprint(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0xf1a9);
+    let mut seen = std::collections::HashSet::new();
+    for &len in VECTOR_LENS.iter().chain(&[256, 257, 5000]) {
+        let data: Vec<i16> = (0..len).map(|_| rng.next() as i16).collect();
+        let hash = kernels::fingerprint_i16(ctx, &data);
+        assert_eq!(hash, fingerprint_i16_ref(&data), "len = {}", len);
+        assert!(seen.insert(hash), "len = {}", len);
+    }
+
+    // Trailing zeros change the length
+    assert_ne!(
+        kernels::fingerprint_i16(ctx, &[0; 31]),
+        kernels::fingerprint_i16(ctx, &[0; 32])
+    );
+}
+
+#[test]
+fn fingerprint_emu() {
+    init();
+    check_fingerprint(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn fingerprint_native() {
+    init();
+    check_fingerprint(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn check_argmax(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0xa59);
+    for &len in &VECTOR_LENS[1..] {
+        for &len in &[len, len + 256, len + 700] {
+            // Many ties, so this checks that the first one is found
+            let mut x: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+            if len > 1 {
+                x[rng.next() as usize % len] = f32::NAN;
+            }
+            let max = x.iter().copied().fold(f32::NAN, f32::max);
+            let min = x.iter().copied().fold(f32::NAN, f32::min);
+            let first = |target: f32| x.iter().position(|&v| v == target).unwrap();
+            assert_eq!(kernels::argmax_f32(ctx, &x), first(max), "len = {}", len);
+            assert_eq!(kernels::argmin_f32(ctx, &x), first(min), "len = {}", len);
+        }
+    }
+
+    let mut x = vec![f32::NAN; 300];
+    assert_eq!(kernels::argmax_f32(ctx, &x), 0);
+    x[299] = f32::NEG_INFINITY;
+    assert_eq!(kernels::argmax_f32(ctx, &x), 299);
+    assert_eq!(kernels::argmin_f32(ctx, &x), 299);
+
+    // Infinities compare equal to the last table entry
+    x[100] = 1.0;
+    x[200] = f32::INFINITY;
+    x[250] = f32::INFINITY;
+    assert_eq!(kernels::argmax_f32(ctx, &x), 200);
+    assert_eq!(kernels::argmin_f32(ctx, &x), 299);
+}
+
+#[test]
+fn argmax_emu() {
+    init();
+    check_argmax(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn argmax_native() {
+    init();
+    check_argmax(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic(expected = "`x` must not be empty")]
+fn argmax_empty() {
+    kernels::argmax_f32(&mut amx::AmxEmuCtx::default(), &[]);
+}
+
+fn check_elementwise(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0xa4b1);
+    for &len in VECTOR_LENS {
+        let a: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+        let b: Vec<f32> = (0..len).map(|_| rng.next_f32()).collect();
+
+        let mut y = b.clone();
+        kernels::axpy_f32(ctx, -3.0, &a, &mut y);
+        let expected: Vec<f32> = a.iter().zip(&b).map(|(x, y)| -3.0 * x + y).collect();
+        assert_eq!(y, expected, "len = {}", len);
+
+        let mut x = a.clone();
+        kernels::scale_f32(ctx, 0.5, &mut x);
+        let expected: Vec<f32> = a.iter().map(|x| x * 0.5).collect();
+        assert_eq!(x, expected, "len = {}", len);
+
+        let mut out = vec![f32::NAN; len];
+        kernels::add_f32(ctx, &a, &b, &mut out);
+        let expected: Vec<f32> = a.iter().zip(&b).map(|(x, y)| x + y).collect();
+        assert_eq!(out, expected, "len = {}", len);
+
+        kernels::mul_f32(ctx, &a, &b, &mut out);
+        let expected: Vec<f32> = a.iter().zip(&b).map(|(x, y)| x * y).collect();
+        assert_eq!(out, expected, "len = {}", len);
+    }
+}
+
+#[test]
+fn elementwise_emu() {
+    init();
+    check_elementwise(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn elementwise_native() {
+    init();
+    check_elementwise(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+fn batch_interleave() {
+    let src: Vec<f32> = (0..3 * 20).map(|i| i as f32).collect();
+    let mut interleaved = vec![f32::NAN; kernels::batch_interleaved_len(3, 20)];
+    assert_eq!(interleaved.len(), 3 * 32);
+    kernels::batch_interleave_f32(3, &src, &mut interleaved);
+    // The element 1 of the matrix 17
+    assert_eq!(interleaved[(3 + 1) * 16 + 1], 17.0 * 3.0 + 1.0);
+    // Padding
+    assert_eq!(interleaved[(3 + 2) * 16 + 15], 0.0);
+
+    let mut dst = vec![f32::NAN; src.len()];
+    kernels::batch_deinterleave_f32(3, &interleaved, &mut dst);
+    assert_eq!(dst, src);
+}
+
+fn check_batched_matmul_f32(ctx: &mut impl Amx) {
+    for &(m, n, k, count) in &[
+        (4, 4, 4, 37),
+        (8, 8, 8, 16),
+        (3, 2, 5, 5),
+        (1, 8, 1, 17),
+        (2, 2, 0, 3),
+    ] {
+        log::debug!("(m, n, k, count) = {:?}", (m, n, k, count));
+        let problems: Vec<_> = (0..count).map(|_| random_problem(m, n, k)).collect();
+        let flatten = |f: fn(&Problem) -> &Vec<f32>| -> Vec<f32> {
+            problems.iter().flat_map(|p| f(p).iter().copied()).collect()
+        };
+        let (a, b, expected) = (flatten(|p| &p.0), flatten(|p| &p.1), flatten(|p| &p.2));
+
+        let interleave = |elems: usize, src: &[f32]| {
+            if elems == 0 {
+                return Vec::new();
+            }
+            let mut dst = vec![f32::NAN; kernels::batch_interleaved_len(elems, count)];
+            kernels::batch_interleave_f32(elems, src, &mut dst);
+            dst
+        };
+        let (a_il, b_il) = (interleave(m * k, &a), interleave(k * n, &b));
+        let mut c_il = vec![f32::NAN; kernels::batch_interleaved_len(m * n, count)];
+        kernels::batched_matmul_f32(ctx, m, n, k, &a_il, &b_il, &mut c_il);
+
+        let mut c = vec![f32::NAN; m * n * count];
+        kernels::batch_deinterleave_f32(m * n, &c_il, &mut c);
+        assert_eq!(c, expected, "(m, n, k, count) = {:?}", (m, n, k, count));
+    }
+}
+
+#[test]
+fn batched_matmul_f32_emu() {
+    init();
+    check_batched_matmul_f32(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn batched_matmul_f32_native() {
+    init();
+    check_batched_matmul_f32(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn load_f64_tile(ctx: &mut impl Amx, tile: ZTile64, m: &[[f64; 8]; 8]) {
+    for (i, row) in m.iter().enumerate() {
+        unsafe { ctx.load512(row.as_ptr(), tile.row(i)) };
+    }
+}
+
+fn store_f64_tile(ctx: &mut impl Amx, tile: ZTile64) -> [[f64; 8]; 8] {
+    let mut m = [[0.0; 8]; 8];
+    for (i, row) in m.iter_mut().enumerate() {
+        unsafe { ctx.store512(row.as_mut_ptr(), tile.row(i)) };
+    }
+    m
+}
+
+fn assert_close(got: &[[f64; 8]; 8], expected: &[[f64; 8]; 8]) {
+    for i in 0..8 {
+        for j in 0..8 {
+            assert!(
+                (got[i][j] - expected[i][j]).abs() < 1e-9,
+                "mismatch at ({}, {}):\n got = {:?}\n expected = {:?}",
+                i,
+                j,
+                got,
+                expected
+            );
+        }
+    }
+}
+
+fn check_potrf_trsm_f64_tile(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0xc401);
+    for _ in 0..10 {
+        // `a = m * mᵀ + 8 * I` is positive-definite
+        let m: Vec<f64> = (0..64).map(|_| rng.next_f32() as f64).collect();
+        let mut a = [[0.0; 8]; 8];
+        for i in 0..8 {
+            for j in 0..8 {
+                a[i][j] = (0..8).map(|p| m[i * 8 + p] * m[j * 8 + p]).sum::<f64>();
+            }
+            a[i][i] += 8.0;
+        }
+        let b: Vec<f64> = (0..64).map(|_| rng.next_f32() as f64).collect();
+
+        // Fill the other tiles, which must be left intact
+        let z: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+        unsafe {
+            for (i, row) in z.chunks_exact(64).enumerate() {
+                ctx.load512(row.as_ptr(), ZRow(i));
+            }
+        }
+
+        let (l_tile, b_tile) = (ZTile64 { base: 5 }, ZTile64 { base: 2 });
+        load_f64_tile(ctx, l_tile, &a);
+        kernels::potrf_f64_tile(ctx, l_tile).unwrap();
+        let l = store_f64_tile(ctx, l_tile);
+
+        // `l` is lower triangular and `l * lᵀ = a`
+        let mut llt = [[0.0; 8]; 8];
+        for i in 0..8 {
+            for j in 0..8 {
+                if j > i {
+                    assert_eq!(l[i][j], 0.0);
+                }
+                llt[i][j] = (0..8).map(|p| l[i][p] * l[j][p]).sum();
+            }
+        }
+        assert_close(&llt, &a);
+
+        let mut b_mat = [[0.0; 8]; 8];
+        for i in 0..8 {
+            b_mat[i].copy_from_slice(&b[i * 8..][..8]);
+        }
+        load_f64_tile(ctx, b_tile, &b_mat);
+        kernels::trsm_f64_tile(ctx, l_tile, b_tile);
+        let x = store_f64_tile(ctx, b_tile);
+
+        // `x * lᵀ = b`
+        let mut xlt = [[0.0; 8]; 8];
+        for i in 0..8 {
+            for j in 0..8 {
+                xlt[i][j] = (0..8).map(|p| x[i][p] * l[j][p]).sum();
+            }
+        }
+        assert_close(&xlt, &b_mat);
+        assert_eq!(store_f64_tile(ctx, l_tile), l);
+
+        let got_z = ctx.read_z();
+        for (i, (got, expected)) in got_z.chunks_exact(64).zip(z.chunks_exact(64)).enumerate() {
+            if i % 8 != l_tile.base && i % 8 != b_tile.base {
+                assert_eq!(got, expected, "z[{}] was clobbered", i);
+            }
+        }
+    }
+}
+
+#[test]
+fn potrf_trsm_f64_tile_emu() {
+    init();
+    check_potrf_trsm_f64_tile(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn potrf_trsm_f64_tile_native() {
+    init();
+    check_potrf_trsm_f64_tile(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+fn potrf_f64_tile_not_positive_definite() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    let mut a = [[0.0; 8]; 8];
+    for (i, row) in a.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    a[3][3] = -1.0;
+    load_f64_tile(&mut ctx, ZTile64 { base: 0 }, &a);
+    assert_eq!(
+        kernels::potrf_f64_tile(&mut ctx, ZTile64 { base: 0 }),
+        Err(kernels::NotPositiveDefinite { column: 3 })
+    );
+}
+
+fn check_exp_softmax(ctx: &mut impl Amx) {
+    let x: Vec<f32> = (0..=2000)
+        .map(|i| -i as f32 / 100.0)
+        .chain([f32::NEG_INFINITY, -1e30, 0.0, 5.0])
+        .collect();
+    let mut out = vec![f32::NAN; x.len()];
+    kernels::exp_approx_f32(ctx, &x, &mut out);
+    for (&x, &got) in x.iter().zip(&out) {
+        let expected = x.min(0.0).exp();
+        let error = (got - expected).abs();
+        assert!(
+            error <= expected * 1e-3 || error < 1e-6,
+            "exp({}): got {}, expected {}",
+            x,
+            got,
+            expected
+        );
+    }
+
+    let mut rng = Xorshift32(0x50f7);
+    for &len in &[1, 16, 17, 100] {
+        let x: Vec<f32> = (0..len).map(|_| rng.next_f32() + 100.0).collect();
+        let mut out = vec![f32::NAN; len];
+        kernels::softmax_f32(ctx, &x, &mut out);
+
+        let max = x.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let sum: f64 = x.iter().map(|&x| ((x - max) as f64).exp()).sum();
+        for (&x, &got) in x.iter().zip(&out) {
+            let expected = (((x - max) as f64).exp() / sum) as f32;
+            assert!(
+                (got - expected).abs() <= expected * 2e-3 + 1e-6,
+                "len = {}: got {}, expected {}",
+                len,
+                got,
+                expected
+            );
+        }
+    }
+
+    let mut out = [f32::NAN; 5];
+    kernels::softmax_f32(ctx, &[f32::NEG_INFINITY; 5], &mut out);
+    assert_eq!(out, [0.2; 5]);
+}
+
+#[test]
+fn exp_softmax_emu() {
+    init();
+    check_exp_softmax(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn exp_softmax_native() {
+    init();
+    check_exp_softmax(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn check_polyval(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0x9e37);
+    for &degree in &[0, 1, 4, 9] {
+        let coeffs: Vec<f32> = (0..=degree).map(|_| rng.next_f32()).collect();
+        for &len in &[0, 1, 16, 17, 128, 300] {
+            let xs: Vec<f32> = (0..len).map(|_| rng.next_f32() * 2.0).collect();
+            let mut out = vec![f32::NAN; len];
+            kernels::polyval_f32(ctx, &coeffs, &xs, &mut out);
+            let expected: Vec<f32> = xs
+                .iter()
+                .map(|&x| {
+                    coeffs[1..]
+                        .iter()
+                        .fold(coeffs[0], |acc, &c| acc.mul_add(x, c))
+                })
+                .collect();
+            assert_eq!(out, expected, "degree = {}, len = {}", degree, len);
+        }
+    }
+
+    let mut out = [f32::NAN; 20];
+    kernels::polyval_f32(ctx, &[], &[1.0; 20], &mut out);
+    assert_eq!(out, [0.0; 20]);
+}
+
+#[test]
+fn polyval_emu() {
+    init();
+    check_polyval(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn polyval_native() {
+    init();
+    check_polyval(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn check_quantize(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0x9a47);
+    for &len in VECTOR_LENS {
+        for &(scale, zero_point) in &[(1.0, 0), (0.37, -20), (1.0 / 64.0, 100)] {
+            let x: Vec<f32> = (0..len)
+                .map(|_| (rng.next() as i32) as f32 / (1u32 << 31) as f32 * 300.0 * scale)
+                .chain([f32::INFINITY, f32::NEG_INFINITY, 1e30, 2.5 * scale])
+                .collect();
+            // The product is rounded only once, as the kernel uses a fused
+            // multiply-add
+            let round = |x: f32| (x as f64 * (1.0 / scale) as f64).round_ties_even();
+
+            let mut q8 = vec![0i8; x.len()];
+            kernels::quantize_f32_to_i8(ctx, scale, zero_point as i8, &x, &mut q8);
+            let expected: Vec<i8> = x
+                .iter()
+                .map(|&x| (round(x) + zero_point as f64) as i8)
+                .collect();
+            assert_eq!(q8, expected, "len = {}", len);
+
+            let mut q16 = vec![0i16; x.len()];
+            kernels::quantize_f32_to_i16(ctx, scale, zero_point as i16 * 200, &x, &mut q16);
+            let zero_point = zero_point * 200;
+            let expected: Vec<i16> = x
+                .iter()
+                .map(|&x| (round(x) + zero_point as f64) as i16)
+                .collect();
+            assert_eq!(q16, expected, "len = {}", len);
+
+            let mut deq = vec![f32::NAN; x.len()];
+            kernels::dequantize_i16_to_f32(ctx, scale, zero_point as i16, &q16, &mut deq);
+            let expected: Vec<f32> = q16
+                .iter()
+                .map(|&q| (q as f32 - zero_point as f32) * scale)
+                .collect();
+            assert_eq!(deq, expected, "len = {}", len);
+        }
+
+        let q: Vec<i8> = (0..len).map(|_| rng.next() as i8).collect();
+        let mut deq = vec![f32::NAN; len];
+        kernels::dequantize_i8_to_f32(ctx, 0.25, -3, &q, &mut deq);
+        let expected: Vec<f32> = q.iter().map(|&q| (q as f32 + 3.0) * 0.25).collect();
+        assert_eq!(deq, expected, "len = {}", len);
+    }
+}
+
+#[test]
+fn quantize_emu() {
+    init();
+    check_quantize(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn quantize_native() {
+    init();
+    check_quantize(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn check_memcpy_fill(ctx: &mut impl Amx) {
+    const LEN: usize = 3072;
+    let mut rng = Xorshift32(0x3e3c);
+    // Aligned to 128-byte boundaries, so that offset `0` takes the path using
+    // 128-byte loads and stores
+    let mut src = Box::new(amx::AmxAligned([0u8; LEN]));
+    let mut dst = Box::new(amx::AmxAligned([0u8; LEN]));
+    for byte in src.0.iter_mut() {
+        *byte = rng.next() as u8;
+    }
+    let pattern: [u8; 64] = std::array::from_fn(|_| rng.next() as u8);
+
+    for &(dst_offset, src_offset) in &[(0, 0), (1, 0), (0, 64), (128, 256), (7, 13)] {
+        for &len in &[0, 1, 63, 64, 127, 128, 512, 1000, 2048] {
+            let (dst, src) = (&mut dst.0[..], &src.0[..]);
+            dst.fill(0xaa);
+            kernels::amx_memcpy(
+                ctx,
+                &mut dst[dst_offset..][..len],
+                &src[src_offset..][..len],
+            );
+            assert_eq!(dst[dst_offset..][..len], src[src_offset..][..len]);
+            assert!(dst[..dst_offset].iter().all(|&x| x == 0xaa));
+            assert!(dst[dst_offset + len..].iter().all(|&x| x == 0xaa));
+
+            dst.fill(0xaa);
+            kernels::amx_fill64(ctx, &mut dst[dst_offset..][..len], &pattern);
+            for (i, &x) in dst[dst_offset..][..len].iter().enumerate() {
+                assert_eq!(x, pattern[i % 64], "len = {}, i = {}", len, i);
+            }
+            assert!(dst[..dst_offset].iter().all(|&x| x == 0xaa));
+            assert!(dst[dst_offset + len..].iter().all(|&x| x == 0xaa));
+        }
+    }
+}
+
+#[test]
+fn memcpy_fill_emu() {
+    init();
+    check_memcpy_fill(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn memcpy_fill_native() {
+    init();
+    check_memcpy_fill(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic(expected = "`dst` and `src` must have the same length")]
+fn memcpy_length_mismatch() {
+    kernels::amx_memcpy(&mut amx::AmxEmuCtx::default(), &mut [0; 3], &[0; 4]);
+}
+
+/// The scalar reference of `kernels::image::filter3x3_u8`, returning the
+/// weighted sums.
+fn filter3x3_ref(src: &[u8], width: usize, height: usize, kernel: &[[i8; 3]; 3]) -> Vec<i32> {
+    let clamp = |i: usize, di: usize, len: usize| (i + di).saturating_sub(1).min(len - 1);
+    let mut out = Vec::with_capacity(src.len());
+    for r in 0..height {
+        for c in 0..width {
+            let mut sum = 0;
+            for (dr, row) in kernel.iter().enumerate() {
+                for (dc, &w) in row.iter().enumerate() {
+                    let pixel = src[clamp(r, dr, height) * width + clamp(c, dc, width)];
+                    sum += w as i32 * pixel as i32;
+                }
+            }
+            out.push(sum);
+        }
+    }
+    out
+}
+
+fn check_image(ctx: &mut impl Amx) {
+    use kernels::image;
+    let mut rng = Xorshift32(0x1a6e);
+    let gaussian = [[1, 2, 1], [2, 4, 2], [1, 2, 1]];
+    let sharpen = [[0, -1, 0], [-1, 5, -1], [0, -1, 0]];
+    let extreme = [[-128, 127, -128], [127, -128, 127], [-128, 127, -128]];
+    let sobel_x = [[-1, 0, 1], [-2, 0, 2], [-1, 0, 1]];
+    let sobel_y = [[-1, -2, -1], [0, 0, 0], [1, 2, 1]];
+    for &(width, height) in &[(1, 1), (5, 3), (16, 16), (17, 33), (40, 20)] {
+        let src: Vec<u8> = (0..width * height).map(|_| rng.next() as u8).collect();
+        let mut dst = vec![0; src.len()];
+        let saturate = |x: i64| x.clamp(0, 255) as u8;
+
+        for &(kernel, shift) in &[(&gaussian, 4), (&sharpen, 0), (&extreme, 7)] {
+            image::filter3x3_u8(ctx, &src, width, height, kernel, shift, &mut dst);
+            let expected: Vec<u8> = filter3x3_ref(&src, width, height, kernel)
+                .into_iter()
+                .map(|sum| saturate((sum as i64 + ((1 << shift) >> 1)) >> shift))
+                .collect();
+            assert_eq!(dst, expected, "{}x{} {:?}", width, height, kernel);
+        }
+
+        image::box_blur3x3_u8(ctx, &src, width, height, &mut dst);
+        let expected: Vec<u8> = filter3x3_ref(&src, width, height, &[[1; 3]; 3])
+            .into_iter()
+            .map(|sum| ((sum + 4) / 9) as u8)
+            .collect();
+        assert_eq!(dst, expected, "{}x{} box blur", width, height);
+
+        image::sobel3x3_u8(ctx, &src, width, height, &mut dst);
+        let gx = filter3x3_ref(&src, width, height, &sobel_x);
+        let gy = filter3x3_ref(&src, width, height, &sobel_y);
+        let expected: Vec<u8> = gx
+            .iter()
+            .zip(&gy)
+            .map(|(x, y)| saturate(x.abs() as i64 + y.abs() as i64))
+            .collect();
+        assert_eq!(dst, expected, "{}x{} Sobel", width, height);
+    }
+}
+
+#[test]
+fn image_emu() {
+    init();
+    check_image(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn image_native() {
+    init();
+    check_image(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic(expected = "`dst.len()` must be `width * height`")]
+fn image_size_mismatch() {
+    kernels::image::box_blur3x3_u8(&mut amx::AmxEmuCtx::default(), &[0; 6], 3, 2, &mut [0; 5]);
+}
+
+fn check_biquad(ctx: &mut impl Amx) {
+    use kernels::{Biquad, BiquadBank16};
+    let mut rng = Xorshift32(0xb10d);
+    let mut uniform = || (rng.next() % 2001) as f32 / 1000.0 - 1.0;
+    // Stable filters: the poles are inside the unit circle
+    let filters: [Biquad; 16] = std::array::from_fn(|_| {
+        let (r, theta) = (0.5 + 0.45 * uniform().abs(), 3.0 * uniform());
+        Biquad {
+            b0: uniform(),
+            b1: uniform(),
+            b2: uniform(),
+            a1: -2.0 * r * theta.cos(),
+            a2: r * r,
+        }
+    });
+    let input: Vec<f32> = (0..16 * 300).map(|_| uniform()).collect();
+
+    // The scalar reference, using the same operations
+    let mut expected = input.clone();
+    for (ch, f) in filters.iter().enumerate() {
+        let (mut s1, mut s2) = (0.0f32, 0.0f32);
+        for x in expected.iter_mut().skip(ch).step_by(16) {
+            let y = f.b0.mul_add(*x, s1);
+            s1 = (-f.a1).mul_add(y, f.b1.mul_add(*x, s2));
+            s2 = (-f.a2).mul_add(y, f.b2 * *x);
+            *x = y;
+        }
+    }
+
+    // Processing in pieces carries the state over
+    let mut bank = BiquadBank16::new(&filters);
+    let mut got = input.clone();
+    for piece in got.chunks_mut(16 * 70) {
+        bank.process(ctx, piece);
+    }
+    assert_eq!(got, expected);
+
+    bank.reset();
+    let mut got = input;
+    bank.process(ctx, &mut got);
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn biquad_emu() {
+    init();
+    check_biquad(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn biquad_native() {
+    init();
+    check_biquad(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic(expected = "`frames.len()` must be a multiple of 16")]
+fn biquad_partial_frame() {
+    let mut bank = kernels::BiquadBank16::new(&[kernels::Biquad::IDENTITY; 16]);
+    bank.process(&mut amx::AmxEmuCtx::default(), &mut [0.0; 17]);
+}
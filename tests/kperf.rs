@@ -0,0 +1,31 @@
+#![cfg(feature = "kperf")]
+use amx::kperf::{Counts, KperfError, Profiler};
+
+#[test]
+fn measure() {
+    let profiler = match Profiler::new() {
+        Ok(x) => x,
+        Err(e) => {
+            // Requires macOS and root privileges
+            if !cfg!(target_os = "macos") {
+                assert_eq!(e, KperfError::Unsupported);
+            }
+            return;
+        }
+    };
+
+    let (sum, counts) =
+        profiler.measure(|| (0..1_000_000u64).map(std::hint::black_box).sum::<u64>());
+    assert_eq!(sum, 499_999_500_000);
+    assert!(counts.instructions >= 1_000_000, "{:?}", counts);
+    assert!(counts.cycles > 0, "{:?}", counts);
+}
+
+#[test]
+fn ipc() {
+    let counts = Counts {
+        cycles: 200,
+        instructions: 500,
+    };
+    assert_eq!(counts.ipc(), 2.5);
+}
@@ -0,0 +1,155 @@
+use amx::{Amx, AmxAligned, RecordingOps};
+use std::convert::TryInto;
+
+/// Check `load_all_*` with `src` at byte offset `misalign` from a 128-byte
+/// boundary.
+fn check_load_all(ctx: &mut impl Amx, misalign: usize) {
+    let mut buf = AmxAligned([0u8; 4096 + 64]);
+    for (i, b) in buf.0.iter_mut().enumerate() {
+        *b = (i * 7 + i / 64) as u8;
+    }
+    let src = &buf.0[misalign..][..4096];
+
+    ctx.load_all_x(src[..512].try_into().unwrap());
+    ctx.load_all_y(src[512..1024].try_into().unwrap());
+    ctx.load_all_z(src.try_into().unwrap());
+    assert_eq!(ctx.read_x()[..], src[..512]);
+    assert_eq!(ctx.read_y()[..], src[512..1024]);
+    assert_eq!(ctx.read_z()[..], src[..]);
+}
+
+#[test]
+fn load_all_emu() {
+    for &misalign in &[0, 64, 1] {
+        check_load_all(&mut amx::AmxEmuCtx::default(), misalign);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn load_all_native() {
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    for &misalign in &[0, 64, 1] {
+        check_load_all(&mut *ctx, misalign);
+    }
+}
+
+#[test]
+fn load_all_uses_pair_loads_when_aligned() {
+    let buf = AmxAligned([0u8; 4160]);
+    let count = |misalign: usize| {
+        let mut ctx = RecordingOps::new(amx::AmxEmuCtx::default());
+        ctx.load_all_z(buf.0[misalign..][..4096].try_into().unwrap());
+        ctx.load_all_x(buf.0[misalign..][..512].try_into().unwrap());
+        ctx.into_parts().1.ops.len()
+    };
+    assert_eq!(count(0), 32 + 4);
+    assert_eq!(count(64), 64 + 8);
+}
+
+/// Check `store_all_*` with `dst` at byte offset `misalign` from a 128-byte
+/// boundary.
+fn check_store_all(ctx: &mut impl Amx, misalign: usize) {
+    let src: Vec<u8> = (0..4096).map(|i| (i * 5 + i / 64) as u8).collect();
+    for i in 0..64 {
+        unsafe { ctx.load512(src[i * 64..].as_ptr(), amx::ZRow(i)) };
+    }
+    ctx.load_all_x(src[1024..1536].try_into().unwrap());
+    ctx.load_all_y(src[2048..2560].try_into().unwrap());
+
+    let mut buf = AmxAligned([0u8; 4096 + 64]);
+    let dst = &mut buf.0[misalign..][..4096];
+    ctx.store_all_z(dst.try_into().unwrap());
+    assert_eq!(dst[..], src[..]);
+    ctx.store_all_x((&mut dst[..512]).try_into().unwrap());
+    assert_eq!(dst[..512], src[1024..1536]);
+    ctx.store_all_y((&mut dst[512..1024]).try_into().unwrap());
+    assert_eq!(dst[512..1024], src[2048..2560]);
+}
+
+#[test]
+fn store_all_emu() {
+    for &misalign in &[0, 64, 1] {
+        check_store_all(&mut amx::AmxEmuCtx::default(), misalign);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn store_all_native() {
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    for &misalign in &[0, 64, 1] {
+        check_store_all(&mut *ctx, misalign);
+    }
+}
+
+#[test]
+fn store_all_uses_pair_stores_when_aligned() {
+    let mut buf = AmxAligned([0u8; 4160]);
+    let mut count = |misalign: usize| {
+        let mut ctx = RecordingOps::new(amx::AmxEmuCtx::default());
+        ctx.store_all_z((&mut buf.0[misalign..][..4096]).try_into().unwrap());
+        ctx.store_all_y((&mut buf.0[misalign..][..512]).try_into().unwrap());
+        ctx.into_parts().1.ops.len()
+    };
+    assert_eq!(count(0), 32 + 4);
+    assert_eq!(count(64), 64 + 8);
+}
+
+/// Check `load_{x,y}_rows` for every row range with `src` at byte offset
+/// `misalign` from a 128-byte boundary.
+fn check_load_rows(ctx: &mut impl Amx, misalign: usize) {
+    let mut buf = AmxAligned([0u8; 512 + 64]);
+    for (i, b) in buf.0.iter_mut().enumerate() {
+        *b = (i * 3 + i / 64 + 1) as u8;
+    }
+    let src = &buf.0[misalign..][..512];
+
+    for start in 0..=8 {
+        for end in start..=8 {
+            ctx.load_all_x(&[0; 512]);
+            ctx.load_all_y(&[0; 512]);
+            unsafe {
+                ctx.load_x_rows(src.as_ptr(), start..end);
+                ctx.load_y_rows(src.as_ptr(), start..end);
+            }
+            let mut expected = [0u8; 512];
+            expected[start * 64..end * 64].copy_from_slice(&src[..(end - start) * 64]);
+            assert_eq!(ctx.read_x()[..], expected[..], "{:?}", start..end);
+            assert_eq!(ctx.read_y()[..], expected[..], "{:?}", start..end);
+        }
+    }
+}
+
+#[test]
+fn load_rows_emu() {
+    for &misalign in &[0, 64, 1] {
+        check_load_rows(&mut amx::AmxEmuCtx::default(), misalign);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn load_rows_native() {
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    for &misalign in &[0, 64, 1] {
+        check_load_rows(&mut *ctx, misalign);
+    }
+}
+
+#[test]
+fn load_rows_uses_pair_loads_when_aligned() {
+    let buf = AmxAligned([0u8; 576]);
+    let count = |misalign: usize, rows: std::ops::Range<usize>| {
+        let mut ctx = RecordingOps::new(amx::AmxEmuCtx::default());
+        unsafe { ctx.load_x_rows(buf.0[misalign..].as_ptr(), rows) };
+        ctx.into_parts().1.ops.len()
+    };
+    assert_eq!(count(0, 0..8), 4);
+    assert_eq!(count(0, 2..7), 3);
+    // `x[1]` alone, then `x[2..4]`, `x[4..6]`, `x[6]`
+    assert_eq!(count(64, 1..7), 4);
+    // The even rows' sources are misaligned
+    assert_eq!(count(64, 0..8), 8);
+    assert_eq!(count(1, 0..8), 8);
+}
@@ -1,7 +1,6 @@
-#![feature(array_chunks)]
-use aligned_box::AlignedBox;
-use amx::{prelude::*, AmxOps, XRow, YRow, ZRow};
+use amx::{prelude::*, AmxAligned, AmxOps, XRow, YRow, ZRow};
 use itertools::iproduct;
+use std::convert::TryInto;
 
 fn init() {
     let _ = env_logger::builder().is_test(true).try_init();
@@ -98,7 +97,7 @@ fn copy_and_check_memory() {
     init();
     let mut ctx = amx::AmxCtx::new().unwrap();
 
-    let mut src: AlignedBox<[u16]> = AlignedBox::slice_from_default(0x80, 4096).unwrap();
+    let mut src = AmxAligned([0u16; 4096]);
     for (i, src) in src.iter_mut().enumerate() {
         *src = i as _;
     }
@@ -124,7 +123,7 @@ fn copy_and_check_memory() {
             interleaved
         );
 
-        let mut got: AlignedBox<[u16]> = AlignedBox::slice_from_value(0x80, 4096, 0xbeef).unwrap();
+        let mut got = AmxAligned([0xbeefu16; 4096]);
         let expected: Vec<u16> = (0..4096)
             .map(|i| {
                 if i as usize * 2 < size.num_bytes() {
@@ -147,7 +146,7 @@ fn copy_and_check_memory() {
             );
         }
 
-        assert_eq!(*got, *expected);
+        assert_eq!(got[..], expected[..]);
     }
 }
 
@@ -156,7 +155,7 @@ fn load_and_check_register() {
     init();
     let mut ctx = amx::AmxCtx::new().unwrap();
 
-    let mut pat1: AlignedBox<[u64]> = AlignedBox::slice_from_default(0x80, 16).unwrap();
+    let mut pat1 = AmxAligned([0u64; 16]);
     for (i, pat1) in pat1.iter_mut().enumerate() {
         *pat1 = i as u64 + (75 - i as u64) * 0x100000000;
     }
@@ -214,8 +213,8 @@ fn load_and_check_register() {
             _ => unreachable!(),
         };
         let got: Vec<u64> = got
-            .array_chunks::<8>()
-            .map(|x| u64::from_le_bytes(*x))
+            .chunks_exact(8)
+            .map(|x| u64::from_le_bytes(x.try_into().unwrap()))
             .collect();
 
         // Calculate the expected result
@@ -232,7 +231,7 @@ fn load_and_check_register() {
             // the resultant low parts go to
             // `z[reg_index][second_half * 4..][..4]`. The high parts go to
             // `z[reg_index + 1][second_half * 4..][..4]`
-            let reg_start = (reg_offset as usize % 2) * 4 + (reg_offset as usize / 2) * 16;
+            let reg_start = (reg_offset % 2) * 4 + (reg_offset / 2) * 16;
             for i in (0..size.num_bytes() / 8).step_by(2) {
                 let low1 = pat1[i] & 0xffff_ffff;
                 let low2 = pat1[i + 1] & 0xffff_ffff;
@@ -244,7 +243,7 @@ fn load_and_check_register() {
         } else {
             // Simple copy with register index wrap-around
             for i in 0..size.num_bytes() / 8 {
-                expected[(reg_offset as usize * 8 + i) % reg_size] = pat1[i];
+                expected[(reg_offset * 8 + i) % reg_size] = pat1[i];
             }
         }
 
@@ -255,3 +254,64 @@ fn load_and_check_register() {
         );
     }
 }
+
+#[test]
+fn load_column_as_row() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+
+    // A row-major 20x24 matrix
+    let matrix: Vec<f32> = (0..20 * 24).map(|i| i as f32).collect();
+
+    for (col, count) in iproduct!([0, 5, 23], [1, 7, 16]) {
+        log::debug!("col = {}, count = {}", col, count);
+        unsafe { ctx.load_column_as_row(matrix[col..].as_ptr(), 24, count, YRow(3)) };
+
+        let got: Vec<f32> = ctx.read_y()[192..256]
+            .chunks_exact(4)
+            .map(|x| f32::from_le_bytes(x.try_into().unwrap()))
+            .collect();
+        let expected: Vec<f32> = (0..16)
+            .map(|i| if i < count { matrix[i * 24 + col] } else { 0.0 })
+            .collect();
+        assert_eq!(got, expected);
+    }
+}
+
+#[test]
+fn strided_matrix_load_store() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+
+    // Row strides that aren't multiples of 64 bytes
+    for &ld in &[64, 65, 100, 130] {
+        log::debug!("ld = {}", ld);
+        let src: Vec<u8> = (0..8 * ld).map(|i| (i * 7 + ld) as u8).collect();
+
+        unsafe { ctx.load_matrix_x(src.as_ptr(), 8, ld) };
+        unsafe { ctx.load_matrix_y(src.as_ptr(), 5, ld) };
+        let (got_x, got_y) = (ctx.read_x(), ctx.read_y());
+        for i in 0..8 {
+            assert_eq!(got_x[i * 64..][..64], src[i * ld..][..64]);
+        }
+        for i in 0..5 {
+            assert_eq!(got_y[i * 64..][..64], src[i * ld..][..64]);
+        }
+
+        for i in 0..8 {
+            unsafe { ctx.load512(src[i * ld..].as_ptr(), ZRow(i)) };
+        }
+        let mut dst = vec![0u8; 8 * ld];
+        unsafe { ctx.store_z_matrix(dst.as_mut_ptr(), 7, ld) };
+        for i in 0..8 {
+            let expected: &[u8] = if i < 7 {
+                &src[i * ld..][..64]
+            } else {
+                &[0; 64]
+            };
+            assert_eq!(dst[i * ld..][..64], *expected);
+            // The gaps between rows must be left untouched
+            assert!(dst[i * ld + 64..(i + 1) * ld].iter().all(|&x| x == 0));
+        }
+    }
+}
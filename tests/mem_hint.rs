@@ -0,0 +1,43 @@
+use amx::{Amx, AmxAligned, MemHint, XRow, YRow};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+/// The hints must not change the data transferred.
+fn check_hinted_load_store(ctx: &mut impl Amx) {
+    init();
+    let src: Vec<u8> = (0..=255).collect();
+    for &hint in &[MemHint::Normal, MemHint::NonTemporal] {
+        log::info!("hint = {:?}", hint);
+        ctx.load_all_x(&[0; 512]);
+        ctx.load_all_y(&[0; 512]);
+        unsafe {
+            ctx.load512_x_hint(src[64..].as_ptr(), XRow(3), hint);
+            ctx.load512_y_hint(src[128..].as_ptr(), YRow(7), hint);
+        }
+        let (x, y) = (ctx.read_x(), ctx.read_y());
+        assert_eq!(x[192..256], src[64..128]);
+        assert_eq!(y[448..512], src[128..192]);
+        assert!(x[..192].iter().chain(&x[256..]).all(|&b| b == 0));
+        assert!(y[..448].iter().all(|&b| b == 0));
+
+        let mut out = AmxAligned([0u8; 128]);
+        unsafe {
+            ctx.store512_x_hint(out.0.as_mut_ptr(), XRow(3), hint);
+            ctx.store512_y_hint(out.0[64..].as_mut_ptr(), YRow(7), hint);
+        }
+        assert_eq!(out.0[..], src[64..192]);
+    }
+}
+
+#[test]
+fn hinted_load_store_emu() {
+    check_hinted_load_store(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn hinted_load_store_native() {
+    check_hinted_load_store(&mut *amx::AmxCtx::new().unwrap());
+}
@@ -0,0 +1,257 @@
+mod common;
+
+use amx::{
+    microkernel::{self, MR, NR},
+    Amx,
+};
+use common::Xorshift32;
+
+impl Xorshift32 {
+    /// Generate a small integer as `f32` so that the products and their sums
+    /// are exact.
+    fn next_f32(&mut self) -> f32 {
+        (self.next() % 17) as f32 - 8.0
+    }
+}
+
+#[test]
+fn pack() {
+    // A column-major 3 × 2 matrix
+    let a = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+    let mut packed = vec![f32::NAN; microkernel::packed_a_len(2, 3)];
+    assert_eq!(packed.len(), MR * 2);
+    unsafe { microkernel::pack_a(2, 3, packed.as_mut_ptr(), a.as_ptr(), 1, 3) };
+    let mut expected = vec![0.0; MR * 2];
+    expected[..3].copy_from_slice(&[1.0, 2.0, 3.0]);
+    expected[MR..][..3].copy_from_slice(&[4.0, 5.0, 6.0]);
+    assert_eq!(packed, expected);
+
+    // The same matrix as a 2 × 3 `b`, i.e., its transpose
+    let mut packed = vec![f32::NAN; microkernel::packed_b_len(2, 3)];
+    unsafe { microkernel::pack_b(2, 3, packed.as_mut_ptr(), a.as_ptr(), 1, 2) };
+    let mut expected = vec![0.0; NR * 2];
+    expected[..3].copy_from_slice(&[1.0, 3.0, 5.0]);
+    expected[NR..][..3].copy_from_slice(&[2.0, 4.0, 6.0]);
+    assert_eq!(packed, expected);
+}
+
+/// Calculate `c = alpha * a * b + beta * c` by a simple GEMM driver, where
+/// `a` and `b` are row-major and `c` is column-major. `distance` is the
+/// prefetch distance passed to the packing routines and the kernel.
+#[allow(clippy::too_many_arguments)]
+fn gemm(
+    ctx: &mut impl Amx,
+    (m, n, k): (usize, usize, usize),
+    alpha: f32,
+    a: &[f32],
+    b: &[f32],
+    beta: f32,
+    c: &mut [f32],
+    distance: usize,
+) {
+    let mut a_packed = vec![0.0; microkernel::packed_a_len(k, m)];
+    let mut b_packed = vec![0.0; microkernel::packed_b_len(k, n)];
+    unsafe {
+        microkernel::pack_a_prefetch(
+            k,
+            m,
+            a_packed.as_mut_ptr(),
+            a.as_ptr(),
+            k as isize,
+            1,
+            distance,
+        );
+        microkernel::pack_b_prefetch(
+            k,
+            n,
+            b_packed.as_mut_ptr(),
+            b.as_ptr(),
+            n as isize,
+            1,
+            distance,
+        );
+    }
+
+    for i0 in (0..m).step_by(MR) {
+        for j0 in (0..n).step_by(NR) {
+            let a_panel = &a_packed[i0 * k..];
+            let b_panel = &b_packed[j0 * k..];
+            if i0 + MR <= m && j0 + NR <= n {
+                unsafe {
+                    microkernel::kernel_f32_prefetch(
+                        ctx,
+                        k,
+                        alpha,
+                        a_panel.as_ptr(),
+                        b_panel.as_ptr(),
+                        beta,
+                        c[i0 + j0 * m..].as_mut_ptr(),
+                        1,
+                        m as isize,
+                        distance,
+                    );
+                }
+            } else {
+                // Partial block
+                let mut block = [0.0; MR * NR];
+                unsafe {
+                    microkernel::kernel_f32_prefetch(
+                        ctx,
+                        k,
+                        alpha,
+                        a_panel.as_ptr(),
+                        b_panel.as_ptr(),
+                        0.0,
+                        block.as_mut_ptr(),
+                        NR as isize,
+                        1,
+                        distance,
+                    );
+                }
+                for i in i0..m.min(i0 + MR) {
+                    for j in j0..n.min(j0 + NR) {
+                        let c = &mut c[i + j * m];
+                        *c = block[(i - i0) * NR + (j - j0)] + beta * *c;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn check_gemm(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0x1234567);
+    for &(m, n, k) in &[(16, 16, 16), (32, 48, 3), (17, 33, 21), (5, 7, 0)] {
+        let a: Vec<f32> = (0..m * k).map(|_| rng.next_f32()).collect();
+        let b: Vec<f32> = (0..k * n).map(|_| rng.next_f32()).collect();
+        let c0: Vec<f32> = (0..m * n).map(|_| rng.next_f32()).collect();
+        let mut expected = c0.clone();
+        for i in 0..m {
+            for j in 0..n {
+                let ab: f32 = (0..k).map(|p| a[i * k + p] * b[p * n + j]).sum();
+                expected[i + j * m] = 2.0 * ab + 0.5 * c0[i + j * m];
+            }
+        }
+
+        for &distance in &[0, 1, 8] {
+            let mut c = c0.clone();
+            gemm(ctx, (m, n, k), 2.0, &a, &b, 0.5, &mut c, distance);
+            assert_eq!(c, expected, "{:?}", (m, n, k, distance));
+        }
+    }
+}
+
+#[test]
+fn gemm_emu() {
+    check_gemm(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn gemm_native() {
+    check_gemm(&mut *amx::AmxCtx::new().unwrap());
+}
+
+amx::amx_kernel! {
+    fn kernel_f32_16x16_u8(f32, mr = 16, nr = 16, unroll = 8);
+    fn kernel_f32_32x32_u3(f32, mr = 32, nr = 32, unroll = 3);
+    fn kernel_f32_16x64_u2(f32, mr = 16, nr = 64, unroll = 2);
+    fn kernel_f32_64x16_u1(f32, mr = 64, nr = 16, unroll = 1);
+    fn kernel_f64_8x8_u4(f64, mr = 8, nr = 8, unroll = 4);
+    fn kernel_f64_16x32_u2(f64, mr = 16, nr = 32, unroll = 2);
+    fn kernel_f64_64x8_u1(f64, mr = 64, nr = 8, unroll = 1);
+}
+
+/// Check a microkernel of the `rows × cols` shape, which is called as
+/// `kernel(k, alpha, a, b, beta, c)` with a row-major block `c`.
+fn check_kernel<T>(
+    rows: usize,
+    cols: usize,
+    mut kernel: impl FnMut(usize, T, *const T, *const T, T, *mut T),
+) where
+    T: Copy + From<i8> + std::ops::Add<Output = T> + std::ops::Mul<Output = T>,
+    T: PartialEq + std::fmt::Debug,
+{
+    let mut rng = Xorshift32(0x2345678);
+    for k in [0, 1, 2, 3, 7, 8, 9, 20] {
+        let mut next = || T::from(rng.next_f32() as i8);
+        let a: Vec<T> = (0..k * rows).map(|_| next()).collect();
+        let b: Vec<T> = (0..k * cols).map(|_| next()).collect();
+        let c0: Vec<T> = (0..rows * cols).map(|_| next()).collect();
+        let ab = |i: usize, j: usize| {
+            (0..k)
+                .map(|p| a[p * rows + i] * b[p * cols + j])
+                .fold(T::from(0), |x, y| x + y)
+        };
+
+        // `beta == 0` doesn't read `c`
+        let mut c = vec![T::from(1); rows * cols];
+        kernel(
+            k,
+            T::from(1),
+            a.as_ptr(),
+            b.as_ptr(),
+            T::from(0),
+            c.as_mut_ptr(),
+        );
+        for (idx, &got) in c.iter().enumerate() {
+            assert_eq!(
+                got,
+                ab(idx / cols, idx % cols),
+                "{:?}",
+                (rows, cols, k, idx)
+            );
+        }
+
+        let mut c = c0.clone();
+        kernel(
+            k,
+            T::from(2),
+            a.as_ptr(),
+            b.as_ptr(),
+            T::from(-1),
+            c.as_mut_ptr(),
+        );
+        for (idx, &got) in c.iter().enumerate() {
+            let expected = T::from(2) * ab(idx / cols, idx % cols) + T::from(-1) * c0[idx];
+            assert_eq!(got, expected, "{:?}", (rows, cols, k, idx));
+        }
+    }
+}
+
+fn check_generated_kernels(ctx: &mut impl Amx) {
+    macro_rules! check {
+        ($($kernel:ident($rows:expr, $cols:expr)),*) => {$(
+            check_kernel($rows, $cols, |k, alpha, a, b, beta, c| unsafe {
+                $kernel(&mut *ctx, k, alpha, a, b, beta, c, $cols, 1)
+            });
+        )*};
+    }
+    check!(
+        kernel_f32_16x16_u8(16, 16),
+        kernel_f32_32x32_u3(32, 32),
+        kernel_f32_16x64_u2(16, 64),
+        kernel_f32_64x16_u1(64, 16),
+        kernel_f64_8x8_u4(8, 8),
+        kernel_f64_16x32_u2(16, 32),
+        kernel_f64_64x8_u1(64, 8)
+    );
+}
+
+#[test]
+fn generated_kernels_emu() {
+    check_generated_kernels(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn generated_kernels_native() {
+    check_generated_kernels(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+fn prefetch_any_pointer() {
+    // Prefetches never fault
+    amx::prefetch::prefetch_read_l1(std::ptr::null::<u8>());
+    amx::prefetch::prefetch_read_l2(usize::MAX as *const u64);
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "nalgebra")]
+mod common;
+
+use amx::{
+    linalg::{self, GemmScalar},
+    Amx,
+};
+use common::Xorshift32;
+use nalgebra::{DMatrix, Matrix3, Matrix3x4, Matrix4, Matrix4x3};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+impl Xorshift32 {
+    /// Generate a small integer so that the products and their sums are
+    /// exact.
+    fn next_small<T: GemmScalar>(&mut self) -> T {
+        nalgebra::convert((self.next() % 17) as f64 - 8.0)
+    }
+}
+
+fn random_matrix<T: GemmScalar>(rng: &mut Xorshift32, m: usize, n: usize) -> DMatrix<T> {
+    DMatrix::from_fn(m, n, |_, _| rng.next_small())
+}
+
+fn check_gemm_dynamic<T: GemmScalar>(ctx: &mut impl Amx) {
+    init();
+    let mut rng = Xorshift32(0x1234567);
+    for &(m, n, k) in &[(1, 1, 1), (3, 5, 7), (8, 8, 8), (17, 33, 9), (40, 2, 0)] {
+        for &(alpha, beta) in &[(1.0, 0.0), (2.0, 1.0), (-1.0, 0.5)] {
+            let (alpha, beta): (T, T) = (nalgebra::convert(alpha), nalgebra::convert(beta));
+            let a = random_matrix::<T>(&mut rng, m, k);
+            let b = random_matrix::<T>(&mut rng, k, n);
+            let c0 = random_matrix::<T>(&mut rng, m, n);
+            let expected = &a * &b * alpha + &c0 * beta;
+
+            let mut c = c0.clone();
+            linalg::gemm_nalgebra_with(&mut *ctx, &mut c, alpha, &a, &b, beta);
+            assert_eq!(c, expected, "{:?}", (m, n, k));
+
+            // Non-contiguous views of larger matrices
+            let a_big = random_matrix::<T>(&mut rng, m + 3, k + 2);
+            let b_big = random_matrix::<T>(&mut rng, k + 1, n);
+            let mut c_big = random_matrix::<T>(&mut rng, m + 1, n + 1);
+            let (a_view, b_view) = (a_big.view((2, 1), (m, k)), b_big.view((1, 0), (k, n)));
+            let expected = a_view * b_view * alpha + c_big.view((1, 1), (m, n)) * beta;
+            linalg::gemm_nalgebra_with(
+                &mut *ctx,
+                &mut c_big.view_mut((1, 1), (m, n)),
+                alpha,
+                &a_view,
+                &b_view,
+                beta,
+            );
+            assert_eq!(c_big.view((1, 1), (m, n)), expected, "{:?}", (m, n, k));
+        }
+    }
+}
+
+#[test]
+fn gemm_dynamic_f32_emu() {
+    check_gemm_dynamic::<f32>(&mut amx::AmxEmuCtx::default());
+}
+
+#[test]
+fn gemm_dynamic_f64_emu() {
+    check_gemm_dynamic::<f64>(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn gemm_dynamic_native() {
+    check_gemm_dynamic::<f32>(&mut *amx::AmxCtx::new().unwrap());
+    check_gemm_dynamic::<f64>(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+fn gemm_fixed_size() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    let a = Matrix4x3::from_fn(|i, j| (i * 3 + j) as f64);
+    let b = Matrix3x4::from_fn(|i, j| (i + j * 2) as f64 - 3.0);
+    let mut c = Matrix4::from_element(f64::NAN);
+    linalg::gemm_nalgebra_with(&mut ctx, &mut c, 1.0, &a, &b, 0.0);
+    assert_eq!(c, a * b);
+
+    let mut c = Matrix3::identity();
+    linalg::gemm_nalgebra_with(&mut ctx, &mut c, 1.0, &a.transpose(), &b.transpose(), 3.0);
+    assert_eq!(c, a.transpose() * b.transpose() + Matrix3::identity() * 3.0);
+}
+
+#[test]
+fn gemm_automatic() {
+    // Uses AMX if available, nalgebra's implementation otherwise
+    let mut rng = Xorshift32(0x7654321);
+    for &(m, n, k) in &[(3, 3, 3), (64, 48, 32)] {
+        let a = random_matrix::<f32>(&mut rng, m, k);
+        let b = random_matrix::<f32>(&mut rng, k, n);
+        let mut c = random_matrix::<f32>(&mut rng, m, n);
+        let expected = &a * &b * 2.0 - &c;
+        linalg::gemm_nalgebra(&mut c, 2.0, &a, &b, -1.0);
+        assert_eq!(c, expected);
+    }
+}
+
+#[test]
+#[should_panic(expected = "`a`'s columns and `b`'s rows must match")]
+fn gemm_shape_mismatch() {
+    let a = DMatrix::<f32>::zeros(2, 3);
+    let b = DMatrix::<f32>::zeros(4, 2);
+    let mut c = DMatrix::<f32>::zeros(2, 2);
+    linalg::gemm_nalgebra_with(&mut amx::AmxEmuCtx::default(), &mut c, 1.0, &a, &b, 0.0);
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "ndarray")]
+mod common;
+
+use amx::{linalg, Amx};
+use common::Xorshift32;
+use ndarray::{s, Array2, ShapeBuilder};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+impl Xorshift32 {
+    /// Generate a small integer as `f32` so that the products and their sums
+    /// are exact.
+    fn next_f32(&mut self) -> f32 {
+        (self.next() % 17) as f32 - 8.0
+    }
+}
+
+fn random_array(rng: &mut Xorshift32, shape: (usize, usize)) -> Array2<f32> {
+    Array2::from_shape_simple_fn(shape, || rng.next_f32())
+}
+
+fn check_gemm(ctx: &mut impl Amx) {
+    init();
+    let mut rng = Xorshift32(0x1234567);
+    for &(m, n, k) in &[(1, 1, 1), (3, 5, 7), (16, 16, 16), (17, 33, 9), (40, 2, 0)] {
+        for &(alpha, beta) in &[(1.0, 0.0), (2.0, 1.0), (-1.0, 0.5)] {
+            // Row-major, transposed (column-major), and strided operands
+            let a = random_array(&mut rng, (m, k));
+            let b_t = random_array(&mut rng, (n, k));
+            let b_wide = random_array(&mut rng, (k, n * 2));
+            let c0 = random_array(&mut rng, (m, n));
+            let expected_ab = a.dot(&b_t.t());
+            let expected_ab_wide = a.dot(&b_wide.slice(s![.., ..;2]));
+
+            let mut c = c0.clone();
+            linalg::gemm(&mut *ctx, a.view(), b_t.t(), c.view_mut(), alpha, beta);
+            assert_eq!(c, &expected_ab * alpha + &c0 * beta, "{:?}", (m, n, k));
+
+            let mut c = Array2::zeros((m, n).f());
+            c.assign(&c0);
+            linalg::gemm(
+                &mut *ctx,
+                a.view(),
+                b_wide.slice(s![.., ..;2]),
+                c.view_mut(),
+                alpha,
+                beta,
+            );
+            assert_eq!(c, &expected_ab_wide * alpha + &c0 * beta, "{:?}", (m, n, k));
+        }
+    }
+}
+
+#[test]
+fn gemm_emu() {
+    check_gemm(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn gemm_native() {
+    check_gemm(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+fn gemm_beta_zero_ignores_nan() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    let a = Array2::from_elem((2, 3), 1.0);
+    let b = Array2::from_elem((3, 4), 2.0);
+    let mut c = Array2::from_elem((2, 4), f32::NAN);
+    linalg::gemm(&mut ctx, a.view(), b.view(), c.view_mut(), 1.0, 0.0);
+    assert_eq!(c, Array2::from_elem((2, 4), 6.0));
+
+    let mut c = Array2::from_elem((4, 2), f32::NAN);
+    linalg::gemm(
+        &mut ctx,
+        a.view(),
+        b.view(),
+        c.view_mut().reversed_axes(),
+        1.0,
+        0.0,
+    );
+    assert_eq!(c, Array2::from_elem((4, 2), 6.0));
+}
+
+#[test]
+#[should_panic(expected = "`a`'s columns and `b`'s rows must match")]
+fn gemm_shape_mismatch() {
+    let a = Array2::zeros((2, 3));
+    let b = Array2::zeros((4, 2));
+    let mut c = Array2::zeros((2, 2));
+    linalg::gemm(
+        &mut amx::AmxEmuCtx::default(),
+        a.view(),
+        b.view(),
+        c.view_mut(),
+        1.0,
+        0.0,
+    );
+}
@@ -1,21 +1,14 @@
-use amx::{prelude::*, XBytes, XRow, YBytes, YRow, ZRow};
+mod common;
+
+use amx::{Amx, IntAccumMode, LaneMask, RoundMode, XBytes, XRow, YBytes, YRow, ZRow, ZWriteMode};
+use common::Xorshift32;
 use itertools::iproduct;
+use std::convert::TryInto;
 
 fn init() {
     let _ = env_logger::builder().is_test(true).try_init();
 }
 
-struct Xorshift32(u32);
-
-impl Xorshift32 {
-    fn next(&mut self) -> u32 {
-        self.0 ^= self.0 << 13;
-        self.0 ^= self.0 >> 17;
-        self.0 ^= self.0 << 5;
-        self.0
-    }
-}
-
 fn read_array_wrapping<T: Copy, const N: usize>(a: &[T], i: usize) -> [T; N] {
     use std::mem::MaybeUninit;
     let mut out = [MaybeUninit::<T>::uninit(); N];
@@ -78,9 +71,510 @@ fn outer_product_i16_xy_to_z() {
             let got_z = ctx.read_z();
 
             assert_eq!(
-                std::mem::transmute::<_, [[u16; 32]; 64]>(got_z),
-                std::mem::transmute::<_, [[u16; 32]; 64]>(expected_z)
+                std::mem::transmute::<[u8; 4096], [[u16; 32]; 64]>(got_z),
+                std::mem::transmute::<[u8; 4096], [[u16; 32]; 64]>(expected_z)
             );
         }
     }
 }
+
+/// Characterize how `mac16` addresses `z`: the upper bits of the `z` row
+/// field are ignored in matrix mode, so there's no way to place a tile at a
+/// different row offset or stride.
+fn check_outer_product_i16_z_row_bits(ctx: &mut impl Amx) {
+    let in_x: Vec<i16> = (0..256).map(|i| i as i16 * 3 - 100).collect();
+    let in_y: Vec<i16> = (0..256).map(|i| i as i16 - 50).collect();
+    for i in 0..8 {
+        unsafe {
+            ctx.load512(&in_x[i * 32], XRow(i));
+            ctx.load512(&in_y[i * 32], YRow(i));
+        }
+    }
+
+    for &tile in &[0, 1] {
+        ctx.clear_z();
+        ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(tile), false);
+        let expected_z = ctx.read_z();
+        for z_index in (tile..64).step_by(2) {
+            ctx.clear_z();
+            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(z_index), false);
+            assert_eq!(ctx.read_z()[..], expected_z[..], "z_index = {}", z_index);
+        }
+    }
+}
+
+#[test]
+fn outer_product_i16_z_row_bits_emu() {
+    init();
+    check_outer_product_i16_z_row_bits(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn outer_product_i16_z_row_bits_native() {
+    init();
+    check_outer_product_i16_z_row_bits(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn check_outer_product_8bit_to_i32(ctx: &mut impl Amx, signed: bool) {
+    let mut rng = Xorshift32(0x1919810);
+    let in_x: Vec<u8> = (0..512).map(|_| rng.next() as u8).collect();
+    let in_y: Vec<u8> = (0..512).map(|_| rng.next() as u8).collect();
+    for i in 0..8 {
+        unsafe {
+            ctx.load512(&in_x[i * 64], XRow(i));
+            ctx.load512(&in_y[i * 64], YRow(i));
+        }
+    }
+    let decode = |x: u8| if signed { x as i8 as i64 } else { x as i64 };
+    let modes = [
+        IntAccumMode::default(),
+        IntAccumMode {
+            shift: 3,
+            saturate: false,
+            round: RoundMode::Floor,
+        },
+        IntAccumMode {
+            shift: 5,
+            saturate: true,
+            round: RoundMode::Nearest,
+        },
+        IntAccumMode {
+            shift: 0,
+            saturate: true,
+            round: RoundMode::Nearest,
+        },
+    ];
+
+    // Start with values near the bounds of `i32` to exercise saturation
+    for i in 0..64 {
+        let z = [if i % 2 == 0 {
+            i32::MAX - 100
+        } else {
+            i32::MIN + 100
+        }; 16];
+        unsafe { ctx.load512(z.as_ptr(), ZRow(i)) };
+    }
+
+    for (x_offset, y_offset, &z_index, &accumulate, mode) in iproduct!(
+        (0..0x200).step_by(37),
+        (0..0x200).step_by(53),
+        &[0, 3, 41],
+        &[false, true],
+        modes.iter().copied()
+    ) {
+        log::debug!(
+            "(x_offset, y_offset, z_index, accumulate, mode) = {:?}",
+            (x_offset, y_offset, z_index, accumulate, mode)
+        );
+
+        let mut expected_z = ctx.read_z();
+        if signed {
+            ctx.outer_product_i8_to_i32(
+                Some(XBytes(x_offset)),
+                Some(YBytes(y_offset)),
+                ZRow(z_index),
+                accumulate,
+                mode,
+            );
+        } else {
+            ctx.outer_product_u8_to_i32(
+                Some(XBytes(x_offset)),
+                Some(YBytes(y_offset)),
+                ZRow(z_index),
+                accumulate,
+                mode,
+            );
+        }
+
+        // Calculate the expected answer
+        for x_i in 0..16 {
+            for y_i in 0..16 {
+                let x = decode(in_x[(x_i + x_offset) % 512]);
+                let y = decode(in_y[(y_i + y_offset) % 512]);
+                let out = &mut expected_z[(y_i * 4 + z_index % 4) * 64 + x_i * 4..][..4];
+                let z = if accumulate {
+                    i32::from_le_bytes(out.try_into().unwrap()) as i64
+                } else {
+                    0
+                };
+                let prod = match mode.round {
+                    RoundMode::Floor => (x * y) >> mode.shift,
+                    RoundMode::Nearest => {
+                        ((x * y) as f64 / (1u64 << mode.shift) as f64 + 0.5).floor() as i64
+                    }
+                };
+                let z = if mode.saturate {
+                    (z + prod).clamp(i32::MIN as i64, i32::MAX as i64) as i32
+                } else {
+                    (z + prod) as i32
+                };
+                out.copy_from_slice(&z.to_le_bytes());
+            }
+        }
+
+        assert_eq!(ctx.read_z(), expected_z);
+    }
+}
+
+#[test]
+fn outer_product_u8_to_i32_emu() {
+    init();
+    check_outer_product_8bit_to_i32(&mut amx::AmxEmuCtx::default(), false);
+}
+
+#[test]
+fn outer_product_i8_to_i32_emu() {
+    init();
+    check_outer_product_8bit_to_i32(&mut amx::AmxEmuCtx::default(), true);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn outer_product_u8_to_i32_native() {
+    init();
+    check_outer_product_8bit_to_i32(&mut *amx::AmxCtx::new().unwrap(), false);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn outer_product_i8_to_i32_native() {
+    init();
+    check_outer_product_8bit_to_i32(&mut *amx::AmxCtx::new().unwrap(), true);
+}
+
+const WRITE_MODES: [ZWriteMode; 3] = [
+    ZWriteMode::Overwrite,
+    ZWriteMode::Accumulate,
+    ZWriteMode::Subtract,
+];
+
+fn check_outer_product_f64_xy_to_z(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0x2545f491);
+    let in_x: Vec<f64> = (0..64)
+        .map(|_| rng.next() as i32 as f64 / 65536.0)
+        .collect();
+    let in_y: Vec<f64> = (0..64)
+        .map(|_| rng.next() as i32 as f64 / 65536.0)
+        .collect();
+    for i in 0..8 {
+        unsafe {
+            ctx.load512(&in_x[i * 8], XRow(i));
+            ctx.load512(&in_y[i * 8], YRow(i));
+        }
+    }
+
+    for (x_offset, y_offset, &z_index, &mode) in iproduct!(
+        (0..0x200).step_by(64),
+        (0..0x200).step_by(72),
+        &[0, 5, 63],
+        &WRITE_MODES
+    ) {
+        let mut expected_z = ctx.read_z();
+        let tile = ctx.outer_product_f64_xy_to_z(
+            Some(XBytes(x_offset)),
+            Some(YBytes(y_offset)),
+            ZRow(z_index),
+            mode,
+        );
+
+        for (y_i, ZRow(row)) in tile.rows().enumerate() {
+            for x_i in 0..8 {
+                let x = in_x[(x_offset / 8 + x_i) % 64];
+                let y = in_y[(y_offset / 8 + y_i) % 64];
+                let out = &mut expected_z[row * 64 + x_i * 8..][..8];
+                let z = if mode == ZWriteMode::Overwrite {
+                    0.0
+                } else {
+                    f64::from_le_bytes(out.try_into().unwrap())
+                };
+                let x = if mode == ZWriteMode::Subtract { -x } else { x };
+                out.copy_from_slice(&x.mul_add(y, z).to_le_bytes());
+            }
+        }
+
+        assert_eq!(ctx.read_z()[..], expected_z[..]);
+    }
+}
+
+#[test]
+fn outer_product_f64_xy_to_z_emu() {
+    init();
+    check_outer_product_f64_xy_to_z(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn outer_product_f64_xy_to_z_native() {
+    init();
+    check_outer_product_f64_xy_to_z(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn check_outer_product_f16_xy_to_z(ctx: &mut impl Amx) {
+    use half::f16;
+
+    // Random finite numbers, including subnormals and ones whose products
+    // overflow
+    let mut rng = Xorshift32(0x1234567);
+    let mut gen = || loop {
+        let x = rng.next() as u16;
+        if x & 0x7c00 != 0x7c00 {
+            break x;
+        }
+    };
+    let in_x: Vec<u16> = (0..256).map(|_| gen()).collect();
+    let in_y: Vec<u16> = (0..256).map(|_| gen()).collect();
+    for i in 0..8 {
+        unsafe {
+            ctx.load512(&in_x[i * 32], XRow(i));
+            ctx.load512(&in_y[i * 32], YRow(i));
+        }
+    }
+
+    for (x_offset, y_offset, &z_index, &mode) in iproduct!(
+        (0..0x200).step_by(62),
+        (0..0x200).step_by(86),
+        &[0, 1, 50],
+        &WRITE_MODES
+    ) {
+        let mut expected_z = ctx.read_z();
+        let tile = ctx.outer_product_f16_xy_to_z(
+            Some(XBytes(x_offset)),
+            Some(YBytes(y_offset)),
+            ZRow(z_index),
+            mode,
+        );
+
+        for (y_i, ZRow(row)) in tile.rows().enumerate() {
+            for x_i in 0..32 {
+                let x = f16::from_bits(in_x[(x_offset / 2 + x_i) % 256]);
+                let y = f16::from_bits(in_y[(y_offset / 2 + y_i) % 256]);
+                let out = &mut expected_z[row * 64 + x_i * 2..][..2];
+                let z = if mode == ZWriteMode::Overwrite {
+                    f16::ZERO
+                } else {
+                    f16::from_le_bytes(out.try_into().unwrap())
+                };
+                let x = if mode == ZWriteMode::Subtract { -x } else { x };
+                // The product is exact in `f64`, and the sum is exact unless
+                // `z` is far larger, so this is rounded only once
+                let result = f16::from_f64(x.to_f64() * y.to_f64() + z.to_f64());
+                out.copy_from_slice(&result.to_le_bytes());
+            }
+        }
+
+        let got_z = ctx.read_z();
+        for (i, (got, expected)) in got_z.chunks(2).zip(expected_z.chunks(2)).enumerate() {
+            let got = f16::from_le_bytes(got.try_into().unwrap());
+            let expected = f16::from_le_bytes(expected.try_into().unwrap());
+            // NaN payloads are unspecified
+            assert!(
+                got.to_bits() == expected.to_bits() || (got.is_nan() && expected.is_nan()),
+                "z[{}][{}]: got {:?}, expected {:?} (x_offset = {}, y_offset = {}, \
+                 z_index = {}, mode = {:?})",
+                i / 32,
+                i % 32,
+                got,
+                expected,
+                x_offset,
+                y_offset,
+                z_index,
+                mode
+            );
+        }
+    }
+}
+
+#[test]
+fn outer_product_f16_xy_to_z_emu() {
+    init();
+    check_outer_product_f16_xy_to_z(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn outer_product_f16_xy_to_z_native() {
+    init();
+    check_outer_product_f16_xy_to_z(&mut *amx::AmxCtx::new().unwrap());
+}
+
+const LANE_MASKS: [LaneMask; 10] = [
+    LaneMask::All,
+    LaneMask::None,
+    LaneMask::Even,
+    LaneMask::Odd,
+    LaneMask::Only(3),
+    LaneMask::Only(20),
+    LaneMask::First(5),
+    LaneMask::First(40),
+    LaneMask::Last(2),
+    LaneMask::Last(0),
+];
+
+fn lane_enabled(mask: LaneMask, i: usize, lanes: usize) -> bool {
+    match mask {
+        LaneMask::All => true,
+        LaneMask::None => false,
+        LaneMask::Even => i % 2 == 0,
+        LaneMask::Odd => i % 2 == 1,
+        LaneMask::Only(k) => i == k,
+        LaneMask::First(n) => i < n,
+        LaneMask::Last(n) => lanes - i <= n,
+    }
+}
+
+fn check_outer_product_masked(ctx: &mut impl Amx) {
+    let in_x: Vec<f32> = (0..128).map(|i| i as f32).collect();
+    let in_y: Vec<f32> = (0..128).map(|i| (i % 7) as f32 - 3.0).collect();
+    let in_xi: Vec<i16> = (0..256).map(|i| i as i16 - 100).collect();
+    let in_yi: Vec<i16> = (0..256).map(|i| (i % 5) as i16 - 2).collect();
+
+    for (&x_mask, &y_mask) in iproduct!(&LANE_MASKS, &LANE_MASKS) {
+        log::debug!("(x_mask, y_mask) = {:?}", (x_mask, y_mask));
+
+        // `f32`: 16 lanes
+        for i in 0..8 {
+            unsafe {
+                ctx.load512(&in_x[i * 16], XRow(i));
+                ctx.load512(&in_y[i * 16], YRow(i));
+            }
+        }
+        let mut expected_z = ctx.read_z();
+        let tile = ctx.outer_product_f32_xy_to_z_masked(
+            Some(XBytes(64)),
+            Some(YBytes(128)),
+            ZRow(1),
+            true,
+            x_mask,
+            y_mask,
+        );
+        for (y_i, ZRow(row)) in tile.rows().enumerate() {
+            for x_i in 0..16 {
+                if !lane_enabled(x_mask, x_i, 16) || !lane_enabled(y_mask, y_i, 16) {
+                    continue;
+                }
+                let out = &mut expected_z[row * 64 + x_i * 4..][..4];
+                let z = f32::from_le_bytes(out.try_into().unwrap());
+                let z = in_x[16 + x_i].mul_add(in_y[32 + y_i], z);
+                out.copy_from_slice(&z.to_le_bytes());
+            }
+        }
+        assert_eq!(ctx.read_z()[..], expected_z[..]);
+
+        // `i16`: 32 lanes
+        for i in 0..8 {
+            unsafe {
+                ctx.load512(&in_xi[i * 32], XRow(i));
+                ctx.load512(&in_yi[i * 32], YRow(i));
+            }
+        }
+        let mut expected_z = ctx.read_z();
+        let tile = ctx.outer_product_i16_xy_to_z_masked(
+            Some(XBytes(0)),
+            Some(YBytes(192)),
+            ZRow(0),
+            false,
+            x_mask,
+            y_mask,
+        );
+        for (y_i, ZRow(row)) in tile.rows().enumerate() {
+            for x_i in 0..32 {
+                if !lane_enabled(x_mask, x_i, 32) || !lane_enabled(y_mask, y_i, 32) {
+                    continue;
+                }
+                let z = in_xi[x_i].wrapping_mul(in_yi[96 + y_i]);
+                expected_z[row * 64 + x_i * 2..][..2].copy_from_slice(&z.to_le_bytes());
+            }
+        }
+        assert_eq!(ctx.read_z()[..], expected_z[..]);
+    }
+}
+
+#[test]
+fn outer_product_masked_emu() {
+    init();
+    check_outer_product_masked(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn outer_product_masked_native() {
+    init();
+    check_outer_product_masked(&mut *amx::AmxCtx::new().unwrap());
+}
+
+/// `matint` only has a `y` lane mask
+fn check_outer_product_8bit_to_i32_masked(ctx: &mut impl Amx) {
+    let in_x: Vec<u8> = (0..64).map(|i| (i * 7) as u8).collect();
+    let in_y: Vec<u8> = (0..64).map(|i| (i * 13 + 100) as u8).collect();
+    unsafe {
+        ctx.load512(in_x.as_ptr(), XRow(0));
+        ctx.load512(in_y.as_ptr(), YRow(0));
+    }
+
+    for (&signed, &y_mask) in iproduct!(&[false, true], &LANE_MASKS) {
+        log::debug!("(signed, y_mask) = {:?}", (signed, y_mask));
+        let decode = |x: u8| if signed { x as i8 as i32 } else { x as i32 };
+
+        let mut expected_z = ctx.read_z();
+        let (x, y) = (Some(XBytes(16)), Some(YBytes(32)));
+        let mode = IntAccumMode::default();
+        let tile = if signed {
+            ctx.outer_product_i8_to_i32_masked(x, y, ZRow(2), true, mode, y_mask)
+        } else {
+            ctx.outer_product_u8_to_i32_masked(x, y, ZRow(2), true, mode, y_mask)
+        };
+        for (y_i, ZRow(row)) in tile.rows().enumerate() {
+            if !lane_enabled(y_mask, y_i, 16) {
+                continue;
+            }
+            for x_i in 0..16 {
+                let out = &mut expected_z[row * 64 + x_i * 4..][..4];
+                let z = i32::from_le_bytes(out.try_into().unwrap())
+                    .wrapping_add(decode(in_x[16 + x_i]) * decode(in_y[32 + y_i]));
+                out.copy_from_slice(&z.to_le_bytes());
+            }
+        }
+        assert_eq!(ctx.read_z()[..], expected_z[..]);
+    }
+}
+
+#[test]
+fn outer_product_8bit_to_i32_masked_emu() {
+    init();
+    check_outer_product_8bit_to_i32_masked(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn outer_product_8bit_to_i32_masked_native() {
+    init();
+    check_outer_product_8bit_to_i32_masked(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic]
+fn matint_subtract() {
+    amx::AmxEmuCtx::default().outer_product_u8_to_i32(
+        None,
+        None,
+        ZRow(0),
+        ZWriteMode::Subtract,
+        IntAccumMode::default(),
+    );
+}
+
+#[test]
+#[should_panic]
+fn int_accum_mode_shift_out_of_range() {
+    amx::AmxEmuCtx::default().outer_product_i8_to_i32(
+        None,
+        None,
+        ZRow(0),
+        false,
+        IntAccumMode {
+            shift: 32,
+            ..Default::default()
+        },
+    );
+}
@@ -0,0 +1,52 @@
+use amx::{Amx, XRow, YRow, ZRow};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+fn check_partial(ctx: &mut impl Amx) {
+    let pattern: Vec<u8> = (0..64).map(|i| i as u8 + 1).collect();
+
+    for &len in &[0, 1, 13, 63, 64] {
+        log::debug!("len = {}", len);
+
+        // The source slice ends right where a full load would overrun it
+        let src = &pattern[64 - len..];
+        unsafe {
+            ctx.load512([0xffu8; 64].as_ptr(), XRow(2));
+            ctx.load512([0xffu8; 64].as_ptr(), YRow(7));
+        }
+        ctx.load_partial_x(src, XRow(2));
+        ctx.load_partial_y(src, YRow(7));
+
+        let mut expected = [0u8; 64];
+        expected[..len].copy_from_slice(src);
+        assert_eq!(ctx.read_x()[128..192], expected);
+        assert_eq!(ctx.read_y()[448..512], expected);
+
+        unsafe { ctx.load512(pattern.as_ptr(), ZRow(33)) };
+        let mut dst = vec![0xffu8; len + 1];
+        ctx.store_partial_z(&mut dst[..len], ZRow(33));
+        assert_eq!(dst[..len], pattern[..len]);
+        assert_eq!(dst[len], 0xff);
+    }
+}
+
+#[test]
+fn partial_emu() {
+    init();
+    check_partial(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn partial_native() {
+    init();
+    check_partial(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+#[should_panic]
+fn load_partial_too_long() {
+    amx::AmxEmuCtx::default().load_partial_x(&[0; 65], XRow(0));
+}
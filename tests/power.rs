@@ -0,0 +1,38 @@
+#![cfg(feature = "power")]
+use amx::power::{EnergyMeter, EnergyReport, PowerError};
+use std::time::Duration;
+
+#[test]
+fn measure() {
+    let meter = match EnergyMeter::new() {
+        Ok(x) => x,
+        Err(e) => {
+            // Requires macOS on Apple silicon
+            if !cfg!(target_os = "macos") {
+                assert_eq!(e, PowerError::Unsupported);
+            }
+            return;
+        }
+    };
+
+    let ((), report) = meter.measure(|| std::thread::sleep(Duration::from_millis(100)));
+    assert!(report.elapsed >= Duration::from_millis(100), "{:?}", report);
+    assert!(!report.channels.is_empty(), "{:?}", report);
+    for (name, joules) in &report.channels {
+        assert!(*joules >= 0.0, "{}: {}", name, joules);
+    }
+}
+
+#[test]
+fn report() {
+    let report = EnergyReport {
+        elapsed: Duration::from_millis(500),
+        channels: vec![
+            ("CPU Energy".to_owned(), 2.0),
+            ("GPU Energy".to_owned(), 0.5),
+        ],
+    };
+    assert_eq!(report.cpu_joules(), Some(2.0));
+    assert_eq!(report.watts("GPU Energy"), Some(1.0));
+    assert_eq!(report.joules("DRAM"), None);
+}
@@ -0,0 +1,53 @@
+#![cfg(target_arch = "aarch64")]
+use amx::probe::{self, ProbeOutcome, ProbeReport, ProbeResult};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+#[test]
+fn scan_ops() {
+    init();
+    let report = probe::scan_ops().unwrap();
+    log::info!("{}", report);
+
+    let find = |opcode, operand| {
+        report
+            .results
+            .iter()
+            .find(|r| (r.opcode, r.operand) == (opcode, operand))
+            .unwrap()
+    };
+    // `ldx` and `fma32` exist on every processor with AMX
+    assert_eq!(find(0, 0).outcome, ProbeOutcome::Accepted);
+    assert_eq!(find(12, 0).outcome, ProbeOutcome::Accepted);
+    // `set`/`clr` isn't probed
+    assert!(report.results.iter().all(|r| r.opcode != 17));
+    assert!(report.accepted().count() >= 2);
+}
+
+#[test]
+fn display() {
+    let report = ProbeReport {
+        results: vec![
+            ProbeResult {
+                opcode: 14,
+                operand: 1 << 63,
+                pointer: false,
+                outcome: ProbeOutcome::Accepted,
+            },
+            ProbeResult {
+                opcode: 25,
+                operand: 0,
+                pointer: true,
+                outcome: ProbeOutcome::IllegalInstruction,
+            },
+        ],
+    };
+    assert_eq!(
+        report.to_string(),
+        "opcode,mnemonic,operand,pointer,outcome\n\
+         14,mac16,0x8000000000000000,false,accepted\n\
+         25,,0x0000000000000000,true,sigill\n"
+    );
+}
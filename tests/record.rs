@@ -0,0 +1,60 @@
+use amx::{Amx, AmxAligned, AmxTrace, RecordingOps, XBytes, XRow, YBytes, YRow, ZRow};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+/// Record a short kernel touching every register set on the emulator.
+fn record_kernel() -> (amx::AmxEmuCtx, AmxTrace) {
+    let mut ctx = RecordingOps::new(amx::AmxEmuCtx::default());
+    let x: Vec<f32> = (0..128).map(|i| i as f32 * 0.5).collect();
+    let y: Vec<f32> = (0..128).map(|i| 3.0 - i as f32).collect();
+    unsafe {
+        ctx.load1024_aligned(AmxAligned([7; 128]).0.as_ptr(), ZRow(10));
+        for i in 0..8 {
+            ctx.load512(x[i * 16..].as_ptr(), XRow(i));
+            ctx.load512(y[i * 16..].as_ptr(), YRow(i));
+        }
+    }
+    ctx.outer_product_f32_xy_to_z(Some(XBytes(64)), Some(YBytes(128)), ZRow(0), false);
+    ctx.outer_product_f32_xy_to_z(Some(XBytes(256)), Some(YBytes(0)), ZRow(0), true);
+    ctx.copy_z_row_to_y(ZRow(4), YRow(7));
+    let mut out = [0u8; 64];
+    unsafe { ctx.store512(out.as_mut_ptr(), ZRow(8)) };
+    ctx.into_parts()
+}
+
+#[test]
+fn record_serialize() {
+    init();
+    let (_, trace) = record_kernel();
+    assert_eq!(trace.ops[0].data.len(), 128);
+    assert_eq!(trace.ops[1].data.len(), 64);
+
+    let mut bytes = Vec::new();
+    trace.write_to(&mut bytes).unwrap();
+    assert_eq!(AmxTrace::read_from(&bytes[..]).unwrap(), trace);
+
+    assert!(AmxTrace::read_from(&bytes[..bytes.len() - 1]).is_err());
+    assert!(AmxTrace::read_from(&b"not a trace"[..]).is_err());
+}
+
+fn check_replay(ctx: &mut impl Amx) {
+    init();
+    let (mut emu, trace) = record_kernel();
+    amx::replay(&trace, &mut *ctx);
+    assert_eq!(ctx.read_x()[..], emu.read_x()[..]);
+    assert_eq!(ctx.read_y()[..], emu.read_y()[..]);
+    assert_eq!(ctx.read_z()[..], emu.read_z()[..]);
+}
+
+#[test]
+fn replay_emu() {
+    check_replay(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn replay_native() {
+    check_replay(&mut *amx::AmxCtx::new().unwrap());
+}
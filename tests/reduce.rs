@@ -0,0 +1,73 @@
+mod common;
+
+use amx::{Amx, Reduce, ZTile32};
+use common::Xorshift32;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+fn check_reduce_z(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0x2545f491);
+    // Small integers, whose sums are exact regardless of the order
+    let z: [f32; 1024] = std::array::from_fn(|_| (rng.next() % 201) as f32 - 100.0);
+    load_z(ctx, &z);
+
+    for base in 0..4 {
+        let tile = ZTile32 { base };
+        let rows: Vec<&[f32]> = tile.rows().map(|row| &z[row.0 * 16..][..16]).collect();
+        for &op in &[Reduce::Sum, Reduce::Max, Reduce::Min] {
+            let expected: Vec<f32> = (0..16)
+                .map(|j| {
+                    rows[1..]
+                        .iter()
+                        .fold(rows[0][j], |acc, row| op.apply(acc, row[j]))
+                })
+                .collect();
+            let got = ctx.reduce_z_rows_f32(tile, op);
+            assert_eq!(got[..], expected[..], "{:?} {:?}", tile, op);
+
+            let expected = expected[1..]
+                .iter()
+                .fold(expected[0], |acc, &x| op.apply(acc, x));
+            assert_eq!(ctx.reduce_z_f32(tile, op), expected, "{:?} {:?}", tile, op);
+        }
+    }
+
+    // The rows are read without being modified
+    let expected: Vec<u8> = z.iter().flat_map(|x| x.to_ne_bytes()).collect();
+    assert_eq!(ctx.read_z()[..], expected[..]);
+}
+
+fn load_z(ctx: &mut impl Amx, z: &[f32; 1024]) {
+    for (i, row) in z.chunks_exact(16).enumerate() {
+        // Safety: Reading a 64-byte region within `row`
+        unsafe { ctx.load512(row.as_ptr(), amx::ZRow(i)) };
+    }
+}
+
+#[test]
+fn reduce_z_emu() {
+    init();
+    check_reduce_z(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn reduce_z_native() {
+    init();
+    check_reduce_z(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+fn reduce_nan() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    let mut z = [0.0f32; 1024];
+    z[5] = f32::NAN;
+    z[4 * 16 + 5] = -1.0;
+    load_z(&mut ctx, &z);
+    let tile = ZTile32 { base: 0 };
+    assert_eq!(ctx.reduce_z_rows_f32(tile, Reduce::Min)[5], -1.0);
+    assert_eq!(ctx.reduce_z_f32(tile, Reduce::Max), 0.0);
+    assert!(ctx.reduce_z_f32(tile, Reduce::Sum).is_nan());
+}
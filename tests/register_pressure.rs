@@ -0,0 +1,53 @@
+//! Issues AMX instructions while almost every general-purpose register holds a
+//! live value, so that operands get assigned to high-numbered registers (this
+//! used to be mis-encoded for `x30` and above).
+#![cfg(target_arch = "aarch64")]
+use amx::{prelude::*, XRow, YRow};
+use std::hint::black_box;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+macro_rules! with_live_values {
+    ($($v:ident),*; $body:block) => {{
+        $( let $v: u64 = black_box(0x0101_0101); )*
+        let ret = $body;
+        // Use every value after the body so that they stay live across it
+        0 $( ^ black_box($v) )* ^ ret
+    }};
+}
+
+#[test]
+fn load_store_under_register_pressure() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+
+    let src: Vec<[u8; 64]> = (0..16).map(|i| [i as u8 + 1; 64]).collect();
+    let mut dst = vec![[0u8; 64]; 16];
+
+    let checksum = with_live_values!(
+        a0, a1, a2, a3, a4, a5, a6, a7, a8, a9, a10, a11, a12, a13, a14, a15,
+        a16, a17, a18, a19, a20, a21, a22, a23, a24, a25;
+        {
+            let src_ptrs: Vec<*const u8> = src.iter().map(|x| black_box(x.as_ptr())).collect();
+            let dst_ptrs: Vec<*mut u8> = dst.iter_mut().map(|x| black_box(x.as_mut_ptr())).collect();
+            for i in 0..8 {
+                unsafe {
+                    ctx.load512(src_ptrs[i], XRow(i));
+                    ctx.load512(src_ptrs[i + 8], YRow(i));
+                }
+            }
+            for i in 0..8 {
+                unsafe {
+                    ctx.store512(dst_ptrs[i], XRow(i));
+                    ctx.store512(dst_ptrs[i + 8], YRow(i));
+                }
+            }
+            0
+        }
+    );
+    black_box(checksum);
+
+    assert_eq!(dst, src);
+}
@@ -0,0 +1,229 @@
+use amx::{
+    Amx, ByteOffset, Index4, Lane, Normal, XBytes, XRow, XRowN, YBytes, YRow, YRowN, ZRegs, ZRow,
+    ZRowN, ZTile16, ZTile32, ZTile64, X8,
+};
+use std::convert::TryInto;
+
+#[test]
+fn byte_offset_arithmetic() {
+    assert_eq!(XBytes(100) + 20, XBytes(120));
+    assert_eq!(XBytes(500) + 20, XBytes(8));
+    assert_eq!(YBytes(10) - 20, YBytes(502));
+    assert_eq!(ByteOffset::<ZRegs>::new(4090) + 10, ByteOffset::new(4));
+
+    let mut x = XBytes(0);
+    x -= 1;
+    assert_eq!(x, XBytes(511));
+    x += 2;
+    assert_eq!(x, XBytes(1));
+
+    assert_eq!(XBytes(500).checked_add(11), Some(XBytes(511)));
+    assert_eq!(XBytes(500).checked_add(12), None);
+    assert_eq!(YBytes(10).checked_sub(10), Some(YBytes(0)));
+    assert_eq!(YBytes(10).checked_sub(11), None);
+}
+
+#[test]
+fn byte_offset_rows() {
+    assert_eq!(XBytes(200).row(), XRow(3));
+    assert_eq!(XBytes(200).row_index(), 3);
+    assert_eq!(XBytes(200).offset_in_row(), 8);
+    assert_eq!(YBytes::from(YRow(5)), YBytes(320));
+    assert_eq!(XBytes::from_row_index(7), XBytes(448));
+}
+
+#[test]
+fn byte_offset_debug() {
+    assert_eq!(format!("{:?}", XBytes(42)), "XBytes(42)");
+    assert_eq!(format!("{:?}", Some(YBytes(1))), "Some(YBytes(1))");
+}
+
+#[test]
+fn byte_offset_lanes() {
+    assert_eq!(XBytes::from_row_and_lane(0, 0, 8), XBytes(0));
+    assert_eq!(XBytes::from_row_and_lane(2, 5, 4), XBytes(148));
+    assert_eq!(YBytes::from_row_and_lane(7, 31, 2), YBytes(510));
+    assert_eq!(YBytes(510).row_index(), 7);
+    assert_eq!(YBytes(510).lane_index(2), 31);
+    assert_eq!(XBytes(148).lane_index(4), 5);
+    assert_eq!(XBytes(148).lane_index(1), 20);
+}
+
+fn check_rotate(ctx: &mut impl Amx) {
+    let x: Vec<u8> = (0..512).map(|i| (i * 3 + 1) as u8).collect();
+    let y: Vec<u8> = (0..512).map(|i| (i * 5 + 2) as u8).collect();
+    for &amount in &[0, 1, 63, 64, 200, 511, 512, 700] {
+        for i in 0..8 {
+            unsafe {
+                ctx.load512(x[i * 64..].as_ptr(), XRow(i));
+                ctx.load512(y[i * 64..].as_ptr(), YRow(i));
+            }
+        }
+        ctx.rotate_x_bytes(amount);
+        let expected_x: Vec<u8> = (0..512).map(|i| x[(i + amount) % 512]).collect();
+        assert_eq!(ctx.read_x()[..], expected_x[..], "amount = {}", amount);
+        assert_eq!(ctx.read_y()[..], y[..]);
+
+        ctx.rotate_y_bytes(amount);
+        let expected_y: Vec<u8> = (0..512).map(|i| y[(i + amount) % 512]).collect();
+        assert_eq!(ctx.read_y()[..], expected_y[..], "amount = {}", amount);
+    }
+}
+
+#[test]
+fn rotate_emu() {
+    check_rotate(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn rotate_native() {
+    check_rotate(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+fn byte_offset_of_element() {
+    assert_eq!(XBytes::of_element::<u8>(1, 63), XBytes(127));
+    assert_eq!(XBytes::of_element::<f32>(2, 5), XBytes(148));
+    assert_eq!(YBytes::of_element::<f64>(3, 7), YBytes(248));
+    assert_eq!(YBytes::of_element::<i16>(7, 31), YBytes(510));
+    for row in 0..8 {
+        for lane in 0..16 {
+            let offset = XBytes::of_element::<u32>(row, lane);
+            assert_eq!(offset, XBytes::from_row_and_lane(row, lane, 4));
+            assert_eq!((offset.row_index(), offset.lane_index(4)), (row, lane));
+        }
+    }
+    assert_eq!(<u16 as Lane>::LANES, 32);
+    assert_eq!(<f64 as Lane>::LANES, 8);
+}
+
+#[test]
+fn z_tile_rows() {
+    let tile = ZTile16::from_z_index(ZRow(63));
+    assert_eq!(tile, ZTile16 { base: 1 });
+    assert_eq!(tile.z_index(), ZRow(1));
+    assert_eq!(ZTile16::LEN, 32);
+    assert_eq!(tile.rows().len(), 32);
+    assert_eq!(tile.rows().last(), Some(ZRow(63)));
+
+    let tile = ZTile32::from_z_index(ZRow(6));
+    assert_eq!(
+        tile.rows().take(3).collect::<Vec<_>>(),
+        [ZRow(2), ZRow(6), ZRow(10)]
+    );
+    assert_eq!(tile.row(15), ZRow(62));
+
+    let tile = ZTile64::from_z_index(ZRow(7));
+    assert_eq!(ZTile64::LEN, 8);
+    assert_eq!(tile.rows().next_back(), Some(ZRow(63)));
+
+    // The four `ZTile32`s partition `z`
+    let mut rows: Vec<usize> = (0..4)
+        .flat_map(|base| ZTile32 { base }.rows().map(|ZRow(i)| i))
+        .collect();
+    rows.sort_unstable();
+    assert_eq!(rows, (0..64).collect::<Vec<_>>());
+}
+
+#[test]
+fn outer_product_returns_tile() {
+    let mut ctx = amx::AmxEmuCtx::default();
+    assert_eq!(
+        ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(5), false),
+        ZTile16 { base: 1 }
+    );
+    assert_eq!(
+        ctx.outer_product_f32_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(7), false),
+        ZTile32 { base: 3 }
+    );
+}
+
+#[test]
+fn const_rows() {
+    assert_eq!(XRowN::<7>::ROW, XRow(7));
+    assert_eq!(YRow::from(YRowN::<0>), YRow(0));
+    assert_eq!(ZRow::from(ZRowN::<63>), ZRow(63));
+
+    let mut ctx = amx::AmxEmuCtx::default();
+    let src: Vec<u8> = (0..64).collect();
+    unsafe {
+        ctx.load512(src.as_ptr(), XRowN::<3>);
+        ctx.load512(src.as_ptr(), ZRowN::<40>);
+    }
+    assert_eq!(ctx.read_x()[192..256], src[..]);
+    assert_eq!(ctx.read_z()[40 * 64..41 * 64], src[..]);
+
+    let mut dst = [0u8; 64];
+    unsafe { ctx.store512(dst.as_mut_ptr(), ZRowN::<40>) };
+    assert_eq!(dst[..], src[..]);
+
+    // `YRowN` selects the same `genlut` output as `YRow`
+    let mut ctx2 = ctx;
+    ctx.lut(XBytes(192), XRow(3), YRowN::<5>, (Normal, Index4, X8));
+    ctx2.lut(XBytes(192), XRow(3), YRow(5), (Normal, Index4, X8));
+    assert_eq!(ctx.read_y()[..], ctx2.read_y()[..]);
+    assert_ne!(ctx.read_y()[320..384], [0; 64]);
+}
+
+#[test]
+fn z_row_tile_rows() {
+    assert_eq!(
+        ZRow(0).tile_rows_16bit().collect::<Vec<_>>(),
+        (0..32).map(|i| ZRow(i * 2)).collect::<Vec<_>>()
+    );
+    assert_eq!(ZRow(6).tile_rows_32bit().next(), Some(ZRow(2)));
+    assert_eq!(ZRow(6).tile_rows_32bit().len(), 16);
+    assert_eq!(
+        ZRow(13).tile_rows_64bit().collect::<Vec<_>>(),
+        ZTile64::from_z_index(ZRow(5)).rows().collect::<Vec<_>>()
+    );
+
+    // Read back the result of a 32-bit outer product without hard-coding the
+    // row stride
+    let mut ctx = amx::AmxEmuCtx::default();
+    let x: Vec<f32> = (0..16).map(|i| i as f32).collect();
+    let y: Vec<f32> = (0..16).map(|i| 2.0 + i as f32).collect();
+    unsafe {
+        ctx.load512(x.as_ptr(), XRow(0));
+        ctx.load512(y.as_ptr(), YRow(0));
+    }
+    ctx.outer_product_f32_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), false);
+    let z = ctx.read_z();
+    for (y_i, ZRow(row)) in ZRow(1).tile_rows_32bit().enumerate() {
+        for x_i in 0..16 {
+            let got = f32::from_le_bytes(z[row * 64 + x_i * 4..][..4].try_into().unwrap());
+            assert_eq!(got, x[x_i] * y[y_i]);
+        }
+    }
+}
+
+#[test]
+fn row_conversions() {
+    use amx::{regs::RowOutOfRange, ZBytes};
+    use std::convert::TryFrom;
+
+    assert_eq!(XRow(3).offset(), XBytes(192));
+    assert_eq!(YRow(7).offset(), YBytes(448));
+    assert_eq!(ZRow(63).offset(), ZBytes(4032));
+    assert_eq!(ZBytes::from(ZRow(2)), ZBytes(128));
+    assert_eq!(ZBytes(200).row(), ZRow(3));
+    assert_eq!(format!("{:?}", ZBytes(5)), "ZBytes(5)");
+
+    assert_eq!(XRow::try_from(7), Ok(XRow(7)));
+    assert_eq!(YRow::try_from(8), Err(RowOutOfRange { index: 8 }));
+    assert_eq!(ZRow::try_from(63), Ok(ZRow(63)));
+    assert_eq!(ZRow::try_from(64), Err(RowOutOfRange { index: 64 }));
+    assert_eq!((XRow::NUM_ROWS, YRow::NUM_ROWS, ZRow::NUM_ROWS), (8, 8, 64));
+}
+
+#[test]
+fn row_arithmetic() {
+    assert_eq!(XRow(6).checked_add(1), Some(XRow(7)));
+    assert_eq!(XRow(6).checked_add(2), None);
+    assert_eq!(YRow(0).checked_sub(1), None);
+    assert_eq!(ZRow(63).checked_sub(63), Some(ZRow(0)));
+    assert_eq!(XRow(6).wrapping_add(3), XRow(1));
+    assert_eq!(YRow(1).wrapping_sub(2), YRow(7));
+    assert_eq!(ZRow(60).wrapping_add(68), ZRow(0));
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "serde")]
+use amx::{Amx, AmxStateSnapshot, AmxTrace, RecordingOps, XRow, YRow, ZRow};
+
+#[test]
+fn snapshot_round_trip() {
+    let mut ctx = RecordingOps::new(amx::AmxEmuCtx::default());
+    let row: Vec<u8> = (0..64).map(|i| i * 3).collect();
+    unsafe {
+        ctx.load512(row.as_ptr(), XRow(1));
+        ctx.load512(row.as_ptr(), YRow(2));
+        ctx.load512(row.as_ptr(), ZRow(63));
+    }
+    let snapshot = ctx.snapshot();
+    let (_, trace) = ctx.into_parts();
+
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let snapshot2: AmxStateSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(snapshot2, snapshot);
+
+    // Load the snapshot into another context
+    let mut emu = amx::AmxEmuCtx::default();
+    snapshot2.restore(&mut emu);
+    assert_eq!(emu.snapshot(), snapshot);
+
+    let json = serde_json::to_string(&trace).unwrap();
+    let trace2: AmxTrace = serde_json::from_str(&json).unwrap();
+    assert_eq!(trace2, trace);
+}
+
+#[test]
+fn snapshot_wrong_length() {
+    let json = format!(
+        r#"{{"x":{:?},"y":{:?},"z":{:?}}}"#,
+        [0u8; 512].to_vec(),
+        [0u8; 511].to_vec(),
+        [0u8; 4096].to_vec()
+    );
+    let err = serde_json::from_str::<AmxStateSnapshot>(&json).unwrap_err();
+    assert!(err.to_string().contains("invalid length 511"), "{}", err);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use amx::{pack_lut_indices, Amx, XBytes, XRow, YBytes, YRow, ZRow};
+use common::Xorshift32;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+/// Rearrange `lane_size`-byte lanes of `src` according to `pattern`.
+fn permute(src: &[u8], pattern: &[u8], lane_size: usize) -> Vec<u8> {
+    pattern
+        .iter()
+        .flat_map(|&i| src[i as usize * lane_size..][..lane_size].to_vec())
+        .collect()
+}
+
+fn check_shuffle(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0xcafe);
+    for _ in 0..32 {
+        let src: Vec<u8> = (0..64).map(|_| rng.next() as u8).collect();
+        unsafe { ctx.load512(src.as_ptr(), XRow(3)) };
+
+        // Bytes (up to 32 source bytes), output to `x`
+        let pattern: Vec<u8> = (0..64).map(|_| (rng.next() % 32) as u8).collect();
+        unsafe { ctx.load512(pack_lut_indices(&pattern, 5).as_ptr(), YRow(0)) };
+        ctx.shuffle_bytes(XRow(3), YBytes(0), XRow(6));
+        assert_eq!(ctx.read_x()[384..448], permute(&src, &pattern, 1)[..]);
+
+        // 16-bit lanes, output to `y`
+        let pattern: Vec<u8> = (0..32).map(|_| (rng.next() % 32) as u8).collect();
+        unsafe { ctx.load512(pack_lut_indices(&pattern, 5).as_ptr(), XRow(1)) };
+        ctx.permute_x16(XRow(3), XBytes(64), YRow(2));
+        assert_eq!(ctx.read_y()[128..192], permute(&src, &pattern, 2)[..]);
+
+        // 32-bit lanes, output to `z`
+        let pattern: Vec<u8> = (0..16).map(|_| (rng.next() % 16) as u8).collect();
+        unsafe { ctx.load512(pack_lut_indices(&pattern, 4).as_ptr(), XRow(1)) };
+        ctx.permute_x32(XRow(3), XBytes(64), ZRow(50));
+        assert_eq!(ctx.read_z()[3200..3264], permute(&src, &pattern, 4)[..]);
+
+        // 64-bit lanes
+        let pattern: Vec<u8> = (0..8).map(|_| (rng.next() % 8) as u8).collect();
+        unsafe { ctx.load512(pack_lut_indices(&pattern, 4).as_ptr(), XRow(1)) };
+        ctx.permute_x64(XRow(3), XBytes(64), XRow(0));
+        assert_eq!(ctx.read_x()[..64], permute(&src, &pattern, 8)[..]);
+    }
+}
+
+#[test]
+fn shuffle_emu() {
+    init();
+    check_shuffle(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn shuffle_native() {
+    init();
+    check_shuffle(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+fn pack_lut_indices_5bit() {
+    let packed = pack_lut_indices(&[0b10101, 0b00011, 0b11111], 5);
+    // 10101 | 00011 << 5 | 11111 << 10
+    assert_eq!(packed[..3], [0b0111_0101, 0b0111_1100, 0]);
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use amx::{Amx, AmxStateSnapshot, XRow, YRow, ZRow};
+use common::Xorshift32;
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+/// Fill every register with random bytes.
+fn randomize_state(ctx: &mut impl Amx, rng: &mut Xorshift32) {
+    let mut row = [0u8; 64];
+    let mut fill = |row: &mut [u8; 64]| row.iter_mut().for_each(|x| *x = rng.next() as u8);
+    for i in 0..8 {
+        fill(&mut row);
+        unsafe { ctx.load512(row.as_ptr(), XRow(i)) };
+        fill(&mut row);
+        unsafe { ctx.load512(row.as_ptr(), YRow(i)) };
+    }
+    for i in 0..64 {
+        fill(&mut row);
+        unsafe { ctx.load512(row.as_ptr(), ZRow(i)) };
+    }
+}
+
+fn check_save_restore(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0x12345678);
+    randomize_state(ctx, &mut rng);
+    let (x, y, z) = (ctx.read_x(), ctx.read_y(), ctx.read_z());
+
+    let snapshot = AmxStateSnapshot::save(ctx);
+    assert_eq!(snapshot.x()[..], x[..]);
+    assert_eq!(snapshot.y()[..], y[..]);
+    assert_eq!(snapshot.z()[..], z[..]);
+
+    randomize_state(ctx, &mut rng);
+    snapshot.restore(ctx);
+    assert_eq!(ctx.read_x()[..], x[..]);
+    assert_eq!(ctx.read_y()[..], y[..]);
+    assert_eq!(ctx.read_z()[..], z[..]);
+}
+
+#[test]
+fn save_restore_emu() {
+    init();
+    check_save_restore(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn save_restore_native() {
+    init();
+    check_save_restore(&mut *amx::AmxCtx::new().unwrap());
+}
+
+fn check_with_state_preserved(ctx: &mut impl Amx) {
+    let mut rng = Xorshift32(0x2468ace0);
+    randomize_state(ctx, &mut rng);
+    let expected = ctx.snapshot();
+
+    assert_eq!(ctx.with_state_preserved(|| 42), 42);
+    assert_eq!(ctx.snapshot(), expected);
+
+    // Also restored on unwinding
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        ctx.with_state_preserved(|| panic!("oops"))
+    }));
+    assert!(result.is_err());
+    assert_eq!(ctx.snapshot(), expected);
+}
+
+#[test]
+fn with_state_preserved_emu() {
+    init();
+    check_with_state_preserved(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn with_state_preserved_native() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    check_with_state_preserved(&mut *ctx);
+
+    // A nested context shares the registers, standing in for a library that
+    // uses AMX behind our back
+    let expected = ctx.snapshot();
+    ctx.with_state_preserved(|| {
+        amx::AmxCtx::scope(|amx| randomize_state(amx, &mut Xorshift32(1))).unwrap();
+    });
+    assert_eq!(ctx.snapshot(), expected);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn resume_in_another_thread() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    randomize_state(&mut *ctx, &mut Xorshift32(0xdeadbeef));
+    let snapshot = AmxStateSnapshot::save(&mut *ctx);
+    drop(ctx);
+
+    std::thread::spawn(move || {
+        let mut ctx = amx::AmxCtx::new().unwrap();
+        snapshot.restore(&mut *ctx);
+        assert_eq!(ctx.read_x()[..], snapshot.x()[..]);
+        assert_eq!(ctx.read_y()[..], snapshot.y()[..]);
+        assert_eq!(ctx.read_z()[..], snapshot.z()[..]);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn snapshot_compare_and_index() {
+    init();
+    let mut ctx = amx::AmxEmuCtx::default();
+    let mut expected = AmxStateSnapshot::default();
+    assert_eq!(ctx.snapshot(), expected);
+
+    let row: Vec<u8> = (0..64).collect();
+    unsafe {
+        ctx.load512(row.as_ptr(), XRow(7));
+        ctx.load512(row.as_ptr(), YRow(2));
+    }
+    assert_ne!(ctx.snapshot(), expected);
+    expected[XRow(7)].copy_from_slice(&row);
+    expected[YRow(2)].copy_from_slice(&row);
+    assert_eq!(ctx.snapshot(), expected);
+    assert_eq!(expected[YRow(2)][..], row[..]);
+    assert_eq!(expected[ZRow(0)], [0; 64]);
+
+    let debug = format!("{:?}", expected);
+    assert!(debug.starts_with("AmxStateSnapshot { x: [0000"));
+    assert!(debug.contains(&format!(
+        ", {}]",
+        row.iter().map(|b| format!("{:02x}", b)).collect::<String>()
+    )));
+}
@@ -0,0 +1,154 @@
+use amx::{
+    Amx, OwnedZTile, PingPongTiles16, PingPongTiles32, RegAlloc, XBytes, XRow, YBytes, YRow, ZRow,
+    ZTile, ZTile16, ZTile32, ZTile64,
+};
+
+#[test]
+fn row_mask() {
+    assert_eq!(ZTile16 { base: 0 }.row_mask(), 0x5555_5555_5555_5555);
+    assert_eq!(ZTile32 { base: 3 }.row_mask(), 0x8888_8888_8888_8888);
+    assert_eq!(ZTile64 { base: 7 }.row_mask(), 0x8080_8080_8080_8080);
+    assert_eq!(ZTile::<1> { base: 0 }.row_mask(), !0);
+}
+
+#[test]
+fn alloc_disjoint() {
+    let a = OwnedZTile::<8>::alloc().unwrap();
+    let b = OwnedZTile::<8>::alloc().unwrap();
+    assert_eq!((*a, *b), (ZTile64 { base: 0 }, ZTile64 { base: 1 }));
+    assert_eq!(a.row_mask() & b.row_mask(), 0);
+
+    // `ZTile32 { base: 0 }` and `ZTile32 { base: 1 }` overlap with `a` and `b`
+    let c = OwnedZTile::<4>::alloc().unwrap();
+    assert_eq!(c.tile(), ZTile32 { base: 2 });
+    assert!(OwnedZTile::<4>::alloc_at(ZTile32 { base: 1 }).is_none());
+    assert!(OwnedZTile::<2>::alloc().is_none());
+    assert!(OwnedZTile::<1>::alloc().is_none());
+
+    // The remaining rows can still be claimed individually
+    let d: Vec<_> = std::iter::from_fn(OwnedZTile::<64>::alloc).collect();
+    assert_eq!(d.len(), 64 - 8 - 8 - 16);
+    assert!(d.iter().all(|t| t.z_index() != ZRow(0)));
+}
+
+#[test]
+fn release_on_drop() {
+    {
+        let _whole = OwnedZTile::<1>::alloc().unwrap();
+        assert!(OwnedZTile::<64>::alloc().is_none());
+    }
+    let a = OwnedZTile::<2>::alloc_at(ZTile16 { base: 1 }).unwrap();
+    assert_eq!(*OwnedZTile::<2>::alloc().unwrap(), ZTile16 { base: 0 });
+    drop(a);
+    assert!(OwnedZTile::<1>::alloc().is_some());
+}
+
+#[test]
+fn per_thread() {
+    let _whole = OwnedZTile::<1>::alloc().unwrap();
+    std::thread::spawn(|| assert!(OwnedZTile::<1>::alloc().is_some()))
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn ping_pong_tiles() {
+    let mut tiles = PingPongTiles32::alloc().unwrap();
+    assert_eq!(
+        (tiles.front(), tiles.back()),
+        (ZTile32 { base: 0 }, ZTile32 { base: 1 })
+    );
+    tiles.swap();
+    assert_eq!(
+        (tiles.front(), tiles.back()),
+        (ZTile32 { base: 1 }, ZTile32 { base: 0 })
+    );
+
+    // The 16-bit tiles would overlap with the 32-bit ones
+    assert!(PingPongTiles16::alloc().is_none());
+    let (front, back) = tiles.into_tiles();
+    assert_eq!((*front, *back), (ZTile32 { base: 1 }, ZTile32 { base: 0 }));
+    drop((front, back));
+
+    let tiles = PingPongTiles16::alloc().unwrap();
+    assert_eq!(tiles.front().row_mask() | tiles.back().row_mask(), !0);
+    assert!(OwnedZTile::<64>::alloc().is_none());
+}
+
+/// Compute a sequence of `i16` outer products, storing each result while the
+/// next one accumulates.
+fn check_ping_pong_pipeline(ctx: &mut impl Amx) {
+    let mut tiles = PingPongTiles16::alloc().unwrap();
+    let x: Vec<i16> = (0..32).collect();
+    let mut out = vec![[0i16; 32 * 32]; 4];
+    for k in 0..=out.len() {
+        if k < out.len() {
+            let y = [k as i16 + 1; 32];
+            unsafe {
+                ctx.load512(x.as_ptr(), XRow(0));
+                ctx.load512(y.as_ptr(), YRow(0));
+            }
+            let z = tiles.front().z_index();
+            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), z, false);
+            ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), z, true);
+        }
+        if k > 0 {
+            unsafe { tiles.store_back(ctx, out[k - 1].as_mut_ptr(), 64) };
+        }
+        tiles.swap();
+    }
+
+    for (k, out) in out.iter().enumerate() {
+        for (i, row) in out.chunks(32).enumerate() {
+            let expected: Vec<i16> = x.iter().map(|&x| x * (k as i16 + 1) * 2).collect();
+            assert_eq!(row, &expected[..], "result {} row {}", k, i);
+        }
+    }
+}
+
+#[test]
+fn ping_pong_pipeline_emu() {
+    check_ping_pong_pipeline(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn ping_pong_pipeline_native() {
+    check_ping_pong_pipeline(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[test]
+fn reg_alloc() {
+    let mut regs = RegAlloc::new();
+    regs.claim_x(XRow(1));
+    regs.claim_y(YRow(0));
+    assert_eq!(regs.alloc_x(), Some(XRow(0)));
+    assert_eq!(regs.alloc_x(), Some(XRow(2)));
+    assert_eq!(regs.alloc_y(), Some(YRow(1)));
+    assert!(regs.is_x_in_use(XRow(2)));
+    assert!(!regs.is_y_in_use(YRow(2)));
+
+    let rest: Vec<_> = std::iter::from_fn(|| regs.alloc_x()).collect();
+    assert_eq!(rest, (3..8).map(XRow).collect::<Vec<_>>());
+    assert_eq!(regs.alloc_x(), None);
+
+    regs.free_x(XRow(5));
+    assert_eq!(regs.alloc_x(), Some(XRow(5)));
+}
+
+#[test]
+#[should_panic(expected = "x[3] is already in use")]
+fn reg_alloc_double_claim() {
+    let mut regs = RegAlloc::new();
+    regs.claim_x(XRow(3));
+    regs.claim_x(XRow(3));
+}
+
+#[test]
+#[should_panic(expected = "y[2] is not in use")]
+fn reg_alloc_double_free() {
+    let mut regs = RegAlloc::new();
+    regs.claim_y(YRow(2));
+    regs.free_y(YRow(2));
+    regs.free_y(YRow(2));
+}
@@ -0,0 +1,121 @@
+use amx::{Amx, LaneMask, MemHint, TracedOp, TracingOps, XBytes, XRow, YBytes, YRow, ZRow};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+#[test]
+fn trace_callback() {
+    init();
+    let mut ops = Vec::new();
+    let mut ctx =
+        TracingOps::with_callback(amx::AmxEmuCtx::default(), |op: &TracedOp| ops.push(*op));
+
+    let src = [1u8; 64];
+    unsafe {
+        ctx.load512(src.as_ptr(), XRow(2));
+        ctx.load512(src.as_ptr(), YRow(3));
+    }
+    ctx.outer_product_f32_xy_to_z(Some(XBytes(128)), Some(YBytes(192)), ZRow(4), false);
+    ctx.copy_z_row_to_x(ZRow(4), XRow(5));
+
+    assert_eq!(
+        ops[..4].iter().map(|op| op.mnemonic()).collect::<Vec<_>>(),
+        ["ldx", "ldy", "fma32", "extrx"]
+    );
+    assert_eq!(ops[0].ptr, Some(src.as_ptr() as *mut ()));
+    assert_eq!(
+        ops[0].to_string(),
+        format!(
+            "ldx row=2 ptr={:p} ({:#018x})",
+            src.as_ptr(),
+            ops[0].operand
+        )
+    );
+    assert_eq!(
+        ops[2].to_string(),
+        "fma32 z_row=4 x_offset=128 y_offset=192 skip_z (0x00000000084200c0)"
+    );
+    assert_eq!(
+        ops[3].to_string(),
+        "extrx z_row=4 x_row=5 (0x0000000000450000)"
+    );
+}
+
+#[test]
+fn trace_lane_masks() {
+    let mut ops = Vec::new();
+    let mut ctx = TracingOps::with_callback(amx::AmxEmuCtx::default(), |op: &TracedOp| {
+        ops.push(op.to_string())
+    });
+    ctx.outer_product_f32_xy_to_z_masked(
+        Some(XBytes(0)),
+        Some(YBytes(0)),
+        ZRow(0),
+        false,
+        LaneMask::First(3),
+        LaneMask::Only(1),
+    );
+    assert_eq!(
+        ops,
+        [
+            "fma32 z_row=0 x_offset=0 y_offset=0 skip_z x_mask=First(3) y_mask=Only(1) \
+          (0x0000862108000000)"
+        ]
+    );
+}
+
+#[test]
+fn trace_mem_hint() {
+    let mut ops = Vec::new();
+    let mut ctx =
+        TracingOps::with_callback(amx::AmxEmuCtx::default(), |op: &TracedOp| ops.push(*op));
+    let src = [1u8; 64];
+    unsafe { ctx.load512_y_hint(src.as_ptr(), YRow(6), MemHint::NonTemporal) };
+    assert_eq!(
+        ops[0].to_string(),
+        format!(
+            "ldy row=6 non_temporal ptr={:p} ({:#018x})",
+            src.as_ptr(),
+            ops[0].operand
+        )
+    );
+    assert_eq!(ops[0].operand >> 56, 0x26);
+}
+
+fn run_i16(ctx: &mut impl Amx, src: &[u8]) {
+    unsafe {
+        ctx.load512(src.as_ptr(), XRow(0));
+        ctx.load512(src.as_ptr(), YRow(0));
+    }
+    ctx.outer_product_i16_xy_to_z(Some(XBytes(0)), Some(YBytes(0)), ZRow(1), false);
+}
+
+#[test]
+fn trace_forwards_to_inner() {
+    init();
+    let src: Vec<u8> = (0..64).collect();
+    let mut plain = amx::AmxEmuCtx::default();
+    let mut traced = TracingOps::new(amx::AmxEmuCtx::default());
+    run_i16(&mut plain, &src);
+    run_i16(&mut traced, &src);
+    assert_eq!(plain.read_z()[..], traced.read_z()[..]);
+    assert_eq!(plain.read_z()[..], traced.into_inner().read_z()[..]);
+}
+
+#[test]
+fn trace_extract_column() {
+    let mut ops = Vec::new();
+    let mut ctx = TracingOps::with_callback(amx::AmxEmuCtx::default(), |op: &TracedOp| {
+        ops.push(op.to_string())
+    });
+    ctx.extract_z_column_to_x::<f32>(ZRow(2), 3, XRow(1));
+    ctx.extract_z_column_to_y::<f64>(ZRow(9), 7, YRow(6));
+    assert_eq!(
+        ops,
+        [
+            "extrx z_column=14 width=4 x_row=1 (0x0000000014e10000)",
+            "extry z_column=57 width=8 y_row=6 (0x0000000007900180)",
+        ]
+    );
+}
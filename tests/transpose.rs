@@ -0,0 +1,86 @@
+use amx::{Amx, ZRow};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+/// Fill `z` with distinct `u16` values and return them.
+fn fill_z(ctx: &mut impl Amx) -> [[u16; 32]; 64] {
+    let mut z = [[0u16; 32]; 64];
+    for (i, row) in z.iter_mut().enumerate() {
+        for (j, x) in row.iter_mut().enumerate() {
+            *x = (i * 32 + j) as u16;
+        }
+        unsafe { ctx.load512(row.as_ptr(), ZRow(i)) };
+    }
+    z
+}
+
+fn read_z_u16(ctx: &mut impl Amx) -> [[u16; 32]; 64] {
+    let mut z = [[0u16; 32]; 64];
+    for (i, row) in z.iter_mut().enumerate() {
+        unsafe { ctx.store512(row.as_mut_ptr(), ZRow(i)) };
+    }
+    z
+}
+
+fn check_transpose_f32(ctx: &mut impl Amx) {
+    let z = fill_z(ctx);
+    ctx.transpose_z_f32();
+    let got = read_z_u16(ctx);
+    for t in 0..4 {
+        for i in 0..16 {
+            for j in 0..16 {
+                // Each `f32` consists of two `u16`s
+                assert_eq!(got[j * 4 + t][i * 2..][..2], z[i * 4 + t][j * 2..][..2]);
+            }
+        }
+    }
+
+    // Transposing twice is a no-op
+    ctx.transpose_z_f32();
+    assert_eq!(read_z_u16(ctx), z);
+}
+
+fn check_transpose_i16(ctx: &mut impl Amx) {
+    let z = fill_z(ctx);
+    ctx.transpose_z_i16();
+    let got = read_z_u16(ctx);
+    for t in 0..2 {
+        for i in 0..32 {
+            for j in 0..32 {
+                assert_eq!(got[j * 2 + t][i], z[i * 2 + t][j]);
+            }
+        }
+    }
+
+    // Transposing twice is a no-op
+    ctx.transpose_z_i16();
+    assert_eq!(read_z_u16(ctx), z);
+}
+
+#[test]
+fn transpose_f32_emu() {
+    init();
+    check_transpose_f32(&mut amx::AmxEmuCtx::default());
+}
+
+#[test]
+fn transpose_i16_emu() {
+    init();
+    check_transpose_i16(&mut amx::AmxEmuCtx::default());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn transpose_f32_native() {
+    init();
+    check_transpose_f32(&mut *amx::AmxCtx::new().unwrap());
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn transpose_i16_native() {
+    init();
+    check_transpose_i16(&mut *amx::AmxCtx::new().unwrap());
+}
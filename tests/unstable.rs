@@ -0,0 +1,88 @@
+#![cfg(feature = "unstable-ops")]
+use amx::{
+    unstable::{self, opcode},
+    Amx, AmxEmuCtx, AmxOps,
+};
+
+fn init() {
+    let _ = env_logger::builder().is_test(true).try_init();
+}
+
+#[test]
+fn known_fields() {
+    init();
+    let fma32 = unstable::known_fields(opcode::FMA32).unwrap();
+    assert_ne!(fma32 & (1 << 63), 0, "vector mode");
+    assert_eq!(fma32 & (1 << 62), 0);
+    assert_eq!(
+        unstable::known_fields(opcode::LDX).unwrap() & 0xff,
+        0xff,
+        "pointer"
+    );
+    for op in [
+        opcode::VECINT,
+        opcode::VECFP,
+        opcode::MATFP,
+        opcode::SET_CLR,
+        23,
+        31,
+    ] {
+        assert_eq!(unstable::known_fields(op), None, "{}", op);
+    }
+}
+
+#[test]
+fn mnemonic() {
+    init();
+    assert_eq!(opcode::mnemonic(opcode::MAC16), Some("mac16"));
+    assert_eq!(opcode::mnemonic(opcode::GENLUT), Some("genlut"));
+    assert_eq!(opcode::mnemonic(23), None);
+}
+
+#[test]
+fn dispatch_emu() {
+    init();
+    let mut src = amx::AmxAligned([0u8; 64]);
+    src.iter_mut().enumerate().for_each(|(i, x)| *x = i as u8);
+    let mut direct = AmxEmuCtx::default();
+    let mut dispatched = AmxEmuCtx::default();
+    // `ldx` to row 2, then `fma32` in the vector mode
+    let ldx = 2 << 56;
+    let fma32 = (1 << 63) | (3 << 20);
+    unsafe {
+        direct.ldx(ldx, src.as_mut_ptr() as *mut ());
+        unstable::dispatch(
+            &mut dispatched,
+            opcode::LDX,
+            ldx,
+            src.as_mut_ptr() as *mut (),
+        );
+    }
+    direct.fma32(fma32);
+    unsafe { unstable::dispatch(&mut dispatched, opcode::FMA32, fma32, std::ptr::null_mut()) };
+    assert_eq!(direct.read_x(), dispatched.read_x());
+    assert_eq!(direct.read_z(), dispatched.read_z());
+    assert_eq!(dispatched.read_x()[128..192], src[..]);
+}
+
+#[test]
+#[should_panic(expected = "opcode 23 can't be dispatched")]
+fn dispatch_unknown() {
+    init();
+    let mut ctx = AmxEmuCtx::default();
+    unsafe { unstable::dispatch(&mut ctx, 23, 0, std::ptr::null_mut()) };
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn issue_native() {
+    init();
+    let mut ctx = amx::AmxCtx::new().unwrap();
+    let mut out = [0u8; 64];
+    unsafe {
+        ctx.load512([7u8; 64].as_ptr(), amx::XRow(0));
+        // `stx` from row 0
+        unstable::issue::<{ opcode::STX }>(out.as_mut_ptr() as u64);
+    }
+    assert_eq!(out, [7u8; 64]);
+}